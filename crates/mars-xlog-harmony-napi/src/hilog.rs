@@ -0,0 +1,119 @@
+//! hilog-only sink used when `Xlog::init` fails.
+#[cfg(target_env = "ohos")]
+use std::ffi::{c_char, c_int, c_uint, CString};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use mars_xlog::LogLevel;
+
+/// Log domain passed to hilog; apps may use `0x0000..=0xFFFF`.
+#[cfg(target_env = "ohos")]
+const HILOG_DOMAIN: u32 = 0x0000;
+
+/// Stand-in for a file-backed logger: same level and console controls,
+/// output goes to hilog only.
+pub(crate) struct HilogSink {
+    level: Mutex<LogLevel>,
+    console: AtomicBool,
+    init_error: String,
+}
+
+impl HilogSink {
+    pub(crate) fn new(level: LogLevel, init_error: String) -> Self {
+        Self {
+            level: Mutex::new(level),
+            console: AtomicBool::new(true),
+            init_error,
+        }
+    }
+
+    /// Message of the `Xlog::init` failure that caused the fallback.
+    pub(crate) fn init_error(&self) -> &str {
+        &self.init_error
+    }
+
+    pub(crate) fn level(&self) -> LogLevel {
+        *self.level.lock().expect("hilog level lock poisoned")
+    }
+
+    pub(crate) fn set_level(&self, level: LogLevel) {
+        *self.level.lock().expect("hilog level lock poisoned") = level;
+    }
+
+    pub(crate) fn is_enabled(&self, level: LogLevel) -> bool {
+        level != LogLevel::None && (level as u8) >= (self.level() as u8)
+    }
+
+    pub(crate) fn set_console_log_open(&self, open: bool) {
+        self.console.store(open, Ordering::Relaxed);
+    }
+
+    pub(crate) fn write_with_meta(
+        &self,
+        level: LogLevel,
+        tag: &str,
+        file: &str,
+        func: &str,
+        line: u32,
+        msg: &str,
+    ) {
+        if !self.is_enabled(level) || !self.console.load(Ordering::Relaxed) {
+            return;
+        }
+        let text = if file.is_empty() && func.is_empty() {
+            msg.to_string()
+        } else {
+            let file_name = file.rsplit(['/', '\\']).next().unwrap_or(file);
+            format!("[{file_name}:{line}, {func}][{msg}")
+        };
+        write_line(level, tag, &text);
+    }
+}
+
+#[cfg(target_env = "ohos")]
+fn write_line(level: LogLevel, tag: &str, text: &str) {
+    let hilog_level = match level {
+        LogLevel::Verbose | LogLevel::Debug => 3,
+        LogLevel::Info => 4,
+        LogLevel::Warn => 5,
+        LogLevel::Error => 6,
+        LogLevel::Fatal => 7,
+        LogLevel::None => return,
+    };
+    let c_tag = CString::new(tag.replace('\0', " ")).unwrap_or_default();
+    let c_text = CString::new(text.replace('\0', " ")).unwrap_or_default();
+    // SAFETY: both strings are nul-terminated and outlive the call; the
+    // format consumes exactly one string argument.
+    unsafe {
+        OH_LOG_Print(
+            LOG_APP,
+            hilog_level,
+            HILOG_DOMAIN,
+            c_tag.as_ptr(),
+            c"%{public}s".as_ptr(),
+            c_text.as_ptr(),
+        );
+    }
+}
+
+#[cfg(not(target_env = "ohos"))]
+fn write_line(level: LogLevel, tag: &str, text: &str) {
+    eprintln!("[{level:?}][{tag}][{text}");
+}
+
+/// `LogType::LOG_APP` from `hilog/log.h`.
+#[cfg(target_env = "ohos")]
+const LOG_APP: c_int = 0;
+
+#[cfg(target_env = "ohos")]
+#[link(name = "hilog_ndk.z")]
+unsafe extern "C" {
+    fn OH_LOG_Print(
+        log_type: c_int,
+        level: c_int,
+        domain: c_uint,
+        tag: *const c_char,
+        fmt: *const c_char,
+        ...
+    ) -> c_int;
+}
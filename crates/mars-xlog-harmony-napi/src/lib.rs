@@ -2,9 +2,17 @@
 //!
 //! This crate exposes a JS-friendly surface that mirrors the core `mars-xlog`
 //! capability set, including global appender controls and metadata-aware writes.
-use mars_xlog::{self, RawLogMeta, Xlog};
+use std::sync::Arc;
+
+use mars_xlog::{self, Decoder, LifecycleHooks, RawLogMeta, Xlog};
 use napi_derive_ohos::napi;
-use napi_ohos::bindgen_prelude::Buffer;
+use napi_ohos::bindgen_prelude::{ArrayBuffer, AsyncTask, Buffer, Function, Object};
+use napi_ohos::threadsafe_function::ThreadsafeFunctionCallMode;
+use napi_ohos::{Env, Task};
+
+mod hilog;
+
+use hilog::HilogSink;
 
 /// Simple smoke-test function to verify the binding works.
 #[napi]
@@ -186,18 +194,137 @@ fn to_u32_line(line: i32) -> u32 {
 #[napi]
 impl XlogConfig {
     /// Build a logger from the provided config.
+    ///
+    /// Throws a JS error carrying the `XlogError` message if init fails.
     #[napi]
-    pub fn build(&self) -> Logger {
+    pub fn build(&self) -> napi_ohos::Result<Logger> {
         let xlog = Xlog::init(to_core_config(self), to_core_level(self.level))
-            .unwrap_or_else(|e| panic!("init xlog failed: {e}"));
+            .map_err(|e| napi_ohos::Error::from_reason(format!("init xlog failed: {e}")))?;
         xlog.set_console_log_open(self.console);
-        Logger { backend: xlog }
+        Ok(Logger {
+            sink: Sink::Xlog(xlog),
+        })
+    }
+
+    /// Like `build`, but returns null instead of throwing on init failure.
+    #[napi]
+    pub fn try_build(&self) -> Option<Logger> {
+        self.build().ok()
+    }
+
+    /// Like `build`, but never fails: if init fails, returns a hilog-only
+    /// logger with the same API so the app keeps logging somewhere.
+    ///
+    /// The fallback honours level and console settings; file-related calls
+    /// are no-ops. `isFallback()` tells the two apart, and `setErrorCallback`
+    /// on a fallback logger immediately reports the init failure.
+    #[napi]
+    pub fn build_with_fallback(&self) -> Logger {
+        match self.build() {
+            Ok(logger) => logger,
+            Err(err) => {
+                let fallback = HilogSink::new(to_core_level(self.level), err.reason.clone());
+                fallback.set_console_log_open(self.console);
+                Logger {
+                    sink: Sink::Hilog(Arc::new(fallback)),
+                }
+            }
+        }
+    }
+}
+
+/// Where a `Logger` sends its output.
+#[derive(Clone)]
+enum Sink {
+    Xlog(Xlog),
+    /// hilog-only stand-in created by `buildWithFallback` after init failed.
+    Hilog(Arc<HilogSink>),
+}
+
+impl Sink {
+    fn write_with_meta(
+        &self,
+        level: mars_xlog::LogLevel,
+        tag: &str,
+        file: &str,
+        func: &str,
+        line: u32,
+        msg: &str,
+    ) {
+        match self {
+            Sink::Xlog(xlog) => xlog.write_with_meta(level, Some(tag), file, func, line, msg),
+            Sink::Hilog(hilog) => hilog.write_with_meta(level, tag, file, func, line, msg),
+        }
     }
 }
 
 #[napi]
 pub struct Logger {
-    backend: Xlog,
+    sink: Sink,
+}
+
+impl Logger {
+    /// The file-backed logger, or `None` for a hilog fallback.
+    fn xlog(&self) -> Option<&Xlog> {
+        match &self.sink {
+            Sink::Xlog(xlog) => Some(xlog),
+            Sink::Hilog(_) => None,
+        }
+    }
+}
+
+/// Flush run on the libuv thread pool so sync flushes never block JS.
+pub struct FlushTask {
+    /// `None` flushes every instance, like `flushAll`.
+    sink: Option<Sink>,
+    sync: bool,
+}
+
+impl Task for FlushTask {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> napi_ohos::Result<Self::Output> {
+        match &self.sink {
+            Some(Sink::Xlog(xlog)) => xlog.flush(self.sync),
+            Some(Sink::Hilog(_)) => {}
+            None => Xlog::flush_all(self.sync),
+        }
+        Ok(())
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> napi_ohos::Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Log write run on the libuv thread pool.
+pub struct LogTask {
+    sink: Sink,
+    level: Level,
+    tag: String,
+    message: String,
+}
+
+impl Task for LogTask {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> napi_ohos::Result<Self::Output> {
+        self.sink.write_with_meta(
+            to_core_level(self.level),
+            &self.tag,
+            "",
+            "",
+            0,
+            &self.message,
+        );
+        Ok(())
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> napi_ohos::Result<Self::JsValue> {
+        Ok(output)
+    }
 }
 
 #[napi]
@@ -205,56 +332,168 @@ impl Logger {
     /// Return whether logs at `level` are enabled.
     #[napi]
     pub fn is_enabled(&self, level: Level) -> bool {
-        self.backend.is_enabled(to_core_level(level))
+        match &self.sink {
+            Sink::Xlog(xlog) => xlog.is_enabled(to_core_level(level)),
+            Sink::Hilog(hilog) => hilog.is_enabled(to_core_level(level)),
+        }
     }
 
     /// Return current logger level.
     #[napi]
     pub fn level(&self) -> Level {
-        from_core_level(self.backend.level())
+        from_core_level(match &self.sink {
+            Sink::Xlog(xlog) => xlog.level(),
+            Sink::Hilog(hilog) => hilog.level(),
+        })
+    }
+
+    /// Return current logger level; same as `level()`, named to pair with `setLevel`.
+    #[napi]
+    pub fn get_level(&self) -> Level {
+        self.level()
     }
 
     /// Set logger level.
     #[napi]
     pub fn set_level(&self, level: Level) {
-        self.backend.set_level(to_core_level(level));
+        match &self.sink {
+            Sink::Xlog(xlog) => xlog.set_level(to_core_level(level)),
+            Sink::Hilog(hilog) => hilog.set_level(to_core_level(level)),
+        }
+    }
+
+    /// True when init failed and this logger writes to hilog only.
+    #[napi]
+    pub fn is_fallback(&self) -> bool {
+        matches!(self.sink, Sink::Hilog(_))
     }
 
     /// Set appender mode.
     #[napi]
     pub fn set_appender_mode(&self, mode: AppenderMode) {
-        self.backend.set_appender_mode(to_core_appender_mode(mode));
+        if let Some(xlog) = self.xlog() {
+            xlog.set_appender_mode(to_core_appender_mode(mode));
+        }
     }
 
     /// Flush buffered logs.
     #[napi]
     pub fn flush(&self, sync: bool) {
-        self.backend.flush(sync);
+        if let Some(xlog) = self.xlog() {
+            xlog.flush(sync);
+        }
+    }
+
+    /// Flush on a libuv worker; the returned Promise resolves when done.
+    #[napi]
+    pub fn flush_async(&self, sync: bool) -> AsyncTask<FlushTask> {
+        AsyncTask::new(FlushTask {
+            sink: Some(self.sink.clone()),
+            sync,
+        })
+    }
+
+    /// Register `callback(message)` for internal logging errors (disk full,
+    /// write failures). It is invoked on the JS thread and replaces any
+    /// previous callback; it does not keep the app's event loop alive.
+    ///
+    /// On a fallback logger the callback is invoked once with the init error.
+    #[napi]
+    pub fn set_error_callback(&self, callback: Function<String, ()>) -> napi_ohos::Result<()> {
+        let tsfn = callback
+            .build_threadsafe_function::<String>()
+            .callee_handled::<false>()
+            .weak::<true>()
+            .build()?;
+        match &self.sink {
+            Sink::Xlog(xlog) => xlog.set_error_handler(move |err| {
+                tsfn.call(err.to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+            }),
+            Sink::Hilog(hilog) => {
+                tsfn.call(
+                    format!("{}; logging to hilog only", hilog.init_error()),
+                    ThreadsafeFunctionCallMode::NonBlocking,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the callback set by `setErrorCallback`.
+    #[napi]
+    pub fn clear_error_callback(&self) {
+        if let Some(xlog) = self.xlog() {
+            xlog.clear_error_handler();
+        }
     }
 
     /// Toggle console logging.
     #[napi]
     pub fn set_console_log_open(&self, open: bool) {
-        self.backend.set_console_log_open(open);
+        match &self.sink {
+            Sink::Xlog(xlog) => xlog.set_console_log_open(open),
+            Sink::Hilog(hilog) => hilog.set_console_log_open(open),
+        }
+    }
+
+    /// Set max file size in bytes; throws for a negative size.
+    #[napi]
+    pub fn set_max_file_size(&self, max_bytes: i64) -> napi_ohos::Result<()> {
+        if let Some(xlog) = self.xlog() {
+            xlog.set_max_file_size(max_bytes)
+                .map_err(|e| napi_ohos::Error::from_reason(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Set max alive time in seconds; throws for less than one day.
+    #[napi]
+    pub fn set_max_alive_time(&self, alive_seconds: i64) -> napi_ohos::Result<()> {
+        if let Some(xlog) = self.xlog() {
+            xlog.set_max_alive_time(alive_seconds)
+                .map_err(|e| napi_ohos::Error::from_reason(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Directory this logger writes finished log files into (empty if unknown).
+    #[napi]
+    pub fn get_current_log_path(&self) -> String {
+        self.xlog().and_then(Xlog::log_dir).unwrap_or_default()
     }
 
-    /// Set max file size in bytes.
+    /// Cache directory configured for this logger (empty if none).
     #[napi]
-    pub fn set_max_file_size(&self, max_bytes: i64) {
-        self.backend.set_max_file_size(max_bytes);
+    pub fn get_current_log_cache_path(&self) -> String {
+        self.xlog().and_then(Xlog::cache_dir).unwrap_or_default()
     }
 
-    /// Set max alive time in seconds.
+    /// List this logger's log files covering `days` days back from today.
     #[napi]
-    pub fn set_max_alive_time(&self, alive_seconds: i64) {
-        self.backend.set_max_alive_time(alive_seconds);
+    pub fn files_for_timespan(&self, days: i32) -> Vec<String> {
+        self.xlog()
+            .map(|xlog| xlog.log_files_for_timespan(days))
+            .unwrap_or_default()
     }
 
     /// Log a message with a tag.
     #[napi]
     pub fn log(&self, level: Level, tag: String, message: String) {
-        self.backend
-            .write(to_core_level(level), Some(&tag), &message);
+        self.sink
+            .write_with_meta(to_core_level(level), &tag, "", "", 0, &message);
+    }
+
+    /// Log on a libuv worker; the returned Promise resolves once written.
+    ///
+    /// Useful in sync appender mode, where `log` does file I/O on the caller.
+    #[napi]
+    pub fn log_async(&self, level: Level, tag: String, message: String) -> AsyncTask<LogTask> {
+        AsyncTask::new(LogTask {
+            sink: self.sink.clone(),
+            level,
+            tag,
+            message,
+        })
     }
 
     /// Log with explicit metadata.
@@ -268,9 +507,9 @@ impl Logger {
         line: i32,
         message: String,
     ) {
-        self.backend.write_with_meta(
+        self.sink.write_with_meta(
             to_core_level(level),
-            Some(&tag),
+            &tag,
             &file,
             &func,
             to_u32_line(line),
@@ -280,6 +519,7 @@ impl Logger {
 
     /// Log with explicit metadata and raw pid/tid/trace flags.
     #[napi]
+    #[allow(clippy::too_many_arguments)]
     pub fn log_with_raw_meta(
         &self,
         level: Level,
@@ -293,8 +533,13 @@ impl Logger {
         trace_log: bool,
         message: String,
     ) {
+        let Some(xlog) = self.xlog() else {
+            // hilog stamps its own pid/tid.
+            self.log_with_meta(level, tag, file, func, line, message);
+            return;
+        };
         let raw_meta = RawLogMeta::new(pid, tid, maintid).with_trace_log(trace_log);
-        self.backend.write_with_meta_raw(
+        xlog.write_with_meta_raw(
             to_core_level(level),
             Some(&tag),
             &file,
@@ -309,7 +554,9 @@ impl Logger {
 /// Get an existing logger by name prefix.
 #[napi]
 pub fn get_logger(name_prefix: String) -> Option<Logger> {
-    Xlog::get(&name_prefix).map(|backend| Logger { backend })
+    Xlog::get(&name_prefix).map(|xlog| Logger {
+        sink: Sink::Xlog(xlog),
+    })
 }
 
 /// Open global/default appender.
@@ -324,14 +571,90 @@ pub fn close_appender() {
     Xlog::appender_close();
 }
 
+/// Open the global/default appender from an `XlogConfig`, using its level and
+/// console settings.
+///
+/// Throws a JS error carrying the `XlogError` message if the appender cannot be
+/// opened, e.g. when a different default config is already open.
+#[napi]
+pub fn appender_open(config: &XlogConfig) -> napi_ohos::Result<()> {
+    Xlog::appender_open(to_core_config(config), to_core_level(config.level))
+        .map_err(|e| napi_ohos::Error::from_reason(format!("open appender failed: {e}")))?;
+    if let Some(xlog) = Xlog::get(&config.name_prefix) {
+        xlog.set_console_log_open(config.console);
+    }
+    Ok(())
+}
+
+/// Close the global/default appender opened by `appenderOpen`.
+#[napi]
+pub fn appender_close() {
+    Xlog::appender_close();
+}
+
 /// Flush all instances.
 #[napi]
 pub fn flush_all(sync: bool) {
     Xlog::flush_all(sync);
 }
 
+/// Flush all instances on a libuv worker; the returned Promise resolves when done.
+#[napi]
+pub fn flush_all_async(sync: bool) -> AsyncTask<FlushTask> {
+    AsyncTask::new(FlushTask { sink: None, sync })
+}
+
+/// Forward an ability-backgrounded event to the shared lifecycle policy.
+#[napi]
+pub fn on_app_background() {
+    LifecycleHooks::global().on_background();
+}
+
+/// Forward an ability-foregrounded event to the shared lifecycle policy.
+#[napi]
+pub fn on_app_foreground() {
+    LifecycleHooks::global().on_foreground();
+}
+
+/// Forward an ability-destroyed event to the shared lifecycle policy.
+#[napi]
+pub fn on_app_terminate() {
+    LifecycleHooks::global().on_terminate();
+}
+
+/// Drive the shared lifecycle policy from an `ApplicationContext`.
+///
+/// Pass `this.context.getApplicationContext()` from the entry ability's
+/// `onCreate`. This registers an `applicationStateChange` listener that calls
+/// `onAppBackground`/`onAppForeground` for you: a sync flush on background,
+/// with async loggers switched to sync until the app returns. Ability
+/// destruction is not an app-wide event, so call `onAppTerminate` yourself.
+#[napi]
+pub fn register_lifecycle(env: Env, application_context: Object) -> napi_ohos::Result<()> {
+    let on: Function<(&str, &Object), ()> = application_context
+        .get("on")?
+        .ok_or_else(|| napi_ohos::Error::from_reason("applicationContext.on is missing"))?;
+    let mut callback = Object::new(&env)?;
+    callback.set(
+        "onApplicationForeground",
+        env.create_function_from_closure::<(), (), _>("onApplicationForeground", |_| {
+            LifecycleHooks::global().on_foreground();
+            Ok(())
+        })?,
+    )?;
+    callback.set(
+        "onApplicationBackground",
+        env.create_function_from_closure::<(), (), _>("onApplicationBackground", |_| {
+            LifecycleHooks::global().on_background();
+            Ok(())
+        })?,
+    )?;
+    on.apply(application_context, ("applicationStateChange", &callback))
+}
+
 /// Write to global/default appender with raw metadata.
 #[napi]
+#[allow(clippy::too_many_arguments)]
 pub fn appender_write_with_raw_meta(
     level: Level,
     tag: String,
@@ -400,3 +723,30 @@ pub fn dump(buffer: Buffer) -> String {
 pub fn memory_dump(buffer: Buffer) -> String {
     Xlog::memory_dump(buffer.as_ref())
 }
+
+/// Same as `dump`, reading an ArkTS `ArrayBuffer` in place without copying.
+#[napi]
+pub fn dump_array_buffer(buffer: ArrayBuffer) -> String {
+    Xlog::dump(&buffer)
+}
+
+/// Same as `memoryDump`, reading an ArkTS `ArrayBuffer` in place without copying.
+#[napi]
+pub fn memory_dump_array_buffer(buffer: ArrayBuffer) -> String {
+    Xlog::memory_dump(&buffer)
+}
+
+/// Decode xlog file contents into plaintext log lines.
+///
+/// `private_key` is the hex server private key for encrypted logs; omit it
+/// or pass an empty string for unencrypted ones. Throws if the key is malformed.
+#[napi]
+pub fn decode_array_buffer(
+    buffer: ArrayBuffer,
+    private_key: Option<String>,
+) -> napi_ohos::Result<String> {
+    let decoder = Decoder::new()
+        .private_key_hex(private_key.as_deref().unwrap_or_default())
+        .map_err(|e| napi_ohos::Error::from_reason(e.to_string()))?;
+    Ok(String::from_utf8_lossy(&decoder.decode(&buffer)).into_owned())
+}
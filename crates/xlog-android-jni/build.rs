@@ -0,0 +1,15 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+
+    // Android 15+ devices may use 16KB pages; 64-bit shared libraries must keep their
+    // LOAD segments 16KB-aligned or they fail to load there.
+    if target_os == "android" && (target_arch == "aarch64" || target_arch == "x86_64") {
+        println!("cargo:rustc-cdylib-link-arg=-Wl,-z,max-page-size=16384");
+        println!("cargo:rustc-cdylib-link-arg=-Wl,-z,common-page-size=4096");
+    }
+}
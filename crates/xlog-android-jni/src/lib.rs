@@ -3,20 +3,49 @@
 //! The exported symbols are consumed from Java/Kotlin via the `XlogBridge`
 //! wrapper in `examples/android-jni`. They map Java-friendly primitives to the
 //! safe Rust API in `mars-xlog`.
-use jni::objects::{JByteArray, JClass, JObject, JString};
-use jni::sys::{jboolean, jbyteArray, jint, jlong, jobjectArray, jstring};
-use jni::JNIEnv;
-use mars_xlog::{AppenderMode, CompressMode, FileIoAction, LogLevel, RawLogMeta, Xlog, XlogConfig};
-use once_cell::sync::Lazy;
+//!
+//! `JNI_OnLoad` also registers every native through `RegisterNatives`, so the
+//! bridge keeps working when the Java class is renamed by R8/ProGuard as long
+//! as the build sets `MARS_XLOG_JNI_CLASS` to the final class name.
+use jni::objects::{JByteArray, JByteBuffer, JClass, JObject, JObjectArray, JString, JValue};
+use jni::sys::{
+    jboolean, jbyteArray, jint, jlong, jobjectArray, jstring, JNI_ERR, JNI_VERSION_1_6,
+};
+use jni::{JNIEnv, JavaVM, NativeMethod};
+use mars_xlog::{
+    AppenderMode, CompressMode, Decoder, Directives, FileIoAction, FlushOutcome, LifecycleHooks,
+    LogLevel, RawLogMeta, Xlog, XlogConfig,
+};
+use once_cell::sync::{Lazy, OnceCell};
 use std::collections::HashMap;
+use std::ffi::c_void;
 use std::ptr;
 use std::sync::atomic::{AtomicI64, Ordering};
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+mod stack_trace;
 
 /// Registry of live logger handles keyed by opaque ids.
-static LOGGERS: Lazy<Mutex<HashMap<i64, Xlog>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+///
+/// Lookups vastly outnumber inserts/removals, so concurrent `nativeWrite`
+/// calls only take the shared read lock.
+static LOGGERS: Lazy<RwLock<HashMap<i64, Xlog>>> = Lazy::new(|| RwLock::new(HashMap::new()));
 /// Monotonic id generator for Java-side handles.
 static NEXT_ID: AtomicI64 = AtomicI64::new(1);
+/// Level listener ids registered from Java, keyed by logger handle.
+static LEVEL_LISTENERS: Lazy<Mutex<HashMap<i64, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+/// JavaVM cached by `JNI_OnLoad`, used to attach worker threads for callbacks.
+static JAVA_VM: OnceCell<JavaVM> = OnceCell::new();
+
+/// Binary name of the Java class the natives are registered on.
+///
+/// Override at build time with `MARS_XLOG_JNI_CLASS` (slash-separated, e.g.
+/// `com/example/a/b`) when the bridge class is relocated or obfuscated.
+const BRIDGE_CLASS: &str = match option_env!("MARS_XLOG_JNI_CLASS") {
+    Some(class) => class,
+    None => "com/tencent/mars/xlog/example/XlogBridge",
+};
 
 /// Allocate a new handle id.
 fn next_id() -> i64 {
@@ -26,21 +55,36 @@ fn next_id() -> i64 {
 /// Insert a logger into the registry and return its id.
 fn insert_logger(logger: Xlog) -> i64 {
     let id = next_id();
-    let mut store = LOGGERS.lock().expect("logger store poisoned");
+    let mut store = LOGGERS.write().expect("logger store poisoned");
     store.insert(id, logger);
     id
 }
 
 /// Look up a logger by id.
 fn get_logger(id: i64) -> Option<Xlog> {
-    let store = LOGGERS.lock().expect("logger store poisoned");
+    let store = LOGGERS.read().expect("logger store poisoned");
     store.get(&id).cloned()
 }
 
-/// Remove a logger by id.
+/// Remove a logger by id, dropping any level listener it registered.
 fn remove_logger(id: i64) -> bool {
-    let mut store = LOGGERS.lock().expect("logger store poisoned");
-    store.remove(&id).is_some()
+    let removed = LOGGERS.write().expect("logger store poisoned").remove(&id);
+    let Some(logger) = removed else {
+        return false;
+    };
+    take_level_listener(&logger, id);
+    true
+}
+
+/// Unregister the Java level listener attached to handle `id`, if any.
+fn take_level_listener(logger: &Xlog, id: i64) {
+    let listener = LEVEL_LISTENERS
+        .lock()
+        .expect("level listener store poisoned")
+        .remove(&id);
+    if let Some(listener) = listener {
+        logger.remove_level_listener(listener);
+    }
 }
 
 /// Convert an optional Java string into Rust.
@@ -56,6 +100,11 @@ fn req_string(env: &mut JNIEnv, input: JString) -> String {
     opt_string(env, input).unwrap_or_default()
 }
 
+/// Return the cached JavaVM, caching it from `env` if `JNI_OnLoad` did not run.
+fn java_vm(env: &JNIEnv) -> Option<&'static JavaVM> {
+    JAVA_VM.get_or_try_init(|| env.get_java_vm()).ok()
+}
+
 /// Map the Java enum ordinal to `LogLevel`.
 fn to_log_level(value: jint) -> LogLevel {
     match value {
@@ -69,6 +118,19 @@ fn to_log_level(value: jint) -> LogLevel {
     }
 }
 
+/// Map `LogLevel` back to the Java enum ordinal.
+fn from_log_level(level: LogLevel) -> jint {
+    match level {
+        LogLevel::Verbose => 0,
+        LogLevel::Debug => 1,
+        LogLevel::Info => 2,
+        LogLevel::Warn => 3,
+        LogLevel::Error => 4,
+        LogLevel::Fatal => 5,
+        LogLevel::None => 6,
+    }
+}
+
 /// Map the Java enum ordinal to `AppenderMode`.
 fn to_appender_mode(value: jint) -> AppenderMode {
     match value {
@@ -85,6 +147,81 @@ fn to_compress_mode(value: jint) -> CompressMode {
     }
 }
 
+/// Config values collected from either positional JNI args or a Java object.
+struct ConfigFields {
+    log_dir: String,
+    name_prefix: String,
+    pub_key: Option<String>,
+    cache_dir: Option<String>,
+    cache_days: jint,
+    mode: jint,
+    compress_mode: jint,
+    compress_level: jint,
+}
+
+impl ConfigFields {
+    /// Read fields from a Java `XlogBridge.XlogConfig` instance.
+    ///
+    /// Fields missing from the Java class fall back to `XlogConfig` defaults,
+    /// so older Java code keeps working as new options are added.
+    fn from_object(env: &mut JNIEnv, config: &JObject) -> Self {
+        Self {
+            log_dir: string_field(env, config, "logDir").unwrap_or_default(),
+            name_prefix: string_field(env, config, "namePrefix").unwrap_or_default(),
+            pub_key: string_field(env, config, "pubKey"),
+            cache_dir: string_field(env, config, "cacheDir"),
+            cache_days: int_field(env, config, "cacheDays", 0),
+            mode: int_field(env, config, "mode", 0),
+            compress_mode: int_field(env, config, "compressMode", 0),
+            compress_level: int_field(env, config, "compressLevel", 6),
+        }
+    }
+
+    fn into_config(self) -> XlogConfig {
+        let mut cfg = XlogConfig::new(self.log_dir, self.name_prefix)
+            .cache_days(self.cache_days)
+            .mode(to_appender_mode(self.mode))
+            .compress_mode(to_compress_mode(self.compress_mode))
+            .compress_level(self.compress_level);
+        if let Some(key) = self.pub_key {
+            if !key.is_empty() {
+                cfg = cfg.pub_key(key);
+            }
+        }
+        if let Some(dir) = self.cache_dir {
+            if !dir.is_empty() {
+                cfg = cfg.cache_dir(dir);
+            }
+        }
+        cfg
+    }
+}
+
+/// Read a `String` field from a Java object, treating errors as absent.
+fn string_field(env: &mut JNIEnv, obj: &JObject, name: &str) -> Option<String> {
+    match env
+        .get_field(obj, name, "Ljava/lang/String;")
+        .and_then(|value| value.l())
+    {
+        Ok(value) => opt_string(env, JString::from(value)),
+        Err(_) => {
+            let _ = env.exception_clear();
+            None
+        }
+    }
+}
+
+/// Read an `int` field from a Java object, falling back to `default`.
+fn int_field(env: &mut JNIEnv, obj: &JObject, name: &str, default: jint) -> jint {
+    match env.get_field(obj, name, "I").and_then(|value| value.i()) {
+        Ok(value) => value,
+        Err(_) => {
+            let _ = env.exception_clear();
+            default
+        }
+    }
+}
+
 /// Convert a JNI boolean to Rust bool.
 fn to_bool(value: jboolean) -> bool {
     value != 0
@@ -117,6 +254,41 @@ fn strings_to_array(env: &mut JNIEnv, values: Vec<String>) -> jobjectArray {
     array.into_raw()
 }
 
+/// Convert a Java `String[]` into Rust, mapping null elements to empty strings.
+fn strings_from_array(env: &mut JNIEnv, input: jobjectArray) -> Vec<String> {
+    if input.is_null() {
+        return Vec::new();
+    }
+    // SAFETY: `input` is a non-null raw local reference passed by JNI for this call.
+    let array = unsafe { JObjectArray::from_raw(input) };
+    let len = env.get_array_length(&array).unwrap_or(0);
+    let mut values = Vec::with_capacity(len.max(0) as usize);
+    for idx in 0..len {
+        let value = match env.get_object_array_element(&array, idx) {
+            Ok(element) => {
+                let element = JString::from(element);
+                let value = env
+                    .get_string(&element)
+                    .map(String::from)
+                    .unwrap_or_default();
+                // Release per element so large arrays can't exhaust the local ref table.
+                let _ = env.delete_local_ref(element);
+                value
+            }
+            Err(_) => String::new(),
+        };
+        values.push(value);
+    }
+    values
+}
+
+/// Convert a Rust byte slice to a Java `byte[]`, returning null on failure.
+fn to_jbyte_array(env: &mut JNIEnv, value: &[u8]) -> jbyteArray {
+    env.byte_array_from_slice(value)
+        .map(|array| array.into_raw())
+        .unwrap_or(ptr::null_mut())
+}
+
 /// Convert a Java byte array to a Rust Vec.
 fn bytes_from_array(env: &mut JNIEnv, input: jbyteArray) -> Vec<u8> {
     // SAFETY: `input` is a raw local reference passed by JNI for this call.
@@ -140,29 +312,38 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeCreat
     compress_level: jint,
     level: jint,
 ) -> jlong {
-    let log_dir = req_string(&mut env, log_dir);
-    let name_prefix = req_string(&mut env, name_prefix);
-    let pub_key = opt_string(&mut env, pub_key);
-    let cache_dir = opt_string(&mut env, cache_dir);
-
-    let mut cfg = XlogConfig::new(log_dir, name_prefix)
-        .cache_days(cache_days)
-        .mode(to_appender_mode(mode))
-        .compress_mode(to_compress_mode(compress_mode))
-        .compress_level(compress_level);
-    if let Some(key) = pub_key {
-        if !key.is_empty() {
-            cfg = cfg.pub_key(key);
-        }
+    let cfg = ConfigFields {
+        log_dir: req_string(&mut env, log_dir),
+        name_prefix: req_string(&mut env, name_prefix),
+        pub_key: opt_string(&mut env, pub_key),
+        cache_dir: opt_string(&mut env, cache_dir),
+        cache_days,
+        mode,
+        compress_mode,
+        compress_level,
     }
-    if let Some(dir) = cache_dir {
-        if !dir.is_empty() {
-            cfg = cfg.cache_dir(dir);
-        }
+    .into_config();
+
+    match Xlog::init(cfg, to_log_level(level)) {
+        Ok(logger) => insert_logger(logger),
+        Err(_) => 0,
     }
+}
 
+#[no_mangle]
+/// Create a new logger from a Java `XlogBridge.XlogConfig` and return its handle id.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeCreateLoggerWithConfig(
+    mut env: JNIEnv,
+    _class: JClass,
+    config: JObject,
+    level: jint,
+) -> jlong {
+    if config.is_null() {
+        return 0;
+    }
+    let cfg = ConfigFields::from_object(&mut env, &config).into_config();
     match Xlog::init(cfg, to_log_level(level)) {
-        Ok(logger) => insert_logger(logger) as jlong,
+        Ok(logger) => insert_logger(logger),
         Err(_) => 0,
     }
 }
@@ -176,7 +357,7 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeGetLo
 ) -> jlong {
     let name_prefix = req_string(&mut env, name_prefix);
     match Xlog::get(&name_prefix) {
-        Some(logger) => insert_logger(logger) as jlong,
+        Some(logger) => insert_logger(logger),
         None => 0,
     }
 }
@@ -191,7 +372,7 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeRelea
     if handle == 0 {
         return 0;
     }
-    if remove_logger(handle as i64) {
+    if remove_logger(handle) {
         1
     } else {
         0
@@ -213,27 +394,36 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeOpenA
     compress_level: jint,
     level: jint,
 ) -> jboolean {
-    let log_dir = req_string(&mut env, log_dir);
-    let name_prefix = req_string(&mut env, name_prefix);
-    let pub_key = opt_string(&mut env, pub_key);
-    let cache_dir = opt_string(&mut env, cache_dir);
-
-    let mut cfg = XlogConfig::new(log_dir, name_prefix)
-        .cache_days(cache_days)
-        .mode(to_appender_mode(mode))
-        .compress_mode(to_compress_mode(compress_mode))
-        .compress_level(compress_level);
-    if let Some(key) = pub_key {
-        if !key.is_empty() {
-            cfg = cfg.pub_key(key);
-        }
+    let cfg = ConfigFields {
+        log_dir: req_string(&mut env, log_dir),
+        name_prefix: req_string(&mut env, name_prefix),
+        pub_key: opt_string(&mut env, pub_key),
+        cache_dir: opt_string(&mut env, cache_dir),
+        cache_days,
+        mode,
+        compress_mode,
+        compress_level,
     }
-    if let Some(dir) = cache_dir {
-        if !dir.is_empty() {
-            cfg = cfg.cache_dir(dir);
-        }
+    .into_config();
+
+    match Xlog::appender_open(cfg, to_log_level(level)) {
+        Ok(()) => 1,
+        Err(_) => 0,
     }
+}
 
+#[no_mangle]
+/// Open the global appender from a Java `XlogBridge.XlogConfig`.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeOpenAppenderWithConfig(
+    mut env: JNIEnv,
+    _class: JClass,
+    config: JObject,
+    level: jint,
+) -> jboolean {
+    if config.is_null() {
+        return 0;
+    }
+    let cfg = ConfigFields::from_object(&mut env, &config).into_config();
     match Xlog::appender_open(cfg, to_log_level(level)) {
         Ok(()) => 1,
         Err(_) => 0,
@@ -259,6 +449,33 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeFlush
     Xlog::flush_all(to_bool(sync));
 }
 
+#[no_mangle]
+/// Forward `onStop`/process-backgrounded to the shared lifecycle policy.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeOnBackground(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    LifecycleHooks::global().on_background();
+}
+
+#[no_mangle]
+/// Forward `onStart`/process-foregrounded to the shared lifecycle policy.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeOnForeground(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    LifecycleHooks::global().on_foreground();
+}
+
+#[no_mangle]
+/// Forward process termination to the shared lifecycle policy.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeOnTerminate(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    LifecycleHooks::global().on_terminate();
+}
+
 #[no_mangle]
 /// Check whether a log level is enabled for the given handle.
 pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeIsEnabled(
@@ -267,7 +484,7 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeIsEna
     handle: jlong,
     level: jint,
 ) -> jboolean {
-    if let Some(logger) = get_logger(handle as i64) {
+    if let Some(logger) = get_logger(handle) {
         if logger.is_enabled(to_log_level(level)) {
             return 1;
         }
@@ -282,16 +499,8 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeGetLe
     _class: JClass,
     handle: jlong,
 ) -> jint {
-    if let Some(logger) = get_logger(handle as i64) {
-        return match logger.level() {
-            LogLevel::Verbose => 0,
-            LogLevel::Debug => 1,
-            LogLevel::Info => 2,
-            LogLevel::Warn => 3,
-            LogLevel::Error => 4,
-            LogLevel::Fatal => 5,
-            LogLevel::None => 6,
-        };
+    if let Some(logger) = get_logger(handle) {
+        return from_log_level(logger.level());
     }
     -1
 }
@@ -304,11 +513,57 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetLe
     handle: jlong,
     level: jint,
 ) {
-    if let Some(logger) = get_logger(handle as i64) {
+    if let Some(logger) = get_logger(handle) {
         logger.set_level(to_log_level(level));
     }
 }
 
+#[no_mangle]
+/// Register `listener.onLevelChanged(int)` for level changes on a handle.
+///
+/// The listener fires whenever the instance level changes, including changes
+/// made from Rust or through another handle, and runs on the thread that made
+/// the change. Passing null removes the current listener; each handle keeps
+/// at most one. Returns `false` for an unknown handle.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetLevelListener(
+    env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    listener: JObject,
+) -> jboolean {
+    let Some(logger) = get_logger(handle) else {
+        return 0;
+    };
+    take_level_listener(&logger, handle);
+    if listener.is_null() {
+        return 1;
+    }
+    let Some(vm) = java_vm(&env) else {
+        return 0;
+    };
+    let Ok(listener) = env.new_global_ref(listener) else {
+        return 0;
+    };
+
+    let id = logger.on_level_changed(move |level| {
+        if let Ok(mut env) = vm.attach_current_thread() {
+            let level = JValue::Int(from_log_level(level));
+            if env
+                .call_method(&listener, "onLevelChanged", "(I)V", &[level])
+                .is_err()
+            {
+                let _ = env.exception_describe();
+                let _ = env.exception_clear();
+            }
+        }
+    });
+    LEVEL_LISTENERS
+        .lock()
+        .expect("level listener store poisoned")
+        .insert(handle, id);
+    1
+}
+
 #[no_mangle]
 /// Set the appender mode for a handle.
 pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetAppenderMode(
@@ -317,7 +572,7 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetAp
     handle: jlong,
     mode: jint,
 ) {
-    if let Some(logger) = get_logger(handle as i64) {
+    if let Some(logger) = get_logger(handle) {
         logger.set_appender_mode(to_appender_mode(mode));
     }
 }
@@ -330,11 +585,77 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeFlush
     handle: jlong,
     sync: jboolean,
 ) {
-    if let Some(logger) = get_logger(handle as i64) {
+    if let Some(logger) = get_logger(handle) {
         logger.flush(to_bool(sync));
     }
 }
 
+#[no_mangle]
+/// Sync-flush a handle, waiting at most `max_millis` so `onStop` stays clear
+/// of the ANR watchdog.
+///
+/// Returns `true` when the flush finished in time, `false` when it continues
+/// in the background or the handle is unknown.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeFlushBounded(
+    _env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    max_millis: jlong,
+) -> jboolean {
+    let Some(logger) = get_logger(handle) else {
+        return 0;
+    };
+    let timeout = Duration::from_millis(max_millis.max(0) as u64);
+    (logger.flush_bounded(timeout) == FlushOutcome::Completed) as jboolean
+}
+
+#[no_mangle]
+/// Flush a handle synchronously on a worker thread, then run `callback`.
+///
+/// Returns `false` when the handle is unknown or the worker could not be
+/// started; in that case `callback` is never invoked.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeFlushAsync(
+    env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    callback: JObject,
+) -> jboolean {
+    let Some(logger) = get_logger(handle) else {
+        return 0;
+    };
+    let Some(vm) = java_vm(&env) else {
+        return 0;
+    };
+    let callback = if callback.is_null() {
+        None
+    } else {
+        match env.new_global_ref(callback) {
+            Ok(callback) => Some(callback),
+            Err(_) => return 0,
+        }
+    };
+
+    let spawned = std::thread::Builder::new()
+        .name("xlog-jni-flush".to_string())
+        .spawn(move || {
+            logger.flush(true);
+            let Some(callback) = callback else {
+                return;
+            };
+            if let Ok(mut env) = vm.attach_current_thread() {
+                if env.call_method(&callback, "run", "()V", &[]).is_err() {
+                    let _ = env.exception_describe();
+                    let _ = env.exception_clear();
+                }
+            }
+        });
+    if spawned.is_ok() {
+        1
+    } else {
+        0
+    }
+}
+
 #[no_mangle]
 /// Enable or disable console logging for a handle.
 pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetConsoleLogOpen(
@@ -343,37 +664,89 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetCo
     handle: jlong,
     open: jboolean,
 ) {
-    if let Some(logger) = get_logger(handle as i64) {
+    if let Some(logger) = get_logger(handle) {
         logger.set_console_log_open(to_bool(open));
     }
 }
 
 #[no_mangle]
-/// Set maximum file size for a handle.
+/// Set maximum file size for a handle; false for an unknown handle or a
+/// negative size.
 pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetMaxFileSize(
     _env: JNIEnv,
     _class: JClass,
     handle: jlong,
     max_bytes: jlong,
-) {
-    if let Some(logger) = get_logger(handle as i64) {
-        logger.set_max_file_size(max_bytes as i64);
+) -> jboolean {
+    match get_logger(handle) {
+        Some(logger) => logger.set_max_file_size(max_bytes).is_ok() as jboolean,
+        None => 0,
     }
 }
 
 #[no_mangle]
-/// Set maximum log file age for a handle.
+/// Set maximum log file age for a handle; false for an unknown handle or an
+/// age under one day.
 pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetMaxAliveTime(
     _env: JNIEnv,
     _class: JClass,
     handle: jlong,
     alive_seconds: jlong,
-) {
-    if let Some(logger) = get_logger(handle as i64) {
-        logger.set_max_alive_time(alive_seconds as i64);
+) -> jboolean {
+    match get_logger(handle) {
+        Some(logger) => logger.set_max_alive_time(alive_seconds).is_ok() as jboolean,
+        None => 0,
     }
 }
 
+#[no_mangle]
+/// Replace the encryption public key for a handle.
+///
+/// A null or empty key disables encryption. Returns false for an unknown
+/// handle or a malformed key, in which case the old key stays in effect.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetPubKey(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    pub_key: JString,
+) -> jboolean {
+    let pub_key = req_string(&mut env, pub_key);
+    match get_logger(handle) {
+        Some(logger) if logger.set_pub_key(&pub_key).is_ok() => 1,
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+/// Apply per-tag level directives such as `info,net=debug` to a handle.
+///
+/// Meant for values read from a system property (see
+/// `XlogBridge.applyDirectivesProperty`). A null or empty string removes the
+/// directives. Returns false for an unknown handle or a malformed string, in
+/// which case the current filter stays in effect.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetDirectives(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    directives: JString,
+) -> jboolean {
+    let directives = req_string(&mut env, directives);
+    match get_logger(handle) {
+        Some(logger) => apply_directives(&logger, &directives) as jboolean,
+        None => 0,
+    }
+}
+
+/// Apply a directive string from Java; an empty one removes the directives.
+fn apply_directives(logger: &Xlog, spec: &str) -> bool {
+    match Directives::parse(spec) {
+        Ok(parsed) if parsed.is_empty() => logger.clear_directives(),
+        Ok(parsed) => logger.set_directives(parsed),
+        Err(_) => return false,
+    }
+    true
+}
+
 #[no_mangle]
 /// Write a log message.
 pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeWrite(
@@ -384,13 +757,118 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeWrite
     tag: JString,
     message: JString,
 ) {
-    if let Some(logger) = get_logger(handle as i64) {
+    if let Some(logger) = get_logger(handle) {
         let tag = opt_string(&mut env, tag);
         let message = req_string(&mut env, message);
         logger.write(to_log_level(level), tag.as_deref(), &message);
     }
 }
 
+#[no_mangle]
+/// Write a UTF-8 message read straight from a direct `ByteBuffer`.
+///
+/// Only the first `length` bytes (clamped to the buffer capacity) are used.
+/// Invalid UTF-8 is replaced rather than rejected. Heap buffers are ignored.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeWriteDirect(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    level: jint,
+    tag: JString,
+    buffer: JByteBuffer,
+    length: jint,
+) {
+    let Some(logger) = get_logger(handle) else {
+        return;
+    };
+    let level = to_log_level(level);
+    if !logger.is_enabled(level) || buffer.is_null() || length <= 0 {
+        return;
+    }
+    let (Ok(addr), Ok(capacity)) = (
+        env.get_direct_buffer_address(&buffer),
+        env.get_direct_buffer_capacity(&buffer),
+    ) else {
+        let _ = env.exception_clear();
+        return;
+    };
+    if addr.is_null() {
+        return;
+    }
+    let len = (length as usize).min(capacity);
+    // SAFETY: `addr` points at a live direct buffer of `capacity` bytes that the
+    // caller keeps reachable for the duration of this call, and `len <= capacity`.
+    let bytes = unsafe { std::slice::from_raw_parts(addr, len) };
+    let message = String::from_utf8_lossy(bytes);
+    let tag = opt_string(&mut env, tag);
+    logger.write(level, tag.as_deref(), &message);
+}
+
+#[no_mangle]
+/// Write a log message with key-value fields.
+///
+/// `keys` and `values` are matched by index; extra entries on either side are
+/// ignored.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeWriteKv(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    level: jint,
+    tag: JString,
+    message: JString,
+    keys: jobjectArray,
+    values: jobjectArray,
+) {
+    if let Some(logger) = get_logger(handle) {
+        let level = to_log_level(level);
+        if !logger.is_enabled(level) {
+            return;
+        }
+        let tag = opt_string(&mut env, tag);
+        let message = req_string(&mut env, message);
+        let keys = strings_from_array(&mut env, keys);
+        let values = strings_from_array(&mut env, values);
+        let fields: Vec<(&str, &str)> = keys
+            .iter()
+            .zip(values.iter())
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        logger.write_kv(level, tag.as_deref(), &message, &fields);
+    }
+}
+
+#[no_mangle]
+/// Write `message` followed by a normalized Java stack trace.
+///
+/// `stack_trace` is the text of `Log.getStackTraceString(t)`; it is
+/// re-indented, long frame lists are trimmed and the cause chain is capped so
+/// exception logs have one shape whichever JVM produced them.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeLogThrowable(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    level: jint,
+    tag: JString,
+    message: JString,
+    stack_trace: JString,
+) {
+    if let Some(logger) = get_logger(handle) {
+        let level = to_log_level(level);
+        if !logger.is_enabled(level) {
+            return;
+        }
+        let tag = opt_string(&mut env, tag);
+        let message = req_string(&mut env, message);
+        let stack_trace = stack_trace::normalize(&req_string(&mut env, stack_trace));
+        let text = match (message.is_empty(), stack_trace.is_empty()) {
+            (_, true) => message,
+            (true, false) => stack_trace,
+            (false, false) => format!("{message}\n{stack_trace}"),
+        };
+        logger.write(level, tag.as_deref(), &text);
+    }
+}
+
 #[no_mangle]
 /// Write a log message with explicit metadata.
 pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeWriteWithMeta(
@@ -404,7 +882,7 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeWrite
     line: jint,
     message: JString,
 ) {
-    if let Some(logger) = get_logger(handle as i64) {
+    if let Some(logger) = get_logger(handle) {
         let tag = opt_string(&mut env, tag);
         let file = req_string(&mut env, file);
         let func = req_string(&mut env, func);
@@ -437,13 +915,12 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeWrite
     trace_log: jboolean,
     message: JString,
 ) {
-    if let Some(logger) = get_logger(handle as i64) {
+    if let Some(logger) = get_logger(handle) {
         let tag = opt_string(&mut env, tag);
         let file = req_string(&mut env, file);
         let func = req_string(&mut env, func);
         let message = req_string(&mut env, message);
-        let raw_meta = RawLogMeta::new(pid as i64, tid as i64, maintid as i64)
-            .with_trace_log(to_bool(trace_log));
+        let raw_meta = RawLogMeta::new(pid, tid, maintid).with_trace_log(to_bool(trace_log));
         logger.write_with_meta_raw(
             to_log_level(level),
             tag.as_deref(),
@@ -476,8 +953,7 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeAppen
     let file = req_string(&mut env, file);
     let func = req_string(&mut env, func);
     let message = req_string(&mut env, message);
-    let raw_meta =
-        RawLogMeta::new(pid as i64, tid as i64, maintid as i64).with_trace_log(to_bool(trace_log));
+    let raw_meta = RawLogMeta::new(pid, tid, maintid).with_trace_log(to_bool(trace_log));
     Xlog::appender_write_with_meta_raw(
         to_log_level(level),
         tag.as_deref(),
@@ -519,6 +995,31 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeFilep
     strings_to_array(&mut env, Xlog::filepaths_from_timespan(timespan, &prefix))
 }
 
+#[no_mangle]
+/// Get the log directory of a single handle.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeCurrentLogPathForHandle(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jstring {
+    let path = get_logger(handle).and_then(|logger| logger.log_dir());
+    to_jstring(&mut env, path)
+}
+
+#[no_mangle]
+/// List a handle's own log files for a given timespan (days from today).
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeListLogFiles(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+    days: jint,
+) -> jobjectArray {
+    let files = get_logger(handle)
+        .map(|logger| logger.log_files_for_timespan(days))
+        .unwrap_or_default();
+    strings_to_array(&mut env, files)
+}
+
 #[no_mangle]
 /// Build log file names for a given timespan (days from today).
 pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeMakeLogfileName(
@@ -545,26 +1046,17 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeOnesh
     compress_mode: jint,
     compress_level: jint,
 ) -> jint {
-    let log_dir = req_string(&mut env, log_dir);
-    let name_prefix = req_string(&mut env, name_prefix);
-    let pub_key = opt_string(&mut env, pub_key);
-    let cache_dir = opt_string(&mut env, cache_dir);
-
-    let mut cfg = XlogConfig::new(log_dir, name_prefix)
-        .cache_days(cache_days)
-        .mode(to_appender_mode(mode))
-        .compress_mode(to_compress_mode(compress_mode))
-        .compress_level(compress_level);
-    if let Some(key) = pub_key {
-        if !key.is_empty() {
-            cfg = cfg.pub_key(key);
-        }
-    }
-    if let Some(dir) = cache_dir {
-        if !dir.is_empty() {
-            cfg = cfg.cache_dir(dir);
-        }
+    let cfg = ConfigFields {
+        log_dir: req_string(&mut env, log_dir),
+        name_prefix: req_string(&mut env, name_prefix),
+        pub_key: opt_string(&mut env, pub_key),
+        cache_dir: opt_string(&mut env, cache_dir),
+        cache_days,
+        mode,
+        compress_mode,
+        compress_level,
     }
+    .into_config();
 
     match Xlog::oneshot_flush(cfg) {
         Ok(action) => match action {
@@ -602,3 +1094,444 @@ pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeMemor
     let bytes = bytes_from_array(&mut env, buffer);
     to_jstring(&mut env, Some(Xlog::memory_dump(&bytes)))
 }
+
+#[no_mangle]
+/// Same as `nativeMemoryDump`, but returns the UTF-8 text as `byte[]`.
+///
+/// This skips the modified-UTF-8 `String` conversion on the JNI side, so
+/// large dumps can be streamed to a file without an extra Java copy.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeMemoryDumpBytes(
+    mut env: JNIEnv,
+    _class: JClass,
+    buffer: jbyteArray,
+) -> jbyteArray {
+    let bytes = bytes_from_array(&mut env, buffer);
+    to_jbyte_array(&mut env, Xlog::memory_dump(&bytes).as_bytes())
+}
+
+#[no_mangle]
+/// Decode an xlog file into plaintext.
+///
+/// `private_key` is the hex server private key; pass null or empty for
+/// unencrypted logs. Returns null if the file can't be read or the key is
+/// malformed.
+pub extern "system" fn Java_com_tencent_mars_xlog_example_XlogBridge_nativeDecodeFile(
+    mut env: JNIEnv,
+    _class: JClass,
+    path: JString,
+    private_key: JString,
+) -> jstring {
+    let path = req_string(&mut env, path);
+    let private_key = req_string(&mut env, private_key);
+    let decoded = Decoder::new()
+        .private_key_hex(&private_key)
+        .and_then(|decoder| decoder.decode_file(path))
+        .ok()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    to_jstring(&mut env, decoded)
+}
+
+/// Build a `NativeMethod` entry for `RegisterNatives`.
+macro_rules! native_method {
+    ($name:literal, $sig:expr, $func:path) => {
+        NativeMethod {
+            name: $name.into(),
+            sig: $sig.into(),
+            fn_ptr: $func as *mut c_void,
+        }
+    };
+}
+
+/// Define `native_methods()` from a list of `native_method!` entries, plus a
+/// test-only view of the same table for checking descriptors.
+macro_rules! native_table {
+    ($(native_method!($name:literal, $sig:expr, $func:path)),* $(,)?) => {
+        /// Table of every native exported by this bridge with its JNI signature.
+        fn native_methods() -> Vec<NativeMethod> {
+            vec![$(native_method!($name, $sig, $func)),*]
+        }
+
+        /// `(descriptor, Rust function name)` for every entry of `native_methods()`.
+        #[cfg(test)]
+        fn native_signatures() -> Vec<(String, &'static str)> {
+            vec![$((String::from($sig), stringify!($func))),*]
+        }
+    };
+}
+
+native_table! {
+    native_method!(
+        "nativeCreateLogger",
+        "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;IIIII)J",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeCreateLogger
+    ),
+    native_method!(
+        "nativeCreateLoggerWithConfig",
+        format!("(L{BRIDGE_CLASS}$XlogConfig;I)J"),
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeCreateLoggerWithConfig
+    ),
+    native_method!(
+        "nativeGetLogger",
+        "(Ljava/lang/String;)J",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeGetLogger
+    ),
+    native_method!(
+        "nativeReleaseLogger",
+        "(J)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeReleaseLogger
+    ),
+    native_method!(
+        "nativeOpenAppender",
+        "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;IIIII)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeOpenAppender
+    ),
+    native_method!(
+        "nativeOpenAppenderWithConfig",
+        format!("(L{BRIDGE_CLASS}$XlogConfig;I)Z"),
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeOpenAppenderWithConfig
+    ),
+    native_method!(
+        "nativeCloseAppender",
+        "()V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeCloseAppender
+    ),
+    native_method!(
+        "nativeFlushAll",
+        "(Z)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeFlushAll
+    ),
+    native_method!(
+        "nativeOnBackground",
+        "()V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeOnBackground
+    ),
+    native_method!(
+        "nativeOnForeground",
+        "()V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeOnForeground
+    ),
+    native_method!(
+        "nativeOnTerminate",
+        "()V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeOnTerminate
+    ),
+    native_method!(
+        "nativeIsEnabled",
+        "(JI)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeIsEnabled
+    ),
+    native_method!(
+        "nativeGetLevel",
+        "(J)I",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeGetLevel
+    ),
+    native_method!(
+        "nativeSetLevel",
+        "(JI)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetLevel
+    ),
+    native_method!(
+        "nativeSetLevelListener",
+        format!("(JL{BRIDGE_CLASS}$LevelListener;)Z"),
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetLevelListener
+    ),
+    native_method!(
+        "nativeSetAppenderMode",
+        "(JI)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetAppenderMode
+    ),
+    native_method!(
+        "nativeFlush",
+        "(JZ)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeFlush
+    ),
+    native_method!(
+        "nativeFlushBounded",
+        "(JJ)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeFlushBounded
+    ),
+    native_method!(
+        "nativeFlushAsync",
+        "(JLjava/lang/Runnable;)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeFlushAsync
+    ),
+    native_method!(
+        "nativeSetConsoleLogOpen",
+        "(JZ)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetConsoleLogOpen
+    ),
+    native_method!(
+        "nativeSetMaxFileSize",
+        "(JJ)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetMaxFileSize
+    ),
+    native_method!(
+        "nativeSetMaxAliveTime",
+        "(JJ)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetMaxAliveTime
+    ),
+    native_method!(
+        "nativeSetPubKey",
+        "(JLjava/lang/String;)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetPubKey
+    ),
+    native_method!(
+        "nativeSetDirectives",
+        "(JLjava/lang/String;)Z",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeSetDirectives
+    ),
+    native_method!(
+        "nativeWrite",
+        "(JILjava/lang/String;Ljava/lang/String;)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeWrite
+    ),
+    native_method!(
+        "nativeWriteDirect",
+        "(JILjava/lang/String;Ljava/nio/ByteBuffer;I)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeWriteDirect
+    ),
+    native_method!(
+        "nativeWriteKv",
+        "(JILjava/lang/String;Ljava/lang/String;[Ljava/lang/String;[Ljava/lang/String;)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeWriteKv
+    ),
+    native_method!(
+        "nativeLogThrowable",
+        "(JILjava/lang/String;Ljava/lang/String;Ljava/lang/String;)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeLogThrowable
+    ),
+    native_method!(
+        "nativeWriteWithMeta",
+        "(JILjava/lang/String;Ljava/lang/String;Ljava/lang/String;ILjava/lang/String;)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeWriteWithMeta
+    ),
+    native_method!(
+        "nativeWriteWithRawMeta",
+        "(JILjava/lang/String;Ljava/lang/String;Ljava/lang/String;IJJJZLjava/lang/String;)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeWriteWithRawMeta
+    ),
+    native_method!(
+        "nativeAppenderWriteWithRawMeta",
+        "(ILjava/lang/String;Ljava/lang/String;Ljava/lang/String;IJJJZLjava/lang/String;)V",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeAppenderWriteWithRawMeta
+    ),
+    native_method!(
+        "nativeCurrentLogPath",
+        "()Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeCurrentLogPath
+    ),
+    native_method!(
+        "nativeCurrentLogCachePath",
+        "()Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeCurrentLogCachePath
+    ),
+    native_method!(
+        "nativeFilepathsFromTimespan",
+        "(ILjava/lang/String;)[Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeFilepathsFromTimespan
+    ),
+    native_method!(
+        "nativeCurrentLogPathForHandle",
+        "(J)Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeCurrentLogPathForHandle
+    ),
+    native_method!(
+        "nativeListLogFiles",
+        "(JI)[Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeListLogFiles
+    ),
+    native_method!(
+        "nativeMakeLogfileName",
+        "(ILjava/lang/String;)[Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeMakeLogfileName
+    ),
+    native_method!(
+        "nativeOneshotFlush",
+        "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;IIII)I",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeOneshotFlush
+    ),
+    native_method!(
+        "nativeDump",
+        "([B)Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeDump
+    ),
+    native_method!(
+        "nativeMemoryDump",
+        "([B)Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeMemoryDump
+    ),
+    native_method!(
+        "nativeMemoryDumpBytes",
+        "([B)[B",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeMemoryDumpBytes
+    ),
+    native_method!(
+        "nativeDecodeFile",
+        "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
+        Java_com_tencent_mars_xlog_example_XlogBridge_nativeDecodeFile
+    ),
+}
+
+/// Register the bridge natives on `class_name` and return how many succeeded.
+///
+/// Methods are registered one by one so a Java class that declares only a
+/// subset of the natives still gets the ones it has; the rest keep resolving
+/// through the exported `Java_*` symbols.
+fn register_natives(env: &mut JNIEnv, class_name: &str) -> usize {
+    let class = match env.find_class(class_name) {
+        Ok(class) => class,
+        Err(_) => {
+            let _ = env.exception_clear();
+            return 0;
+        }
+    };
+    let mut registered = 0;
+    for method in native_methods() {
+        if env
+            .register_native_methods(&class, std::slice::from_ref(&method))
+            .is_ok()
+        {
+            registered += 1;
+        } else {
+            let _ = env.exception_clear();
+        }
+    }
+    registered
+}
+
+#[no_mangle]
+/// Cache the JavaVM and register natives on the bridge class.
+///
+/// # Safety
+///
+/// Must only be called by the JVM while loading this library.
+pub unsafe extern "system" fn JNI_OnLoad(
+    vm: *mut jni::sys::JavaVM,
+    _reserved: *mut c_void,
+) -> jint {
+    // SAFETY: the JVM passes a valid `JavaVM` pointer to `JNI_OnLoad`.
+    let vm = match unsafe { JavaVM::from_raw(vm) } {
+        Ok(vm) => vm,
+        Err(_) => return JNI_ERR,
+    };
+    if let Ok(mut env) = vm.get_env() {
+        register_natives(&mut env, BRIDGE_CLASS);
+    }
+    let _ = JAVA_VM.set(vm);
+    JNI_VERSION_1_6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_directives, native_signatures};
+    use mars_xlog::testing::Capture;
+    use mars_xlog::LogLevel;
+
+    const SOURCE: &str = include_str!("lib.rs");
+
+    /// Split a JNI method descriptor into its argument and return descriptors.
+    fn parse_descriptor(sig: &str) -> (Vec<String>, String) {
+        let (args, ret) = sig[1..].split_once(')').expect("descriptor has `)`");
+        let mut parsed = Vec::new();
+        let mut rest = args;
+        while !rest.is_empty() {
+            let dims = rest.len() - rest.trim_start_matches('[').len();
+            let end = match rest.as_bytes()[dims] {
+                b'L' => rest.find(';').expect("object descriptor ends with `;`") + 1,
+                _ => dims + 1,
+            };
+            parsed.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+        (parsed, ret.to_string())
+    }
+
+    /// Parameter types (without `JNIEnv` and the class/receiver) and return
+    /// type of `func`, read from this file.
+    fn rust_signature(func: &str) -> (Vec<String>, String) {
+        let start = SOURCE
+            .find(&format!("fn {func}("))
+            .unwrap_or_else(|| panic!("{func} not found"));
+        let after = &SOURCE[start..];
+        let open = after.find('(').expect("parameter list");
+        let close = after.find(')').expect("parameter list end");
+        let params = after[open + 1..close]
+            .split(',')
+            .filter_map(|param| param.split_once(':'))
+            .map(|(_, ty)| ty.trim().to_string())
+            .skip(2)
+            .collect();
+        let tail = &after[close + 1..after.find('{').expect("function body")];
+        let ret = tail
+            .trim()
+            .strip_prefix("->")
+            .map_or("()", str::trim)
+            .to_string();
+        (params, ret)
+    }
+
+    /// Whether the Rust type `ty` can carry the JNI type `descriptor`.
+    fn matches(ty: &str, descriptor: &str) -> bool {
+        match ty {
+            "()" => descriptor == "V",
+            "jboolean" => descriptor == "Z",
+            "jint" => descriptor == "I",
+            "jlong" => descriptor == "J",
+            "JString" | "jstring" => descriptor == "Ljava/lang/String;",
+            "JByteBuffer" => descriptor == "Ljava/nio/ByteBuffer;",
+            "JByteArray" | "jbyteArray" => descriptor == "[B",
+            "JObjectArray" | "jobjectArray" => descriptor.starts_with("[L"),
+            "JObject" => descriptor.starts_with('L'),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn registered_descriptors_match_rust_signatures() {
+        let table = native_signatures();
+        assert!(!table.is_empty());
+        for (sig, func) in table {
+            let (args, ret) = parse_descriptor(&sig);
+            let (params, rust_ret) = rust_signature(func);
+            assert!(
+                matches(&rust_ret, &ret),
+                "{func}: returns {rust_ret} but is registered as {sig}"
+            );
+            assert_eq!(
+                params.len(),
+                args.len(),
+                "{func}: takes {params:?} but is registered as {sig}"
+            );
+            for (param, arg) in params.iter().zip(&args) {
+                assert!(
+                    matches(param, arg),
+                    "{func}: parameter {param} does not match {arg} in {sig}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn directives_without_default_keep_the_handle_level() {
+        let capture = Capture::new(LogLevel::Warn);
+        let logger = capture.logger("app");
+        assert!(apply_directives(&logger, "net=debug"));
+        assert_eq!(logger.level(), LogLevel::Debug);
+        logger.log(LogLevel::Debug, Some("net"), "net debug");
+        logger.log(LogLevel::Info, Some("ui"), "ui info");
+        logger.log(LogLevel::Debug, None, "untagged debug");
+        logger.log(LogLevel::Warn, Some("ui"), "ui warn");
+
+        assert!(!apply_directives(&logger, "net=loud"));
+        assert!(apply_directives(&logger, ""));
+        assert_eq!(logger.level(), LogLevel::Warn);
+        logger.log(LogLevel::Debug, Some("net"), "net debug after clear");
+
+        let messages: Vec<String> = capture
+            .records()
+            .into_iter()
+            .map(|record| record.message)
+            .collect();
+        assert_eq!(messages, ["net debug", "ui warn"]);
+    }
+}
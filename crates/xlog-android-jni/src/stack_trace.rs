@@ -0,0 +1,139 @@
+//! Normalization of Java stack traces for `nativeLogThrowable`.
+//!
+//! Input is whatever `Throwable.printStackTrace` / `Log.getStackTraceString`
+//! produced. Output always has the same shape regardless of JVM or Android
+//! version:
+//!
+//! ```text
+//! java.lang.IllegalStateException: boom
+//!     at com.example.Foo.bar(Foo.java:12)
+//!     ... 40 more
+//! Caused by: java.io.IOException: disk full
+//!     at com.example.Io.write(Io.java:7)
+//! ```
+//!
+//! Headers start at column 0, frames are indented by four spaces, each
+//! throwable keeps at most [`MAX_FRAMES`] frames, and the cause chain is cut
+//! after [`MAX_CAUSES`] entries.
+
+/// Frames kept per throwable; the rest are folded into `... N more`.
+const MAX_FRAMES: usize = 32;
+/// `Caused by:` / `Suppressed:` sections kept after the top throwable.
+const MAX_CAUSES: usize = 8;
+
+const INDENT: &str = "    ";
+
+struct Section<'a> {
+    header: Vec<&'a str>,
+    frames: Vec<&'a str>,
+    /// Frames the JVM already elided with `... N more`.
+    elided: usize,
+}
+
+/// Rewrite `trace` into the normalized layout described in the module docs.
+pub(crate) fn normalize(trace: &str) -> String {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut dropped_causes = 0usize;
+    for raw in trace.lines() {
+        let line = raw.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let starts_section = line.starts_with("Caused by:") || line.starts_with("Suppressed:");
+        if sections.is_empty() || starts_section {
+            if starts_section && sections.len() > MAX_CAUSES {
+                dropped_causes += 1;
+                continue;
+            }
+            sections.push(Section {
+                header: vec![line],
+                frames: Vec::new(),
+                elided: 0,
+            });
+            continue;
+        }
+        if dropped_causes > 0 {
+            continue;
+        }
+        let section = sections.last_mut().expect("section pushed above");
+        if let Some(count) = elided_count(line) {
+            section.elided += count;
+        } else if line.starts_with("at ") {
+            section.frames.push(line);
+        } else if section.frames.is_empty() {
+            // Multi-line exception message.
+            section.header.push(line);
+        } else {
+            section.frames.push(line);
+        }
+    }
+
+    let mut out = String::with_capacity(trace.len());
+    for section in &sections {
+        for line in &section.header {
+            out.push_str(line);
+            out.push('\n');
+        }
+        for frame in section.frames.iter().take(MAX_FRAMES) {
+            out.push_str(INDENT);
+            out.push_str(frame);
+            out.push('\n');
+        }
+        let more = section.elided + section.frames.len().saturating_sub(MAX_FRAMES);
+        if more > 0 {
+            out.push_str(&format!("{INDENT}... {more} more\n"));
+        }
+    }
+    if dropped_causes > 0 {
+        out.push_str(&format!("... {dropped_causes} more causes\n"));
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+/// Parse the JVM's `... N more` marker.
+fn elided_count(line: &str) -> Option<usize> {
+    line.strip_prefix("...")?
+        .trim()
+        .strip_suffix("more")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, MAX_FRAMES};
+
+    #[test]
+    fn normalizes_indentation_and_cause_chain() {
+        let trace = "java.lang.IllegalStateException: boom\r\n\
+                     \tat com.example.Foo.bar(Foo.java:12)\r\n\
+                     \tat com.example.Foo.main(Foo.java:3)\r\n\
+                     Caused by: java.io.IOException: disk\n  full\n\
+                     \tat com.example.Io.write(Io.java:7)\n\
+                     \t... 2 more\n\n";
+        assert_eq!(
+            normalize(trace),
+            "java.lang.IllegalStateException: boom\n    \
+             at com.example.Foo.bar(Foo.java:12)\n    \
+             at com.example.Foo.main(Foo.java:3)\n\
+             Caused by: java.io.IOException: disk\n\
+             full\n    \
+             at com.example.Io.write(Io.java:7)\n    \
+             ... 2 more"
+        );
+    }
+
+    #[test]
+    fn trims_long_frame_lists() {
+        let mut trace = String::from("java.lang.StackOverflowError\n");
+        for idx in 0..MAX_FRAMES + 5 {
+            trace.push_str(&format!("\tat Rec.f(Rec.java:{idx})\n"));
+        }
+        trace.push_str("\t... 10 more\n");
+        let normalized = normalize(&trace);
+        assert_eq!(normalized.lines().count(), 1 + MAX_FRAMES + 1);
+        assert!(normalized.ends_with("    ... 15 more"));
+    }
+}
@@ -0,0 +1,22 @@
+fn main() {
+    #[cfg(feature = "header")]
+    generate_header();
+}
+
+#[cfg(feature = "header")]
+fn generate_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR");
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml"))
+        .expect("read cbindgen.toml");
+    // Parse only this crate's source: every exported type is defined here, and
+    // skipping `cargo metadata` keeps offline builds working.
+    cbindgen::Builder::new()
+        .with_src(format!("{crate_dir}/src/lib.rs"))
+        .with_config(config)
+        .generate()
+        .expect("generate C header")
+        .write_to_file(format!("{crate_dir}/include/mars_xlog_capi.h"));
+}
@@ -0,0 +1,212 @@
+#ifndef MARS_XLOG_CAPI_H
+#define MARS_XLOG_CAPI_H
+
+/* Generated by cbindgen from crates/xlog-capi/src/lib.rs; do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Result codes returned by fallible calls.
+typedef enum XlogStatus {
+  // The call succeeded.
+  XLOG_STATUS_OK = 0,
+  // A pointer was null or an enum value was out of range.
+  XLOG_STATUS_INVALID_ARGUMENT = 1,
+  // The configuration is incomplete or malformed.
+  XLOG_STATUS_INVALID_CONFIG = 2,
+  // A live instance with the same name prefix uses a different configuration.
+  XLOG_STATUS_CONFIG_CONFLICT = 3,
+  // The encryption public key is not a valid 128-char secp256k1 point.
+  XLOG_STATUS_INVALID_PUB_KEY = 4,
+  // A public key was supplied to a build without encryption support.
+  XLOG_STATUS_CRYPTO_DISABLED = 5,
+  // The logger could not be created.
+  XLOG_STATUS_INIT_FAILED = 6,
+  // A log, cache, or mmap directory could not be created or written.
+  XLOG_STATUS_DIR_NOT_WRITABLE = 7,
+  // Another process already holds the lock for this name prefix.
+  XLOG_STATUS_INSTANCE_EXISTS = 8,
+} XlogStatus;
+
+// Log severity levels, passed as `int32_t`.
+typedef enum XlogLevel {
+  // Verbose diagnostic output.
+  XLOG_LEVEL_VERBOSE = 0,
+  // Debug output.
+  XLOG_LEVEL_DEBUG = 1,
+  // Informational output.
+  XLOG_LEVEL_INFO = 2,
+  // Warnings.
+  XLOG_LEVEL_WARN = 3,
+  // Errors.
+  XLOG_LEVEL_ERROR = 4,
+  // Fatal errors.
+  XLOG_LEVEL_FATAL = 5,
+  // Logging disabled.
+  XLOG_LEVEL_NONE = 6,
+} XlogLevel;
+
+// Appender modes, passed as `int32_t`.
+typedef enum XlogAppenderMode {
+  // Buffer in memory and write from a background thread.
+  XLOG_APPENDER_MODE_ASYNC = 0,
+  // Write each line synchronously.
+  XLOG_APPENDER_MODE_SYNC = 1,
+} XlogAppenderMode;
+
+// Compression algorithms, passed as `int32_t`.
+typedef enum XlogCompressMode {
+  // Raw deflate.
+  XLOG_COMPRESS_MODE_ZLIB = 0,
+  // zstd.
+  XLOG_COMPRESS_MODE_ZSTD = 1,
+} XlogCompressMode;
+
+// Opaque handle to a logger instance. Release with [`xlog_release`].
+typedef struct XlogHandle XlogHandle;
+
+// Logger configuration. Initialize with [`xlog_options_init`] before
+// setting fields, so new fields added later keep their defaults.
+typedef struct XlogOptions {
+  // Directory for log files. Required.
+  const char *log_dir;
+  // Log file prefix and instance name. Required.
+  const char *name_prefix;
+  // Optional 128-char hex public key enabling encryption; null or empty disables it.
+  const char *pub_key;
+  // Optional cache directory for mmap buffers; null to use `log_dir` only.
+  const char *cache_dir;
+  // Days to keep logs in `cache_dir` before moving them to `log_dir`.
+  int32_t cache_days;
+  // An `XlogAppenderMode` value.
+  int32_t mode;
+  // An `XlogCompressMode` value.
+  int32_t compress_mode;
+  // Compression level forwarded to the compressor.
+  int32_t compress_level;
+} XlogOptions;
+
+// One structured key/value field for [`xlog_write_kv`].
+typedef struct XlogField {
+  // Field name.
+  const char *key;
+  // Field value.
+  const char *value;
+} XlogField;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message for the most recent failed call on this thread, or null.
+//
+// The string stays valid until the next failing call on the same thread.
+const char *xlog_last_error(void);
+
+// Fill `options` with defaults: no strings, async mode, zlib level 6.
+//
+// # Safety
+// `options` must be null or point to writable memory for one [`XlogOptions`].
+void xlog_options_init(struct XlogOptions *options);
+
+// Check `options` without creating a logger.
+//
+// Runs the same checks as [`XlogConfig::validate`], directory probes
+// included; [`xlog_last_error`] lists every issue found.
+//
+// # Safety
+// `options` must be null or point to a valid [`XlogOptions`] whose string
+// fields are null or NUL-terminated.
+enum XlogStatus xlog_options_validate(const struct XlogOptions *options);
+
+// Create or reuse the logger named by `options.name_prefix`.
+//
+// On success `*out` receives a handle to release with [`xlog_release`].
+//
+// # Safety
+// `options` must satisfy [`xlog_options_validate`]'s requirements and `out`
+// must be null or writable.
+enum XlogStatus xlog_open(const struct XlogOptions *options,
+                          int32_t level,
+                          struct XlogHandle **out);
+
+// Look up a live logger by name prefix; returns null if none exists.
+//
+// # Safety
+// `name_prefix` must be null or NUL-terminated.
+struct XlogHandle *xlog_get(const char *name_prefix);
+
+// Release a handle. The logger closes once its last handle is released.
+//
+// # Safety
+// `handle` must be null or a pointer from [`xlog_open`]/[`xlog_get`] that
+// has not been released yet.
+void xlog_release(struct XlogHandle *handle);
+
+// Current minimum level of the logger, or `XLOG_LEVEL_NONE` for a null handle.
+//
+// # Safety
+// `handle` must be null or a live handle.
+int32_t xlog_level(const struct XlogHandle *handle);
+
+// Set the logger's minimum level.
+//
+// # Safety
+// `handle` must be null or a live handle.
+enum XlogStatus xlog_set_level(const struct XlogHandle *handle, int32_t level);
+
+// Whether a record at `level` would be written.
+//
+// # Safety
+// `handle` must be null or a live handle.
+bool xlog_is_enabled(const struct XlogHandle *handle, int32_t level);
+
+// Write one line. A null `tag` uses the logger's name prefix.
+//
+// # Safety
+// `handle` must be null or a live handle; strings must be null or NUL-terminated.
+enum XlogStatus xlog_write(const struct XlogHandle *handle,
+                           int32_t level,
+                           const char *tag,
+                           const char *message);
+
+// Write one line with source location metadata.
+//
+// # Safety
+// `handle` must be null or a live handle; strings must be null or NUL-terminated.
+enum XlogStatus xlog_write_with_meta(const struct XlogHandle *handle,
+                                     int32_t level,
+                                     const char *tag,
+                                     const char *file,
+                                     const char *function,
+                                     uint32_t line,
+                                     const char *message);
+
+// Write one line with `count` structured key/value fields appended.
+//
+// # Safety
+// `handle` must be null or a live handle; strings must be null or
+// NUL-terminated; `fields` must point to `count` readable entries when
+// `count` is non-zero.
+enum XlogStatus xlog_write_kv(const struct XlogHandle *handle,
+                              int32_t level,
+                              const char *tag,
+                              const char *message,
+                              const struct XlogField *fields,
+                              size_t count);
+
+// Flush the logger's buffer, blocking until written when `sync` is true.
+//
+// # Safety
+// `handle` must be null or a live handle.
+enum XlogStatus xlog_flush(const struct XlogHandle *handle, bool sync);
+
+// Flush every live logger.
+void xlog_flush_all(bool sync);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MARS_XLOG_CAPI_H */
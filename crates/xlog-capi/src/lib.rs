@@ -0,0 +1,702 @@
+//! Stable C API over the safe `mars-xlog` layer.
+//!
+//! C/C++ and other FFI consumers get the validated Rust configuration, the
+//! named-instance registry, and structured logging, instead of the raw mars
+//! wrapper exposed by `mars-xlog-sys`. The header lives in
+//! `include/mars_xlog_capi.h` and is regenerated with
+//! `cargo build -p mars-xlog-capi --features header`.
+//!
+//! Conventions:
+//! - Strings are NUL-terminated UTF-8. Message bodies with invalid UTF-8 are
+//!   logged lossily; configuration strings must be valid.
+//! - Fallible calls return [`XlogStatus`]; [`xlog_last_error`] describes the
+//!   most recent failure on the calling thread.
+//! - Enum-typed arguments are passed as `int32_t` so out-of-range values are
+//!   rejected instead of being undefined behavior.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use mars_xlog::{AppenderMode, CompressMode, ConfigIssue, LogLevel, Xlog, XlogConfig, XlogError};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Result codes returned by fallible calls.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum XlogStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A pointer was null or an enum value was out of range.
+    InvalidArgument = 1,
+    /// The configuration is incomplete or malformed.
+    InvalidConfig = 2,
+    /// A live instance with the same name prefix uses a different configuration.
+    ConfigConflict = 3,
+    /// The encryption public key is not a valid 128-char secp256k1 point.
+    InvalidPubKey = 4,
+    /// A public key was supplied to a build without encryption support.
+    CryptoDisabled = 5,
+    /// The logger could not be created.
+    InitFailed = 6,
+    /// A log, cache, or mmap directory could not be created or written.
+    DirNotWritable = 7,
+    /// Another process already holds the lock for this name prefix.
+    InstanceExists = 8,
+}
+
+/// Log severity levels, passed as `int32_t`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum XlogLevel {
+    /// Verbose diagnostic output.
+    Verbose = 0,
+    /// Debug output.
+    Debug = 1,
+    /// Informational output.
+    Info = 2,
+    /// Warnings.
+    Warn = 3,
+    /// Errors.
+    Error = 4,
+    /// Fatal errors.
+    Fatal = 5,
+    /// Logging disabled.
+    None = 6,
+}
+
+/// Appender modes, passed as `int32_t`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum XlogAppenderMode {
+    /// Buffer in memory and write from a background thread.
+    Async = 0,
+    /// Write each line synchronously.
+    Sync = 1,
+}
+
+/// Compression algorithms, passed as `int32_t`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum XlogCompressMode {
+    /// Raw deflate.
+    Zlib = 0,
+    /// zstd.
+    Zstd = 1,
+}
+
+/// Logger configuration. Initialize with [`xlog_options_init`] before
+/// setting fields, so new fields added later keep their defaults.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XlogOptions {
+    /// Directory for log files. Required.
+    pub log_dir: *const c_char,
+    /// Log file prefix and instance name. Required.
+    pub name_prefix: *const c_char,
+    /// Optional 128-char hex public key enabling encryption; null or empty disables it.
+    pub pub_key: *const c_char,
+    /// Optional cache directory for mmap buffers; null to use `log_dir` only.
+    pub cache_dir: *const c_char,
+    /// Days to keep logs in `cache_dir` before moving them to `log_dir`.
+    pub cache_days: i32,
+    /// An `XlogAppenderMode` value.
+    pub mode: i32,
+    /// An `XlogCompressMode` value.
+    pub compress_mode: i32,
+    /// Compression level forwarded to the compressor.
+    pub compress_level: i32,
+}
+
+/// One structured key/value field for [`xlog_write_kv`].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XlogField {
+    /// Field name.
+    pub key: *const c_char,
+    /// Field value.
+    pub value: *const c_char,
+}
+
+/// Opaque handle to a logger instance. Release with [`xlog_release`].
+pub struct XlogHandle {
+    logger: Xlog,
+}
+
+struct CapiError {
+    status: XlogStatus,
+    message: String,
+}
+
+impl CapiError {
+    fn new(status: XlogStatus, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<XlogError> for CapiError {
+    fn from(err: XlogError) -> Self {
+        let status = match &err {
+            XlogError::InvalidConfig => XlogStatus::InvalidConfig,
+            XlogError::InvalidNamePrefix { .. }
+            | XlogError::InvalidDirectives(_)
+            | XlogError::InvalidValue(_) => XlogStatus::InvalidConfig,
+            XlogError::ConfigConflict { .. } | XlogError::NamePrefixCollision { .. } => {
+                XlogStatus::ConfigConflict
+            }
+            XlogError::InvalidKey => XlogStatus::InvalidPubKey,
+            XlogError::CryptoDisabled => XlogStatus::CryptoDisabled,
+            XlogError::NulInPath(_) => XlogStatus::InvalidArgument,
+            XlogError::DirNotWritable(..) => XlogStatus::DirNotWritable,
+            XlogError::InstanceExists(_) => XlogStatus::InstanceExists,
+            XlogError::InitFailed | XlogError::Ffi(_) | XlogError::Internal(_) => {
+                XlogStatus::InitFailed
+            }
+            // Errors added to mars-xlog after this binding was written.
+            _ => XlogStatus::InitFailed,
+        };
+        Self::new(status, err.to_string())
+    }
+}
+
+impl From<Vec<ConfigIssue>> for CapiError {
+    /// The status comes from the first issue; the message lists them all.
+    fn from(issues: Vec<ConfigIssue>) -> Self {
+        let status = match issues.first() {
+            Some(ConfigIssue::InvalidPubKey) => XlogStatus::InvalidPubKey,
+            Some(ConfigIssue::CryptoDisabled) => XlogStatus::CryptoDisabled,
+            Some(ConfigIssue::NotADirectory(_) | ConfigIssue::DirNotWritable { .. }) => {
+                XlogStatus::DirNotWritable
+            }
+            _ => XlogStatus::InvalidConfig,
+        };
+        let message = issues
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        Self::new(status, message)
+    }
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = Some(message));
+}
+
+fn status_of(result: Result<(), CapiError>) -> XlogStatus {
+    match result {
+        Ok(()) => XlogStatus::Ok,
+        Err(err) => {
+            set_last_error(err.message);
+            err.status
+        }
+    }
+}
+
+/// Read a required configuration string.
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn config_str(ptr: *const c_char, field: &str) -> Result<String, CapiError> {
+    optional_config_str(ptr, field)?
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| CapiError::new(XlogStatus::InvalidConfig, format!("{field} is required")))
+}
+
+/// Read an optional configuration string; null and empty both mean unset.
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn optional_config_str(
+    ptr: *const c_char,
+    field: &str,
+) -> Result<Option<String>, CapiError> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    let value = CStr::from_ptr(ptr).to_str().map_err(|_| {
+        CapiError::new(
+            XlogStatus::InvalidConfig,
+            format!("{field} is not valid UTF-8"),
+        )
+    })?;
+    Ok((!value.is_empty()).then(|| value.to_string()))
+}
+
+/// Borrow a message string, replacing invalid UTF-8. Null reads as empty.
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn text<'a>(ptr: *const c_char) -> std::borrow::Cow<'a, str> {
+    if ptr.is_null() {
+        return std::borrow::Cow::Borrowed("");
+    }
+    CStr::from_ptr(ptr).to_string_lossy()
+}
+
+fn to_level(level: i32) -> Result<LogLevel, CapiError> {
+    Ok(match level {
+        0 => LogLevel::Verbose,
+        1 => LogLevel::Debug,
+        2 => LogLevel::Info,
+        3 => LogLevel::Warn,
+        4 => LogLevel::Error,
+        5 => LogLevel::Fatal,
+        6 => LogLevel::None,
+        _ => {
+            return Err(CapiError::new(
+                XlogStatus::InvalidArgument,
+                format!("invalid log level {level}"),
+            ))
+        }
+    })
+}
+
+fn from_level(level: LogLevel) -> XlogLevel {
+    match level {
+        LogLevel::Verbose => XlogLevel::Verbose,
+        LogLevel::Debug => XlogLevel::Debug,
+        LogLevel::Info => XlogLevel::Info,
+        LogLevel::Warn => XlogLevel::Warn,
+        LogLevel::Error => XlogLevel::Error,
+        LogLevel::Fatal => XlogLevel::Fatal,
+        LogLevel::None => XlogLevel::None,
+    }
+}
+
+/// Convert and validate C options into an [`XlogConfig`].
+///
+/// # Safety
+/// `options` must be null or point to a valid [`XlogOptions`] whose string
+/// fields are null or NUL-terminated.
+unsafe fn to_config(options: *const XlogOptions) -> Result<XlogConfig, CapiError> {
+    let Some(options) = options.as_ref() else {
+        return Err(CapiError::new(
+            XlogStatus::InvalidArgument,
+            "options must not be null",
+        ));
+    };
+    let mode = match options.mode {
+        0 => AppenderMode::Async,
+        1 => AppenderMode::Sync,
+        other => {
+            return Err(CapiError::new(
+                XlogStatus::InvalidConfig,
+                format!("invalid appender mode {other}"),
+            ))
+        }
+    };
+    let compress_mode = match options.compress_mode {
+        0 => CompressMode::Zlib,
+        1 => CompressMode::Zstd,
+        other => {
+            return Err(CapiError::new(
+                XlogStatus::InvalidConfig,
+                format!("invalid compress mode {other}"),
+            ))
+        }
+    };
+    let mut config = XlogConfig::new(
+        config_str(options.log_dir, "log_dir")?,
+        config_str(options.name_prefix, "name_prefix")?,
+    )
+    .cache_days(options.cache_days)
+    .mode(mode)
+    .compress_mode(compress_mode)
+    .compress_level(options.compress_level);
+    if let Some(cache_dir) = optional_config_str(options.cache_dir, "cache_dir")? {
+        config = config.cache_dir(cache_dir);
+    }
+    if let Some(pub_key) = optional_config_str(options.pub_key, "pub_key")? {
+        config = config.pub_key(pub_key);
+    }
+    config.validate()?;
+    Ok(config)
+}
+
+/// Borrow the logger behind a handle.
+///
+/// # Safety
+/// `handle` must be null or a live pointer from [`xlog_open`] or [`xlog_get`].
+unsafe fn logger<'a>(handle: *const XlogHandle) -> Result<&'a Xlog, CapiError> {
+    handle
+        .as_ref()
+        .map(|handle| &handle.logger)
+        .ok_or_else(|| CapiError::new(XlogStatus::InvalidArgument, "handle must not be null"))
+}
+
+/// Message for the most recent failed call on this thread, or null.
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn xlog_last_error() -> *const c_char {
+    LAST_ERROR.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Fill `options` with defaults: no strings, async mode, zlib level 6.
+///
+/// # Safety
+/// `options` must be null or point to writable memory for one [`XlogOptions`].
+#[no_mangle]
+pub unsafe extern "C" fn xlog_options_init(options: *mut XlogOptions) {
+    if options.is_null() {
+        return;
+    }
+    options.write(XlogOptions {
+        log_dir: ptr::null(),
+        name_prefix: ptr::null(),
+        pub_key: ptr::null(),
+        cache_dir: ptr::null(),
+        cache_days: 0,
+        mode: XlogAppenderMode::Async as i32,
+        compress_mode: XlogCompressMode::Zlib as i32,
+        compress_level: 6,
+    });
+}
+
+/// Check `options` without creating a logger.
+///
+/// Runs the same checks as [`XlogConfig::validate`], directory probes
+/// included; [`xlog_last_error`] lists every issue found.
+///
+/// # Safety
+/// `options` must be null or point to a valid [`XlogOptions`] whose string
+/// fields are null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_options_validate(options: *const XlogOptions) -> XlogStatus {
+    status_of(to_config(options).map(|_| ()))
+}
+
+/// Create or reuse the logger named by `options.name_prefix`.
+///
+/// On success `*out` receives a handle to release with [`xlog_release`].
+///
+/// # Safety
+/// `options` must satisfy [`xlog_options_validate`]'s requirements and `out`
+/// must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_open(
+    options: *const XlogOptions,
+    level: i32,
+    out: *mut *mut XlogHandle,
+) -> XlogStatus {
+    status_of((|| {
+        if out.is_null() {
+            return Err(CapiError::new(
+                XlogStatus::InvalidArgument,
+                "out must not be null",
+            ));
+        }
+        let config = to_config(options)?;
+        let logger = Xlog::init(config, to_level(level)?)?;
+        out.write(Box::into_raw(Box::new(XlogHandle { logger })));
+        Ok(())
+    })())
+}
+
+/// Look up a live logger by name prefix; returns null if none exists.
+///
+/// # Safety
+/// `name_prefix` must be null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_get(name_prefix: *const c_char) -> *mut XlogHandle {
+    if name_prefix.is_null() {
+        return ptr::null_mut();
+    }
+    match Xlog::get(&text(name_prefix)) {
+        Some(logger) => Box::into_raw(Box::new(XlogHandle { logger })),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Release a handle. The logger closes once its last handle is released.
+///
+/// # Safety
+/// `handle` must be null or a pointer from [`xlog_open`]/[`xlog_get`] that
+/// has not been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_release(handle: *mut XlogHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Current minimum level of the logger, or `XLOG_LEVEL_NONE` for a null handle.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_level(handle: *const XlogHandle) -> i32 {
+    logger(handle).map_or(XlogLevel::None, |logger| from_level(logger.level())) as i32
+}
+
+/// Set the logger's minimum level.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_set_level(handle: *const XlogHandle, level: i32) -> XlogStatus {
+    status_of((|| {
+        logger(handle)?.set_level(to_level(level)?);
+        Ok(())
+    })())
+}
+
+/// Whether a record at `level` would be written.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_is_enabled(handle: *const XlogHandle, level: i32) -> bool {
+    match (logger(handle), to_level(level)) {
+        (Ok(logger), Ok(level)) => logger.is_enabled(level),
+        _ => false,
+    }
+}
+
+/// Write one line. A null `tag` uses the logger's name prefix.
+///
+/// # Safety
+/// `handle` must be null or a live handle; strings must be null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_write(
+    handle: *const XlogHandle,
+    level: i32,
+    tag: *const c_char,
+    message: *const c_char,
+) -> XlogStatus {
+    xlog_write_with_meta(handle, level, tag, ptr::null(), ptr::null(), 0, message)
+}
+
+/// Write one line with source location metadata.
+///
+/// # Safety
+/// `handle` must be null or a live handle; strings must be null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_write_with_meta(
+    handle: *const XlogHandle,
+    level: i32,
+    tag: *const c_char,
+    file: *const c_char,
+    function: *const c_char,
+    line: u32,
+    message: *const c_char,
+) -> XlogStatus {
+    status_of((|| {
+        let logger = logger(handle)?;
+        let level = to_level(level)?;
+        if !logger.is_enabled(level) {
+            return Ok(());
+        }
+        let tag = (!tag.is_null()).then(|| text(tag));
+        logger.write_with_meta(
+            level,
+            tag.as_deref(),
+            &text(file),
+            &text(function),
+            line,
+            &text(message),
+        );
+        Ok(())
+    })())
+}
+
+/// Write one line with `count` structured key/value fields appended.
+///
+/// # Safety
+/// `handle` must be null or a live handle; strings must be null or
+/// NUL-terminated; `fields` must point to `count` readable entries when
+/// `count` is non-zero.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_write_kv(
+    handle: *const XlogHandle,
+    level: i32,
+    tag: *const c_char,
+    message: *const c_char,
+    fields: *const XlogField,
+    count: usize,
+) -> XlogStatus {
+    status_of((|| {
+        let logger = logger(handle)?;
+        let level = to_level(level)?;
+        if !logger.is_enabled(level) {
+            return Ok(());
+        }
+        if fields.is_null() && count != 0 {
+            return Err(CapiError::new(
+                XlogStatus::InvalidArgument,
+                "fields must not be null when count is non-zero",
+            ));
+        }
+        let fields = if count == 0 {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(fields, count)
+        };
+        let owned: Vec<_> = fields
+            .iter()
+            .map(|field| (text(field.key), text(field.value)))
+            .collect();
+        let pairs: Vec<(&str, &str)> = owned
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_ref()))
+            .collect();
+        let tag = (!tag.is_null()).then(|| text(tag));
+        logger.write_kv(level, tag.as_deref(), &text(message), &pairs);
+        Ok(())
+    })())
+}
+
+/// Flush the logger's buffer, blocking until written when `sync` is true.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn xlog_flush(handle: *const XlogHandle, sync: bool) -> XlogStatus {
+    status_of((|| {
+        logger(handle)?.flush(sync);
+        Ok(())
+    })())
+}
+
+/// Flush every live logger.
+#[no_mangle]
+pub extern "C" fn xlog_flush_all(sync: bool) {
+    Xlog::flush_all(sync);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::mem::MaybeUninit;
+    use std::ptr;
+
+    use super::*;
+
+    fn options(dir: &CStr, prefix: &CStr) -> XlogOptions {
+        let mut options = MaybeUninit::uninit();
+        unsafe {
+            xlog_options_init(options.as_mut_ptr());
+            let mut options = options.assume_init();
+            options.log_dir = dir.as_ptr();
+            options.name_prefix = prefix.as_ptr();
+            options
+        }
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(xlog_last_error()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn validation_rejects_bad_options_with_messages() {
+        let dir = CString::new("/tmp/xlog-capi").unwrap();
+        let prefix = CString::new("capi").unwrap();
+        let valid = options(&dir, &prefix);
+        unsafe {
+            assert_eq!(xlog_options_validate(&valid), XlogStatus::Ok);
+            assert_eq!(
+                xlog_options_validate(ptr::null()),
+                XlogStatus::InvalidArgument
+            );
+
+            let mut missing = valid;
+            missing.name_prefix = ptr::null();
+            assert_eq!(xlog_options_validate(&missing), XlogStatus::InvalidConfig);
+            assert_eq!(last_error(), "name_prefix is required");
+
+            let mut bad_mode = valid;
+            bad_mode.mode = 7;
+            assert_eq!(xlog_options_validate(&bad_mode), XlogStatus::InvalidConfig);
+
+            let key = CString::new("abcd").unwrap();
+            let mut bad_key = valid;
+            bad_key.pub_key = key.as_ptr();
+            // `CryptoDisabled` when feature unification turns on no-crypt.
+            assert!(matches!(
+                xlog_options_validate(&bad_key),
+                XlogStatus::InvalidPubKey | XlogStatus::CryptoDisabled
+            ));
+            assert!(last_error().starts_with("pub_key "));
+
+            let mut bad_cache = valid;
+            bad_cache.cache_days = -1;
+            bad_cache.compress_level = 42;
+            assert_eq!(xlog_options_validate(&bad_cache), XlogStatus::InvalidConfig);
+            assert_eq!(
+                last_error(),
+                "cache_days is -1; it must not be negative; \
+                 compress_level 42 is outside 0..=9 for Zlib"
+            );
+        }
+    }
+
+    #[test]
+    fn open_write_and_lookup_through_the_registry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = CString::new(tmp.path().to_str().unwrap()).unwrap();
+        let prefix = CString::new("capi_registry").unwrap();
+        let mut opts = options(&dir, &prefix);
+        opts.mode = XlogAppenderMode::Sync as i32;
+
+        unsafe {
+            let mut handle = ptr::null_mut();
+            assert_eq!(
+                xlog_open(&opts, XlogLevel::Info as i32, &mut handle),
+                XlogStatus::Ok
+            );
+            assert!(!handle.is_null());
+            assert!(!xlog_is_enabled(handle, XlogLevel::Debug as i32));
+            assert_eq!(xlog_set_level(handle, 42), XlogStatus::InvalidArgument);
+            assert_eq!(
+                xlog_set_level(handle, XlogLevel::Debug as i32),
+                XlogStatus::Ok
+            );
+
+            let again = xlog_get(prefix.as_ptr());
+            assert!(!again.is_null());
+            assert_eq!(xlog_level(again), XlogLevel::Debug as i32);
+
+            let msg = CString::new("hello from c").unwrap();
+            let key = CString::new("user").unwrap();
+            let value = CString::new("42").unwrap();
+            let fields = [XlogField {
+                key: key.as_ptr(),
+                value: value.as_ptr(),
+            }];
+            assert_eq!(
+                xlog_write(handle, XlogLevel::Info as i32, ptr::null(), msg.as_ptr()),
+                XlogStatus::Ok
+            );
+            assert_eq!(
+                xlog_write_kv(
+                    again,
+                    XlogLevel::Info as i32,
+                    ptr::null(),
+                    msg.as_ptr(),
+                    fields.as_ptr(),
+                    fields.len()
+                ),
+                XlogStatus::Ok
+            );
+            assert_eq!(xlog_flush(handle, true), XlogStatus::Ok);
+
+            xlog_release(again);
+            xlog_release(handle);
+        }
+        assert!(unsafe { xlog_get(prefix.as_ptr()) }.is_null());
+    }
+}
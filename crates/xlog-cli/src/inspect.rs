@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use mars_xlog_core::decoder::{scan, BlockInfo, Decoder, ScannedBlock, SeqTracker};
+use mars_xlog_core::log_entry::entries;
+use mars_xlog_core::protocol::{
+    MAGIC_ASYNC_NO_CRYPT_ZLIB_START, MAGIC_ASYNC_NO_CRYPT_ZSTD_START, MAGIC_ASYNC_ZLIB_START,
+    MAGIC_ASYNC_ZSTD_START, MAGIC_LEGACY_COMPRESS_START, MAGIC_LEGACY_COMPRESS_START1,
+    MAGIC_LEGACY_NO_COMPRESS_START, MAGIC_SYNC_NO_CRYPT_ZLIB_START, MAGIC_SYNC_NO_CRYPT_ZSTD_START,
+    MAGIC_SYNC_ZLIB_START, MAGIC_SYNC_ZSTD_START,
+};
+
+use crate::{CliResult, KeyArgs};
+
+#[derive(Args)]
+pub(crate) struct InspectArgs {
+    /// xlog file to inspect.
+    file: PathBuf,
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+pub(crate) fn run(args: &InspectArgs) -> CliResult {
+    let decoder = args.key.decoder()?;
+    let file = File::open(&args.file)?;
+    // SAFETY: the map is read-only and only lives for this command.
+    let input = unsafe { memmap2::Mmap::map(&file)? };
+
+    let mut out = io::stdout().lock();
+    writeln!(
+        out,
+        "file:       {} ({} bytes)",
+        args.file.display(),
+        input.len()
+    )?;
+    write_report(&input, &decoder, &mut out)?;
+    Ok(())
+}
+
+/// Append mode, compression, and crypt format encoded by a start magic.
+fn describe_magic(magic: u8) -> (&'static str, &'static str, &'static str) {
+    match magic {
+        MAGIC_LEGACY_NO_COMPRESS_START => ("legacy", "none", "v1 header, unencrypted"),
+        MAGIC_LEGACY_COMPRESS_START => ("legacy", "zlib", "v1 header, unencrypted"),
+        MAGIC_LEGACY_COMPRESS_START1 => ("legacy", "zlib (chunked)", "v1 header, unencrypted"),
+        // Sync blocks are always written uncompressed and in plaintext.
+        MAGIC_SYNC_ZLIB_START | MAGIC_SYNC_ZSTD_START => ("sync", "none", "ecdh+tea capable"),
+        MAGIC_SYNC_NO_CRYPT_ZLIB_START | MAGIC_SYNC_NO_CRYPT_ZSTD_START => ("sync", "none", "none"),
+        MAGIC_ASYNC_ZLIB_START => ("async", "zlib", "ecdh+tea"),
+        MAGIC_ASYNC_ZSTD_START => ("async", "zstd", "ecdh+tea"),
+        MAGIC_ASYNC_NO_CRYPT_ZLIB_START => ("async", "zlib", "none"),
+        MAGIC_ASYNC_NO_CRYPT_ZSTD_START => ("async", "zstd", "none"),
+        _ => ("unknown", "unknown", "unknown"),
+    }
+}
+
+fn write_report(input: &[u8], decoder: &Decoder, out: &mut dyn Write) -> io::Result<()> {
+    let mut blocks: Vec<BlockInfo> = Vec::new();
+    let mut skipped = Vec::new();
+    for item in scan(input) {
+        match item {
+            ScannedBlock::Block(block) => blocks.push(block),
+            ScannedBlock::Skipped { offset, len } => skipped.push((offset, len)),
+        }
+    }
+
+    let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else {
+        writeln!(out, "blocks:     0")?;
+        return writeln!(out, "integrity:  no decodable blocks found");
+    };
+    writeln!(
+        out,
+        "header:     magic {:#04x}, seq {}, hours {}-{}, payload {} bytes{}",
+        first.magic,
+        first.seq,
+        first.begin_hour,
+        first.end_hour,
+        first.payload_len,
+        if first.encrypted {
+            ", client key present"
+        } else {
+            ""
+        }
+    )?;
+    writeln!(out, "blocks:     {}", blocks.len())?;
+
+    let mut formats: BTreeMap<u8, (usize, usize)> = BTreeMap::new();
+    for block in &blocks {
+        let (count, encrypted) = formats.entry(block.magic).or_default();
+        *count += 1;
+        *encrypted += usize::from(block.encrypted);
+    }
+    for (magic, (count, encrypted)) in formats {
+        let (mode, compression, crypt) = describe_magic(magic);
+        writeln!(
+            out,
+            "format:     {magic:#04x} {mode}, compression {compression}, crypt {crypt}: \
+             {count} blocks, {encrypted} encrypted"
+        )?;
+    }
+
+    writeln!(
+        out,
+        "start:      {}",
+        entry_time(input, decoder, first, true)
+    )?;
+    writeln!(
+        out,
+        "end:        {}",
+        entry_time(input, decoder, last, false)
+    )?;
+
+    let gaps = seq_gaps(&blocks);
+    if gaps.is_empty() && skipped.is_empty() {
+        return writeln!(out, "integrity:  ok");
+    }
+    for (offset, len) in skipped {
+        writeln!(
+            out,
+            "integrity:  {len} unframed bytes skipped at offset {offset}"
+        )?;
+    }
+    for (from, to) in gaps {
+        writeln!(out, "integrity:  log seq:{from}-{to} is missing")?;
+    }
+    Ok(())
+}
+
+/// Time of the first or last entry in `block`, falling back to the header hours.
+fn entry_time(input: &[u8], decoder: &Decoder, block: &BlockInfo, first: bool) -> String {
+    let hour = if first {
+        block.begin_hour
+    } else {
+        block.end_hour
+    };
+    let plain = match decoder.decode_block(input, block) {
+        Ok(plain) => plain,
+        Err(err) => return format!("hour {hour} (block not decodable: {err})"),
+    };
+    let text = String::from_utf8_lossy(&plain);
+    let mut times = entries(&text).filter_map(|entry| entry.time);
+    let time = if first { times.next() } else { times.last() };
+    match time {
+        Some(time) => time.to_string(),
+        None => format!("hour {hour} (no timestamped entries)"),
+    }
+}
+
+/// Missing async sequence ranges, as the decoder's markers report them.
+fn seq_gaps(blocks: &[BlockInfo]) -> Vec<(u16, u16)> {
+    let mut seq = SeqTracker::new();
+    blocks
+        .iter()
+        .filter_map(|block| seq.check(block.seq))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use mars_xlog_core::decoder::Decoder;
+    use mars_xlog_core::protocol::{
+        LogHeader, MAGIC_ASYNC_ZSTD_START, MAGIC_END, MAGIC_SYNC_NO_CRYPT_ZLIB_START,
+    };
+
+    use super::write_report;
+
+    fn block(magic: u8, seq: u16, client_pubkey: [u8; 64], payload: &[u8]) -> Vec<u8> {
+        let header = LogHeader {
+            magic,
+            seq,
+            begin_hour: 9,
+            end_hour: 10,
+            len: payload.len() as u32,
+            client_pubkey,
+        };
+        let mut out = header.encode().to_vec();
+        out.extend_from_slice(payload);
+        out.push(MAGIC_END);
+        out
+    }
+
+    #[test]
+    fn reports_formats_times_and_integrity_gaps() {
+        let line = |time: &str| format!("[I][2024-01-02 +8.0 {time}][1, 2][t][a.rs:1, f][m\n");
+        let mut input = block(
+            MAGIC_SYNC_NO_CRYPT_ZLIB_START,
+            0,
+            [0; 64],
+            (line("09:00:00.000") + &line("09:30:00.000")).as_bytes(),
+        );
+        input.extend(block(MAGIC_ASYNC_ZSTD_START, 1, [7; 64], &[0; 8]));
+        input.extend(block(MAGIC_ASYNC_ZSTD_START, 4, [7; 64], &[0; 8]));
+        input.extend(b"junk");
+
+        let mut out = Vec::new();
+        write_report(&input, &Decoder::new(), &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+
+        assert!(report.contains("blocks:     3\n"), "{report}");
+        assert!(report.contains("0x08 sync, compression none, crypt none: 1 blocks, 0 encrypted"));
+        assert!(
+            report.contains("0x0c async, compression zstd, crypt ecdh+tea: 2 blocks, 2 encrypted")
+        );
+        assert!(report.contains("start:      2024-01-02 +8.0 09:00:00.000\n"));
+        assert!(report.contains("end:        hour 10 (block not decodable"));
+        assert!(report.contains("integrity:  4 unframed bytes skipped"));
+        assert!(report.contains("integrity:  log seq:2-3 is missing"));
+    }
+}
@@ -0,0 +1,110 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use mars_xlog_core::crypto::generate_server_keypair;
+use mars_xlog_core::decoder::Decoder;
+
+use crate::{CliResult, OutputArgs};
+
+#[derive(Args)]
+pub(crate) struct KeygenArgs {
+    /// Where to write the hex private key.
+    #[arg(long, default_value = "xlog_private.key")]
+    out: PathBuf,
+    /// Overwrite an existing private key file.
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+pub(crate) struct DecryptArgs {
+    /// Encrypted xlog file to decode.
+    file: PathBuf,
+    /// Private key file written by `xlog-cli keygen`.
+    #[arg(short, long)]
+    key_file: PathBuf,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+/// Write a new private key file and print the matching `pub_key` hex.
+pub(crate) fn keygen(args: &KeygenArgs) -> CliResult {
+    let (private_key, public_hex) = generate_server_keypair();
+    write_private_key(&args.out, &hex_line(&private_key), args.force).map_err(|err| {
+        if err.kind() == ErrorKind::AlreadyExists {
+            format!(
+                "{} already exists, pass --force to overwrite",
+                args.out.display()
+            )
+        } else {
+            format!("write {}: {err}", args.out.display())
+        }
+    })?;
+    eprintln!("private key written to {}", args.out.display());
+    println!("{public_hex}");
+    Ok(())
+}
+
+pub(crate) fn decrypt(args: &DecryptArgs) -> CliResult {
+    let key = fs::read_to_string(&args.key_file)?;
+    let decoder = Decoder::new().private_key_hex(key.trim())?;
+    let mut out = args.output.open()?;
+    decoder.decode_file_to(&args.file, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn hex_line(private_key: &[u8; 32]) -> String {
+    let mut line: String = private_key
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    line.push('\n');
+    line
+}
+
+fn write_private_key(path: &Path, contents: &str, force: bool) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{hex_line, write_private_key};
+
+    #[test]
+    fn private_key_files_are_not_overwritten_without_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("xlog.key");
+        let first = hex_line(&[0x11; 32]);
+
+        write_private_key(&path, &first, false).unwrap();
+        assert!(write_private_key(&path, &hex_line(&[0x22; 32]), false).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), first);
+
+        write_private_key(&path, &hex_line(&[0x22; 32]), true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().trim(), "22".repeat(32));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o077, 0);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! `xlog-cli`: command-line tools for decoding and triaging xlog files.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use mars_xlog_core::decoder::{DecodeError, Decoder};
+
+mod inspect;
+mod keys;
+mod merge;
+mod stats;
+
+type CliResult = Result<(), Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[command(name = "xlog-cli", version, about = "Decode and triage xlog files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decode one xlog file to plaintext.
+    Decode {
+        /// xlog file to decode.
+        file: PathBuf,
+        #[command(flatten)]
+        key: KeyArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Decode several files and merge their entries with a source column.
+    Merge(merge::MergeArgs),
+    /// Generate a server key pair: writes the private key file and prints the `pub_key` hex.
+    Keygen(keys::KeygenArgs),
+    /// Decode an encrypted xlog file with a private key file from `keygen`.
+    Decrypt(keys::DecryptArgs),
+    /// Summarize a file's block framing, formats, time range, and gaps without a full decode.
+    Inspect(inspect::InspectArgs),
+    /// Count entries by level and tag, list the noisiest tags, and report time coverage.
+    Stats(stats::StatsArgs),
+}
+
+#[derive(Args)]
+struct KeyArgs {
+    /// Server private key (64 hex chars) used to decrypt encrypted blocks.
+    #[arg(long, env = "XLOG_PRIVATE_KEY", hide_env_values = true)]
+    private_key: Option<String>,
+}
+
+impl KeyArgs {
+    fn decoder(&self) -> Result<Decoder, DecodeError> {
+        Decoder::new().private_key_hex(self.private_key.as_deref().unwrap_or_default())
+    }
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Write to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    fn open(&self) -> io::Result<Box<dyn Write>> {
+        open_output(self.output.as_deref())
+    }
+}
+
+fn open_output(path: Option<&Path>) -> io::Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    })
+}
+
+fn decode(file: &Path, key: &KeyArgs, output: &OutputArgs) -> CliResult {
+    let decoder = key.decoder()?;
+    let mut out = output.open()?;
+    decoder.decode_file_to(file, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Command::Decode { file, key, output } => decode(file, key, output),
+        Command::Merge(args) => merge::run(args),
+        Command::Keygen(args) => keys::keygen(args),
+        Command::Decrypt(args) => keys::decrypt(args),
+        Command::Inspect(args) => inspect::run(args),
+        Command::Stats(args) => stats::run(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("xlog-cli: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
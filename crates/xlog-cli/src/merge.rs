@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use mars_xlog_core::log_entry::entries;
+
+use crate::{CliResult, KeyArgs, OutputArgs};
+
+#[derive(Args)]
+pub(crate) struct MergeArgs {
+    /// xlog files to merge, e.g. one per process or instance.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Interleave entries chronologically instead of concatenating files.
+    #[arg(long)]
+    by_time: bool,
+    #[command(flatten)]
+    key: KeyArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+struct Source {
+    name: String,
+    text: String,
+}
+
+pub(crate) fn run(args: &MergeArgs) -> CliResult {
+    let decoder = args.key.decoder()?;
+    let names = source_names(&args.files);
+    let mut sources = Vec::with_capacity(args.files.len());
+    for (path, name) in args.files.iter().zip(names) {
+        let decoded = decoder.decode_file(path)?;
+        sources.push(Source {
+            name,
+            text: String::from_utf8_lossy(&decoded).into_owned(),
+        });
+    }
+
+    let mut out = args.output.open()?;
+    write_merged(&sources, args.by_time, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Label sources by file name, falling back to the full path when names collide.
+fn source_names(files: &[PathBuf]) -> Vec<String> {
+    let file_name = |path: &Path| {
+        path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    };
+    let unique: HashSet<String> = files.iter().map(|path| file_name(path)).collect();
+    if unique.len() == files.len() {
+        files.iter().map(|path| file_name(path)).collect()
+    } else {
+        files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect()
+    }
+}
+
+/// Write every entry prefixed with its source, optionally sorted by timestamp.
+///
+/// Sorting is stable, so entries with equal timestamps keep their source
+/// order, and lines without a timestamp stay behind the entry before them.
+fn write_merged(sources: &[Source], by_time: bool, out: &mut dyn Write) -> io::Result<()> {
+    let mut rows = Vec::new();
+    for (index, source) in sources.iter().enumerate() {
+        let mut key = None;
+        for entry in entries(&source.text) {
+            if let Some(entry_key) = entry.time_key() {
+                key = Some(entry_key);
+            }
+            rows.push((key, index, entry.raw));
+        }
+    }
+    if by_time {
+        rows.sort_by_key(|(key, _, _)| *key);
+    }
+
+    let width = sources
+        .iter()
+        .map(|source| source.name.len())
+        .max()
+        .unwrap_or(0);
+    for (_, index, raw) in rows {
+        let name = &sources[index].name;
+        for line in raw.lines() {
+            writeln!(out, "{name:<width$} | {line}")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{source_names, write_merged, Source};
+
+    fn line(time: &str, msg: &str) -> String {
+        format!("[I][2024-01-02 +8.0 {time}][1, 2][t][a.rs:1, f][{msg}\n")
+    }
+
+    #[test]
+    fn interleaves_sources_by_time_with_source_column() {
+        let sources = [
+            Source {
+                name: "main".into(),
+                text: line("10:00:00.000", "m1") + &line("10:00:02.000", "m2"),
+            },
+            Source {
+                name: "push".into(),
+                text: line("10:00:01.000", "p1") + "continued\n",
+            },
+        ];
+
+        let mut out = Vec::new();
+        write_merged(&sources, true, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let columns: Vec<_> = out
+            .lines()
+            .map(|row| {
+                let (name, line) = row.split_once(" | ").unwrap();
+                (name, line.rsplit("][").next().unwrap())
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("main", "m1"),
+                ("push", "p1"),
+                ("push", "continued"),
+                ("main", "m2")
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_paths_when_file_names_collide() {
+        let files = [
+            PathBuf::from("a/app_20240102.xlog"),
+            PathBuf::from("b/app_20240102.xlog"),
+        ];
+        assert_eq!(
+            source_names(&files),
+            ["a/app_20240102.xlog", "b/app_20240102.xlog"]
+        );
+        assert_eq!(source_names(&files[..1]), ["app_20240102.xlog"]);
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use mars_xlog_core::log_entry::{entries, LogEntry};
+use mars_xlog_core::record::LogLevel;
+
+use crate::{CliResult, KeyArgs};
+
+/// Every level an entry can carry, in report order; `N` entries come from
+/// writers that log at `LogLevel::None`.
+const LEVELS: [LogLevel; 7] = [
+    LogLevel::Verbose,
+    LogLevel::Debug,
+    LogLevel::Info,
+    LogLevel::Warn,
+    LogLevel::Error,
+    LogLevel::Fatal,
+    LogLevel::None,
+];
+
+#[derive(Args)]
+pub(crate) struct StatsArgs {
+    /// xlog file, or a directory whose `*.xlog` files are all counted.
+    path: PathBuf,
+    /// Number of tags listed in the noisiest-tags table.
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+    #[command(flatten)]
+    key: KeyArgs,
+}
+
+#[derive(Default)]
+struct TagStats {
+    entries: usize,
+    bytes: usize,
+    levels: [usize; LEVELS.len()],
+}
+
+#[derive(Default)]
+struct Stats {
+    files: usize,
+    file_bytes: u64,
+    entries: usize,
+    unparsed: usize,
+    levels: [usize; LEVELS.len()],
+    tags: HashMap<String, TagStats>,
+    first: Option<((String, String), String)>,
+    last: Option<((String, String), String)>,
+}
+
+impl Stats {
+    fn add_text(&mut self, text: &str) {
+        for entry in entries(text) {
+            self.add_entry(&entry);
+        }
+    }
+
+    fn add_entry(&mut self, entry: &LogEntry<'_>) {
+        let Some(level) = entry.level else {
+            self.unparsed += 1;
+            return;
+        };
+        let slot = LEVELS
+            .iter()
+            .position(|l| *l == level)
+            .expect("LEVELS lists every level");
+        self.entries += 1;
+        self.levels[slot] += 1;
+        let tag = self.tags.entry(entry.tag.to_string()).or_default();
+        tag.entries += 1;
+        tag.bytes += entry.raw.len();
+        tag.levels[slot] += 1;
+
+        if let (Some((date, clock)), Some(time)) = (entry.time_key(), entry.time) {
+            let key = (date.to_string(), clock.to_string());
+            if self.first.as_ref().is_none_or(|(first, _)| key < *first) {
+                self.first = Some((key.clone(), time.to_string()));
+            }
+            if self.last.as_ref().is_none_or(|(last, _)| key > *last) {
+                self.last = Some((key, time.to_string()));
+            }
+        }
+    }
+
+    fn write_report(&self, top: usize, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "files:      {} ({} bytes)",
+            self.files, self.file_bytes
+        )?;
+        writeln!(
+            out,
+            "entries:    {} ({} unparsed lines)",
+            self.entries, self.unparsed
+        )?;
+        match (&self.first, &self.last) {
+            (Some((_, first)), Some((_, last))) => writeln!(out, "time:       {first} .. {last}")?,
+            _ => writeln!(out, "time:       no timestamped entries")?,
+        }
+        write!(out, "levels:    ")?;
+        for (level, count) in LEVELS.iter().zip(self.levels) {
+            write!(out, " {} {count}", level.short())?;
+        }
+        writeln!(out)?;
+
+        let mut tags: Vec<(&String, &TagStats)> = self.tags.iter().collect();
+        tags.sort_by(|a, b| b.1.entries.cmp(&a.1.entries).then_with(|| a.0.cmp(b.0)));
+        writeln!(
+            out,
+            "tags:       {} distinct, top {}",
+            tags.len(),
+            top.min(tags.len())
+        )?;
+        writeln!(
+            out,
+            "  {:>9} {:>6} {:>11}  levels (V/D/I/W/E/F/N)  tag",
+            "entries", "share", "bytes"
+        )?;
+        for (name, tag) in tags.into_iter().take(top) {
+            let share = tag.entries as f64 * 100.0 / self.entries.max(1) as f64;
+            let levels: Vec<String> = tag.levels.iter().map(usize::to_string).collect();
+            writeln!(
+                out,
+                "  {:>9} {:>5.1}% {:>11}  {:<20}  {}",
+                tag.entries,
+                share,
+                tag.bytes,
+                levels.join("/"),
+                if name.is_empty() { "(none)" } else { name }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn run(args: &StatsArgs) -> CliResult {
+    let decoder = args.key.decoder()?;
+    let mut stats = Stats::default();
+    for file in input_files(&args.path)? {
+        stats.files += 1;
+        stats.file_bytes += fs::metadata(&file)?.len();
+        let decoded = decoder.decode_file(&file)?;
+        stats.add_text(&String::from_utf8_lossy(&decoded));
+    }
+
+    let mut out = io::stdout().lock();
+    stats.write_report(args.top, &mut out)?;
+    Ok(())
+}
+
+/// `path` itself, or the sorted `*.xlog` files directly inside it.
+fn input_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let file = entry?.path();
+        if file.is_file() && file.extension().is_some_and(|ext| ext == "xlog") {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stats;
+
+    fn line(level: &str, time: &str, tag: &str, msg: &str) -> String {
+        format!("[{level}][2024-01-02 +8.0 {time}][1, 2][{tag}][a.rs:1, f][{msg}\n")
+    }
+
+    #[test]
+    fn counts_levels_tags_and_time_coverage() {
+        let mut stats = Stats::default();
+        stats.add_text(
+            &(line("I", "10:00:01.000", "net", "a")
+                + &line("E", "10:00:00.000", "net", "bb")
+                + "continued\n"
+                + &line("D", "11:30:00.000", "ui", "c")
+                + &line("N", "11:00:00.000", "net", "d")),
+        );
+        stats.add_text("[F]xlog decoder: [1 2] decrypt failed\n");
+        stats.files = 2;
+
+        let mut out = Vec::new();
+        stats.write_report(1, &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+
+        assert!(
+            report.contains("entries:    4 (1 unparsed lines)\n"),
+            "{report}"
+        );
+        assert!(report.contains(
+            "time:       2024-01-02 +8.0 10:00:00.000 .. 2024-01-02 +8.0 11:30:00.000\n"
+        ));
+        assert!(report.contains("levels:     V 0 D 1 I 1 W 0 E 1 F 0 N 1\n"));
+        assert!(report.contains("tags:       2 distinct, top 1\n"));
+        let net = report.lines().last().unwrap();
+        assert!(net.ends_with("  net"), "{report}");
+        assert!(net.contains(" 75.0% "));
+        assert!(net.contains("0/0/1/0/1/0/1"));
+        assert!(!report.contains(" ui\n"));
+    }
+}
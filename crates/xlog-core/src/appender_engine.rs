@@ -1,5 +1,5 @@
 use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicU8, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
@@ -24,7 +24,8 @@ const ASYNC_PENDING_MMAP_PERSIST_INTERVAL: Duration = Duration::from_millis(500)
 const ASYNC_FLUSH_RETRY_DELAY: Duration = Duration::from_micros(100);
 const ASYNC_BUFFER_FLUSH_THRESHOLD_NUM: usize = 1;
 const ASYNC_BUFFER_FLUSH_THRESHOLD_DEN: usize = 3;
-const MIN_LOG_ALIVE_SECONDS: i64 = 24 * 60 * 60;
+/// Shortest retention accepted for log files, one day as in Mars.
+pub const MIN_LOG_ALIVE_SECONDS: i64 = 24 * 60 * 60;
 const EXPIRED_SWEEP_INTERVAL: Duration = Duration::from_secs(2 * 60);
 const CACHE_MOVE_INTERVAL: Duration = Duration::from_secs(3 * 60);
 
@@ -71,6 +72,21 @@ pub enum AppenderEngineError {
     InvalidMode,
 }
 
+/// Callback notified of write, flush, and housekeeping failures.
+///
+/// Engine operations keep best-effort semantics and never stop on errors;
+/// this lets embedders observe degraded logging (disk full, permissions).
+pub type EngineErrorHandler = Arc<dyn Fn(&AppenderEngineError) + Send + Sync>;
+
+type SharedErrorHandler = Arc<RwLock<Option<EngineErrorHandler>>>;
+
+fn report_error(handler: &RwLock<Option<EngineErrorHandler>>, err: &AppenderEngineError) {
+    let handler = handler.read().ok().and_then(|guard| guard.clone());
+    if let Some(handler) = handler {
+        handler(err);
+    }
+}
+
 struct EngineState {
     file_manager: FileManager,
     buffer: PersistentBuffer,
@@ -111,6 +127,7 @@ pub struct AppenderEngine {
     async_flush_epoch: Arc<AtomicU64>,
     async_flush_reason: Arc<AtomicU8>,
     async_flush_requeue_count: Arc<AtomicU64>,
+    error_handler: SharedErrorHandler,
     worker: Mutex<Option<JoinHandle<()>>>,
 }
 
@@ -178,6 +195,8 @@ impl AppenderEngine {
         let worker_flush_epoch = Arc::clone(&async_flush_epoch);
         let worker_flush_reason = Arc::clone(&async_flush_reason);
         let worker_flush_requeue_count = Arc::clone(&async_flush_requeue_count);
+        let error_handler: SharedErrorHandler = Arc::new(RwLock::new(None));
+        let worker_error_handler = Arc::clone(&error_handler);
         let worker = thread::Builder::new()
             .name("xlog-appender-engine".to_string())
             .spawn(move || {
@@ -189,6 +208,7 @@ impl AppenderEngine {
                     async_flush_epoch: worker_flush_epoch,
                     async_flush_reason: worker_flush_reason,
                     async_flush_requeue_count: worker_flush_requeue_count,
+                    error_handler: worker_error_handler,
                     flush_timeout,
                 })
             })
@@ -206,10 +226,21 @@ impl AppenderEngine {
             async_flush_epoch,
             async_flush_reason,
             async_flush_requeue_count,
+            error_handler,
             worker: Mutex::new(Some(worker)),
         }
     }
 
+    /// Install (or clear with `None`) the handler notified of engine failures.
+    ///
+    /// The handler runs on whichever thread hit the error, including the
+    /// engine worker, so it should return quickly.
+    pub fn set_error_handler(&self, handler: Option<EngineErrorHandler>) {
+        if let Ok(mut slot) = self.error_handler.write() {
+            *slot = handler;
+        }
+    }
+
     /// Return the current engine mode.
     pub fn mode(&self) -> EngineMode {
         engine_mode_from_u8(self.mode.load(Ordering::Relaxed))
@@ -250,6 +281,17 @@ impl AppenderEngine {
         state.max_alive_time = alive_seconds;
     }
 
+    /// Move cache files older than `cache_days` into the log directory now
+    /// instead of waiting for the periodic housekeeping pass.
+    pub fn move_old_cache_files(&self) -> Result<(), AppenderEngineError> {
+        let mut state = self.state.lock().expect("state lock poisoned");
+        state
+            .file_manager
+            .move_old_cache_files(state.max_file_size)?;
+        state.last_cache_move_at = Instant::now();
+        Ok(())
+    }
+
     /// Return the current max logfile size.
     pub fn max_file_size(&self) -> u64 {
         self.max_file_size.load(Ordering::Relaxed)
@@ -284,6 +326,18 @@ impl AppenderEngine {
     /// async mode the block is appended to the mmap-backed buffer and may
     /// trigger a worker flush when thresholds are exceeded.
     pub fn write_block(&self, block: &[u8], force_flush: bool) -> Result<(), AppenderEngineError> {
+        let result = self.write_block_inner(block, force_flush);
+        if let Err(err) = &result {
+            report_error(&self.error_handler, err);
+        }
+        result
+    }
+
+    fn write_block_inner(
+        &self,
+        block: &[u8],
+        force_flush: bool,
+    ) -> Result<(), AppenderEngineError> {
         validate_block(block)?;
         let write_begin = Instant::now();
         let mode_snapshot = self.mode();
@@ -656,6 +710,7 @@ struct WorkerLoopCtx {
     async_flush_epoch: Arc<AtomicU64>,
     async_flush_reason: Arc<AtomicU8>,
     async_flush_requeue_count: Arc<AtomicU64>,
+    error_handler: SharedErrorHandler,
     flush_timeout: Duration,
 }
 
@@ -668,8 +723,10 @@ fn run_worker_loop(ctx: WorkerLoopCtx) {
         async_flush_epoch,
         async_flush_reason,
         async_flush_requeue_count,
+        error_handler,
         flush_timeout,
     } = ctx;
+    let report = |err: AppenderEngineError| report_error(&error_handler, &err);
     let poll_interval = flush_timeout.min(EXPIRED_SWEEP_INTERVAL);
     loop {
         match rx.recv_timeout(poll_interval) {
@@ -684,7 +741,7 @@ fn run_worker_loop(ctx: WorkerLoopCtx) {
                         .lock()
                         .map_err(|_| ())
                         .and_then(|mut s| {
-                            flush_pending_locked(&mut s, move_file, false).map_err(|_| ())
+                            flush_pending_locked(&mut s, move_file, false).map_err(report)
                         })
                         .unwrap_or(false)
                 } else {
@@ -692,7 +749,7 @@ fn run_worker_loop(ctx: WorkerLoopCtx) {
                         Ok(mut s) => {
                             pending_async_flush.store(false, Ordering::Release);
                             flush_pending_locked(&mut s, move_file, false)
-                                .map_err(|_| ())
+                                .map_err(report)
                                 .unwrap_or(false)
                         }
                         Err(_) => {
@@ -721,8 +778,8 @@ fn run_worker_loop(ctx: WorkerLoopCtx) {
                     .lock()
                     .map_err(|_| ())
                     .and_then(|mut s| {
-                        let flushed = flush_pending_locked(&mut s, true, false).map_err(|_| ())?;
-                        maybe_housekeep_locked(&mut s, true).map_err(|_| ())?;
+                        let flushed = flush_pending_locked(&mut s, true, false).map_err(report)?;
+                        maybe_housekeep_locked(&mut s, true).map_err(report)?;
                         Ok(flushed)
                     })
                     .unwrap_or(false);
@@ -740,7 +797,7 @@ fn run_worker_loop(ctx: WorkerLoopCtx) {
                 let flushed = state
                     .try_lock()
                     .map_err(|_| ())
-                    .and_then(|mut s| handle_timeout_locked(&mut s, flush_timeout).map_err(|_| ()))
+                    .and_then(|mut s| handle_timeout_locked(&mut s, flush_timeout).map_err(report))
                     .unwrap_or(false);
                 if flushed {
                     record_engine_timeout_flush();
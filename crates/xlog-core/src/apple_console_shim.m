@@ -39,8 +39,11 @@ void xlog_core_apple_console_nslog(const char* text) {
     }
 }
 
+// `privacy` matches `AppleOsLogPrivacy`: 0 public, 1 private message, 2 all private.
+// os_log needs literal format strings, so each level of privacy gets its own call.
 void xlog_core_apple_console_oslog(
     int level,
+    int privacy,
     const char* tag,
     const char* file,
     int line,
@@ -52,14 +55,41 @@ void xlog_core_apple_console_oslog(
     }
     @autoreleasepool {
         os_log_t log_t = os_log_create("", safe_cstr(tag));
-        os_log_with_type(
-            log_t,
-            to_oslog_type(level),
-            "[%{public}s:%d, %{public}s][%{public}s",
-            safe_cstr(file),
-            line,
-            safe_cstr(func),
-            safe_cstr(msg)
-        );
+        os_log_type_t type = to_oslog_type(level);
+        switch (privacy) {
+            case 1:
+                os_log_with_type(
+                    log_t,
+                    type,
+                    "[%{public}s:%d, %{public}s][%{private}s",
+                    safe_cstr(file),
+                    line,
+                    safe_cstr(func),
+                    safe_cstr(msg)
+                );
+                break;
+            case 2:
+                os_log_with_type(
+                    log_t,
+                    type,
+                    "[%{private}s:%d, %{private}s][%{private}s",
+                    safe_cstr(file),
+                    line,
+                    safe_cstr(func),
+                    safe_cstr(msg)
+                );
+                break;
+            default:
+                os_log_with_type(
+                    log_t,
+                    type,
+                    "[%{public}s:%d, %{public}s][%{public}s",
+                    safe_cstr(file),
+                    line,
+                    safe_cstr(func),
+                    safe_cstr(msg)
+                );
+                break;
+        }
     }
 }
@@ -140,6 +140,27 @@ impl EcdhTeaCipher {
     }
 }
 
+/// Generates a server key pair for encrypted logs.
+///
+/// Returns the 32-byte private key kept by the decoding side and the
+/// 128-char public key hex that writers take as their `pub_key`.
+pub fn generate_server_keypair() -> ([u8; 32], String) {
+    let secret = SecretKey::random(&mut OsRng);
+    let public_hex = encode_uncompressed_pubkey(&secret.public_key());
+    (secret.to_bytes().into(), public_hex)
+}
+
+/// Derives the 128-char public key hex for a server private key.
+pub fn server_pubkey_hex(private_key: &[u8; 32]) -> Result<String, CryptoError> {
+    let secret = SecretKey::from_slice(private_key).map_err(|_| CryptoError::InvalidKeyMaterial)?;
+    Ok(encode_uncompressed_pubkey(&secret.public_key()))
+}
+
+fn encode_uncompressed_pubkey(public_key: &PublicKey) -> String {
+    let point = public_key.to_encoded_point(false);
+    hex::encode(&point.as_bytes()[1..])
+}
+
 fn decode_uncompressed_pubkey(server_pubkey_hex: &str) -> Result<PublicKey, CryptoError> {
     if server_pubkey_hex.len() != 128 {
         return Err(CryptoError::InvalidServerPubkeyLength);
@@ -210,7 +231,10 @@ pub fn tea_decrypt_in_place(data: &mut [u8], key: &[u32; 4]) {
 
 #[cfg(test)]
 mod tests {
-    use super::{tea_decrypt_in_place, CryptoError, EcdhTeaCipher};
+    use super::{
+        generate_server_keypair, server_pubkey_hex, tea_decrypt_in_place, CryptoError,
+        EcdhTeaCipher,
+    };
 
     const SAMPLE_PUBKEY: &str =
         "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
@@ -231,6 +255,21 @@ mod tests {
         assert!(!EcdhTeaCipher::new("").unwrap().enabled());
     }
 
+    #[test]
+    fn server_keypairs_derive_usable_public_keys() {
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        assert_eq!(server_pubkey_hex(&one).unwrap(), SAMPLE_PUBKEY);
+        assert!(matches!(
+            server_pubkey_hex(&[0; 32]),
+            Err(CryptoError::InvalidKeyMaterial)
+        ));
+
+        let (private_key, public_hex) = generate_server_keypair();
+        assert_eq!(server_pubkey_hex(&private_key).unwrap(), public_hex);
+        assert!(EcdhTeaCipher::new(&public_hex).unwrap().enabled());
+    }
+
     #[test]
     fn constructor_rejects_invalid_key_material() {
         assert!(matches!(
@@ -0,0 +1,1284 @@
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use thiserror::Error;
+
+use crate::crypto::{tea_decrypt_in_place, EcdhTeaCipher};
+use crate::log_entry::entries;
+use crate::protocol::{
+    magic_start_is_valid, CompressionKind, LogHeader, HEADER_LEN, MAGIC_ASYNC_NO_CRYPT_ZLIB_START,
+    MAGIC_ASYNC_NO_CRYPT_ZSTD_START, MAGIC_ASYNC_ZLIB_START, MAGIC_ASYNC_ZSTD_START, MAGIC_END,
+    MAGIC_LEGACY_COMPRESS_START, MAGIC_LEGACY_COMPRESS_START1, MAGIC_LEGACY_NO_COMPRESS_START,
+    MAGIC_SYNC_NO_CRYPT_ZLIB_START, MAGIC_SYNC_NO_CRYPT_ZSTD_START, MAGIC_SYNC_ZLIB_START,
+    MAGIC_SYNC_ZSTD_START, TAILER_LEN,
+};
+
+const TEA_BLOCK_LEN: usize = 8;
+
+/// Header length of the legacy formats: the key field is 4 bytes instead of 64.
+const LEGACY_HEADER_LEN: usize = HEADER_LEN - 64 + 4;
+/// Cap on what one block may inflate to. Writers flush at most a 150 KiB
+/// buffer per block, so anything past this is a decompression bomb.
+const MAX_INFLATED_BLOCK_LEN: usize = 32 * 1024 * 1024;
+
+fn is_legacy_magic(magic: u8) -> bool {
+    matches!(
+        magic,
+        MAGIC_LEGACY_NO_COMPRESS_START | MAGIC_LEGACY_COMPRESS_START | MAGIC_LEGACY_COMPRESS_START1
+    )
+}
+
+/// Block header as seen by the decoder, covering both current and legacy layouts.
+struct BlockHeader {
+    magic: u8,
+    seq: u16,
+    begin_hour: u8,
+    end_hour: u8,
+    header_len: usize,
+    len: usize,
+    /// All zeros for legacy blocks, which never carry an ECDH public key.
+    client_pubkey: [u8; 64],
+}
+
+impl BlockHeader {
+    fn read(input: &[u8], offset: usize) -> Option<Self> {
+        let buf = input.get(offset..)?;
+        let magic = *buf.first()?;
+        if is_legacy_magic(magic) {
+            if buf.len() < LEGACY_HEADER_LEN {
+                return None;
+            }
+            return Some(Self {
+                magic,
+                seq: u16::from_le_bytes([buf[1], buf[2]]),
+                begin_hour: buf[3],
+                end_hour: buf[4],
+                header_len: LEGACY_HEADER_LEN,
+                len: u32::from_le_bytes([buf[5], buf[6], buf[7], buf[8]]) as usize,
+                client_pubkey: [0; 64],
+            });
+        }
+        let header = LogHeader::decode(buf).ok()?;
+        Some(Self {
+            magic: header.magic,
+            seq: header.seq,
+            begin_hour: header.begin_hour,
+            end_hour: header.end_hour,
+            header_len: HEADER_LEN,
+            len: header.len as usize,
+            client_pubkey: header.client_pubkey,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+/// Errors returned by [`Decoder`] setup and file helpers.
+pub enum DecodeError {
+    /// Reading the input file failed.
+    #[error("read log file failed: {0}")]
+    Io(#[from] io::Error),
+    /// The private key was not 32 bytes of hex data.
+    #[error("private key must be 64 hex chars")]
+    InvalidPrivateKey,
+    /// A block started with a byte that is not a known start magic.
+    #[error("bad block magic {magic:#04x} at offset {offset}")]
+    BadMagic {
+        /// Byte offset of the block in the input.
+        offset: usize,
+        /// The unexpected magic byte.
+        magic: u8,
+    },
+    /// The input ended inside a block header or payload.
+    #[error("truncated block at offset {offset}: needs {needed} bytes, {available} available")]
+    TruncatedBlock {
+        /// Byte offset of the block in the input.
+        offset: usize,
+        /// Bytes the header says the block spans, including the tail marker.
+        needed: usize,
+        /// Bytes left in the input from `offset`.
+        available: usize,
+    },
+    /// The block's integrity check failed.
+    ///
+    /// xlog blocks carry no checksum; the check is that the end marker sits
+    /// exactly where the header's length field says the payload ends.
+    #[error("block at offset {offset} fails its integrity check (no end marker at {tail})")]
+    CrcMismatch {
+        /// Byte offset of the block in the input.
+        offset: usize,
+        /// Byte offset where the end marker was expected.
+        tail: usize,
+    },
+    /// An encrypted block could not be decrypted.
+    #[error("encrypted block at offset {block} could not be decrypted: {reason}")]
+    DecryptError {
+        /// Byte offset of the block in the input.
+        block: usize,
+        /// Why decryption failed.
+        reason: String,
+    },
+    /// A compressed block produced no output.
+    #[error("decompress error in block at offset {block}: {reason}")]
+    DecompressError {
+        /// Byte offset of the block in the input.
+        block: usize,
+        /// The decompressor's error message.
+        reason: String,
+    },
+}
+
+/// Decoder that turns xlog files and buffers back into plaintext log lines.
+///
+/// Decoding is best effort, matching the behavior of the official Python
+/// decoders: corrupt regions are skipped after resynchronizing on the next
+/// valid block, and each problem is reported inline as a `[F]` marker line
+/// instead of aborting the whole decode. Services handling untrusted input
+/// that prefer to reject it use the `try_decode*` methods, which stop at the
+/// first malformed block with a [`DecodeError`] carrying its byte offset.
+/// Neither mode panics on malformed input.
+///
+/// Besides the current sync/async formats, the legacy pre-zstd blocks
+/// (`0x03`-`0x05`) written by older mars releases are decoded too, so mixed
+/// archives from long-lived installs can be read with one decoder.
+#[derive(Debug, Clone, Default)]
+pub struct Decoder {
+    private_key: Option<[u8; 32]>,
+}
+
+impl Decoder {
+    /// Create a decoder without a private key; encrypted blocks are skipped.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the server private key used to decrypt encrypted async blocks.
+    pub fn private_key(mut self, key: [u8; 32]) -> Self {
+        self.private_key = Some(key);
+        self
+    }
+
+    /// Set the server private key from hex. An empty string leaves it unset.
+    pub fn private_key_hex(self, key: &str) -> Result<Self, DecodeError> {
+        if key.is_empty() {
+            return Ok(self);
+        }
+        let mut raw = [0u8; 32];
+        hex::decode_to_slice(key, &mut raw).map_err(|_| DecodeError::InvalidPrivateKey)?;
+        Ok(self.private_key(raw))
+    }
+
+    /// Decode an xlog file into plaintext bytes.
+    pub fn decode_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, DecodeError> {
+        let input = fs::read(path)?;
+        Ok(self.decode(&input))
+    }
+
+    /// Decode every `.xlog` file in `dir` whose name starts with `prefix`.
+    ///
+    /// An empty `prefix` matches all log files. Results are ordered by file
+    /// date and rotation index. With the `rayon` feature enabled, files are
+    /// decoded in parallel.
+    pub fn decode_dir(
+        &self,
+        dir: impl AsRef<Path>,
+        prefix: &str,
+    ) -> Result<Vec<DecodedFile>, DecodeError> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if name.starts_with(prefix) && name.ends_with(LOG_FILE_EXT) && path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort_by_cached_key(|path| log_file_sort_key(path));
+
+        let decode = |path: &PathBuf| -> Result<DecodedFile, DecodeError> {
+            Ok(DecodedFile {
+                output: self.decode_file(path)?,
+                path: path.clone(),
+            })
+        };
+        #[cfg(feature = "rayon")]
+        let files = paths.par_iter().map(decode).collect();
+        #[cfg(not(feature = "rayon"))]
+        let files = paths.iter().map(decode).collect();
+        files
+    }
+
+    /// Decode a log directory like [`Decoder::decode_dir`] and merge all
+    /// entries into one timestamp-ordered text.
+    ///
+    /// Entries with equal timestamps keep their file order, and lines without
+    /// a timestamp (decoder markers, foreign output) stay behind the entry
+    /// that preceded them.
+    pub fn decode_dir_merged(
+        &self,
+        dir: impl AsRef<Path>,
+        prefix: &str,
+    ) -> Result<Vec<u8>, DecodeError> {
+        let files = self.decode_dir(dir, prefix)?;
+        let texts: Vec<Cow<'_, str>> = files
+            .iter()
+            .map(|file| String::from_utf8_lossy(&file.output))
+            .collect();
+
+        let mut merged = Vec::new();
+        for text in &texts {
+            let mut key = ("", "");
+            for entry in entries(text) {
+                if let Some(entry_key) = entry.time_key() {
+                    key = entry_key;
+                }
+                merged.push((key, entry.raw));
+            }
+        }
+        merged.sort_by_key(|(key, _)| *key);
+
+        let mut out = Vec::with_capacity(texts.iter().map(|text| text.len()).sum());
+        for (_, raw) in merged {
+            out.extend_from_slice(raw.as_bytes());
+        }
+        Ok(out)
+    }
+
+    /// Decode a raw xlog buffer (file contents or an mmap snapshot).
+    ///
+    /// Use [`crate::log_entry::entries`] on the output for structured records.
+    pub fn decode(&self, input: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut blocks = BlockCursor::new(self, input);
+        while blocks.decode_next(&mut out) {}
+        out
+    }
+
+    /// Decode the payload of one block found by [`scan`] in the same `input`.
+    pub fn decode_block(&self, input: &[u8], block: &BlockInfo) -> Result<Vec<u8>, DecodeError> {
+        self.decode_block_at(input, block.offset)
+    }
+
+    /// Decode the payload of the block starting at `offset` in `input`, e.g.
+    /// an offset recorded in a [`crate::index::LogIndex`].
+    pub fn decode_block_at(&self, input: &[u8], offset: usize) -> Result<Vec<u8>, DecodeError> {
+        let (header, _) = check_block(input, offset)?;
+        let payload_start = offset + header.header_len;
+        let mut out = Vec::new();
+        self.decode_payload(
+            &header,
+            &input[payload_start..payload_start + header.len],
+            offset,
+            &mut DecodeState::default(),
+            &mut out,
+        )?;
+        Ok(out)
+    }
+
+    /// Decode a raw xlog buffer, failing on the first malformed block.
+    ///
+    /// Unlike [`Decoder::decode`], leading garbage is not skipped: the input
+    /// must start with a block. Sequence gaps are still reported inline.
+    pub fn try_decode(&self, input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut out = Vec::new();
+        self.try_decode_to(input, &mut out)?;
+        Ok(out)
+    }
+
+    /// Strict variant of [`Decoder::decode_to`]; see [`Decoder::try_decode`].
+    pub fn try_decode_to<W: Write + ?Sized>(
+        &self,
+        input: &[u8],
+        writer: &mut W,
+    ) -> Result<(), DecodeError> {
+        let mut buf = Vec::new();
+        let mut blocks = BlockCursor::strict(self, input);
+        while blocks.try_decode_next(&mut buf)? {
+            writer.write_all(&buf)?;
+            buf.clear();
+        }
+        Ok(())
+    }
+
+    /// Decode a raw xlog buffer, streaming plaintext into `writer` block by block.
+    ///
+    /// Only one decoded block is buffered at a time, so large inputs can be
+    /// piped straight into a compressor or network body.
+    pub fn decode_to<W: Write + ?Sized>(&self, input: &[u8], writer: &mut W) -> io::Result<()> {
+        let mut buf = Vec::new();
+        let mut blocks = BlockCursor::new(self, input);
+        while blocks.decode_next(&mut buf) {
+            writer.write_all(&buf)?;
+            buf.clear();
+        }
+        Ok(())
+    }
+
+    /// Decode an xlog file into `writer` without loading it into memory.
+    pub fn decode_file_to<W: Write + ?Sized>(
+        &self,
+        path: impl AsRef<Path>,
+        writer: &mut W,
+    ) -> Result<(), DecodeError> {
+        let file = fs::File::open(path)?;
+        // SAFETY: the map is read-only and dropped before returning; a log file
+        // truncated concurrently can at worst fault the read, as with any mmap reader.
+        let input = unsafe { memmap2::Mmap::map(&file)? };
+        self.decode_to(&input, writer)?;
+        Ok(())
+    }
+
+    /// Async variant of [`Decoder::decode_to`] for tokio writers.
+    ///
+    /// Decoding itself stays synchronous; only the writes are awaited, one
+    /// block at a time.
+    #[cfg(feature = "tokio")]
+    pub async fn decode_to_async<W>(&self, input: &[u8], writer: &mut W) -> io::Result<()>
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let mut buf = Vec::new();
+        let mut blocks = BlockCursor::new(self, input);
+        while blocks.decode_next(&mut buf) {
+            writer.write_all(&buf).await?;
+            buf.clear();
+        }
+        Ok(())
+    }
+
+    fn decode_payload(
+        &self,
+        header: &BlockHeader,
+        payload: &[u8],
+        offset: usize,
+        state: &mut DecodeState,
+        out: &mut Vec<u8>,
+    ) -> Result<(), DecodeError> {
+        let (zstd, crypt) = match header.magic {
+            MAGIC_ASYNC_ZLIB_START => (false, true),
+            MAGIC_ASYNC_NO_CRYPT_ZLIB_START => (false, false),
+            MAGIC_ASYNC_ZSTD_START => (true, true),
+            MAGIC_ASYNC_NO_CRYPT_ZSTD_START => (true, false),
+            // Legacy key fields were never an ECDH key the decoder can use.
+            MAGIC_LEGACY_COMPRESS_START => (false, false),
+            MAGIC_LEGACY_COMPRESS_START1 => {
+                let joined = join_legacy_chunks(payload);
+                return inflate_into(false, &joined, offset, out);
+            }
+            // Sync and legacy no-compress blocks are stored as plaintext.
+            _ => {
+                out.extend_from_slice(payload);
+                return Ok(());
+            }
+        };
+
+        let mut raw = Cow::Borrowed(payload);
+        if crypt && header.client_pubkey != [0; 64] {
+            let key = self.tea_key(&header.client_pubkey, state).ok_or_else(|| {
+                DecodeError::DecryptError {
+                    block: offset,
+                    reason: if self.private_key.is_some() {
+                        "client public key rejected".to_string()
+                    } else {
+                        "no private key configured".to_string()
+                    },
+                }
+            })?;
+            let buf = raw.to_mut();
+            let block_end = buf.len() / TEA_BLOCK_LEN * TEA_BLOCK_LEN;
+            tea_decrypt_in_place(&mut buf[..block_end], &key);
+        }
+
+        inflate_into(zstd, &raw, offset, out)
+    }
+
+    fn tea_key(&self, client_pubkey: &[u8; 64], state: &mut DecodeState) -> Option<[u32; 4]> {
+        if let Some((cached_pubkey, key)) = &state.tea_key {
+            if cached_pubkey == client_pubkey {
+                return Some(*key);
+            }
+        }
+        let private_key = self.private_key?;
+        let cipher =
+            EcdhTeaCipher::new_with_private_key(&hex::encode(client_pubkey), private_key).ok()?;
+        let key = cipher.tea_key_words();
+        state.tea_key = Some((*client_pubkey, key));
+        Some(key)
+    }
+}
+
+/// Framing-level summary of one block, read without touching its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Byte offset of the block in the input.
+    pub offset: usize,
+    /// Start magic, which encodes append mode, compression, and crypt format.
+    pub magic: u8,
+    /// Sequence number; async blocks count up from 1, sync blocks use 0.
+    pub seq: u16,
+    /// Local hour of the first entry in the block.
+    pub begin_hour: u8,
+    /// Local hour of the last entry in the block.
+    pub end_hour: u8,
+    /// Payload length, excluding header and tail marker.
+    pub payload_len: usize,
+    /// Total block length, including header and tail marker.
+    pub block_len: usize,
+    /// Whether the header carries a client public key, i.e. the payload is encrypted.
+    pub encrypted: bool,
+}
+
+/// One item produced by [`scan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScannedBlock {
+    /// A well-framed block.
+    Block(BlockInfo),
+    /// Bytes that do not frame as blocks and were skipped to resynchronize.
+    Skipped {
+        /// Byte offset of the skipped region.
+        offset: usize,
+        /// Length of the skipped region.
+        len: usize,
+    },
+}
+
+/// Walk the block framing of an xlog buffer without decoding any payload.
+///
+/// This is cheap enough to triage large or damaged files; decode individual
+/// blocks with [`Decoder::decode_block`] when their contents are needed.
+pub fn scan(input: &[u8]) -> BlockScan<'_> {
+    BlockScan {
+        input,
+        offset: 0,
+        started: false,
+    }
+}
+
+/// Iterator returned by [`scan`].
+#[derive(Debug, Clone)]
+pub struct BlockScan<'a> {
+    input: &'a [u8],
+    offset: usize,
+    started: bool,
+}
+
+impl Iterator for BlockScan<'_> {
+    type Item = ScannedBlock;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.input;
+        let offset = self.offset;
+        if offset >= input.len() {
+            return None;
+        }
+        // Like `Decoder::decode`, require two good blocks for the first sync.
+        let count = if self.started { 1 } else { 2 };
+        self.started = true;
+        if count == 1 {
+            if let Ok((header, end)) = check_block(input, offset) {
+                self.offset = end;
+                return Some(ScannedBlock::Block(BlockInfo {
+                    offset,
+                    magic: header.magic,
+                    seq: header.seq,
+                    begin_hour: header.begin_hour,
+                    end_hour: header.end_hour,
+                    payload_len: header.len,
+                    block_len: end - offset,
+                    encrypted: header.client_pubkey != [0; 64],
+                }));
+            }
+        }
+        let start = find_block_start(input, offset, count).unwrap_or(input.len());
+        self.offset = start;
+        if start == offset {
+            return self.next();
+        }
+        Some(ScannedBlock::Skipped {
+            offset,
+            len: start - offset,
+        })
+    }
+}
+
+/// File-level summary of an `.xlog` file, read from block headers only.
+///
+/// Nothing is decompressed or decrypted, so this is cheap enough for an
+/// upload scheduler to rank many files by age and format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFileHeader {
+    /// Local date and hour of the first block: the date comes from the
+    /// `prefix_YYYYMMDD[_N].xlog` file name, the hour from the block header.
+    /// `None` when the name carries no date or the file has no valid block.
+    pub start_time: Option<NaiveDateTime>,
+    /// Local hour of the last entry in the last block.
+    pub end_hour: Option<u8>,
+    /// Start magic of the first block, i.e. its crypt/compress format version.
+    pub magic: Option<u8>,
+    /// Compression of the first block; `None` for legacy pre-zstd blocks.
+    pub compression: Option<CompressionKind>,
+    /// Whether any block carries a client public key.
+    pub encrypted: bool,
+    /// Number of well-framed blocks.
+    pub block_count: usize,
+    /// Byte offsets of blocks written by the sync path, including the marker
+    /// blocks written when the appender recovers or restarts.
+    pub sync_markers: Vec<usize>,
+    /// Bytes that did not frame as blocks.
+    pub corrupt_bytes: usize,
+}
+
+impl LogFileHeader {
+    /// Summarize the `.xlog` file at `path`.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, DecodeError> {
+        let path = path.as_ref();
+        let input = fs::read(path)?;
+        let date = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(file_name_date);
+        Ok(Self::from_bytes(&input, date))
+    }
+
+    /// Summarize an in-memory xlog buffer; `date` is the day the file covers.
+    pub fn from_bytes(input: &[u8], date: Option<NaiveDate>) -> Self {
+        let mut header = Self {
+            start_time: None,
+            end_hour: None,
+            magic: None,
+            compression: None,
+            encrypted: false,
+            block_count: 0,
+            sync_markers: Vec::new(),
+            corrupt_bytes: 0,
+        };
+        for item in scan(input) {
+            let block = match item {
+                ScannedBlock::Block(block) => block,
+                ScannedBlock::Skipped { len, .. } => {
+                    header.corrupt_bytes += len;
+                    continue;
+                }
+            };
+            if header.magic.is_none() {
+                header.magic = Some(block.magic);
+                header.compression = magic_compression(block.magic);
+                header.start_time =
+                    date.and_then(|date| date.and_hms_opt(u32::from(block.begin_hour), 0, 0));
+            }
+            if magic_is_sync(block.magic) {
+                header.sync_markers.push(block.offset);
+            }
+            header.encrypted |= block.encrypted;
+            header.end_hour = Some(block.end_hour);
+            header.block_count += 1;
+        }
+        header
+    }
+}
+
+/// Date encoded in a `prefix_YYYYMMDD[_N]` file stem.
+fn file_name_date(stem: &str) -> Option<NaiveDate> {
+    stem.rsplit('_')
+        .take(2)
+        .find(|part| part.len() == 8 && part.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|part| NaiveDate::parse_from_str(part, "%Y%m%d").ok())
+}
+
+fn magic_compression(magic: u8) -> Option<CompressionKind> {
+    match magic {
+        MAGIC_SYNC_ZLIB_START
+        | MAGIC_SYNC_NO_CRYPT_ZLIB_START
+        | MAGIC_ASYNC_ZLIB_START
+        | MAGIC_ASYNC_NO_CRYPT_ZLIB_START => Some(CompressionKind::Zlib),
+        MAGIC_SYNC_ZSTD_START
+        | MAGIC_SYNC_NO_CRYPT_ZSTD_START
+        | MAGIC_ASYNC_ZSTD_START
+        | MAGIC_ASYNC_NO_CRYPT_ZSTD_START => Some(CompressionKind::Zstd),
+        _ => None,
+    }
+}
+
+fn magic_is_sync(magic: u8) -> bool {
+    matches!(
+        magic,
+        MAGIC_SYNC_ZLIB_START
+            | MAGIC_SYNC_NO_CRYPT_ZLIB_START
+            | MAGIC_SYNC_ZSTD_START
+            | MAGIC_SYNC_NO_CRYPT_ZSTD_START
+    )
+}
+
+/// One decoded file returned by [`Decoder::decode_dir`].
+#[derive(Debug, Clone)]
+pub struct DecodedFile {
+    /// Path of the source `.xlog` file.
+    pub path: PathBuf,
+    /// Decoded plaintext.
+    pub output: Vec<u8>,
+}
+
+pub(crate) const LOG_FILE_EXT: &str = ".xlog";
+
+/// Order `prefix_YYYYMMDD[_N].xlog` names by date and then rotation index.
+pub(crate) fn log_file_sort_key(path: &Path) -> (String, u64) {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    match stem.rsplit_once('_') {
+        Some((base, index)) if index.len() != 8 => match index.parse() {
+            Ok(index) => (base.to_string(), index),
+            Err(_) => (stem.to_string(), 0),
+        },
+        _ => (stem.to_string(), 0),
+    }
+}
+
+/// Walks the blocks of one input, resynchronizing over corrupt regions
+/// unless built with [`BlockCursor::strict`].
+struct BlockCursor<'a> {
+    decoder: &'a Decoder,
+    input: &'a [u8],
+    offset: Option<usize>,
+    strict: bool,
+    state: DecodeState,
+}
+
+impl<'a> BlockCursor<'a> {
+    fn new(decoder: &'a Decoder, input: &'a [u8]) -> Self {
+        Self {
+            decoder,
+            input,
+            offset: find_block_start(input, 0, 2),
+            strict: false,
+            state: DecodeState::default(),
+        }
+    }
+
+    fn strict(decoder: &'a Decoder, input: &'a [u8]) -> Self {
+        Self {
+            decoder,
+            input,
+            offset: Some(0),
+            strict: true,
+            state: DecodeState::default(),
+        }
+    }
+
+    /// Append the next block's plaintext and markers to `out`, reporting
+    /// block errors as inline markers.
+    ///
+    /// Returns `false` once the input is exhausted.
+    fn decode_next(&mut self, out: &mut Vec<u8>) -> bool {
+        match self.try_decode_next(out) {
+            Ok(more) => more,
+            Err(err) => {
+                push_marker(out, format_args!("{err}"));
+                true
+            }
+        }
+    }
+
+    /// Like [`BlockCursor::decode_next`], but returns block errors instead.
+    ///
+    /// In lenient mode only payload errors are returned; framing errors are
+    /// skipped by resynchronizing on the next good block.
+    fn try_decode_next(&mut self, out: &mut Vec<u8>) -> Result<bool, DecodeError> {
+        let input = self.input;
+        let Some(mut offset) = self.offset.take() else {
+            return Ok(false);
+        };
+        if offset >= input.len() {
+            return Ok(false);
+        }
+        let mut end = check_block(input, offset);
+        if !self.strict && end.is_err() {
+            let Some(start) = find_block_start(input, offset, 1) else {
+                return Ok(false);
+            };
+            push_marker(
+                out,
+                format_args!(
+                    "decode error, skipped {} bytes at offset {offset}",
+                    start - offset
+                ),
+            );
+            offset = start;
+            end = check_block(input, offset);
+        }
+        let (header, block_end) = end?;
+
+        let payload_start = offset + header.header_len;
+        let payload_end = payload_start + header.len;
+        self.offset = Some(block_end);
+        self.state.check_seq(header.seq, out);
+        self.decoder.decode_payload(
+            &header,
+            &input[payload_start..payload_end],
+            offset,
+            &mut self.state,
+            out,
+        )?;
+        Ok(true)
+    }
+}
+
+/// Follows async block sequence numbers to find the blocks missing between
+/// them, by the rules behind the decoder's `log seq:<from>-<to> is missing`
+/// markers.
+///
+/// Sync blocks (seq 0) are ignored and seq 1 starts a new run, as writers
+/// restart the count when they reopen a file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SeqTracker {
+    last_seq: u16,
+}
+
+impl SeqTracker {
+    /// Create a tracker that has not seen a block yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the next block's `seq`, returning the inclusive range of
+    /// sequence numbers missing before it, if any.
+    pub fn check(&mut self, seq: u16) -> Option<(u16, u16)> {
+        let gap = (seq > 1 && self.last_seq != 0 && seq != self.last_seq.wrapping_add(1))
+            .then(|| (self.last_seq.wrapping_add(1), seq - 1));
+        if seq != 0 {
+            self.last_seq = seq;
+        }
+        gap
+    }
+}
+
+#[derive(Default)]
+struct DecodeState {
+    seq: SeqTracker,
+    tea_key: Option<([u8; 64], [u32; 4])>,
+}
+
+impl DecodeState {
+    fn check_seq(&mut self, seq: u16, out: &mut Vec<u8>) {
+        if let Some((from, to)) = self.seq.check(seq) {
+            push_marker(out, format_args!("log seq:{from}-{to} is missing"));
+        }
+    }
+}
+
+fn inflate_into(
+    zstd: bool,
+    raw: &[u8],
+    offset: usize,
+    out: &mut Vec<u8>,
+) -> Result<(), DecodeError> {
+    let before = out.len();
+    // One byte past the cap, so hitting it is distinguishable from filling it.
+    let limit = MAX_INFLATED_BLOCK_LEN as u64 + 1;
+    let result = if zstd {
+        match zstd::stream::read::Decoder::new(raw) {
+            Ok(decoder) => decoder.take(limit).read_to_end(out).map(|_| ()),
+            Err(err) => Err(err),
+        }
+    } else {
+        flate2::read::DeflateDecoder::new(raw)
+            .take(limit)
+            .read_to_end(out)
+            .map(|_| ())
+    };
+    if out.len() - before > MAX_INFLATED_BLOCK_LEN {
+        out.truncate(before);
+        return Err(DecodeError::DecompressError {
+            block: offset,
+            reason: format!("block inflates past {MAX_INFLATED_BLOCK_LEN} bytes"),
+        });
+    }
+    // Async blocks may end with a flushed-but-unfinished stream; keep any
+    // partial output and only report blocks that produced nothing at all.
+    match result {
+        Err(err) if out.len() == before => Err(DecodeError::DecompressError {
+            block: offset,
+            reason: err.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Concatenate the `u16`-length-prefixed chunks of a legacy `0x05` block.
+fn join_legacy_chunks(mut payload: &[u8]) -> Vec<u8> {
+    let mut joined = Vec::with_capacity(payload.len());
+    while payload.len() >= 2 {
+        let chunk_len = u16::from_le_bytes([payload[0], payload[1]]) as usize;
+        let end = (2 + chunk_len).min(payload.len());
+        joined.extend_from_slice(&payload[2..end]);
+        payload = &payload[end..];
+    }
+    joined
+}
+
+pub(crate) fn push_marker(out: &mut Vec<u8>, args: std::fmt::Arguments<'_>) {
+    let mut line = String::from("[F]xlog decoder: ");
+    let _ = line.write_fmt(args);
+    line.push('\n');
+    out.extend_from_slice(line.as_bytes());
+}
+
+/// Validate the block framing at `offset`, returning its header and end offset.
+fn check_block(input: &[u8], offset: usize) -> Result<(BlockHeader, usize), DecodeError> {
+    let available = input.len().saturating_sub(offset);
+    let magic = input.get(offset).copied().unwrap_or_default();
+    if !magic_start_is_valid(magic) && !is_legacy_magic(magic) {
+        return Err(DecodeError::BadMagic { offset, magic });
+    }
+    let Some(header) = BlockHeader::read(input, offset) else {
+        let needed = if is_legacy_magic(magic) {
+            LEGACY_HEADER_LEN
+        } else {
+            HEADER_LEN
+        };
+        return Err(DecodeError::TruncatedBlock {
+            offset,
+            needed,
+            available,
+        });
+    };
+    let needed = header
+        .header_len
+        .saturating_add(header.len)
+        .saturating_add(TAILER_LEN);
+    if needed > available {
+        return Err(DecodeError::TruncatedBlock {
+            offset,
+            needed,
+            available,
+        });
+    }
+    let tail = offset + needed - TAILER_LEN;
+    if input[tail] != MAGIC_END {
+        return Err(DecodeError::CrcMismatch { offset, tail });
+    }
+    Ok((header, offset + needed))
+}
+
+/// Like [`block_end`], but keeps the error so callers reading a growing file
+/// can tell a block still being written (`TruncatedBlock`) from a corrupt one.
+pub(crate) fn checked_block_end(input: &[u8], offset: usize) -> Result<usize, DecodeError> {
+    check_block(input, offset).map(|(_, end)| end)
+}
+
+/// Return the end offset of a complete block starting at `offset`.
+fn block_end(input: &[u8], offset: usize) -> Option<usize> {
+    check_block(input, offset).ok().map(|(_, end)| end)
+}
+
+/// Check that `count` consecutive blocks (or the end of input) start at `offset`.
+fn is_good_block(input: &[u8], mut offset: usize, count: usize) -> bool {
+    for _ in 0..count {
+        if offset == input.len() {
+            return true;
+        }
+        match block_end(input, offset) {
+            Some(next) => offset = next,
+            None => return false,
+        }
+    }
+    true
+}
+
+pub(crate) fn find_block_start(input: &[u8], from: usize, count: usize) -> Option<usize> {
+    (from..input.len()).find(|&offset| {
+        (magic_start_is_valid(input[offset]) || is_legacy_magic(input[offset]))
+            && is_good_block(input, offset, count)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use k256::elliptic_curve::sec1::ToEncodedPoint;
+    use k256::SecretKey;
+
+    use super::{scan, DecodeError, Decoder, LogFileHeader, ScannedBlock, MAX_INFLATED_BLOCK_LEN};
+    use crate::compress::{StreamCompressor, ZlibStreamCompressor, ZstdStreamCompressor};
+    use crate::crypto::EcdhTeaCipher;
+    use crate::protocol::{
+        CompressionKind, LogHeader, MAGIC_ASYNC_NO_CRYPT_ZLIB_START,
+        MAGIC_ASYNC_NO_CRYPT_ZSTD_START, MAGIC_ASYNC_ZSTD_START, MAGIC_END,
+        MAGIC_SYNC_NO_CRYPT_ZLIB_START,
+    };
+
+    const SERVER_PRIVKEY: [u8; 32] = [0x11; 32];
+
+    fn server_pubkey_hex() -> String {
+        let secret = SecretKey::from_slice(&SERVER_PRIVKEY).unwrap();
+        let point = secret.public_key().to_encoded_point(false);
+        hex::encode(&point.as_bytes()[1..])
+    }
+
+    fn block(magic: u8, seq: u16, client_pubkey: [u8; 64], payload: &[u8]) -> Vec<u8> {
+        let header = LogHeader {
+            magic,
+            seq,
+            begin_hour: 1,
+            end_hour: 2,
+            len: payload.len() as u32,
+            client_pubkey,
+        };
+        let mut out = header.encode().to_vec();
+        out.extend_from_slice(payload);
+        out.push(MAGIC_END);
+        out
+    }
+
+    fn zlib(input: &[u8]) -> Vec<u8> {
+        let mut compressor = ZlibStreamCompressor::new(6);
+        let mut out = Vec::new();
+        compressor.compress_chunk(input, &mut out).unwrap();
+        compressor.flush(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn decodes_sync_and_async_plain_blocks() {
+        let mut input = block(MAGIC_SYNC_NO_CRYPT_ZLIB_START, 0, [0; 64], b"sync line\n");
+        input.extend(block(
+            MAGIC_ASYNC_NO_CRYPT_ZLIB_START,
+            1,
+            [0; 64],
+            &zlib(b"async line\n"),
+        ));
+
+        let out = Decoder::new().decode(&input);
+        assert_eq!(out, b"sync line\nasync line\n");
+    }
+
+    #[test]
+    fn decrypts_async_zstd_blocks_with_private_key() {
+        let cipher = EcdhTeaCipher::new(&server_pubkey_hex()).unwrap();
+        let mut compressor = ZstdStreamCompressor::new(3).unwrap();
+        let mut payload = Vec::new();
+        compressor
+            .compress_chunk(b"secret line\n", &mut payload)
+            .unwrap();
+        compressor.flush(&mut payload).unwrap();
+        let payload = cipher.encrypt_async(&payload);
+        let input = block(MAGIC_ASYNC_ZSTD_START, 1, cipher.client_pubkey(), &payload);
+
+        let out = Decoder::new()
+            .private_key_hex(&hex::encode(SERVER_PRIVKEY))
+            .unwrap()
+            .decode(&input);
+        assert_eq!(out, b"secret line\n");
+
+        let out = String::from_utf8(Decoder::new().decode(&input)).unwrap();
+        assert!(out.starts_with("[F]xlog decoder: encrypted block"));
+    }
+
+    #[test]
+    fn resyncs_after_garbage_and_reports_seq_gaps() {
+        let mut input = Vec::new();
+        for (seq, line) in [(1, b"a\n"), (2, b"b\n")] {
+            input.extend(block(
+                MAGIC_ASYNC_NO_CRYPT_ZLIB_START,
+                seq,
+                [0; 64],
+                &zlib(line),
+            ));
+        }
+        let garbage_at = input.len();
+        input.extend_from_slice(&[0xff; 5]);
+        input.extend(block(
+            MAGIC_ASYNC_NO_CRYPT_ZLIB_START,
+            5,
+            [0; 64],
+            &zlib(b"c\n"),
+        ));
+
+        let out = String::from_utf8(Decoder::new().decode(&input)).unwrap();
+        assert_eq!(
+            out,
+            format!(
+                "a\nb\n\
+                 [F]xlog decoder: decode error, skipped 5 bytes at offset {garbage_at}\n\
+                 [F]xlog decoder: log seq:3-4 is missing\n\
+                 c\n"
+            )
+        );
+    }
+
+    #[test]
+    fn decodes_legacy_pre_zstd_blocks() {
+        fn legacy_block(magic: u8, seq: u16, payload: &[u8]) -> Vec<u8> {
+            let mut out = vec![magic];
+            out.extend_from_slice(&seq.to_le_bytes());
+            out.extend_from_slice(&[1, 2]);
+            out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(payload);
+            out.push(MAGIC_END);
+            out
+        }
+
+        let deflated = zlib(b"chunked\n");
+        let (head, tail) = deflated.split_at(deflated.len() / 2);
+        let mut chunked = Vec::new();
+        for chunk in [head, tail] {
+            chunked.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+            chunked.extend_from_slice(chunk);
+        }
+
+        let mut input = legacy_block(0x03, 0, b"plain\n");
+        input.extend(legacy_block(0x04, 1, &zlib(b"deflated\n")));
+        input.extend(legacy_block(0x05, 2, &chunked));
+
+        let out = Decoder::new().decode(&input);
+        assert_eq!(out, b"plain\ndeflated\nchunked\n");
+    }
+
+    #[test]
+    fn decodes_log_dir_in_rotation_order_and_merges_by_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let line = |time: &str, msg: &str| {
+            format!("[I][2024-01-02 +8.0 {time}][1, 2][t][a.rs:1, f][{msg}\n").into_bytes()
+        };
+        let write = |name: &str, body: Vec<u8>| {
+            let data = block(MAGIC_SYNC_NO_CRYPT_ZLIB_START, 0, [0; 64], &body);
+            std::fs::write(dir.path().join(name), data).unwrap();
+        };
+        write("app_20240102_10.xlog", line("10:00:03.000", "c"));
+        write("app_20240102.xlog", line("10:00:02.000", "b"));
+        write("app_20240102_2.xlog", line("10:00:01.000", "a"));
+        write("other_20240102.xlog", line("10:00:00.000", "x"));
+        std::fs::write(dir.path().join("app.mmap3"), b"ignored").unwrap();
+
+        let decoder = Decoder::new();
+        let files = decoder.decode_dir(dir.path(), "app_").unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|file| file.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "app_20240102.xlog",
+                "app_20240102_2.xlog",
+                "app_20240102_10.xlog"
+            ]
+        );
+
+        let merged = decoder.decode_dir_merged(dir.path(), "app_").unwrap();
+        let msgs: Vec<_> = crate::log_entry::entries(std::str::from_utf8(&merged).unwrap())
+            .map(|entry| entry.msg)
+            .collect();
+        assert_eq!(msgs, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn streams_decoded_blocks_into_writers() {
+        let mut input = block(MAGIC_SYNC_NO_CRYPT_ZLIB_START, 0, [0; 64], b"first\n");
+        input.extend(block(
+            MAGIC_ASYNC_NO_CRYPT_ZLIB_START,
+            1,
+            [0; 64],
+            &zlib(b"second\n"),
+        ));
+        let decoder = Decoder::new();
+
+        let mut sink = Vec::new();
+        decoder.decode_to(&input, &mut sink).unwrap();
+        assert_eq!(sink, decoder.decode(&input));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app_20240102.xlog");
+        std::fs::write(&path, &input).unwrap();
+        let mut from_file = Vec::new();
+        decoder.decode_file_to(&path, &mut from_file).unwrap();
+        assert_eq!(from_file, sink);
+
+        #[cfg(feature = "tokio")]
+        {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let mut async_sink = Vec::new();
+            runtime
+                .block_on(decoder.decode_to_async(&input, &mut async_sink))
+                .unwrap();
+            assert_eq!(async_sink, sink);
+        }
+    }
+
+    #[test]
+    fn strict_decode_reports_block_errors_with_offsets() {
+        let good = block(MAGIC_SYNC_NO_CRYPT_ZLIB_START, 0, [0; 64], b"ok\n");
+        let decoder = Decoder::new();
+        assert_eq!(decoder.try_decode(&good).unwrap(), b"ok\n");
+        assert!(decoder.try_decode(&[]).unwrap().is_empty());
+
+        let mut bad_magic = good.clone();
+        bad_magic.extend_from_slice(b"junk");
+        assert!(matches!(
+            decoder.try_decode(&bad_magic),
+            Err(DecodeError::BadMagic { offset, magic: b'j' }) if offset == good.len()
+        ));
+
+        let truncated = &good[..good.len() - 2];
+        assert!(matches!(
+            decoder.try_decode(truncated),
+            Err(DecodeError::TruncatedBlock { offset: 0, needed, available })
+                if needed == good.len() && available == good.len() - 2
+        ));
+
+        let mut bad_tail = good.clone();
+        *bad_tail.last_mut().unwrap() = 0xff;
+        assert!(matches!(
+            decoder.try_decode(&bad_tail),
+            Err(DecodeError::CrcMismatch { offset: 0, tail }) if tail == good.len() - 1
+        ));
+
+        let mut input = good.clone();
+        input.extend(block(MAGIC_ASYNC_ZSTD_START, 1, [7; 64], &[0; 16]));
+        assert!(matches!(
+            decoder.try_decode(&input),
+            Err(DecodeError::DecryptError { block, .. }) if block == good.len()
+        ));
+
+        let mut input = good.clone();
+        input.extend(block(MAGIC_ASYNC_NO_CRYPT_ZSTD_START, 1, [0; 64], b"nope"));
+        assert!(matches!(
+            decoder.try_decode(&input),
+            Err(DecodeError::DecompressError { block, .. }) if block == good.len()
+        ));
+    }
+
+    #[test]
+    fn rejects_blocks_that_inflate_past_the_cap() {
+        let bomb = zlib(&vec![0; MAX_INFLATED_BLOCK_LEN + 1]);
+        let input = block(MAGIC_ASYNC_NO_CRYPT_ZLIB_START, 1, [0; 64], &bomb);
+
+        let decoder = Decoder::new();
+        assert!(matches!(
+            decoder.try_decode(&input),
+            Err(DecodeError::DecompressError { block: 0, reason }) if reason.contains("inflates past")
+        ));
+        let out = String::from_utf8(decoder.decode(&input)).unwrap();
+        assert!(out.starts_with("[F]xlog decoder:"), "{out}");
+        assert!(out.len() < 1024);
+
+        let fits = zlib(&vec![b'a'; MAX_INFLATED_BLOCK_LEN]);
+        let input = block(MAGIC_ASYNC_NO_CRYPT_ZLIB_START, 1, [0; 64], &fits);
+        assert_eq!(
+            decoder.try_decode(&input).unwrap().len(),
+            MAX_INFLATED_BLOCK_LEN
+        );
+    }
+
+    #[test]
+    fn scans_block_framing_and_decodes_single_blocks() {
+        let first = block(MAGIC_SYNC_NO_CRYPT_ZLIB_START, 0, [0; 64], b"one\n");
+        let second = block(MAGIC_ASYNC_ZSTD_START, 2, [7; 64], &[0; 8]);
+        let mut input = b"xx".to_vec();
+        input.extend(&first);
+        input.extend(&first);
+        input.extend(b"garbage");
+        input.extend(&second);
+
+        let scanned: Vec<_> = scan(&input).collect();
+        assert_eq!(scanned.len(), 5, "{scanned:?}");
+        assert_eq!(scanned[0], ScannedBlock::Skipped { offset: 0, len: 2 });
+        assert_eq!(
+            scanned[3],
+            ScannedBlock::Skipped {
+                offset: 2 + 2 * first.len(),
+                len: 7
+            }
+        );
+        let (ScannedBlock::Block(one), ScannedBlock::Block(two)) = (scanned[1], scanned[4]) else {
+            panic!("unexpected scan: {scanned:?}");
+        };
+        assert_eq!(
+            (one.offset, one.block_len, one.encrypted),
+            (2, first.len(), false)
+        );
+        assert_eq!((two.seq, two.payload_len, two.encrypted), (2, 8, true));
+
+        let decoder = Decoder::new();
+        assert_eq!(decoder.decode_block(&input, &one).unwrap(), b"one\n");
+        assert!(matches!(
+            decoder.decode_block(&input, &two),
+            Err(DecodeError::DecryptError { .. })
+        ));
+    }
+
+    #[test]
+    fn file_header_summarizes_blocks_without_decoding() {
+        let sync = block(MAGIC_SYNC_NO_CRYPT_ZLIB_START, 0, [0; 64], b"tip\n");
+        let async_block = block(MAGIC_ASYNC_ZSTD_START, 1, [7; 64], &[0; 8]);
+        let mut input = sync.clone();
+        input.extend(&async_block);
+        input.extend(b"junk");
+        input.extend(&sync);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo_20260316_2.xlog");
+        std::fs::write(&path, &input).unwrap();
+        let header = LogFileHeader::read(&path).unwrap();
+
+        let date = chrono::NaiveDate::from_ymd_opt(2026, 3, 16).unwrap();
+        assert_eq!(header.start_time, date.and_hms_opt(1, 0, 0));
+        assert_eq!(header.end_hour, Some(2));
+        assert_eq!(header.magic, Some(MAGIC_SYNC_NO_CRYPT_ZLIB_START));
+        assert_eq!(header.compression, Some(CompressionKind::Zlib));
+        assert!(header.encrypted);
+        assert_eq!(header.block_count, 3);
+        assert_eq!(
+            header.sync_markers,
+            vec![0, sync.len() + async_block.len() + 4]
+        );
+        assert_eq!(header.corrupt_bytes, 4);
+
+        let empty = LogFileHeader::from_bytes(b"", None);
+        assert_eq!((empty.start_time, empty.block_count), (None, 0));
+    }
+
+    #[test]
+    fn malformed_input_never_panics() {
+        let mut input = block(MAGIC_ASYNC_NO_CRYPT_ZLIB_START, 1, [0; 64], &zlib(b"a\n"));
+        input.extend(block(MAGIC_ASYNC_ZSTD_START, 2, [1; 64], &[0xff; 9]));
+        let decoder = Decoder::new().private_key([0x22; 32]);
+        for cut in 0..=input.len() {
+            let mut sample = input[..cut].to_vec();
+            decoder.decode(&sample);
+            let _ = decoder.try_decode(&sample);
+            assert_eq!(
+                scan(&sample)
+                    .map(|item| match item {
+                        ScannedBlock::Block(block) => block.block_len,
+                        ScannedBlock::Skipped { len, .. } => len,
+                    })
+                    .sum::<usize>(),
+                sample.len()
+            );
+            if let Some(byte) = sample.get_mut(cut / 2) {
+                *byte ^= 0x5a;
+            }
+            decoder.decode(&sample);
+            let _ = decoder.try_decode(&sample);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_private_key_hex() {
+        assert!(Decoder::new().private_key_hex("zz").is_err());
+        assert!(Decoder::new().private_key_hex("").is_ok());
+    }
+}
@@ -0,0 +1,246 @@
+//! Notifications about log files a [`FileManager`](crate::file_manager::FileManager)
+//! creates, rotates, or removes.
+//!
+//! Events come from the writer itself rather than from a filesystem watcher,
+//! so they are exact for this process and cost nothing while no listener is
+//! registered. They are queued under the file lock and delivered in order on
+//! a dedicated thread, so a listener may log, flush, or do slow work such as
+//! starting an upload without stalling or deadlocking the writer.
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// A change to the set of log files in the log or cache directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEvent {
+    /// A new (empty) log file was opened for writing.
+    Created(PathBuf),
+    /// Writing moved on from `from` to the newly created `to` in the same
+    /// directory, because of size rotation or a new day. Follows the
+    /// matching [`FileEvent::Created`].
+    Rotated {
+        /// File that was being written before.
+        from: PathBuf,
+        /// File now being written.
+        to: PathBuf,
+    },
+    /// A log file was deleted as expired, or moved out of the cache
+    /// directory into the log directory.
+    Removed(PathBuf),
+}
+
+/// Callback receiving [`FileEvent`]s.
+pub type FileEventListener = Box<dyn FnMut(&FileEvent) + Send>;
+
+#[derive(Default)]
+struct EventState {
+    next_id: u64,
+    listeners: Vec<(u64, Arc<Mutex<FileEventListener>>)>,
+    pending: Vec<FileEvent>,
+    /// Last file created per directory, used to report rotations.
+    last_created: HashMap<PathBuf, PathBuf>,
+    worker_running: bool,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<EventState>,
+    ready: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, EventState> {
+        self.state.lock().expect("file events lock poisoned")
+    }
+}
+
+/// Listener registry shared by every clone of a file manager.
+#[derive(Clone, Default)]
+pub struct FileEvents {
+    shared: Arc<Shared>,
+}
+
+impl std::fmt::Debug for FileEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let listeners = self.shared.lock().listeners.len();
+        f.debug_struct("FileEvents")
+            .field("listeners", &listeners)
+            .finish()
+    }
+}
+
+impl FileEvents {
+    /// Registers `listener` and returns an id for [`FileEvents::remove_listener`].
+    ///
+    /// The delivery thread starts with the first listener and exits once the
+    /// last one is removed.
+    pub fn add_listener(&self, listener: FileEventListener) -> u64 {
+        let mut state = self.shared.lock();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.listeners.push((id, Arc::new(Mutex::new(listener))));
+        if !state.worker_running && !state.closed {
+            let shared = Arc::clone(&self.shared);
+            let spawned = thread::Builder::new()
+                .name("xlog-file-events".to_string())
+                .spawn(move || deliver(shared));
+            state.worker_running = spawned.is_ok();
+        }
+        id
+    }
+
+    /// Removes a listener; returns `false` when `id` is unknown.
+    pub fn remove_listener(&self, id: u64) -> bool {
+        let mut state = self.shared.lock();
+        let before = state.listeners.len();
+        state
+            .listeners
+            .retain(|(listener_id, _)| *listener_id != id);
+        let removed = state.listeners.len() != before;
+        if removed {
+            self.shared.ready.notify_all();
+        }
+        removed
+    }
+
+    /// Delivers what is already queued, then stops the delivery thread and
+    /// ignores later events.
+    pub fn close(&self) {
+        let mut state = self.shared.lock();
+        state.closed = true;
+        self.shared.ready.notify_all();
+    }
+
+    pub(crate) fn file_created(&self, path: &Path) {
+        let mut state = self.shared.lock();
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let previous = state.last_created.insert(dir, path.to_path_buf());
+        if !state.worker_running || state.closed {
+            return;
+        }
+        state.pending.push(FileEvent::Created(path.to_path_buf()));
+        if let Some(from) = previous.filter(|from| from != path) {
+            state.pending.push(FileEvent::Rotated {
+                from,
+                to: path.to_path_buf(),
+            });
+        }
+        self.shared.ready.notify_all();
+    }
+
+    pub(crate) fn file_removed(&self, path: &Path) {
+        let mut state = self.shared.lock();
+        if state.worker_running && !state.closed {
+            state.pending.push(FileEvent::Removed(path.to_path_buf()));
+            self.shared.ready.notify_all();
+        }
+    }
+}
+
+/// Clears `worker_running` if the delivery thread unwinds, so the next
+/// listener starts a new one instead of queueing events nobody delivers.
+struct WorkerExit<'a>(&'a Shared);
+
+impl Drop for WorkerExit<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let mut state = self.0.state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.worker_running = false;
+            state.pending.clear();
+        }
+    }
+}
+
+fn deliver(shared: Arc<Shared>) {
+    let _exit = WorkerExit(&shared);
+    loop {
+        let (events, listeners) = {
+            let mut state = shared.lock();
+            while state.pending.is_empty() {
+                if state.closed || state.listeners.is_empty() {
+                    state.worker_running = false;
+                    return;
+                }
+                state = shared.ready.wait(state).expect("file events lock poisoned");
+            }
+            let listeners: Vec<_> = state
+                .listeners
+                .iter()
+                .map(|(_, listener)| Arc::clone(listener))
+                .collect();
+            (std::mem::take(&mut state.pending), listeners)
+        };
+        for event in &events {
+            for listener in &listeners {
+                // A panicking listener must not stop delivery to the others.
+                let mut listener = listener.lock().unwrap_or_else(PoisonError::into_inner);
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| listener(event)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::{FileEvent, FileEvents};
+
+    #[test]
+    fn reports_rotation_per_directory_until_listener_is_removed() {
+        let events = FileEvents::default();
+        events.file_created(&PathBuf::from("log/a_0.xlog"));
+
+        let (tx, rx) = mpsc::channel();
+        let id = events.add_listener(Box::new(move |event| {
+            let _ = tx.send(event.clone());
+        }));
+        events.file_created(&PathBuf::from("log/a_1.xlog"));
+        events.file_created(&PathBuf::from("cache/a_1.xlog"));
+        events.file_removed(&PathBuf::from("cache/a_1.xlog"));
+
+        let seen: Vec<FileEvent> = (0..4)
+            .map(|_| rx.recv_timeout(Duration::from_secs(5)).expect("event"))
+            .collect();
+        assert_eq!(
+            seen,
+            vec![
+                FileEvent::Created("log/a_1.xlog".into()),
+                FileEvent::Rotated {
+                    from: "log/a_0.xlog".into(),
+                    to: "log/a_1.xlog".into(),
+                },
+                FileEvent::Created("cache/a_1.xlog".into()),
+                FileEvent::Removed("cache/a_1.xlog".into()),
+            ]
+        );
+
+        assert!(events.remove_listener(id));
+        assert!(!events.remove_listener(id));
+        events.file_removed(&PathBuf::from("log/a_0.xlog"));
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn panicking_listener_does_not_stop_delivery() {
+        let events = FileEvents::default();
+        let (tx, rx) = mpsc::channel();
+        events.add_listener(Box::new(|_| panic!("listener failed")));
+        events.add_listener(Box::new(move |event| {
+            let _ = tx.send(event.clone());
+        }));
+
+        for name in ["log/a_0.xlog", "log/a_1.xlog"] {
+            events.file_removed(&PathBuf::from(name));
+            assert_eq!(
+                rx.recv_timeout(Duration::from_secs(5)).expect("event"),
+                FileEvent::Removed(name.into())
+            );
+        }
+    }
+}
@@ -16,9 +16,13 @@ pub(crate) struct CacheMaintenance<'a> {
     pub(crate) cache_days: i32,
 }
 
+/// Moves eligible cache files into the log directory.
+///
+/// Returns each moved cache path, paired with its destination when the move
+/// created a new log file rather than appending to an existing one.
 pub(crate) fn move_old_cache_files(
     config: CacheMaintenance<'_>,
-) -> Result<Vec<PathBuf>, FileManagerError> {
+) -> Result<Vec<(PathBuf, Option<PathBuf>)>, FileManagerError> {
     let Some(cache_dir) = config.cache_dir else {
         return Ok(Vec::new());
     };
@@ -30,7 +34,7 @@ pub(crate) fn move_old_cache_files(
     }
 
     let now = SystemTime::now();
-    let mut removed_paths = Vec::new();
+    let mut moved_paths = Vec::new();
     let entries = fs::read_dir(cache_dir)
         .map_err(|e| FileManagerError::ReadDir(cache_dir.to_path_buf(), e))?;
     for entry in entries {
@@ -57,13 +61,14 @@ pub(crate) fn move_old_cache_files(
         }
 
         let dest = config.log_dir.join(file_name);
+        let created = !dest.exists();
         append_file_to_file(&path, &dest)?;
         fs::remove_file(&path).map_err(|e| FileManagerError::RemoveFile(path.clone(), e))?;
         record_cache_move();
-        removed_paths.push(path);
+        moved_paths.push((path, created.then_some(dest)));
     }
 
-    Ok(removed_paths)
+    Ok(moved_paths)
 }
 
 pub(crate) fn delete_expired_files(
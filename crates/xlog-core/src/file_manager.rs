@@ -12,6 +12,7 @@ use crate::active_append::{
     append_slices_direct, append_slices_keep_open, flush_active_append_file, rollback_file_to_len,
     sync_active_append_file_data, ActiveAppendFile, ACTIVE_APPEND_BUFFER_CAPACITY,
 };
+use crate::file_events::FileEvents;
 use crate::file_maintenance::{delete_expired_files, move_old_cache_files, CacheMaintenance};
 use crate::file_naming::{day_key, make_date_prefix, LOG_EXT_WITH_DOT};
 use crate::file_ops::append_file_to_file;
@@ -19,6 +20,8 @@ use crate::file_policy::{AppendRoutePlan, CacheRoutePlanner};
 use crate::file_runtime::{RuntimeState, TargetDirs};
 use crate::file_target::resolve_append_target;
 use crate::metrics::record_file_append;
+use crate::recovery::build_plain_tip_block;
+
 const CACHE_AVAILABLE_THRESHOLD_BYTES: u64 = 1024 * 1024 * 1024;
 
 #[derive(Debug, Error)]
@@ -69,6 +72,8 @@ pub struct FileManager {
     cache_dir: Option<PathBuf>,
     name_prefix: String,
     cache_days: i32,
+    file_tip: Option<Arc<str>>,
+    events: FileEvents,
     runtime: Arc<Mutex<RuntimeState>>,
     _lock_files: Arc<Vec<File>>,
 }
@@ -118,6 +123,8 @@ impl FileManager {
             cache_dir,
             name_prefix,
             cache_days,
+            file_tip: None,
+            events: FileEvents::default(),
             runtime: Arc::new(Mutex::new(RuntimeState::default())),
             _lock_files: Arc::new(lock_files),
         })
@@ -143,6 +150,23 @@ impl FileManager {
         self.cache_days
     }
 
+    /// Sets a line written as a plaintext sync block at the start of every
+    /// log file this manager creates, including rotated and next-day files.
+    pub fn with_file_tip(mut self, tip: impl Into<String>) -> Self {
+        let mut tip = tip.into();
+        if !tip.ends_with('\n') {
+            tip.push('\n');
+        }
+        self.file_tip = Some(Arc::from(tip));
+        self
+    }
+
+    /// Returns the listener registry notified when this manager creates,
+    /// rotates, or removes log files. Shared by all clones.
+    pub fn events(&self) -> &FileEvents {
+        &self.events
+    }
+
     /// Returns the mmap sidecar path associated with this file set.
     pub fn mmap_path(&self) -> PathBuf {
         let base = self.cache_dir.as_ref().unwrap_or(&self.log_dir);
@@ -261,13 +285,17 @@ impl FileManager {
     /// configured, this is a no-op.
     pub fn move_old_cache_files(&self, _max_file_size: u64) -> Result<(), FileManagerError> {
         self.flush_active_file_if_needed()?;
-        for path in move_old_cache_files(CacheMaintenance {
+        for (path, created) in move_old_cache_files(CacheMaintenance {
             log_dir: &self.log_dir,
             cache_dir: self.cache_dir.as_deref(),
             name_prefix: &self.name_prefix,
             cache_days: self.cache_days,
         })? {
             self.mark_runtime_path_removed(&path);
+            self.events.file_removed(&path);
+            if let Some(dest) = created {
+                self.events.file_created(&dest);
+            }
         }
 
         Ok(())
@@ -280,6 +308,7 @@ impl FileManager {
             delete_expired_files(&self.log_dir, self.cache_dir.as_deref(), max_alive_seconds)?
         {
             self.mark_runtime_path_removed(&path);
+            self.events.file_removed(&path);
         }
         Ok(())
     }
@@ -533,10 +562,15 @@ impl FileManager {
     ) -> Result<(), FileManagerError> {
         let log_path =
             self.select_append_path(now, &self.log_dir, &self.name_prefix, max_file_size);
+        let created = !log_path.exists();
         append_file_to_file(cache_path, &log_path)?;
+        if created {
+            self.events.file_created(&log_path);
+        }
         fs::remove_file(cache_path)
             .map_err(|e| FileManagerError::RemoveFile(cache_path.to_path_buf(), e))?;
         self.mark_runtime_path_removed(cache_path);
+        self.events.file_removed(cache_path);
         Ok(())
     }
 
@@ -624,6 +658,7 @@ impl FileManager {
         if must_reopen {
             close_active_append_file(runtime)?;
         }
+        let mut fresh_file = false;
         if runtime.active_file.is_none() {
             let file = open_append_file(path, &path_buf)?;
             let len = match cached_local_len {
@@ -633,6 +668,10 @@ impl FileManager {
                     .map_err(|e| FileManagerError::Metadata(path_buf.clone(), e))?
                     .len(),
             };
+            fresh_file = len == 0;
+            if fresh_file {
+                self.events.file_created(&path_buf);
+            }
             runtime.active_file = Some(ActiveAppendFile {
                 path: path_buf.clone(),
                 day_key,
@@ -654,6 +693,20 @@ impl FileManager {
             .expect("active file initialized")
             .logical_len;
 
+        let tip_block = if fresh_file {
+            self.file_tip.as_deref().and_then(build_plain_tip_block)
+        } else {
+            None
+        };
+        let with_tip: Vec<&[u8]>;
+        let slices = match tip_block.as_deref() {
+            Some(tip) => {
+                with_tip = std::iter::once(tip).chain(slices.iter().copied()).collect();
+                &with_tip[..]
+            }
+            None => slices,
+        };
+
         let written = slices.iter().map(|slice| slice.len() as u64).sum::<u64>();
         let append_begin = Instant::now();
         let result = {
@@ -0,0 +1,440 @@
+//! Live tail of a log directory, decoding entries as they are written.
+//!
+//! [`LogFollower`] tracks every `<prefix>_*.xlog` file in one directory and
+//! decodes only the bytes appended since the last poll, so a viewer can show
+//! new lines while the app keeps logging. A block that is still being written
+//! is left for the next poll, unless a newer file has taken over or its
+//! header claims an impossible length; such blocks and other corrupt regions
+//! are skipped after resynchronizing, with a `[F]` marker entry like
+//! [`Decoder::decode`].
+//!
+//! Only data that has reached the file is seen: async-mode entries appear
+//! after the instance flushes its buffer.
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::decoder::{
+    checked_block_end, find_block_start, log_file_sort_key, push_marker, DecodeError, Decoder,
+    LOG_FILE_EXT,
+};
+use crate::log_entry::{entries, LogEntry};
+
+/// Default delay between directory polls when nothing new was found.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Longest block still treated as being written. Writers flush at most a
+/// 150 KiB buffer per block, so a header claiming more is corrupt.
+const MAX_PENDING_BLOCK_LEN: usize = 1024 * 1024;
+
+/// One decoded entry yielded by a [`LogFollower`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FollowedEntry {
+    /// File the entry was read from.
+    pub path: PathBuf,
+    /// Raw entry text, including continuation lines and the trailing newline.
+    pub text: String,
+}
+
+impl FollowedEntry {
+    /// Parse [`text`](Self::text) into its structured fields.
+    pub fn entry(&self) -> LogEntry<'_> {
+        entries(&self.text).next().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+struct FollowedFile {
+    /// Bytes consumed so far; always a block boundary once synced.
+    offset: u64,
+    /// Bytes read after `offset` that do not form a whole block yet.
+    pending: Vec<u8>,
+    /// Decoded text after the last newline, completed by a later block.
+    partial: String,
+}
+
+impl FollowedFile {
+    /// File position up to which bytes have been read.
+    fn read_end(&self) -> u64 {
+        self.offset + self.pending.len() as u64
+    }
+}
+
+/// Follows the log files of one instance like `tail -f`.
+///
+/// Used as an [`Iterator`], it blocks between polls and never ends; use
+/// [`LogFollower::poll`] to drive it from an existing loop instead. With the
+/// `tokio` feature, [`LogFollower::into_stream`] gives an async `Stream`.
+#[derive(Debug)]
+pub struct LogFollower {
+    dir: PathBuf,
+    prefix: String,
+    decoder: Decoder,
+    poll_interval: Duration,
+    from_start: bool,
+    started: bool,
+    files: HashMap<PathBuf, FollowedFile>,
+    ready: VecDeque<FollowedEntry>,
+}
+
+impl LogFollower {
+    /// Follow files named `<prefix>_*.xlog` in `dir`, starting at their
+    /// current end.
+    pub fn new(dir: impl Into<PathBuf>, prefix: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: prefix.into(),
+            decoder: Decoder::new(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            from_start: false,
+            started: false,
+            files: HashMap::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Use `decoder` for blocks, e.g. one with the private key for encrypted logs.
+    pub fn decoder(mut self, decoder: Decoder) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Also yield entries already in the files when following starts.
+    pub fn from_start(mut self, from_start: bool) -> Self {
+        self.from_start = from_start;
+        self
+    }
+
+    /// Delay between polls while the files are idle.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Read what was appended since the last call, without waiting.
+    ///
+    /// Files are visited in date and rotation order. Files that disappear
+    /// (deleted or moved away) are forgotten.
+    pub fn poll(&mut self) -> Result<Vec<FollowedEntry>, DecodeError> {
+        self.fill()?;
+        Ok(self.ready.drain(..).collect())
+    }
+
+    fn fill(&mut self) -> Result<(), DecodeError> {
+        let paths = self.list_files()?;
+        self.files.retain(|path, _| paths.contains(path));
+        let skip_existing = !self.started && !self.from_start;
+        self.started = true;
+        let last = paths.len().saturating_sub(1);
+        for (idx, path) in paths.into_iter().enumerate() {
+            let len = match fs::metadata(&path) {
+                Ok(meta) => meta.len(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let file = self.files.entry(path.clone()).or_default();
+            if skip_existing {
+                file.offset = len;
+            }
+            if len < file.read_end() {
+                // Truncated or replaced; start over.
+                *file = FollowedFile::default();
+            }
+            // A file followed by a newer one is no longer written to.
+            let rotated = idx < last;
+            let settled = file.pending.is_empty() || !rotated;
+            if len == file.read_end() && settled {
+                continue;
+            }
+            match read_from(&path, file.read_end(), &mut file.pending) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            }
+            let (consumed, text) = decode_complete_blocks(&self.decoder, &file.pending, rotated);
+            file.pending.drain(..consumed);
+            file.offset += consumed as u64;
+            file.partial.push_str(&String::from_utf8_lossy(&text));
+            let complete = file.partial.rfind('\n').map_or(0, |pos| pos + 1);
+            let rest = file.partial.split_off(complete);
+            let done = std::mem::replace(&mut file.partial, rest);
+            self.ready.extend(entries(&done).map(|entry| FollowedEntry {
+                path: path.clone(),
+                text: entry.raw.to_string(),
+            }));
+        }
+        Ok(())
+    }
+
+    fn list_files(&self) -> Result<Vec<PathBuf>, DecodeError> {
+        let file_prefix = format!("{}_", self.prefix);
+        let mut paths = Vec::new();
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(paths),
+            Err(err) => return Err(err.into()),
+        };
+        for entry in read_dir {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if name.starts_with(&file_prefix) && name.ends_with(LOG_FILE_EXT) {
+                paths.push(path);
+            }
+        }
+        paths.sort_by_cached_key(|path| log_file_sort_key(path));
+        Ok(paths)
+    }
+
+    /// Turn this follower into a `Stream` that polls on the tokio timer.
+    ///
+    /// File reads stay synchronous; they only cover newly appended bytes.
+    /// Must be polled inside a tokio runtime with the time driver enabled.
+    #[cfg(feature = "tokio")]
+    pub fn into_stream(self) -> FollowStream {
+        FollowStream {
+            follower: self,
+            sleep: None,
+        }
+    }
+}
+
+impl Iterator for LogFollower {
+    type Item = Result<FollowedEntry, DecodeError>;
+
+    /// Blocks until the next entry is written; never returns `None`.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.ready.pop_front() {
+                return Some(Ok(entry));
+            }
+            if let Err(err) = self.fill() {
+                return Some(Err(err));
+            }
+            if self.ready.is_empty() {
+                thread::sleep(self.poll_interval);
+            }
+        }
+    }
+}
+
+/// Async counterpart of the [`LogFollower`] iterator; see
+/// [`LogFollower::into_stream`].
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct FollowStream {
+    follower: LogFollower,
+    sleep: Option<std::pin::Pin<Box<tokio::time::Sleep>>>,
+}
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for FollowStream {
+    type Item = Result<FollowedEntry, DecodeError>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::future::Future;
+        use std::task::Poll;
+
+        let this = self.get_mut();
+        loop {
+            if let Some(sleep) = this.sleep.as_mut() {
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.sleep = None;
+            }
+            if let Some(entry) = this.follower.ready.pop_front() {
+                return Poll::Ready(Some(Ok(entry)));
+            }
+            if let Err(err) = this.follower.fill() {
+                return Poll::Ready(Some(Err(err)));
+            }
+            if this.follower.ready.is_empty() {
+                this.sleep = Some(Box::pin(tokio::time::sleep(this.follower.poll_interval)));
+            }
+        }
+    }
+}
+
+/// Append the bytes of `path` from `offset` to its current end to `buf`.
+fn read_from(path: &Path, offset: u64, buf: &mut Vec<u8>) -> io::Result<()> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_to_end(buf)?;
+    Ok(())
+}
+
+/// Decode the whole blocks at the front of `input`, returning how many bytes
+/// they span and their text.
+///
+/// A trailing block that is still being written is not consumed, unless the
+/// file has `rotated` or its header claims more than any writer flushes at
+/// once; such a block is corrupt and skipped like the lenient decoder does.
+fn decode_complete_blocks(decoder: &Decoder, input: &[u8], rotated: bool) -> (usize, Vec<u8>) {
+    let mut out = Vec::new();
+    let mut offset = 0;
+    while offset < input.len() {
+        let err = match checked_block_end(input, offset) {
+            Ok(end) => {
+                match decoder.decode_block_at(input, offset) {
+                    Ok(text) => out.extend_from_slice(&text),
+                    Err(err) => push_marker(&mut out, format_args!("{err}")),
+                }
+                offset = end;
+                continue;
+            }
+            Err(DecodeError::TruncatedBlock { needed, .. })
+                if !rotated && needed <= MAX_PENDING_BLOCK_LEN =>
+            {
+                break;
+            }
+            Err(err) => err,
+        };
+        let next = match find_block_start(input, offset + 1, 1) {
+            Some(next) => next,
+            // Nothing more will be appended to a rotated file.
+            None if rotated => input.len(),
+            // The next block may still be on its way.
+            None => break,
+        };
+        push_marker(
+            &mut out,
+            format_args!("{err}; skipped {} bytes", next - offset),
+        );
+        offset = next;
+    }
+    (offset, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::Path;
+
+    use super::LogFollower;
+    use crate::protocol::HEADER_LEN_OFFSET;
+    use crate::recovery::build_plain_tip_block;
+
+    fn append(path: &Path, bytes: &[u8]) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
+    fn line(msg: &str) -> String {
+        format!("[I][2024-01-02 +8.0 10:00:00.000][1, 2][t][a.rs:1, f][{msg}\n")
+    }
+
+    #[test]
+    fn yields_appended_entries_and_waits_for_partial_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("app_20240102.xlog");
+        append(&first, &build_plain_tip_block(&line("old")).unwrap());
+        fs::write(dir.path().join("other_20240102.xlog"), b"ignored").unwrap();
+
+        let mut follower = LogFollower::new(dir.path(), "app");
+        assert!(follower.poll().unwrap().is_empty());
+
+        let block = build_plain_tip_block(&(line("a") + &line("b"))).unwrap();
+        let (head, tail) = block.split_at(block.len() / 2);
+        append(&first, head);
+        assert!(follower.poll().unwrap().is_empty());
+        append(&first, tail);
+        let got = follower.poll().unwrap();
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[0].entry().msg, "a");
+        assert_eq!(got[1].path, first);
+
+        let rotated = dir.path().join("app_20240102_1.xlog");
+        append(&rotated, b"junk");
+        append(&rotated, &build_plain_tip_block(&line("c")).unwrap());
+        let got: Vec<_> = follower
+            .poll()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.text)
+            .collect();
+        assert_eq!(got.len(), 2);
+        assert!(got[0].starts_with("[F]xlog decoder: bad block magic"));
+        assert_eq!(got[1], line("c"));
+
+        let mut replay = LogFollower::new(dir.path(), "app").from_start(true);
+        let msgs: Vec<String> = replay
+            .by_ref()
+            .take(3)
+            .map(|entry| entry.unwrap().entry().msg.to_string())
+            .collect();
+        assert_eq!(msgs, ["old", "a", "b"]);
+
+        #[cfg(feature = "tokio")]
+        {
+            use futures_core::Stream;
+
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+                .unwrap();
+            let mut stream = std::pin::pin!(LogFollower::new(dir.path(), "app")
+                .poll_interval(std::time::Duration::from_millis(10))
+                .into_stream());
+            let writer_path = rotated.clone();
+            let next = runtime.block_on(async move {
+                let writer = tokio::spawn(async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+                    append(&writer_path, &build_plain_tip_block(&line("live")).unwrap());
+                });
+                let next = std::future::poll_fn(|cx| stream.as_mut().poll_next(cx)).await;
+                writer.await.unwrap();
+                next
+            });
+            assert_eq!(next.unwrap().unwrap().entry().msg, "live");
+        }
+    }
+
+    #[test]
+    fn skips_impossible_and_abandoned_blocks_instead_of_waiting() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("app_20240102.xlog");
+        let mut follower = LogFollower::new(dir.path(), "app").from_start(true);
+
+        // A corrupt length far past anything a writer flushes.
+        let mut bogus = build_plain_tip_block(&line("lost")).unwrap();
+        bogus[HEADER_LEN_OFFSET..HEADER_LEN_OFFSET + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        append(&first, &bogus);
+        assert!(follower.poll().unwrap().is_empty());
+        append(&first, &build_plain_tip_block(&line("a")).unwrap());
+        let got = follower.poll().unwrap();
+        assert_eq!(got.len(), 2);
+        assert!(got[0]
+            .text
+            .starts_with("[F]xlog decoder: truncated block at offset 0"));
+        assert_eq!(got[1].entry().msg, "a");
+
+        // Bytes read once are kept until their block completes.
+        let block = build_plain_tip_block(&line("cut")).unwrap();
+        append(&first, &block[..block.len() / 2]);
+        assert!(follower.poll().unwrap().is_empty());
+        assert_eq!(follower.files[&first].pending.len(), block.len() / 2);
+
+        // Once a newer file exists, the half block will never be finished.
+        let rotated = dir.path().join("app_20240102_1.xlog");
+        append(&rotated, &build_plain_tip_block(&line("b")).unwrap());
+        let got = follower.poll().unwrap();
+        assert_eq!(got.len(), 2);
+        assert!(got[0].text.starts_with("[F]xlog decoder: truncated block"));
+        assert_eq!(got[0].path, first);
+        assert_eq!(got[1].entry().msg, "b");
+        assert!(follower.files[&first].pending.is_empty());
+    }
+}
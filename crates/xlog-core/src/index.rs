@@ -0,0 +1,406 @@
+//! Sidecar block index for large xlog files.
+//!
+//! Building an index decodes the file once and records, per block, its byte
+//! range, the first and last entry times, and the tags it contains. Later
+//! seek-by-time and tag-filtered reads decode only the blocks that can match.
+//!
+//! The index is stored next to the log as `<file>.idx`, in a line-based text
+//! format:
+//!
+//! ```text
+//! xlog-index 1 <file length>
+//! <offset>\t<block len>\t<first time|->\t<last time|->[\t<tag>]...
+//! ```
+//!
+//! Times use the sortable `YYYY-MM-DD HH:MM:SS.mmm` form of
+//! [`LogEntry::time_key`](crate::log_entry::LogEntry::time_key); tabs,
+//! newlines and backslashes in tags are backslash-escaped. Log files only
+//! grow, so an index whose recorded length differs from the file is stale and
+//! [`LogIndex::load_or_build`] rebuilds it.
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::decoder::{scan, DecodeError, Decoder, ScannedBlock};
+use crate::log_entry::{entries, LogEntry};
+
+const INDEX_MAGIC: &str = "xlog-index";
+const INDEX_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+/// Errors returned while reading, writing, or using a [`LogIndex`].
+pub enum IndexError {
+    /// Reading the log or index file failed.
+    #[error("index io failed: {0}")]
+    Io(#[from] io::Error),
+    /// Decoding an indexed block failed.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    /// The sidecar file could not be parsed.
+    #[error("malformed index at line {line}: {reason}")]
+    Malformed {
+        /// 1-based line number in the sidecar file.
+        line: usize,
+        /// What was wrong with the line.
+        reason: &'static str,
+    },
+}
+
+/// Index record for one block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedBlock {
+    /// Byte offset of the block in the log file.
+    pub offset: usize,
+    /// Total block length, including header and tail marker.
+    pub len: usize,
+    /// Earliest entry time in the block, as `YYYY-MM-DD HH:MM:SS.mmm`.
+    pub first_time: Option<String>,
+    /// Latest entry time in the block.
+    pub last_time: Option<String>,
+    /// Distinct tags in the block, in first-seen order.
+    pub tags: Vec<String>,
+}
+
+impl IndexedBlock {
+    /// Whether the block may hold entries between `from` and `to` inclusive.
+    ///
+    /// Bounds compare as strings, so prefixes such as `2024-01-02 10` work.
+    /// Blocks without timestamped entries never overlap.
+    pub fn overlaps(&self, from: &str, to: &str) -> bool {
+        match (&self.first_time, &self.last_time) {
+            (Some(first), Some(last)) => last.as_str() >= from && not_after(first, to),
+            _ => false,
+        }
+    }
+
+    /// Whether any entry in the block carries `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// Block index of one xlog file; see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogIndex {
+    /// Length of the log file the index was built from.
+    pub file_len: u64,
+    /// Indexed blocks in file order.
+    pub blocks: Vec<IndexedBlock>,
+}
+
+impl LogIndex {
+    /// Decode every block of `input` once and record its times and tags.
+    ///
+    /// Blocks that fail to decode are indexed without times or tags.
+    pub fn build(input: &[u8], decoder: &Decoder) -> Self {
+        let mut blocks = Vec::new();
+        for item in scan(input) {
+            let ScannedBlock::Block(info) = item else {
+                continue;
+            };
+            let mut block = IndexedBlock {
+                offset: info.offset,
+                len: info.block_len,
+                first_time: None,
+                last_time: None,
+                tags: Vec::new(),
+            };
+            if let Ok(plain) = decoder.decode_block(input, &info) {
+                let text = String::from_utf8_lossy(&plain);
+                for entry in entries(&text) {
+                    if entry.level.is_none() {
+                        continue;
+                    }
+                    if let Some(time) = sortable_time(&entry) {
+                        if block.first_time.as_ref().is_none_or(|first| time < *first) {
+                            block.first_time = Some(time.clone());
+                        }
+                        if block.last_time.as_ref().is_none_or(|last| time > *last) {
+                            block.last_time = Some(time);
+                        }
+                    }
+                    if !block.has_tag(entry.tag) {
+                        block.tags.push(entry.tag.to_string());
+                    }
+                }
+            }
+            blocks.push(block);
+        }
+        Self {
+            file_len: input.len() as u64,
+            blocks,
+        }
+    }
+
+    /// Sidecar path for `log`: the same path with `.idx` appended.
+    pub fn sidecar_path(log: impl AsRef<Path>) -> PathBuf {
+        let mut path = log.as_ref().as_os_str().to_owned();
+        path.push(".idx");
+        PathBuf::from(path)
+    }
+
+    /// Load the sidecar index for `log`, rebuilding and rewriting it when it
+    /// is missing, unreadable, or stale.
+    ///
+    /// Failing to write the rebuilt sidecar (e.g. a read-only directory) is
+    /// not an error; the fresh index is still returned.
+    pub fn load_or_build(log: impl AsRef<Path>, decoder: &Decoder) -> Result<Self, IndexError> {
+        let log = log.as_ref();
+        let input = fs::read(log)?;
+        let sidecar = Self::sidecar_path(log);
+        if let Ok(text) = fs::read_to_string(&sidecar) {
+            if let Ok(index) = Self::parse(&text) {
+                if index.file_len == input.len() as u64 {
+                    return Ok(index);
+                }
+            }
+        }
+        let index = Self::build(&input, decoder);
+        let mut text = Vec::new();
+        index.write_to(&mut text)?;
+        let _ = fs::write(&sidecar, text);
+        Ok(index)
+    }
+
+    /// Serialize in the sidecar format.
+    pub fn write_to<W: Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{INDEX_MAGIC} {INDEX_VERSION} {}", self.file_len)?;
+        for block in &self.blocks {
+            write!(
+                out,
+                "{}\t{}\t{}\t{}",
+                block.offset,
+                block.len,
+                block.first_time.as_deref().unwrap_or("-"),
+                block.last_time.as_deref().unwrap_or("-")
+            )?;
+            for tag in &block.tags {
+                write!(out, "\t{}", escape_tag(tag))?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Parse the sidecar format written by [`LogIndex::write_to`].
+    pub fn parse(text: &str) -> Result<Self, IndexError> {
+        let mut lines = text.lines();
+        let malformed = |line, reason| IndexError::Malformed { line, reason };
+        let header = lines.next().ok_or(malformed(1, "empty index"))?;
+        let mut fields = header.split(' ');
+        if fields.next() != Some(INDEX_MAGIC) {
+            return Err(malformed(1, "missing xlog-index header"));
+        }
+        if fields.next().and_then(|v| v.parse::<u32>().ok()) != Some(INDEX_VERSION) {
+            return Err(malformed(1, "unsupported index version"));
+        }
+        let file_len = fields
+            .next()
+            .and_then(|v| v.parse().ok())
+            .ok_or(malformed(1, "missing file length"))?;
+
+        let mut blocks = Vec::new();
+        for (idx, line) in lines.enumerate() {
+            let line_no = idx + 2;
+            let mut fields = line.split('\t');
+            let mut number = || -> Result<usize, IndexError> {
+                fields
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or(malformed(line_no, "bad block offset or length"))
+            };
+            let offset = number()?;
+            let len = number()?;
+            let mut time = || match fields.next() {
+                Some("-") => Ok(None),
+                Some(time) => Ok(Some(time.to_string())),
+                None => Err(malformed(line_no, "missing block time")),
+            };
+            let first_time = time()?;
+            let last_time = time()?;
+            blocks.push(IndexedBlock {
+                offset,
+                len,
+                first_time,
+                last_time,
+                tags: fields.map(unescape_tag).collect(),
+            });
+        }
+        Ok(Self { file_len, blocks })
+    }
+
+    /// Decode the entries of `input` between `from` and `to` inclusive,
+    /// touching only blocks whose time range overlaps.
+    ///
+    /// Bounds are compared against `YYYY-MM-DD HH:MM:SS.mmm` as strings, so a
+    /// prefix like `2024-01-02 10` selects a whole hour.
+    pub fn decode_time_range(
+        &self,
+        input: &[u8],
+        decoder: &Decoder,
+        from: &str,
+        to: &str,
+    ) -> Result<String, IndexError> {
+        self.decode_filtered(
+            input,
+            decoder,
+            |block| block.overlaps(from, to),
+            |entry| {
+                sortable_time(entry)
+                    .is_some_and(|time| time.as_str() >= from && not_after(&time, to))
+            },
+        )
+    }
+
+    /// Decode only the entries tagged `tag`, touching only blocks that hold it.
+    pub fn decode_tag(
+        &self,
+        input: &[u8],
+        decoder: &Decoder,
+        tag: &str,
+    ) -> Result<String, IndexError> {
+        self.decode_filtered(
+            input,
+            decoder,
+            |block| block.has_tag(tag),
+            |entry| entry.level.is_some() && entry.tag == tag,
+        )
+    }
+
+    fn decode_filtered(
+        &self,
+        input: &[u8],
+        decoder: &Decoder,
+        block_filter: impl Fn(&IndexedBlock) -> bool,
+        entry_filter: impl Fn(&LogEntry<'_>) -> bool,
+    ) -> Result<String, IndexError> {
+        let mut out = String::new();
+        for block in self.blocks.iter().filter(|block| block_filter(block)) {
+            let plain = decoder.decode_block_at(input, block.offset)?;
+            let text = String::from_utf8_lossy(&plain);
+            for entry in entries(&text).filter(|entry| entry_filter(entry)) {
+                out.push_str(entry.raw);
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS.mmm`, dropping the zone offset like `time_key`.
+fn sortable_time(entry: &LogEntry<'_>) -> Option<String> {
+    entry
+        .time_key()
+        .map(|(date, clock)| format!("{date} {clock}"))
+}
+
+/// `time <= to`, treating `to` as inclusive of everything it prefixes.
+fn not_after(time: &str, to: &str) -> bool {
+    time <= to || time.starts_with(to)
+}
+
+fn escape_tag(tag: &str) -> String {
+    let mut out = String::with_capacity(tag.len());
+    for ch in tag.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+fn unescape_tag(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::LogIndex;
+    use crate::decoder::Decoder;
+    use crate::protocol::{LogHeader, MAGIC_END, MAGIC_SYNC_NO_CRYPT_ZLIB_START};
+
+    fn block(lines: &[(&str, &str, &str)]) -> Vec<u8> {
+        let payload: String = lines
+            .iter()
+            .map(|(time, tag, msg)| {
+                format!("[I][2024-01-02 +8.0 {time}][1, 2][{tag}][a.rs:1, f][{msg}\n")
+            })
+            .collect();
+        let header = LogHeader {
+            magic: MAGIC_SYNC_NO_CRYPT_ZLIB_START,
+            seq: 0,
+            begin_hour: 9,
+            end_hour: 11,
+            len: payload.len() as u32,
+            client_pubkey: [0; 64],
+        };
+        let mut out = header.encode().to_vec();
+        out.extend_from_slice(payload.as_bytes());
+        out.push(MAGIC_END);
+        out
+    }
+
+    #[test]
+    fn seeks_by_time_and_tag_and_round_trips_the_sidecar() {
+        let mut input = block(&[("09:00:00.000", "net", "a"), ("09:30:00.000", "ui", "b")]);
+        input.extend(block(&[("10:00:00.000", "net", "c")]));
+        input.extend(block(&[("11:00:00.000", "db\ttab", "d")]));
+        let decoder = Decoder::new();
+
+        let index = LogIndex::build(&input, &decoder);
+        assert_eq!(index.blocks.len(), 3);
+        assert_eq!(
+            index.blocks[0].first_time.as_deref(),
+            Some("2024-01-02 09:00:00.000")
+        );
+        assert_eq!(index.blocks[0].tags, ["net", "ui"]);
+
+        let net = index.decode_tag(&input, &decoder, "net").unwrap();
+        assert_eq!(net.lines().count(), 2);
+        assert!(net.ends_with("][c\n"));
+
+        let hour = index
+            .decode_time_range(&input, &decoder, "2024-01-02 09:15", "2024-01-02 10")
+            .unwrap();
+        let msgs: Vec<_> = hour
+            .lines()
+            .map(|l| l.rsplit("][").next().unwrap())
+            .collect();
+        assert_eq!(msgs, ["b", "c"]);
+
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("app_20240102.xlog");
+        std::fs::write(&log, &input).unwrap();
+        let built = LogIndex::load_or_build(&log, &decoder).unwrap();
+        assert_eq!(built, index);
+        let sidecar = std::fs::read_to_string(LogIndex::sidecar_path(&log)).unwrap();
+        assert_eq!(LogIndex::parse(&sidecar).unwrap(), index);
+
+        input.extend(block(&[("12:00:00.000", "net", "e")]));
+        std::fs::write(&log, &input).unwrap();
+        let rebuilt = LogIndex::load_or_build(&log, &decoder).unwrap();
+        assert_eq!(rebuilt.blocks.len(), 4);
+    }
+}
@@ -14,8 +14,12 @@ pub mod buffer;
 pub mod compress;
 /// ECDH+TEA encryption helpers.
 pub mod crypto;
+/// Best-effort decoder turning xlog files back into plaintext.
+pub mod decoder;
 /// Human-readable dump utilities for log buffers.
 pub mod dump;
+/// Created/rotated/removed notifications for log files.
+pub mod file_events;
 mod file_maintenance;
 /// File lifecycle, cache movement, and active log append helpers.
 pub mod file_manager;
@@ -24,8 +28,14 @@ mod file_ops;
 mod file_policy;
 mod file_runtime;
 mod file_target;
+/// Live tail of a log directory, decoding entries as they are written.
+pub mod follow;
 /// Line formatter used by the Rust runtime path.
 pub mod formatter;
+/// Sidecar block index for seek-by-time and tag-filtered decodes.
+pub mod index;
+/// Structured parsing of decoded log lines.
+pub mod log_entry;
 mod metrics;
 /// Thin mmap storage wrapper used by persistent buffers.
 pub mod mmap_store;
@@ -42,3 +52,5 @@ pub mod record;
 mod recovery;
 /// Instance registry helpers.
 pub mod registry;
+/// Interned C strings for console log tags.
+pub mod tag_intern;
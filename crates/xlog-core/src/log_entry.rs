@@ -0,0 +1,192 @@
+use crate::record::LogLevel;
+
+const DECODER_MARKER_PREFIX: &str = "[F]xlog decoder: ";
+
+/// One log line parsed back out of decoded xlog text.
+///
+/// Fields borrow from the decoded text. Lines that do not follow the mars
+/// layout (foreign output, decoder markers) are still yielded, with only
+/// [`msg`](Self::msg) and [`raw`](Self::raw) populated, so nothing is
+/// silently dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LogEntry<'a> {
+    /// Timestamp text as written by the formatter, e.g. `2024-01-02 +8.0 10:11:12.345`.
+    pub time: Option<&'a str>,
+    /// Severity, if the line carried a known level letter.
+    pub level: Option<LogLevel>,
+    /// User tag/category.
+    pub tag: &'a str,
+    /// Process id.
+    pub pid: Option<i64>,
+    /// Thread id.
+    pub tid: Option<i64>,
+    /// Whether the line was written from the main thread (`*` suffix on the tid).
+    pub main_thread: bool,
+    /// Source file name.
+    pub file: &'a str,
+    /// Source line number.
+    pub line: Option<i32>,
+    /// Function name.
+    pub func: &'a str,
+    /// Message body without the trailing newline; multi-line bodies keep their inner newlines.
+    pub msg: &'a str,
+    /// Full source text of the entry, including continuation lines and the trailing newline.
+    pub raw: &'a str,
+}
+
+impl<'a> LogEntry<'a> {
+    /// Parse a single mars-formatted line, returning `None` for foreign layouts.
+    pub fn parse(raw: &'a str) -> Option<Self> {
+        let line = trim_newline(raw);
+        let rest = line.strip_prefix('[')?;
+        let (level, rest) = rest.split_once("][")?;
+        let level = LogLevel::from_short(level)?;
+        let (time, rest) = rest.split_once("][")?;
+        let (ids, rest) = rest.split_once("][")?;
+        let (pid, tid) = ids.split_once(", ")?;
+        let (tid, main_thread) = match tid.strip_suffix('*') {
+            Some(tid) => (tid, true),
+            None => (tid, false),
+        };
+        let (tag, rest) = rest.split_once("][")?;
+        let (location, msg) = rest.split_once("][")?;
+        let (file_line, func) = location.split_once(", ")?;
+        let (file, line_no) = file_line.rsplit_once(':')?;
+
+        Some(Self {
+            time: Some(time),
+            level: Some(level),
+            tag,
+            pid: pid.parse().ok(),
+            tid: tid.parse().ok(),
+            main_thread,
+            file,
+            line: line_no.parse().ok(),
+            func,
+            msg,
+            raw,
+        })
+    }
+
+    /// Sortable `(date, clock)` parts of [`time`](Self::time).
+    ///
+    /// The zone offset is skipped: files from one device share it, and it is
+    /// not zero padded, so it would break lexicographic ordering.
+    pub fn time_key(&self) -> Option<(&'a str, &'a str)> {
+        let time = self.time?;
+        let date = time.split(' ').next().unwrap_or_default();
+        let clock = time.rsplit(' ').next().unwrap_or_default();
+        Some((date, clock))
+    }
+
+    fn foreign(raw: &'a str) -> Self {
+        Self {
+            msg: trim_newline(raw),
+            raw,
+            ..Self::default()
+        }
+    }
+}
+
+/// Iterator over the [`LogEntry`] values in decoded xlog text.
+///
+/// Created by [`entries`]. Lines that do not start a mars record are treated
+/// as continuations of the previous record's message, matching how multi-line
+/// bodies are written; such lines before the first record, and decoder marker
+/// lines, are yielded as foreign entries.
+#[derive(Debug, Clone)]
+pub struct LogEntries<'a> {
+    rest: &'a str,
+}
+
+/// Iterate over the structured entries of decoded xlog text.
+pub fn entries(text: &str) -> LogEntries<'_> {
+    LogEntries { rest: text }
+}
+
+impl<'a> Iterator for LogEntries<'a> {
+    type Item = LogEntry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let text = self.rest;
+        let first_end = line_end(text, 0);
+        let first = &text[..first_end];
+
+        let Some(mut entry) = LogEntry::parse(first) else {
+            self.rest = &text[first_end..];
+            return Some(LogEntry::foreign(first));
+        };
+
+        let mut end = first_end;
+        while end < text.len() {
+            let next_end = line_end(text, end);
+            let next = &text[end..next_end];
+            if next.starts_with(DECODER_MARKER_PREFIX) || LogEntry::parse(next).is_some() {
+                break;
+            }
+            end = next_end;
+        }
+        if end > first_end {
+            // Extend the borrowed body across the continuation lines.
+            let msg_start = entry.msg.as_ptr() as usize - text.as_ptr() as usize;
+            entry.msg = trim_newline(&text[msg_start..end]);
+            entry.raw = &text[..end];
+        }
+        self.rest = &text[end..];
+        Some(entry)
+    }
+}
+
+fn line_end(text: &str, from: usize) -> usize {
+    text[from..]
+        .find('\n')
+        .map_or(text.len(), |idx| from + idx + 1)
+}
+
+fn trim_newline(line: &str) -> &str {
+    line.strip_suffix('\n').unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{entries, LogEntry};
+    use crate::record::LogLevel;
+
+    #[test]
+    fn parses_mars_lines_and_keeps_foreign_ones() {
+        let text = "boot banner\n\
+            [I][2024-01-02 +8.0 10:11:12.345][123, 456*][net][client.rs:42, connect][hello\n\
+            [E][2024-01-02 +8.0 10:11:13.000][123, 789][db][store.rs:7, ][first\nsecond\n\
+            [F]xlog decoder: log seq:3-4 is missing\n";
+
+        let parsed: Vec<LogEntry<'_>> = entries(text).collect();
+        assert_eq!(parsed.len(), 4);
+
+        assert_eq!(parsed[0].msg, "boot banner");
+        assert_eq!(parsed[0].level, None);
+
+        let first = parsed[1];
+        assert_eq!(first.level, Some(LogLevel::Info));
+        assert_eq!(first.time, Some("2024-01-02 +8.0 10:11:12.345"));
+        assert_eq!((first.pid, first.tid), (Some(123), Some(456)));
+        assert!(first.main_thread);
+        assert_eq!(first.tag, "net");
+        assert_eq!((first.file, first.line), ("client.rs", Some(42)));
+        assert_eq!(first.func, "connect");
+        assert_eq!(first.msg, "hello");
+        assert_eq!(first.time_key(), Some(("2024-01-02", "10:11:12.345")));
+
+        let second = parsed[2];
+        assert_eq!(second.level, Some(LogLevel::Error));
+        assert!(!second.main_thread);
+        assert_eq!(second.func, "");
+        assert_eq!(second.msg, "first\nsecond");
+        assert!(second.raw.starts_with("[E][") && second.raw.ends_with("second\n"));
+
+        assert_eq!(parsed[3].msg, "[F]xlog decoder: log seq:3-4 is missing");
+        assert_eq!(parsed[3].level, None);
+    }
+}
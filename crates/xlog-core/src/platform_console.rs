@@ -31,6 +31,14 @@ use crate::formatter::extract_file_name;
 ))]
 use crate::platform_tid::{current_tid, main_tid};
 use crate::record::LogLevel;
+#[cfg(any(
+    target_os = "android",
+    target_os = "ios",
+    target_os = "macos",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+use crate::tag_intern;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 /// Apple console sink selection used by [`set_apple_console_fun`].
@@ -51,6 +59,28 @@ pub enum AppleConsoleFun {
 ))]
 static APPLE_CONSOLE_FUN: AtomicU8 = AtomicU8::new(AppleConsoleFun::OsLog as u8);
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Which parts of an `os_log` console line Console.app shows unredacted.
+///
+/// The tag is the `os_log` category and is always public. Only affects the
+/// [`AppleConsoleFun::OsLog`] sink.
+pub enum AppleOsLogPrivacy {
+    /// File, function and message are public.
+    Public = 0,
+    /// File and function are public; the message is `<private>`.
+    PrivateMessage = 1,
+    /// File, function and message are all `<private>`.
+    Private = 2,
+}
+
+#[cfg(any(
+    target_os = "ios",
+    target_os = "macos",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+static APPLE_OSLOG_PRIVACY: AtomicU8 = AtomicU8::new(AppleOsLogPrivacy::Public as u8);
+
 #[cfg(any(
     target_os = "ios",
     target_os = "macos",
@@ -71,6 +101,26 @@ pub fn set_apple_console_fun(fun: AppleConsoleFun) {
 /// No-op on non-Apple targets.
 pub fn set_apple_console_fun(_fun: AppleConsoleFun) {}
 
+#[cfg(any(
+    target_os = "ios",
+    target_os = "macos",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+/// Select which parts of `os_log` console lines are public.
+pub fn set_apple_oslog_privacy(privacy: AppleOsLogPrivacy) {
+    APPLE_OSLOG_PRIVACY.store(privacy as u8, Ordering::Relaxed);
+}
+
+#[cfg(not(any(
+    target_os = "ios",
+    target_os = "macos",
+    target_os = "tvos",
+    target_os = "watchos"
+)))]
+/// No-op on non-Apple targets.
+pub fn set_apple_oslog_privacy(_privacy: AppleOsLogPrivacy) {}
+
 /// Forward one formatted log line to the platform console when supported.
 ///
 /// Empty messages are ignored. Android uses `__android_log_write`, Apple
@@ -108,13 +158,14 @@ pub fn write_console_line(
         {
             let mode = APPLE_CONSOLE_FUN.load(Ordering::Relaxed);
             if mode == AppleConsoleFun::OsLog as u8 {
-                let c_tag = to_console_cstring(tag);
+                let c_tag = tag_intern::c_tag(tag);
                 let c_file = to_console_cstring(file_name);
                 let c_func = to_console_cstring(func_name);
                 let c_msg = to_console_cstring(msg);
                 unsafe {
                     xlog_core_apple_console_oslog(
                         apple_level(level),
+                        i32::from(APPLE_OSLOG_PRIVACY.load(Ordering::Relaxed)),
                         c_tag.as_ptr(),
                         c_file.as_ptr(),
                         line as i32,
@@ -234,8 +285,7 @@ fn write_android_line(level: LogLevel, tag: &str, file: &str, func: &str, line:
     let mut out = format!("[{file_name}:{line}, {func_name}]:{msg}");
     out = out.replace('\0', " ");
     let tag = if tag.is_empty() { "mars-xlog" } else { tag };
-    let safe_tag = tag.replace('\0', " ");
-    let c_tag = CString::new(safe_tag).expect("nul bytes replaced");
+    let c_tag = tag_intern::c_tag(tag);
     let c_msg = CString::new(out).expect("nul bytes replaced");
     unsafe {
         __android_log_write(android_priority(level), c_tag.as_ptr(), c_msg.as_ptr());
@@ -271,6 +321,7 @@ unsafe extern "C" {
     fn xlog_core_apple_console_nslog(text: *const libc::c_char);
     fn xlog_core_apple_console_oslog(
         level: i32,
+        privacy: i32,
         tag: *const libc::c_char,
         file: *const libc::c_char,
         line: i32,
@@ -33,6 +33,14 @@ pub const MAGIC_ASYNC_ZSTD_START: u8 = 0x0C;
 /// Magic byte for async + zstd + plaintext blocks.
 pub const MAGIC_ASYNC_NO_CRYPT_ZSTD_START: u8 = 0x0D;
 
+/// Magic byte for pre-zstd uncompressed blocks with a 4-byte crypt key field.
+pub const MAGIC_LEGACY_NO_COMPRESS_START: u8 = 0x03;
+/// Magic byte for pre-zstd raw-deflate blocks with a 4-byte crypt key field.
+pub const MAGIC_LEGACY_COMPRESS_START: u8 = 0x04;
+/// Magic byte for a pre-zstd raw-deflate stream split into
+/// `u16`-length-prefixed chunks.
+pub const MAGIC_LEGACY_COMPRESS_START1: u8 = 0x05;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 /// Compression family encoded into the xlog magic byte.
 pub enum CompressionKind {
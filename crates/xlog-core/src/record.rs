@@ -32,6 +32,20 @@ impl LogLevel {
             LogLevel::None => "N",
         }
     }
+
+    /// Parse a single-letter level tag produced by [`LogLevel::short`].
+    pub fn from_short(short: &str) -> Option<Self> {
+        match short {
+            "V" => Some(LogLevel::Verbose),
+            "D" => Some(LogLevel::Debug),
+            "I" => Some(LogLevel::Info),
+            "W" => Some(LogLevel::Warn),
+            "E" => Some(LogLevel::Error),
+            "F" => Some(LogLevel::Fatal),
+            "N" => Some(LogLevel::None),
+            _ => None,
+        }
+    }
 }
 
 /// Rust-native representation of a log entry metadata block.
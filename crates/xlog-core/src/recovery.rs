@@ -42,6 +42,20 @@ pub(crate) fn build_sync_tip_block(sample_header: Option<LogHeader>, tip: &str)
     Some(out)
 }
 
+/// Builds an unencrypted sync block carrying `tip`, for callers that have no
+/// pending block to copy the crypto profile from.
+pub(crate) fn build_plain_tip_block(tip: &str) -> Option<Vec<u8>> {
+    let sample = LogHeader {
+        magic: select_magic(CompressionKind::Zlib, AppendMode::Sync, false),
+        seq: 0,
+        begin_hour: 0,
+        end_hour: 0,
+        len: 0,
+        client_pubkey: [0; 64],
+    };
+    build_sync_tip_block(Some(sample), tip)
+}
+
 pub(crate) fn current_mark_info() -> String {
     let now = Local::now();
     format!(
@@ -0,0 +1,119 @@
+//! Interned C strings for log tags.
+//!
+//! Console sinks hand the tag to a C API on every call. Apps use a handful of
+//! distinct tags, so the first [`MAX_INTERNED`] short tags are converted once
+//! and kept for the life of the process; later lookups are a hash, a few
+//! atomic loads and a string compare, with no allocation and no NUL scan.
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// Slots in the intern table; tags beyond this are converted per call.
+pub const MAX_INTERNED: usize = 128;
+/// Longer tags are not interned, so the table cannot pin large strings.
+pub const MAX_INTERNED_TAG_LEN: usize = 64;
+const MAX_PROBES: usize = 8;
+
+struct Entry {
+    tag: Box<str>,
+    c_tag: CString,
+}
+
+static SLOTS: [AtomicPtr<Entry>; MAX_INTERNED] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; MAX_INTERNED];
+
+/// Returns `tag` as a C string, interning it when there is room.
+///
+/// NUL bytes are replaced with spaces; such tags are never interned.
+pub fn c_tag(tag: &str) -> Cow<'static, CStr> {
+    match intern(tag) {
+        Some(c_tag) => Cow::Borrowed(c_tag),
+        None => Cow::Owned(
+            CString::new(tag.replace('\0', " ")).expect("console tag nul bytes replaced"),
+        ),
+    }
+}
+
+/// Returns the interned C string for `tag`, inserting it if a slot is free.
+///
+/// `None` when the tag is too long, contains a NUL byte, or its probe window
+/// is full.
+pub fn intern(tag: &str) -> Option<&'static CStr> {
+    if tag.len() > MAX_INTERNED_TAG_LEN {
+        return None;
+    }
+    let start = hash(tag) % MAX_INTERNED;
+    let mut pending: Option<Box<Entry>> = None;
+    for probe in 0..MAX_PROBES {
+        let slot = &SLOTS[(start + probe) % MAX_INTERNED];
+        let current = slot.load(Ordering::Acquire);
+        if !current.is_null() {
+            // SAFETY: published entries are leaked and never freed.
+            let entry = unsafe { &*current };
+            if &*entry.tag == tag {
+                return Some(entry.c_tag.as_c_str());
+            }
+            continue;
+        }
+
+        let entry = match pending.take() {
+            Some(entry) => entry,
+            None => Box::new(Entry {
+                tag: tag.into(),
+                c_tag: CString::new(tag).ok()?,
+            }),
+        };
+        let raw = Box::into_raw(entry);
+        match slot.compare_exchange(ptr::null_mut(), raw, Ordering::AcqRel, Ordering::Acquire) {
+            // SAFETY: `raw` is now owned by the table and never freed.
+            Ok(_) => return Some(unsafe { &*raw }.c_tag.as_c_str()),
+            Err(winner) => {
+                // SAFETY: the CAS failed, so `raw` was never published.
+                pending = Some(unsafe { Box::from_raw(raw) });
+                // SAFETY: published entries are leaked and never freed.
+                let entry = unsafe { &*winner };
+                if &*entry.tag == tag {
+                    return Some(entry.c_tag.as_c_str());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// FNV-1a; tags are short, so a simple byte hash is enough.
+fn hash(tag: &str) -> usize {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in tag.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{c_tag, intern, MAX_INTERNED_TAG_LEN};
+
+    #[test]
+    fn repeated_tags_share_one_c_string() {
+        let first = intern("intern-test").expect("interned");
+        let second = intern("intern-test").expect("interned");
+        assert_eq!(first.to_str(), Ok("intern-test"));
+        assert!(std::ptr::eq(first, second));
+        assert!(matches!(c_tag("intern-test"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn nul_and_long_tags_fall_back_to_owned() {
+        assert!(intern("bad\0tag").is_none());
+        assert_eq!(c_tag("bad\0tag").to_str(), Ok("bad tag"));
+
+        let long = "t".repeat(MAX_INTERNED_TAG_LEN + 1);
+        assert!(intern(&long).is_none());
+        assert!(matches!(c_tag(&long), Cow::Owned(_)));
+    }
+}
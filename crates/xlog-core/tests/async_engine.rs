@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 use std::fs;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
@@ -371,6 +371,31 @@ fn sync_mode_rejects_async_pending_apis() {
     ));
 }
 
+#[test]
+fn error_handler_sees_rejected_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager =
+        FileManager::new(dir.path().to_path_buf(), None, "errors".to_string(), 0).unwrap();
+    let buffer =
+        PersistentBuffer::open_with_capacity(manager.mmap_path(), DEFAULT_BUFFER_BLOCK_LEN)
+            .unwrap();
+    let engine = AppenderEngine::new(manager, buffer, EngineMode::Sync, 0, 10 * 24 * 60 * 60);
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    engine.set_error_handler(Some(Arc::new(move |err: &AppenderEngineError| {
+        sink.lock().unwrap().push(err.to_string());
+    })));
+
+    engine.write_block(&make_block(0, "ok"), false).unwrap();
+    assert!(engine.write_block(b"not a block", false).is_err());
+    engine.set_error_handler(None);
+    assert!(engine.write_block(b"not a block", false).is_err());
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    assert!(seen[0].starts_with("buffer error"));
+}
+
 #[test]
 fn flush_with_reason_updates_async_flush_state() {
     let dir = tempfile::tempdir().unwrap();
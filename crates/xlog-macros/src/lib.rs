@@ -0,0 +1,183 @@
+//! Attribute macros for `mars-xlog`.
+//!
+//! Use these through the `macros` feature of `mars-xlog`, which re-exports
+//! them; the expansions refer to items by their `::mars_xlog` path.
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, Expr, FnArg, Ident, ItemFn, LitStr, Pat};
+
+#[derive(Default)]
+struct InstrumentArgs {
+    logger: Option<Expr>,
+    level: Option<Expr>,
+    tag: Option<LitStr>,
+    skip: Vec<Ident>,
+}
+
+/// Log entry and exit of a function, with its arguments and duration.
+///
+/// ```ignore
+/// #[xlog_instrument(logger = LOGGER, tag = "net", level = Debug, skip(body))]
+/// fn send(host: &str, port: u16, body: &[u8]) -> io::Result<()> { .. }
+/// ```
+///
+/// On entry this writes `send started {host="example.com", port=443}`; when
+/// the function returns, including early returns and unwinding, it writes
+/// `send finished {elapsed=..}`. Both lines carry the function name as
+/// their metadata.
+///
+/// - `logger` (required): expression that borrows or derefs to `Xlog`.
+/// - `level`: a `LogLevel` variant name (`Debug`) or any other `LogLevel`
+///   expression, such as a constant, used as written; defaults to `Info`.
+/// - `tag`: string literal tag; defaults to the logger's name prefix.
+/// - `skip(..)`: arguments left out of the entry line.
+///
+/// Every argument that is a plain binding and not skipped must implement
+/// `Debug`. `self`, destructured patterns and an argument used directly as
+/// `logger` are never logged.
+#[proc_macro_attribute]
+pub fn xlog_instrument(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = InstrumentArgs::default();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("logger") {
+            args.logger = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("level") {
+            args.level = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("tag") {
+            args.tag = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("skip") {
+            meta.parse_nested_meta(|nested| {
+                args.skip.push(nested.path.require_ident()?.clone());
+                Ok(())
+            })?;
+        } else {
+            return Err(meta.error("expected `logger`, `level`, `tag` or `skip`"));
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with parser);
+    let item = parse_macro_input!(item as ItemFn);
+    expand_instrument(args, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// `LogLevel` variants accepted as a bare `level = <Variant>`.
+const LEVEL_VARIANTS: [&str; 7] = ["Verbose", "Debug", "Info", "Warn", "Error", "Fatal", "None"];
+
+/// A bare variant name becomes its `::mars_xlog::LogLevel` path; any other
+/// expression, including a lone identifier such as a constant or a local,
+/// is used as written.
+fn level_tokens(level: Option<Expr>) -> TokenStream2 {
+    match level {
+        Some(Expr::Path(path))
+            if path.qself.is_none()
+                && path
+                    .path
+                    .get_ident()
+                    .is_some_and(|ident| LEVEL_VARIANTS.iter().any(|name| ident == name)) =>
+        {
+            let variant = path.path.get_ident();
+            quote!(::mars_xlog::LogLevel::#variant)
+        }
+        Some(level) => quote!(#level),
+        None => quote!(::mars_xlog::LogLevel::Info),
+    }
+}
+
+fn expand_instrument(args: InstrumentArgs, item: ItemFn) -> syn::Result<TokenStream2> {
+    let Some(logger) = args.logger else {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "xlog_instrument requires `logger = <expr>`",
+        ));
+    };
+    let level = level_tokens(args.level);
+    let tag = match args.tag {
+        Some(tag) => quote!(::std::option::Option::Some(#tag)),
+        None => quote!(::std::option::Option::None),
+    };
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+    // The logger itself is usually an argument and rarely implements `Debug`.
+    let logger_arg = match &logger {
+        Expr::Path(path) => path.path.get_ident().cloned(),
+        _ => None,
+    };
+    let func = sig.ident.unraw().to_string();
+    let logged: Vec<&Ident> = sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(typed) => match &*typed.pat {
+                Pat::Ident(binding)
+                    if !args.skip.contains(&binding.ident)
+                        && logger_arg.as_ref() != Some(&binding.ident) =>
+                {
+                    Some(&binding.ident)
+                }
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let names = logged.iter().map(|ident| ident.unraw().to_string());
+    let line = quote_spanned!(sig.ident.span()=> ::std::line!());
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __xlog_logger: &::mars_xlog::Xlog = &(#logger);
+            let __xlog_guard = ::mars_xlog::TimedGuard::__instrument(
+                __xlog_logger,
+                #level,
+                #tag,
+                #func,
+                ::std::file!(),
+                #line,
+                &[#((#names, &#logged as &dyn ::std::fmt::Debug)),*],
+            );
+            #block
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::level_tokens;
+
+    #[test]
+    fn only_variant_names_become_log_level_paths() {
+        for variant in ["Verbose", "Debug", "Info", "Warn", "Error", "Fatal", "None"] {
+            let expr = syn::parse_str(variant).expect("variant");
+            assert_eq!(
+                level_tokens(Some(expr)).to_string(),
+                format!(":: mars_xlog :: LogLevel :: {variant}")
+            );
+        }
+        assert_eq!(
+            level_tokens(None).to_string(),
+            ":: mars_xlog :: LogLevel :: Info"
+        );
+
+        assert_eq!(level_tokens(Some(parse_quote!(LEVEL))).to_string(), "LEVEL");
+        assert_eq!(level_tokens(Some(parse_quote!(level))).to_string(), "level");
+        assert_eq!(
+            level_tokens(Some(parse_quote!(LogLevel::Warn))).to_string(),
+            "LogLevel :: Warn"
+        );
+        assert_eq!(
+            level_tokens(Some(parse_quote!(cfg.level()))).to_string(),
+            "cfg . level ()"
+        );
+    }
+}
@@ -2,11 +2,13 @@ use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+#[cfg(not(feature = "cmake"))]
 fn push_file(list: &mut Vec<PathBuf>, path: PathBuf) {
     println!("cargo:rerun-if-changed={}", path.display());
     list.push(path);
 }
 
+#[cfg(not(feature = "cmake"))]
 fn collect_c_files(dir: &Path, out: &mut Vec<PathBuf>) {
     let entries = match fs::read_dir(dir) {
         Ok(entries) => entries,
@@ -24,37 +26,241 @@ fn collect_c_files(dir: &Path, out: &mut Vec<PathBuf>) {
     }
 }
 
+/// Link a prebuilt `mars_xlog` library from `lib_dir` instead of compiling mars.
+///
+/// The library kind comes from `MARS_XLOG_LIB_KIND` (`static` or `dylib`); when
+/// unset, a static archive in `lib_dir` wins over a shared library.
+fn link_prebuilt(lib_dir: &Path) {
+    println!("cargo:rerun-if-env-changed=MARS_XLOG_LIB_KIND");
+    if !lib_dir.is_dir() {
+        panic!(
+            "prebuilt mars_xlog lib dir not found: {} (MARS_XLOG_LIB_DIR)",
+            lib_dir.display()
+        );
+    }
+
+    let kind = env::var("MARS_XLOG_LIB_KIND").unwrap_or_else(|_| {
+        let has_static = ["libmars_xlog.a", "mars_xlog.lib"]
+            .iter()
+            .any(|name| lib_dir.join(name).exists());
+        if has_static { "static" } else { "dylib" }.to_string()
+    });
+    if kind != "static" && kind != "dylib" {
+        panic!("MARS_XLOG_LIB_KIND must be `static` or `dylib`, got `{kind}`");
+    }
+
+    println!("cargo:rerun-if-changed={}", lib_dir.display());
+    println!("cargo:rustc-link-search=native={}", lib_dir.display());
+    println!("cargo:rustc-link-lib={kind}=mars_xlog");
+}
+
+/// Generate `$OUT_DIR/bindings.rs` from the wrapper header, plus a
+/// `drift_check.rs` that makes every generated function coerce against its
+/// hand-written declaration so signature drift fails the build.
+#[cfg(feature = "bindgen")]
+fn generate_bindings(header: &Path, mars_parent: &Path) {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let bindings = bindgen::Builder::default()
+        .header(header.display().to_string())
+        .clang_arg(format!("-I{}", mars_parent.display()))
+        .allowlist_function("mars_xlog_.*")
+        .blocklist_type("mars_xlog_config_t")
+        .blocklist_type("XLoggerInfo(_t)?")
+        .raw_line("use super::{MarsXlogConfig as mars_xlog_config_t, XLoggerInfo};")
+        .ctypes_prefix("libc")
+        .layout_tests(false)
+        .generate_comments(false)
+        .generate()
+        .expect("generate mars_xlog bindings");
+    let source = bindings.to_string();
+
+    let mut check = String::from("const _: () = {\n");
+    for line in source.lines() {
+        let Some(rest) = line.trim_start().strip_prefix("pub fn ") else {
+            continue;
+        };
+        let name = rest.split('(').next().unwrap_or_default().trim();
+        check.push_str(&format!("    let _ = [bindings::{name}, crate::{name}];\n"));
+    }
+    check.push_str("};\n");
+
+    fs::write(out_dir.join("bindings.rs"), source).expect("write bindings.rs");
+    fs::write(out_dir.join("drift_check.rs"), check).expect("write drift_check.rs");
+}
+
+/// Build mars with its own CMake project and link every static library it
+/// produces, compiling only the C wrapper with `cc`.
+///
+/// The CMake target defaults to `xlog` and can be overridden with
+/// `MARS_CMAKE_TARGET`.
+#[cfg(feature = "cmake")]
+fn build_with_cmake(
+    mars_dir: &Path,
+    mars_parent: &Path,
+    native_dir: &Path,
+    is_msvc: bool,
+    is_ohos: bool,
+) {
+    println!("cargo:rerun-if-env-changed=MARS_CMAKE_TARGET");
+    println!(
+        "cargo:rerun-if-changed={}",
+        mars_dir.join("CMakeLists.txt").display()
+    );
+    let target = env::var("MARS_CMAKE_TARGET").unwrap_or_else(|_| "xlog".to_string());
+
+    println!("cargo:rerun-if-env-changed=MARS_XLOG_OPT_SIZE");
+    let mut config = cmake::Config::new(mars_dir);
+    config
+        .define("CMAKE_POSITION_INDEPENDENT_CODE", "ON")
+        // NDK r27+: build with 16KB-compatible ELF alignment on 64-bit Android.
+        .define("ANDROID_SUPPORT_FLEXIBLE_PAGE_SIZES", "ON")
+        .build_target(&target);
+    if cfg!(feature = "size-opt") || env::var_os("MARS_XLOG_OPT_SIZE").is_some() {
+        config.profile("MinSizeRel");
+    }
+    let dst = config.build();
+
+    let mut libs = Vec::new();
+    collect_static_libs(&dst.join("build"), &mut libs);
+    if libs.is_empty() {
+        panic!(
+            "mars CMake target `{target}` produced no static libraries under {}",
+            dst.display()
+        );
+    }
+    for lib in &libs {
+        let dir = lib.parent().unwrap_or_else(|| Path::new("."));
+        let stem = lib.file_stem().and_then(|n| n.to_str()).unwrap_or_default();
+        let name = stem.strip_prefix("lib").unwrap_or(stem);
+        if cfg!(feature = "system-zstd") && name.contains("zstd") {
+            continue;
+        }
+        println!("cargo:rustc-link-search=native={}", dir.display());
+        println!("cargo:rustc-link-lib=static={name}");
+    }
+
+    // The CMake build ships the real strutil, so only the C wrapper is compiled here.
+    let wrapper = native_dir.join("mars_xlog_wrapper.cc");
+    println!("cargo:rerun-if-changed={}", wrapper.display());
+    let mut build = cc::Build::new();
+    build.cpp(true);
+    if is_msvc {
+        build.flag("/std:c++14").flag("/EHsc").flag("/utf-8");
+        configure_msvc(&mut build, mars_dir);
+    } else {
+        build.flag_if_supported("-std=gnu++14");
+        build.flag_if_supported("-fno-exceptions");
+    }
+    if is_ohos {
+        build.define("OHOS", None);
+    }
+    if cfg!(feature = "no-crypt") {
+        build.define("MARS_XLOG_NO_CRYPT", None);
+    }
+    build
+        .include(mars_parent)
+        .include(mars_dir)
+        .include(mars_dir.join("comm"))
+        .include(mars_dir.join("comm/xlogger"))
+        .include(mars_dir.join("xlog"))
+        .include(mars_dir.join("xlog/src"))
+        .include(mars_dir.join("boost"))
+        .file(wrapper)
+        .compile("mars_xlog_wrapper");
+}
+
+#[cfg(feature = "cmake")]
+fn collect_static_libs(dir: &Path, out: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_static_libs(&path, out);
+        } else if path
+            .extension()
+            .map(|ext| ext == "a" || ext == "lib")
+            .unwrap_or(false)
+        {
+            out.push(path);
+        }
+    }
+}
+
 fn main() {
     println!("cargo:rerun-if-env-changed=MARS_SRC_DIR");
+    println!("cargo:rerun-if-env-changed=MARS_XLOG_LIB_DIR");
+
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let target = env::var("TARGET").unwrap_or_default();
+    // 64-bit Android ABIs must be 16KB page-size compatible (Android 15+ devices).
+    let is_android_64bit = target_os == "android"
+        && (target.contains("aarch64") || target.contains("arm64") || target.contains("x86_64"));
+    let is_ohos = target_os == "ohos"
+        || target_os == "harmony"
+        || target_os == "harmonyos"
+        || target.contains("ohos");
+    let is_msvc =
+        target_os == "windows" && env::var("CARGO_CFG_TARGET_ENV").as_deref() == Ok("msvc");
 
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
     let mars_dir = env::var("MARS_SRC_DIR")
         .map(PathBuf::from)
         .unwrap_or_else(|_| manifest_dir.join("../../third_party/mars/mars"));
+    let mars_parent = mars_dir
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    let native_dir = manifest_dir.join("native");
+
+    #[cfg(feature = "bindgen")]
+    generate_bindings(&native_dir.join("mars_xlog_wrapper.h"), &mars_parent);
+
+    if let Some(lib_dir) = env::var_os("MARS_XLOG_LIB_DIR") {
+        link_prebuilt(Path::new(&lib_dir));
+        link_platform_libs(&target_os, is_ohos, is_android_64bit);
+        return;
+    }
 
     if !mars_dir.exists() {
         panic!(
-            "mars source dir not found: {} (set MARS_SRC_DIR to override)",
+            "mars source dir not found: {} (set MARS_SRC_DIR to override, or MARS_XLOG_LIB_DIR to link a prebuilt library)",
             mars_dir.display()
         );
     }
 
-    let mars_parent = mars_dir
-        .parent()
-        .unwrap_or_else(|| Path::new("."))
-        .to_path_buf();
+    #[cfg(feature = "cmake")]
+    build_with_cmake(&mars_dir, &mars_parent, &native_dir, is_msvc, is_ohos);
+    #[cfg(not(feature = "cmake"))]
+    build_with_cc(
+        &mars_dir,
+        &mars_parent,
+        &native_dir,
+        &target_os,
+        &target,
+        is_msvc,
+        is_ohos,
+    );
 
-    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
-    let target = env::var("TARGET").unwrap_or_default();
-    let is_android_armv7 = target_os == "android" && target.contains("armv7");
-    let is_android_arm64 =
-        target_os == "android" && (target.contains("aarch64") || target.contains("arm64"));
-    let is_ohos = target_os == "ohos"
-        || target_os == "harmony"
-        || target_os == "harmonyos"
-        || target.contains("ohos");
+    link_platform_libs(&target_os, is_ohos, is_android_64bit);
+}
 
-    let native_dir = manifest_dir.join("native");
+/// Compile the wrapper and the hand-maintained mars source list with `cc`.
+#[cfg(not(feature = "cmake"))]
+fn build_with_cc(
+    mars_dir: &Path,
+    mars_parent: &Path,
+    native_dir: &Path,
+    target_os: &str,
+    target: &str,
+    is_msvc: bool,
+    is_ohos: bool,
+) {
+    let is_android_armv7 = target_os == "android" && target.contains("armv7");
+    println!("cargo:rerun-if-env-changed=MARS_XLOG_OPT_SIZE");
+    let opt_size = cfg!(feature = "size-opt") || env::var_os("MARS_XLOG_OPT_SIZE").is_some();
     let mut cpp_files: Vec<PathBuf> = Vec::new();
     let mut c_files: Vec<PathBuf> = Vec::new();
 
@@ -146,6 +352,12 @@ fn main() {
             &mut cpp_files,
             mars_dir.join("comm/unix/xlogger_threadinfo.cc"),
         );
+    } else if target_os == "windows" {
+        push_file(&mut cpp_files, mars_dir.join("xlog/windows/ConsoleLog.cc"));
+        push_file(
+            &mut cpp_files,
+            mars_dir.join("comm/windows/xlogger_threadinfo.cc"),
+        );
     } else {
         push_file(&mut cpp_files, mars_dir.join("xlog/unix/ConsoleLog.cc"));
         push_file(
@@ -163,8 +375,12 @@ fn main() {
     push_file(&mut c_files, mars_dir.join("comm/assert/__assert.c"));
     push_file(&mut c_files, mars_dir.join("comm/time_utils.c"));
 
-    // micro-ecc
-    if let Ok(entries) = fs::read_dir(mars_dir.join("xlog/crypt/micro-ecc-master")) {
+    // micro-ecc; `no-crypt` swaps it for always-failing stubs. log_crypt.cc stays
+    // because the appender also uses it for plaintext block framing, but the wrapper
+    // never hands it a public key.
+    if cfg!(feature = "no-crypt") {
+        push_file(&mut c_files, native_dir.join("uecc_stub.c"));
+    } else if let Ok(entries) = fs::read_dir(mars_dir.join("xlog/crypt/micro-ecc-master")) {
         for ent in entries.flatten() {
             let path = ent.path();
             if path.extension().map(|e| e == "c").unwrap_or(false) {
@@ -173,8 +389,18 @@ fn main() {
         }
     }
 
-    // zstd (only lib/*.c)
-    collect_c_files(&mars_dir.join("zstd/lib"), &mut c_files);
+    // zstd (only lib/*.c); size-optimized builds keep just the streaming codec the
+    // logger uses and drop the legacy/deprecated/dictBuilder trees.
+    // `system-zstd` links the platform libzstd instead (see `link_platform_libs`).
+    if !cfg!(feature = "system-zstd") {
+        if opt_size {
+            for dir in ["common", "compress", "decompress"] {
+                collect_c_files(&mars_dir.join("zstd/lib").join(dir), &mut c_files);
+            }
+        } else {
+            collect_c_files(&mars_dir.join("zstd/lib"), &mut c_files);
+        }
+    }
     for path in &c_files {
         println!("cargo:rerun-if-changed={}", path.display());
     }
@@ -182,20 +408,35 @@ fn main() {
     // C++ build
     let mut cpp_build = cc::Build::new();
     cpp_build.cpp(true);
-    cpp_build.flag_if_supported("-std=gnu++14");
-    cpp_build.flag_if_supported("-fno-exceptions");
+    if is_msvc {
+        cpp_build.flag("/std:c++14");
+        cpp_build.flag("/EHsc");
+        cpp_build.flag("/utf-8");
+        configure_msvc(&mut cpp_build, mars_dir);
+    } else {
+        cpp_build.flag_if_supported("-std=gnu++14");
+        cpp_build.flag_if_supported("-fno-exceptions");
+    }
 
     if target_os == "android" {
         cpp_build.define("ANDROID", None);
+        // Query the page size at runtime instead of baking bionic's 4KB PAGE_SIZE in.
+        cpp_build.define("__BIONIC_NO_PAGE_SIZE_MACRO", None);
+    }
+    if cfg!(feature = "no-crypt") {
+        cpp_build.define("MARS_XLOG_NO_CRYPT", None);
     }
     if is_ohos {
         cpp_build.define("OHOS", None);
     }
 
-    // include paths
+    // include paths; system zstd headers must win over the bundled copy
+    if let Some(dir) = system_zstd_include() {
+        cpp_build.include(dir);
+    }
     cpp_build
-        .include(&mars_parent)
-        .include(&mars_dir)
+        .include(mars_parent)
+        .include(mars_dir)
         .include(mars_dir.join("comm"))
         .include(mars_dir.join("comm/xlogger"))
         .include(mars_dir.join("xlog"))
@@ -215,12 +456,20 @@ fn main() {
         cpp_build.file(file);
     }
 
+    if opt_size {
+        configure_opt_size(&mut cpp_build);
+    }
+
     cpp_build.compile("mars_xlog_cpp");
 
     // C build
     let mut c_build = cc::Build::new();
+    if is_msvc {
+        configure_msvc(&mut c_build, mars_dir);
+    }
     if target_os == "android" {
         c_build.define("ANDROID", None);
+        c_build.define("__BIONIC_NO_PAGE_SIZE_MACRO", None);
     }
     if is_ohos {
         c_build.define("OHOS", None);
@@ -231,9 +480,12 @@ fn main() {
         c_build.define("uECC_PLATFORM", Some("uECC_arch_other"));
     }
 
+    if let Some(dir) = system_zstd_include() {
+        c_build.include(dir);
+    }
     c_build
-        .include(&mars_parent)
-        .include(&mars_dir)
+        .include(mars_parent)
+        .include(mars_dir)
         .include(mars_dir.join("comm"))
         .include(mars_dir.join("comm/xlogger"))
         .include(mars_dir.join("xlog"))
@@ -249,7 +501,80 @@ fn main() {
     for file in &c_files {
         c_build.file(file);
     }
+    if opt_size {
+        configure_opt_size(&mut c_build);
+        c_build.define("ZSTD_LEGACY_SUPPORT", Some("0"));
+    }
     c_build.compile("mars_xlog_c");
+}
+
+/// Optimize for size and give every function/data item its own section so the
+/// final link (which uses `--gc-sections` by default under rustc) can drop unused code.
+#[cfg(not(feature = "cmake"))]
+fn configure_opt_size(build: &mut cc::Build) {
+    build
+        .opt_level_str("z")
+        .flag_if_supported("-ffunction-sections")
+        .flag_if_supported("-fdata-sections")
+        .flag_if_supported("/Gy")
+        .flag_if_supported("/Gw");
+}
+
+/// Header dir for the platform zstd when `system-zstd` is on (`ZSTD_INCLUDE_DIR`).
+#[cfg(not(feature = "cmake"))]
+fn system_zstd_include() -> Option<PathBuf> {
+    if !cfg!(feature = "system-zstd") {
+        return None;
+    }
+    println!("cargo:rerun-if-env-changed=ZSTD_INCLUDE_DIR");
+    env::var_os("ZSTD_INCLUDE_DIR").map(PathBuf::from)
+}
+
+/// Shared windows-msvc defines, include paths, and CRT selection.
+fn configure_msvc(build: &mut cc::Build, mars_dir: &Path) {
+    // Match the CRT rustc links (`-C target-feature=+crt-static` selects /MT).
+    let crt_static = env::var("CARGO_CFG_TARGET_FEATURE")
+        .map(|features| features.split(',').any(|f| f == "crt-static"))
+        .unwrap_or(false);
+    build
+        .static_crt(crt_static)
+        .define("WIN32", None)
+        .define("_WIN32_WINNT", Some("0x0601"))
+        .define("NOMINMAX", None)
+        .define("WIN32_LEAN_AND_MEAN", None)
+        .define("_CRT_SECURE_NO_WARNINGS", None)
+        .include(mars_dir.join("comm/windows"));
+}
+
+/// Link the C++ runtime and system libraries mars depends on.
+fn link_platform_libs(target_os: &str, is_ohos: bool, is_android_64bit: bool) {
+    if cfg!(feature = "system-zstd") {
+        println!("cargo:rerun-if-env-changed=ZSTD_LIB_DIR");
+        if let Some(dir) = env::var_os("ZSTD_LIB_DIR") {
+            println!(
+                "cargo:rustc-link-search=native={}",
+                Path::new(&dir).display()
+            );
+        }
+        println!("cargo:rustc-link-lib=zstd");
+    }
+
+    if target_os == "windows" {
+        // MSVC pulls in its C++ runtime from the objects' CRT directives; only zlib and
+        // the Win32 libraries used by mars/boost need to be named here.
+        println!("cargo:rerun-if-env-changed=ZLIB_LIB_DIR");
+        if let Some(dir) = env::var_os("ZLIB_LIB_DIR") {
+            println!(
+                "cargo:rustc-link-search=native={}",
+                Path::new(&dir).display()
+            );
+        }
+        println!("cargo:rustc-link-lib=zlib");
+        println!("cargo:rustc-link-lib=advapi32");
+        println!("cargo:rustc-link-lib=shell32");
+        println!("cargo:rustc-link-lib=ws2_32");
+        return;
+    }
 
     // link stdlib
     if target_os == "ios" || target_os == "macos" || target_os == "tvos" || target_os == "watchos" {
@@ -282,7 +607,9 @@ fn main() {
         println!("cargo:rustc-link-lib=stdc++");
     }
 
-    if is_android_arm64 {
+    // These only reach link steps in this package; final cdylibs such as the JNI crate
+    // pass the same flags from their own build scripts.
+    if is_android_64bit {
         println!("cargo:rustc-link-arg=-Wl,-z,max-page-size=16384");
         println!("cargo:rustc-link-arg=-Wl,-z,common-page-size=4096");
     }
@@ -325,7 +325,7 @@ fn run() -> Result<(), String> {
                 let msg = format!("BENCH|W|T{thread_idx:02}|{:08}|{}", local_idx, payload);
                 logger.write_message(&msg);
                 warmup_written += 1;
-                if flush_every > 0 && warmup_written % flush_every == 0 {
+                if flush_every > 0 && warmup_written.is_multiple_of(flush_every) {
                     mark_flush_every_hint();
                     logger.flush(false);
                 }
@@ -359,7 +359,7 @@ fn run() -> Result<(), String> {
                         lat_ns: lat,
                     });
                 }
-                if flush_every > 0 && (local_idx + 1) % flush_every == 0 {
+                if flush_every > 0 && (local_idx + 1).is_multiple_of(flush_every) {
                     mark_flush_every_hint();
                     logger.flush(false);
                 }
@@ -3,6 +3,7 @@
 #include <string>
 #include <vector>
 
+#include "mars/comm/xlogger/xlogger_category.h"
 #include "mars/xlog/appender.h"
 #include "mars/xlog/xlogger_interface.h"
 
@@ -20,9 +21,11 @@ mars::xlog::XLogConfig ToCppConfig(const mars_xlog_config_t* cfg) {
     if (cfg->nameprefix) {
         out.nameprefix_ = cfg->nameprefix;
     }
+#ifndef MARS_XLOG_NO_CRYPT
     if (cfg->pub_key) {
         out.pub_key_ = cfg->pub_key;
     }
+#endif
     out.compress_mode_ = static_cast<mars::xlog::TCompressMode>(cfg->compress_mode);
     out.compress_level_ = cfg->compress_level;
     if (cfg->cache_dir) {
@@ -108,6 +111,63 @@ void mars_xlog_set_level(uintptr_t instance, int level) {
     mars::xlog::SetLevel(instance, (TLogLevel)level);
 }
 
+uintptr_t mars_xlog_category_new(uintptr_t instance, int level) {
+    mars::comm::XloggerCategory* category = nullptr;
+    if (instance == 0) {
+        category = mars::comm::XloggerCategory::NewInstance(
+            0, [](const XLoggerInfo* info, const char* log) { xlogger_Write(info, log); });
+    } else {
+        auto* parent = reinterpret_cast<mars::comm::XloggerCategory*>(instance);
+        category = mars::comm::XloggerCategory::NewInstance(
+            parent->GetAppender(),
+            [parent](const XLoggerInfo* info, const char* log) { parent->Write(info, log); });
+    }
+    if (category == nullptr) {
+        return 0;
+    }
+    category->SetLevel((TLogLevel)level);
+    return reinterpret_cast<uintptr_t>(category);
+}
+
+void mars_xlog_category_release(uintptr_t category) {
+    if (category == 0) {
+        return;
+    }
+    mars::comm::XloggerCategory::DelayRelease(reinterpret_cast<mars::comm::XloggerCategory*>(category));
+}
+
+int mars_xlog_category_get_level(uintptr_t category) {
+    if (category == 0) {
+        return (int)kLevelNone;
+    }
+    return (int)reinterpret_cast<mars::comm::XloggerCategory*>(category)->GetLevel();
+}
+
+void mars_xlog_category_set_level(uintptr_t category, int level) {
+    if (category == 0) {
+        return;
+    }
+    reinterpret_cast<mars::comm::XloggerCategory*>(category)->SetLevel((TLogLevel)level);
+}
+
+int mars_xlog_category_is_enabled(uintptr_t category, int level) {
+    if (category == 0) {
+        return 0;
+    }
+    return reinterpret_cast<mars::comm::XloggerCategory*>(category)->IsEnabledFor((TLogLevel)level) ? 1 : 0;
+}
+
+void mars_xlog_category_write(uintptr_t category, const XLoggerInfo* info, const char* log) {
+    if (category == 0) {
+        return;
+    }
+    auto* cat = reinterpret_cast<mars::comm::XloggerCategory*>(category);
+    if (info != nullptr && !cat->IsEnabledFor(info->level)) {
+        return;
+    }
+    cat->Write(info, log);
+}
+
 void mars_xlog_set_appender_mode(uintptr_t instance, int mode) {
     mars::xlog::SetAppenderMode(instance, (mars::xlog::TAppenderMode)mode);
 }
@@ -36,6 +36,15 @@ int mars_xlog_is_enabled(uintptr_t instance, int level);
 int mars_xlog_get_level(uintptr_t instance);
 void mars_xlog_set_level(uintptr_t instance, int level);
 
+// categories: sub-loggers with their own level that write through an instance
+// (0 = global/default logger). Release a category before its instance.
+uintptr_t mars_xlog_category_new(uintptr_t instance, int level);
+void mars_xlog_category_release(uintptr_t category);
+int mars_xlog_category_get_level(uintptr_t category);
+void mars_xlog_category_set_level(uintptr_t category, int level);
+int mars_xlog_category_is_enabled(uintptr_t category, int level);
+void mars_xlog_category_write(uintptr_t category, const XLoggerInfo* info, const char* log);
+
 // controls
 void mars_xlog_set_appender_mode(uintptr_t instance, int mode);
 void mars_xlog_flush(uintptr_t instance, int is_sync);
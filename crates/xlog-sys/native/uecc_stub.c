@@ -0,0 +1,31 @@
+// Link-time stand-ins for micro-ecc used by the `no-crypt` feature.
+//
+// The wrapper never passes a public key to mars in that mode, so LogCrypt does
+// not reach these; they only satisfy the references in log_crypt.cc and fail
+// closed if they are ever called.
+
+#include <stddef.h>
+
+#include "uECC.h"
+
+uECC_Curve uECC_secp256k1(void) {
+    return NULL;
+}
+
+int uECC_make_key(uint8_t* public_key, uint8_t* private_key, uECC_Curve curve) {
+    (void)public_key;
+    (void)private_key;
+    (void)curve;
+    return 0;
+}
+
+int uECC_shared_secret(const uint8_t* public_key,
+                       const uint8_t* private_key,
+                       uint8_t* secret,
+                       uECC_Curve curve) {
+    (void)public_key;
+    (void)private_key;
+    (void)secret;
+    (void)curve;
+    return 0;
+}
@@ -197,6 +197,55 @@ extern "C" {
     /// - `level` must be a valid `TLogLevel` value.
     pub fn mars_xlog_set_level(instance: usize, level: c_int);
 
+    /// Create a category: a sub-logger with its own level that writes through `instance`.
+    ///
+    /// Passing 0 for `instance` writes through the global/default logger. Returns 0 on
+    /// failure.
+    ///
+    /// # Safety
+    /// - `instance` must be 0 or a valid handle returned by this library, and must stay
+    ///   alive until the category is released.
+    /// - `level` must be a valid `TLogLevel` value.
+    pub fn mars_xlog_category_new(instance: usize, level: c_int) -> usize;
+
+    /// Release a category created by `mars_xlog_category_new`.
+    ///
+    /// Mars frees the category after a short delay so in-flight writes can finish.
+    ///
+    /// # Safety
+    /// - `category` must be 0 or a handle returned by `mars_xlog_category_new` that has not
+    ///   been released yet.
+    pub fn mars_xlog_category_release(category: usize);
+
+    /// Get the current log level for `category` (`kLevelNone` for a null handle).
+    ///
+    /// # Safety
+    /// - `category` must be 0 or a live handle returned by `mars_xlog_category_new`.
+    pub fn mars_xlog_category_get_level(category: usize) -> c_int;
+
+    /// Set the log level for `category`.
+    ///
+    /// # Safety
+    /// - `category` must be 0 or a live handle returned by `mars_xlog_category_new`.
+    /// - `level` must be a valid `TLogLevel` value.
+    pub fn mars_xlog_category_set_level(category: usize, level: c_int);
+
+    /// Returns non-zero if logging at `level` is enabled for `category`.
+    ///
+    /// # Safety
+    /// - `category` must be 0 or a live handle returned by `mars_xlog_category_new`.
+    /// - `level` must be a valid `TLogLevel` value.
+    pub fn mars_xlog_category_is_enabled(category: usize, level: c_int) -> c_int;
+
+    /// Write a log entry through `category`, dropping it if `info.level` is below the
+    /// category level.
+    ///
+    /// # Safety
+    /// - `category` must be 0 or a live handle returned by `mars_xlog_category_new`.
+    /// - `info` must point to writable memory for the duration of the call if non-null.
+    /// - `log` must be a valid NUL-terminated C string if non-null.
+    pub fn mars_xlog_category_write(category: usize, info: *const XLoggerInfo, log: *const c_char);
+
     /// Set the appender mode for `instance` (`TAppenderMode` as int).
     ///
     /// # Safety
@@ -319,3 +368,16 @@ extern "C" {
     /// 0 = printf, 1 = NSLog, 2 = OSLog. This is a no-op on non-Apple platforms.
     pub fn mars_xlog_set_console_fun(fun: c_int);
 }
+
+/// Declarations generated from `native/mars_xlog_wrapper.h` by the `bindgen` feature.
+///
+/// They are only used to check the hand-written declarations above; every generated
+/// function must coerce to the same fn pointer type as its hand-written counterpart.
+#[cfg(feature = "bindgen")]
+#[allow(dead_code, unused_imports, clippy::all)]
+mod bindings {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+#[cfg(feature = "bindgen")]
+include!(concat!(env!("OUT_DIR"), "/drift_check.rs"));
@@ -3,7 +3,7 @@
 //! This crate exposes a high-level surface for Kotlin/Swift consumers and
 //! mirrors the core `mars-xlog` capability set as closely as possible.
 use mars_xlog as core;
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 use tracing::info;
 use tracing_subscriber::prelude::*;
 
@@ -87,6 +87,17 @@ pub struct XlogConfig {
     pub compress_mode: CompressMode,
     /// Compression level forwarded to the compressor.
     pub compress_level: i32,
+    /// Public key as the raw 64-byte uncompressed point (x || y), as handed
+    /// out by key-provisioning services. Use instead of `pub_key`; setting
+    /// both is an error.
+    #[uniffi(default = None)]
+    pub pub_key_bytes: Option<Vec<u8>>,
+    /// Per-tag level directives such as `info,net=debug`, applied at init in
+    /// place of `level`; pass a value read from Android system properties or
+    /// iOS `UserDefaults` to raise verbosity without a release. A malformed
+    /// string fails init.
+    #[uniffi(default = None)]
+    pub directives: Option<String>,
 }
 
 /// Errors surfaced through UniFFI.
@@ -96,13 +107,85 @@ pub enum XlogError {
     Message { details: String },
 }
 
+/// Callback interface receiving entries written by a logger instance.
+#[uniffi::export(with_foreign)]
+pub trait LogListener: Send + Sync {
+    /// Called after an entry passed the level and tag filters and was
+    /// written, whichever handle, the global appender or `tracing` wrote it.
+    fn on_log(&self, level: LogLevel, tag: String, message: String);
+}
+
 /// Logger handle exposed to foreign-language callers.
 #[derive(uniffi::Object)]
 pub struct Logger {
     inner: core::Xlog,
+    /// Id of the entry listener registered through `set_listener`.
+    listener: Mutex<Option<u64>>,
+}
+
+impl Logger {
+    fn from_core(inner: core::Xlog) -> Self {
+        Self {
+            inner,
+            listener: Mutex::new(None),
+        }
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        if let Ok(slot) = self.listener.get_mut() {
+            if let Some(id) = slot.take() {
+                self.inner.remove_entry_listener(id);
+            }
+        }
+    }
+}
+
+/// Layer this binding installed as the process-wide tracing subscriber,
+/// with the instance it forwards into; `None` when installing failed.
+static TRACING_INIT: OnceLock<Option<(usize, core::XlogLayerHandle)>> = OnceLock::new();
+
+/// Runtime controls for the tracing layer installed by `Logger`, e.g. for a
+/// hidden debug menu. Changes take effect without recreating the logger.
+#[derive(uniffi::Object)]
+pub struct TracingLayer {
+    handle: core::XlogLayerHandle,
 }
 
-static TRACING_INIT: OnceLock<()> = OnceLock::new();
+#[uniffi::export]
+impl TracingLayer {
+    /// Start or stop forwarding `tracing` events.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.handle.set_enabled(enabled);
+    }
+
+    /// Return whether events are being forwarded.
+    pub fn is_enabled(&self) -> bool {
+        self.handle.enabled()
+    }
+
+    /// Set the minimum forwarded level; the logger's own level is unchanged.
+    pub fn set_level(&self, level: LogLevel) {
+        self.handle.set_level(to_core_level(level));
+    }
+
+    /// Return the minimum forwarded level.
+    pub fn level(&self) -> LogLevel {
+        from_core_level(self.handle.level())
+    }
+
+    /// Filter events per target with directives such as `info,net=debug`.
+    /// An empty string removes them; a malformed one is rejected and the
+    /// current filter stays in effect.
+    pub fn set_directives(&self, directives: String) -> Result<(), XlogError> {
+        match parse_directives(&directives)? {
+            Some(directives) => self.handle.set_directives(directives),
+            None => self.handle.clear_directives(),
+        }
+        Ok(())
+    }
+}
 
 fn to_core_level(level: LogLevel) -> core::LogLevel {
     match level {
@@ -142,20 +225,44 @@ fn to_core_compress_mode(mode: CompressMode) -> core::CompressMode {
     }
 }
 
-fn to_core_config(cfg: XlogConfig) -> core::XlogConfig {
+fn to_core_config(cfg: XlogConfig) -> Result<core::XlogConfig, XlogError> {
+    if cfg.pub_key_bytes.is_some() && !cfg.pub_key.is_empty() {
+        return Err(to_error("set either pub_key or pub_key_bytes, not both"));
+    }
     let mut config = core::XlogConfig::new(cfg.log_dir, cfg.name_prefix)
         .cache_days(cfg.cache_days)
         .mode(to_core_appender_mode(cfg.mode))
         .compress_mode(to_core_compress_mode(cfg.compress_mode))
         .compress_level(cfg.compress_level);
 
-    if !cfg.pub_key.is_empty() {
+    if let Some(bytes) = cfg.pub_key_bytes {
+        config = config
+            .pub_key_bytes(raw_pub_key(&bytes)?)
+            .map_err(|e| to_error(e.to_string()))?;
+    } else if !cfg.pub_key.is_empty() {
         config = config.pub_key(cfg.pub_key);
     }
     if !cfg.cache_dir.is_empty() {
         config = config.cache_dir(cfg.cache_dir);
     }
-    config
+    Ok(config)
+}
+
+/// Check the length of a raw public key before the core validates the point.
+///
+/// A leading `0x04` SEC1 tag (65 bytes) is the usual mistake, so it gets
+/// its own message.
+fn raw_pub_key(bytes: &[u8]) -> Result<[u8; 64], XlogError> {
+    match bytes.try_into() {
+        Ok(key) => Ok(key),
+        Err(_) if bytes.len() == 65 && bytes[0] == 0x04 => Err(to_error(
+            "pub_key_bytes must be 64 bytes; drop the leading 0x04 SEC1 tag",
+        )),
+        Err(_) => Err(to_error(format!(
+            "pub_key_bytes must be 64 bytes (uncompressed x || y), got {}",
+            bytes.len()
+        ))),
+    }
 }
 
 fn to_core_raw_meta(meta: RawLogMeta) -> core::RawLogMeta {
@@ -175,19 +282,30 @@ fn from_core_file_io_action(action: core::FileIoAction) -> FileIoAction {
     }
 }
 
+fn parse_directives(spec: &str) -> Result<Option<core::Directives>, XlogError> {
+    let directives = core::Directives::parse(spec).map_err(|e| to_error(e.to_string()))?;
+    Ok((!directives.is_empty()).then_some(directives))
+}
+
 fn to_error(details: impl Into<String>) -> XlogError {
     XlogError::Message {
         details: details.into(),
     }
 }
 
-fn init_tracing(logger: core::Xlog, level: core::LogLevel) {
-    let _ = TRACING_INIT.get_or_init(|| {
-        let (layer, _handle) =
-            core::XlogLayer::with_config(logger, core::XlogLayerConfig::new(level).enabled(true));
-        let subscriber = tracing_subscriber::registry().with(layer);
-        let _ = tracing::subscriber::set_global_default(subscriber);
-    });
+fn init_tracing(
+    logger: &core::Xlog,
+    layer: core::XlogLayer,
+    handle: core::XlogLayerHandle,
+) -> bool {
+    TRACING_INIT
+        .get_or_init(|| {
+            let subscriber = tracing_subscriber::registry().with(layer);
+            tracing::subscriber::set_global_default(subscriber)
+                .ok()
+                .map(|()| (logger.instance(), handle))
+        })
+        .is_some()
 }
 
 #[uniffi::export]
@@ -195,13 +313,69 @@ impl Logger {
     /// Create a new logger instance and configure tracing.
     #[uniffi::constructor]
     pub fn new(config: XlogConfig, level: LogLevel) -> Result<Self, XlogError> {
-        let cfg = to_core_config(config);
-        let level = to_core_level(level);
-        let logger = core::Xlog::init(cfg, level).map_err(|e| to_error(e.to_string()))?;
-        logger.set_console_log_open(true);
-        init_tracing(logger.clone(), level);
-        info!("Initialized logger successfully");
-        Ok(Self { inner: logger })
+        Self::with_options(config, level, true)
+    }
+
+    /// Create a new logger instance, optionally installing the global
+    /// tracing subscriber.
+    ///
+    /// Pass `install_tracing = false` when the host app already owns the
+    /// process-wide subscriber; `install_tracing()` can still be called later.
+    #[uniffi::constructor]
+    pub fn with_options(
+        config: XlogConfig,
+        level: LogLevel,
+        install_tracing: bool,
+    ) -> Result<Self, XlogError> {
+        let directives = parse_directives(config.directives.as_deref().unwrap_or_default())?;
+        let mut builder = core::XlogBuilder::new(to_core_config(config)?)
+            .level(to_core_level(level))
+            .console(true);
+        if let Some(directives) = directives {
+            builder = builder.directives(directives);
+        }
+        let (logger, layer, handle) = builder.build().map_err(|e| to_error(e.to_string()))?;
+        if install_tracing && init_tracing(&logger, layer, handle) {
+            info!("Initialized logger successfully");
+        }
+        Ok(Self::from_core(logger))
+    }
+
+    /// Install a global tracing subscriber forwarding into this logger.
+    ///
+    /// Returns `false` when another subscriber was already set as the global
+    /// default. Only the first call in the process has any effect.
+    pub fn install_tracing(&self) -> bool {
+        let mut config = core::XlogLayerConfig::new(self.inner.level());
+        if let Some(directives) = self.inner.directives() {
+            config = config.directives(directives);
+        }
+        let (layer, handle) = core::XlogLayer::with_config(self.inner.clone(), config);
+        init_tracing(&self.inner, layer, handle)
+    }
+
+    /// Controls for the installed tracing layer, or `None` when this binding
+    /// installed no layer or it forwards into a different logger.
+    pub fn tracing_layer(&self) -> Option<Arc<TracingLayer>> {
+        match TRACING_INIT.get() {
+            Some(Some((instance, handle))) if *instance == self.inner.instance() => {
+                Some(Arc::new(TracingLayer {
+                    handle: handle.clone(),
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Look up an already-initialized logger by `name_prefix`.
+    ///
+    /// Unlike `new`, this never re-initializes the instance, so its
+    /// configuration is left untouched.
+    #[uniffi::constructor]
+    pub fn get(name_prefix: String) -> Result<Self, XlogError> {
+        core::Xlog::get(&name_prefix)
+            .map(Self::from_core)
+            .ok_or_else(|| to_error(format!("logger not found: {name_prefix}")))
     }
 
     /// Return whether logs at `level` are enabled.
@@ -219,6 +393,26 @@ impl Logger {
         self.inner.set_level(to_core_level(level));
     }
 
+    /// Apply per-tag level directives such as `info,net=debug`, e.g. after a
+    /// debug menu changed the stored value. An empty string removes them; a
+    /// malformed one is rejected and the current filter stays in effect.
+    pub fn set_directives(&self, directives: String) -> Result<(), XlogError> {
+        match parse_directives(&directives)? {
+            Some(directives) => self.inner.set_directives(directives),
+            None => self.inner.clear_directives(),
+        }
+        Ok(())
+    }
+
+    /// Re-read the `XLOG_DIRECTIVES` environment variable and apply it.
+    ///
+    /// Returns `false` when it is unset or empty.
+    pub fn reload_env_directives(&self) -> Result<bool, XlogError> {
+        self.inner
+            .reload_env_directives()
+            .map_err(|e| to_error(e.to_string()))
+    }
+
     /// Set appender mode for this instance.
     pub fn set_appender_mode(&self, mode: AppenderMode) {
         self.inner.set_appender_mode(to_core_appender_mode(mode));
@@ -234,14 +428,50 @@ impl Logger {
         self.inner.set_console_log_open(open);
     }
 
-    /// Set max file size in bytes.
-    pub fn set_max_file_size(&self, max_bytes: i64) {
-        self.inner.set_max_file_size(max_bytes);
+    /// Set max file size in bytes; negative sizes are rejected.
+    pub fn set_max_file_size(&self, max_bytes: i64) -> Result<(), XlogError> {
+        self.inner
+            .set_max_file_size(max_bytes)
+            .map_err(|e| to_error(e.to_string()))
+    }
+
+    /// Set max alive time in seconds; ages under one day are rejected.
+    pub fn set_max_alive_time(&self, alive_seconds: i64) -> Result<(), XlogError> {
+        self.inner
+            .set_max_alive_time(alive_seconds)
+            .map_err(|e| to_error(e.to_string()))
     }
 
-    /// Set max alive time in seconds.
-    pub fn set_max_alive_time(&self, alive_seconds: i64) {
-        self.inner.set_max_alive_time(alive_seconds);
+    /// Directory this logger writes finished log files into.
+    pub fn current_log_path(&self) -> Option<String> {
+        self.inner.log_dir()
+    }
+
+    /// Cache directory configured for this logger, if any.
+    pub fn current_log_cache_path(&self) -> Option<String> {
+        self.inner.cache_dir()
+    }
+
+    /// List this logger's log files covering `days` days back from today.
+    pub fn log_files_for_timespan(&self, days: i32) -> Vec<String> {
+        self.inner.log_files_for_timespan(days)
+    }
+
+    /// Register (or clear with `None`) a listener for entries written by
+    /// this logger's instance, replacing the one set earlier on this handle.
+    /// The listener stays registered until this handle is released.
+    pub fn set_listener(&self, listener: Option<Arc<dyn LogListener>>) {
+        let Ok(mut slot) = self.listener.lock() else {
+            return;
+        };
+        if let Some(id) = slot.take() {
+            self.inner.remove_entry_listener(id);
+        }
+        *slot = listener.map(|listener| {
+            self.inner.on_entry_written(move |level, tag, message| {
+                listener.on_log(from_core_level(level), tag.to_string(), message.to_string());
+            })
+        });
     }
 
     /// Log a message without file/function metadata.
@@ -249,6 +479,12 @@ impl Logger {
         self.inner.write(to_core_level(level), Some(&tag), &message);
     }
 
+    /// Log a binary payload rendered as a hex + ASCII dump by the Rust side.
+    pub fn log_bytes(&self, level: LogLevel, tag: String, data: Vec<u8>) {
+        self.inner
+            .write_bytes(to_core_level(level), Some(&tag), &data);
+    }
+
     /// Log a message with explicit metadata from the caller.
     pub fn log_with_meta(
         &self,
@@ -297,30 +533,62 @@ impl Logger {
 /// Look up an existing logger by `name_prefix`.
 #[uniffi::export]
 pub fn get_logger(name_prefix: String) -> Result<Logger, XlogError> {
-    core::Xlog::get(&name_prefix)
-        .map(|logger| Logger { inner: logger })
-        .ok_or_else(|| to_error(format!("logger not found: {name_prefix}")))
+    Logger::get(name_prefix)
 }
 
 /// Open the global/default appender.
 #[uniffi::export]
-pub fn open_appender(config: XlogConfig, level: LogLevel) -> Result<(), XlogError> {
-    core::Xlog::appender_open(to_core_config(config), to_core_level(level))
+pub fn appender_open(config: XlogConfig, level: LogLevel) -> Result<(), XlogError> {
+    core::Xlog::appender_open(to_core_config(config)?, to_core_level(level))
         .map_err(|e| to_error(e.to_string()))
 }
 
 /// Close the global/default appender.
 #[uniffi::export]
-pub fn close_appender() {
+pub fn appender_close() {
     core::Xlog::appender_close();
 }
 
+/// Open the global/default appender.
+///
+/// Kept for existing callers; prefer `appender_open`.
+#[uniffi::export]
+pub fn open_appender(config: XlogConfig, level: LogLevel) -> Result<(), XlogError> {
+    appender_open(config, level)
+}
+
+/// Close the global/default appender.
+///
+/// Kept for existing callers; prefer `appender_close`.
+#[uniffi::export]
+pub fn close_appender() {
+    appender_close();
+}
+
 /// Flush all registered instances.
 #[uniffi::export]
 pub fn flush_all(sync: bool) {
     core::Xlog::flush_all(sync);
 }
 
+/// Forward an app-backgrounded event to the shared lifecycle policy.
+#[uniffi::export]
+pub fn on_app_background() {
+    core::LifecycleHooks::global().on_background();
+}
+
+/// Forward an app-foregrounded event to the shared lifecycle policy.
+#[uniffi::export]
+pub fn on_app_foreground() {
+    core::LifecycleHooks::global().on_foreground();
+}
+
+/// Forward an app-terminating event to the shared lifecycle policy.
+#[uniffi::export]
+pub fn on_app_terminate() {
+    core::LifecycleHooks::global().on_terminate();
+}
+
 /// Write via global/default appender with raw metadata.
 #[uniffi::export]
 pub fn appender_write_with_raw_meta(
@@ -371,7 +639,7 @@ pub fn make_logfile_name(timespan: i32, prefix: String) -> Vec<String> {
 /// Flush once and return file I/O action.
 #[uniffi::export]
 pub fn oneshot_flush(config: XlogConfig) -> Result<FileIoAction, XlogError> {
-    core::Xlog::oneshot_flush(to_core_config(config))
+    core::Xlog::oneshot_flush(to_core_config(config)?)
         .map(from_core_file_io_action)
         .map_err(|e| to_error(e.to_string()))
 }
@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mars_xlog::{AppenderMode, CompressMode, LogLevel, Xlog, XlogConfig};
+use tempfile::TempDir;
+
+static NEXT_BENCH_ID: AtomicUsize = AtomicUsize::new(1);
+
+const PAYLOAD_SIZE: usize = 256;
+const BATCH_WRITES: usize = 256;
+
+struct BenchLogger {
+    _dir: TempDir,
+    logger: Xlog,
+    message: String,
+}
+
+impl BenchLogger {
+    fn new(label: &str, mode: AppenderMode, compress: CompressMode, level: i32) -> Self {
+        let dir = TempDir::new().expect("tempdir");
+        let prefix = format!(
+            "criterion-{}-{}",
+            label,
+            NEXT_BENCH_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        let cfg = XlogConfig::new(dir.path().display().to_string(), prefix)
+            .mode(mode)
+            .compress_mode(compress)
+            .compress_level(level);
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init xlog");
+        logger.set_max_file_size(0).expect("max file size");
+        Self {
+            _dir: dir,
+            logger,
+            message: make_message(PAYLOAD_SIZE),
+        }
+    }
+}
+
+fn make_message(payload_size: usize) -> String {
+    let mut message = String::with_capacity(payload_size + 48);
+    message.push_str("BENCH|");
+    while message.len() < payload_size {
+        message.push_str("rust-xlog-benchmark-line|");
+    }
+    message.truncate(payload_size);
+    message
+}
+
+/// Sync vs async crossed with the zlib and zstd levels apps actually ship.
+fn bench_mode_compress(c: &mut Criterion) {
+    let mut group = c.benchmark_group("public_mode_compress_batch256_flush");
+    group.throughput(Throughput::Bytes((PAYLOAD_SIZE * BATCH_WRITES) as u64));
+    let codecs = [
+        ("zlib", CompressMode::Zlib, 1),
+        ("zlib", CompressMode::Zlib, 6),
+        ("zlib", CompressMode::Zlib, 9),
+        ("zstd", CompressMode::Zstd, 1),
+        ("zstd", CompressMode::Zstd, 3),
+        ("zstd", CompressMode::Zstd, 10),
+    ];
+    for (mode_name, mode) in [("sync", AppenderMode::Sync), ("async", AppenderMode::Async)] {
+        for (codec_name, codec, level) in codecs {
+            let id = format!("{mode_name}_{codec_name}");
+            let ctx = BenchLogger::new(&id, mode, codec, level);
+            group.bench_with_input(BenchmarkId::new(id, level), &level, |b, _| {
+                b.iter(|| {
+                    for _ in 0..BATCH_WRITES {
+                        ctx.logger.write_with_meta(
+                            LogLevel::Info,
+                            Some("bench"),
+                            "criterion_api_matrix.rs",
+                            "bench_mode_compress",
+                            1,
+                            black_box(ctx.message.as_str()),
+                        );
+                    }
+                    ctx.logger.flush(true);
+                });
+            });
+            ctx.logger.flush(true);
+        }
+    }
+    group.finish();
+}
+
+/// Per-call cost of each public entry point in async mode, without flushing,
+/// so front-end overhead (metadata capture, formatting, dispatch) dominates.
+fn bench_api_surface(c: &mut Criterion) {
+    let mut group = c.benchmark_group("public_api_surface_async");
+    group.throughput(Throughput::Bytes(PAYLOAD_SIZE as u64));
+    let ctx = BenchLogger::new("api", AppenderMode::Async, CompressMode::Zlib, 6);
+
+    group.bench_function("write_with_meta", |b| {
+        b.iter(|| {
+            ctx.logger.write_with_meta(
+                LogLevel::Info,
+                Some("bench"),
+                "criterion_api_matrix.rs",
+                "bench_api_surface",
+                1,
+                black_box(ctx.message.as_str()),
+            );
+        });
+    });
+
+    group.bench_function("log_track_caller", |b| {
+        b.iter(|| {
+            ctx.logger.log(
+                LogLevel::Info,
+                Some("bench"),
+                black_box(ctx.message.as_str()),
+            );
+        });
+    });
+
+    group.bench_function("filtered_level", |b| {
+        b.iter(|| {
+            ctx.logger.log(
+                LogLevel::Debug,
+                Some("bench"),
+                black_box(ctx.message.as_str()),
+            );
+        });
+    });
+
+    #[cfg(feature = "macros")]
+    group.bench_function("macro_xlog_info", |b| {
+        b.iter(|| {
+            mars_xlog::xlog_info!(&ctx.logger; "{}", black_box(ctx.message.as_str()));
+        });
+    });
+
+    #[cfg(feature = "tracing")]
+    group.bench_function("tracing_layer_info", |b| {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let (layer, _handle) = mars_xlog::XlogLayer::new(ctx.logger.clone());
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            b.iter(|| {
+                tracing::info!(target: "bench", "{}", black_box(ctx.message.as_str()));
+            });
+        });
+    });
+
+    ctx.logger.flush(true);
+    group.finish();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_secs(1))
+        .measurement_time(Duration::from_secs(3))
+        .sample_size(20);
+    targets = bench_mode_compress, bench_api_surface
+);
+criterion_main!(benches);
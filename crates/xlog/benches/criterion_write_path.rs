@@ -26,7 +26,7 @@ impl BenchLogger {
             .compress_mode(CompressMode::Zlib)
             .compress_level(6);
         let logger = Xlog::init(cfg, LogLevel::Info).expect("init xlog");
-        logger.set_max_file_size(0);
+        logger.set_max_file_size(0).expect("max file size");
         Self {
             _dir: dir,
             logger,
@@ -182,7 +182,9 @@ fn run() -> Result<(), String> {
     }
     let logger = Xlog::init(cfg, LogLevel::Info).map_err(|e| format!("xlog init: {e}"))?;
     if opts.max_file_size > 0 {
-        logger.set_max_file_size(opts.max_file_size);
+        logger
+            .set_max_file_size(opts.max_file_size)
+            .map_err(|e| e.to_string())?;
     }
 
     let payload_pool = Arc::new(PayloadPool::new(
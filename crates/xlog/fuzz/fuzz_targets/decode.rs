@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mars_xlog::{Decoder, LogFileHeader};
+
+fuzz_target!(|data: &[u8]| {
+    let decoder = Decoder::new();
+    // The lenient and strict paths skip damaged blocks differently.
+    let _ = decoder.decode(data);
+    let _ = decoder.try_decode(data);
+    let _ = LogFileHeader::from_bytes(data, None);
+});
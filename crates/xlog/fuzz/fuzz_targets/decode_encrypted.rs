@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mars_xlog::Decoder;
+
+// The first 32 bytes are the private key, so encrypted blocks reach the
+// ECDH and TEA paths instead of being skipped for lack of a key.
+fuzz_target!(|data: &[u8]| {
+    let Some((key, input)) = data.split_first_chunk::<32>() else {
+        return;
+    };
+    let _ = Decoder::new().private_key(*key).decode(input);
+});
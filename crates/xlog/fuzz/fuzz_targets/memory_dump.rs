@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mars_xlog::Xlog;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Xlog::memory_dump(data);
+});
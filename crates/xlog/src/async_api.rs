@@ -0,0 +1,151 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use mars_xlog_core::follow::{FollowStream, LogFollower};
+
+use crate::{FlushOutcome, Xlog, XlogError};
+
+/// Result of [`Xlog::upload_logs`].
+#[derive(Debug)]
+pub struct UploadReport<E> {
+    /// Files the upload callback accepted, in date and rotation order.
+    pub uploaded: Vec<PathBuf>,
+    /// Files the callback rejected, with its error.
+    pub failed: Vec<(PathBuf, E)>,
+}
+
+impl Xlog {
+    /// Async variant of `flush(true)`.
+    ///
+    /// The sync flush runs on tokio's blocking pool, so the calling task
+    /// yields instead of blocking a runtime worker.
+    pub async fn flush_async(&self) {
+        let logger = self.clone();
+        blocking(move || logger.flush(true)).await;
+    }
+
+    /// Async variant of [`Xlog::flush_bounded`].
+    pub async fn flush_bounded_async(&self, timeout: Duration) -> FlushOutcome {
+        let logger = self.clone();
+        blocking(move || logger.flush_bounded(timeout)).await
+    }
+
+    /// Flush, move eligible cache files into the log directory, and pass each
+    /// log file from today and the previous `days` days to `upload`.
+    ///
+    /// Files are uploaded one at a time, oldest first; a failed upload is
+    /// recorded and the rest still run. Deleting or archiving uploaded files
+    /// is left to the caller.
+    pub async fn upload_logs<F, Fut, E>(
+        &self,
+        days: i32,
+        mut upload: F,
+    ) -> Result<UploadReport<E>, XlogError>
+    where
+        F: FnMut(PathBuf) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+    {
+        let logger = self.clone();
+        let files = blocking(move || {
+            logger.migrate_cached_logs()?;
+            let mut files = Vec::new();
+            for timespan in (0..=days.max(0)).rev() {
+                files.extend(logger.log_files_for_timespan(timespan));
+            }
+            Ok::<_, XlogError>(files)
+        })
+        .await?;
+
+        let mut report = UploadReport {
+            uploaded: Vec::new(),
+            failed: Vec::new(),
+        };
+        for file in files {
+            let path = PathBuf::from(file);
+            match upload(path.clone()).await {
+                Ok(()) => report.uploaded.push(path),
+                Err(err) => report.failed.push((path, err)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Stream of entries written to this instance's log directory from now
+    /// on; see [`LogFollower`].
+    ///
+    /// Async-mode lines appear once they are flushed. Files still in the
+    /// cache directory are not followed.
+    pub fn follow_stream(&self) -> FollowStream {
+        LogFollower::new(
+            self.log_dir().unwrap_or_default(),
+            self.inner.name_prefix.clone(),
+        )
+        .into_stream()
+    }
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(err) => match err.try_into_panic() {
+            Ok(payload) => std::panic::resume_unwind(payload),
+            Err(err) => panic!("xlog blocking task did not complete: {err}"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_core::Stream;
+
+    use crate::test_support::LogDir;
+    use crate::{AppenderMode, LogLevel, Xlog};
+
+    #[test]
+    fn flush_upload_and_follow() {
+        let dir = LogDir::new();
+        let cfg = dir.config("tokio").mode(AppenderMode::Async);
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+
+        runtime.block_on(async {
+            logger.write(LogLevel::Info, Some("t"), "before upload");
+            logger.flush_async().await;
+            let mut seen = Vec::new();
+            let report = logger
+                .upload_logs(1, |path| {
+                    seen.push(path.clone());
+                    async move {
+                        if path.exists() {
+                            Ok(())
+                        } else {
+                            Err("missing")
+                        }
+                    }
+                })
+                .await
+                .expect("upload");
+            assert_eq!(report.uploaded, seen);
+            assert_eq!(report.uploaded.len(), 1);
+            assert!(report.failed.is_empty());
+
+            let mut stream = std::pin::pin!(logger.follow_stream());
+            let pending = std::future::poll_fn(|cx| {
+                std::task::Poll::Ready(stream.as_mut().poll_next(cx).is_pending())
+            })
+            .await;
+            assert!(pending);
+            logger.write(LogLevel::Info, Some("t"), "followed");
+            logger.flush_async().await;
+            let entry = std::future::poll_fn(|cx| stream.as_mut().poll_next(cx))
+                .await
+                .expect("stream item")
+                .expect("entry");
+            assert_eq!(entry.entry().msg, "followed");
+        });
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::AlertCallback;
+use crate::LogLevel;
+
+static NEXT_ALERT_ID: AtomicU64 = AtomicU64::new(1);
+
+struct ThresholdRule {
+    id: u64,
+    level: LogLevel,
+    count: usize,
+    window: Duration,
+    hits: VecDeque<Instant>,
+    callback: Arc<Mutex<AlertCallback>>,
+}
+
+/// Per-instance threshold alerts evaluated on every accepted record.
+///
+/// The write path only pays an atomic load while no rule is registered.
+pub(super) struct ThresholdAlerts {
+    active: AtomicBool,
+    rules: Mutex<Vec<ThresholdRule>>,
+}
+
+impl ThresholdAlerts {
+    pub(super) fn new() -> Self {
+        Self {
+            active: AtomicBool::new(false),
+            rules: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(super) fn add(
+        &self,
+        level: LogLevel,
+        count: usize,
+        window: Duration,
+        callback: AlertCallback,
+    ) -> u64 {
+        let id = NEXT_ALERT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut rules = self.rules.lock().expect("alert rules lock poisoned");
+        rules.push(ThresholdRule {
+            id,
+            level,
+            count: count.max(1),
+            window,
+            hits: VecDeque::new(),
+            callback: Arc::new(Mutex::new(callback)),
+        });
+        self.active.store(true, Ordering::Release);
+        id
+    }
+
+    pub(super) fn remove(&self, id: u64) -> bool {
+        let mut rules = self.rules.lock().expect("alert rules lock poisoned");
+        let before = rules.len();
+        rules.retain(|rule| rule.id != id);
+        self.active.store(!rules.is_empty(), Ordering::Release);
+        rules.len() != before
+    }
+
+    pub(super) fn record(&self, level: LogLevel) {
+        if !self.active.load(Ordering::Acquire) || level == LogLevel::None {
+            return;
+        }
+        let now = Instant::now();
+        let mut fired = Vec::new();
+        {
+            let mut rules = self.rules.lock().expect("alert rules lock poisoned");
+            for rule in rules.iter_mut() {
+                if level.rank() < rule.level.rank() {
+                    continue;
+                }
+                while let Some(oldest) = rule.hits.front() {
+                    if now.duration_since(*oldest) <= rule.window {
+                        break;
+                    }
+                    rule.hits.pop_front();
+                }
+                rule.hits.push_back(now);
+                if rule.hits.len() >= rule.count {
+                    // Re-arm: the next alert needs another full `count` records.
+                    rule.hits.clear();
+                    fired.push(Arc::clone(&rule.callback));
+                }
+            }
+        }
+        // Callbacks run unlocked; `try_lock` skips one that is still running,
+        // e.g. when it logs at the alerted level itself.
+        for callback in fired {
+            if let Ok(mut callback) = callback.try_lock() {
+                callback();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::test_support::{GlobalAppender, LogDir};
+    use crate::{LogLevel, RawLogMeta, Xlog};
+
+    #[test]
+    fn threshold_alert_fires_once_per_count_within_window() {
+        let dir = LogDir::new();
+        let logger = Xlog::init(dir.config("alert"), LogLevel::Info).expect("init");
+        let fired = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&fired);
+        let id = logger.on_threshold(LogLevel::Error, 3, Duration::from_secs(60), move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        logger.write(LogLevel::Warn, None, "not counted");
+        logger.write(LogLevel::Error, None, "e1");
+        logger.write(LogLevel::Fatal, None, "e2");
+        assert_eq!(fired.load(Ordering::Relaxed), 0);
+        logger.write(LogLevel::Error, None, "e3");
+        assert_eq!(fired.load(Ordering::Relaxed), 1);
+        logger.write(LogLevel::Error, None, "e4");
+        assert_eq!(fired.load(Ordering::Relaxed), 1);
+
+        assert!(logger.remove_threshold_alert(id));
+        for _ in 0..3 {
+            logger.write(LogLevel::Error, None, "after removal");
+        }
+        assert_eq!(fired.load(Ordering::Relaxed), 1);
+        assert!(!logger.remove_threshold_alert(id));
+    }
+
+    #[test]
+    fn threshold_alert_counts_global_appender_writes() {
+        let _appender = GlobalAppender::lock();
+        let dir = LogDir::new();
+        let cfg = dir.config("alert-global");
+        Xlog::appender_open(cfg.clone(), LogLevel::Info).expect("open appender");
+        let logger = Xlog::get(&cfg.name_prefix).expect("default instance");
+        let fired = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&fired);
+        logger.on_threshold(LogLevel::Error, 2, Duration::from_secs(60), move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        for msg in ["global e1", "global e2"] {
+            Xlog::appender_write_with_meta_raw(
+                LogLevel::Error,
+                None,
+                "main.rs",
+                "main",
+                1,
+                msg,
+                RawLogMeta::default(),
+            );
+        }
+        assert_eq!(fired.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::EntryListener;
+use crate::LogLevel;
+
+static NEXT_ENTRY_LISTENER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Per-instance callbacks receiving every entry that passed the filters.
+///
+/// The write path only pays an atomic load while no listener is registered.
+pub(super) struct EntryListeners {
+    active: AtomicBool,
+    listeners: Mutex<Vec<(u64, Arc<Mutex<EntryListener>>)>>,
+}
+
+impl EntryListeners {
+    pub(super) fn new() -> Self {
+        Self {
+            active: AtomicBool::new(false),
+            listeners: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(super) fn add(&self, listener: EntryListener) -> u64 {
+        let id = NEXT_ENTRY_LISTENER_ID.fetch_add(1, Ordering::Relaxed);
+        let mut listeners = self.listeners.lock().expect("entry listener lock poisoned");
+        listeners.push((id, Arc::new(Mutex::new(listener))));
+        self.active.store(true, Ordering::Release);
+        id
+    }
+
+    pub(super) fn remove(&self, id: u64) -> bool {
+        let mut listeners = self.listeners.lock().expect("entry listener lock poisoned");
+        let before = listeners.len();
+        listeners.retain(|(listener_id, _)| *listener_id != id);
+        self.active.store(!listeners.is_empty(), Ordering::Release);
+        listeners.len() != before
+    }
+
+    pub(super) fn notify(&self, level: LogLevel, tag: &str, msg: &str) {
+        if !self.active.load(Ordering::Acquire) {
+            return;
+        }
+        // Snapshot so listeners can register or remove listeners themselves.
+        let listeners: Vec<_> = self
+            .listeners
+            .lock()
+            .expect("entry listener lock poisoned")
+            .iter()
+            .map(|(_, listener)| Arc::clone(listener))
+            .collect();
+        // `try_lock` skips a listener that is still running, e.g. when it
+        // logs to the same instance itself.
+        for listener in listeners {
+            if let Ok(mut listener) = listener.try_lock() {
+                listener(level, tag, msg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::test_support::LogDir;
+    use crate::{LogLevel, Xlog};
+
+    #[test]
+    fn entry_listener_sees_every_written_entry_of_the_instance() {
+        let dir = LogDir::new();
+        let cfg = dir.config("entry-listener");
+        let prefix = cfg.name_prefix.clone();
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let echo = logger.clone();
+        let id = logger.on_entry_written(move |level, tag, msg| {
+            sink.lock()
+                .expect("seen lock poisoned")
+                .push(format!("{level:?} {tag} {msg}"));
+            echo.write(LogLevel::Info, Some("echo"), "not passed back");
+        });
+
+        logger.write(LogLevel::Debug, Some("ui"), "below level");
+        logger.write(LogLevel::Info, Some("ui"), "direct");
+        Xlog::get(&prefix)
+            .expect("live instance")
+            .write(LogLevel::Warn, None, "other handle");
+        logger.write_bytes(LogLevel::Info, Some("bin"), b"ok");
+        logger.set_directives("info,net=error".parse().expect("parse"));
+        logger.write(LogLevel::Warn, Some("net"), "filtered by tag");
+        assert!(logger.remove_entry_listener(id));
+        assert!(!logger.remove_entry_listener(id));
+        logger.write(LogLevel::Info, Some("ui"), "after removal");
+
+        let seen = seen.lock().expect("seen lock poisoned");
+        assert_eq!(seen.len(), 3, "{seen:?}");
+        assert_eq!(seen[0], "Info ui direct");
+        assert_eq!(seen[1], format!("Warn {prefix} other handle"));
+        assert!(seen[2].starts_with("Info bin ") && seen[2].contains("6f 6b"));
+    }
+}
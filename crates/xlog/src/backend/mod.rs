@@ -1,6 +1,14 @@
+use std::io;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::{AppenderMode, FileIoAction, LogLevel, RawLogMeta, XlogConfig, XlogError};
+use mars_xlog_core::file_events::FileEventListener;
+
+use crate::directives::TagFilter;
+use crate::{
+    AppenderMode, FileIoAction, FlushOutcome, LogLevel, RawLogMeta, XlogConfig, XlogError,
+    XlogMetricsSnapshot,
+};
 
 #[cfg(not(feature = "rust-backend"))]
 compile_error!(
@@ -13,21 +21,73 @@ compile_error!(
     target_os = "tvos",
     target_os = "watchos"
 ))]
-use crate::ConsoleFun;
+use crate::{ConsoleFun, OsLogPrivacy};
 
+mod alerts;
+mod entries;
 mod metrics;
+mod multiline;
+mod reporter;
 mod rust;
 
+pub(crate) use reporter::level_slot;
+
+/// Callback invoked with the new level after an instance's level changes.
+pub(crate) type LevelListener = Box<dyn FnMut(LogLevel) + Send>;
+/// Callback receiving the level, tag and message of each written entry.
+pub(crate) type EntryListener = Box<dyn FnMut(LogLevel, &str, &str) + Send>;
+/// Callback invoked when a threshold alert fires.
+pub(crate) type AlertCallback = Box<dyn FnMut() + Send>;
+/// Callback invoked when background writes or file maintenance fail.
+pub(crate) type ErrorHandler = Arc<dyn Fn(&XlogError) + Send + Sync>;
+/// Callback receiving periodic health counters.
+pub(crate) type MetricsReporter = Box<dyn FnMut(XlogMetricsSnapshot) + Send>;
+
 pub(crate) trait XlogBackend: Send + Sync {
     fn instance(&self) -> usize;
     fn is_enabled(&self, level: LogLevel) -> bool;
     fn level(&self) -> LogLevel;
     fn set_level(&self, level: LogLevel);
+    /// Per-tag directives checked after the level on every write.
+    fn tag_filter(&self) -> &TagFilter;
+    /// An explicit level change: also what tags the directives do not cover
+    /// use, and what clearing them restores.
+    fn set_base_level(&self, level: LogLevel) {
+        self.tag_filter().set_base(level);
+        self.set_level(level);
+    }
+    fn add_level_listener(&self, listener: LevelListener) -> u64;
+    fn remove_level_listener(&self, id: u64) -> bool;
+    fn add_entry_listener(&self, listener: EntryListener) -> u64;
+    fn remove_entry_listener(&self, id: u64) -> bool;
+    fn add_file_listener(&self, listener: FileEventListener) -> u64;
+    fn remove_file_listener(&self, id: u64) -> bool;
+    fn add_threshold_alert(
+        &self,
+        level: LogLevel,
+        count: usize,
+        window: Duration,
+        callback: AlertCallback,
+    ) -> u64;
+    fn remove_threshold_alert(&self, id: u64) -> bool;
+    fn appender_mode(&self) -> AppenderMode;
     fn set_appender_mode(&self, mode: AppenderMode);
     fn flush(&self, sync: bool);
+    fn flush_bounded(&self, timeout: Duration) -> FlushOutcome;
     fn set_console_log_open(&self, open: bool);
     fn set_max_file_size(&self, max_bytes: i64);
     fn set_max_alive_time(&self, alive_seconds: i64);
+    fn set_pub_key(&self, pub_key: &str) -> Result<(), XlogError>;
+    fn migrate_cached_logs(&self) -> Result<(), XlogError>;
+    fn set_error_handler(&self, handler: Option<ErrorHandler>);
+    fn set_metrics_reporter(
+        &self,
+        interval: Duration,
+        reporter: Option<MetricsReporter>,
+    ) -> io::Result<()>;
+    fn log_dir(&self) -> Option<String>;
+    fn cache_dir(&self) -> Option<String>;
+    fn filepaths_from_timespan(&self, timespan: i32, prefix: &str) -> Vec<String>;
     #[allow(clippy::too_many_arguments)]
     fn write_with_meta(
         &self,
@@ -53,6 +113,8 @@ pub(crate) trait XlogBackendProvider: Send + Sync {
     fn appender_open(&self, config: &XlogConfig, level: LogLevel) -> Result<(), XlogError>;
     fn appender_close(&self);
     fn flush_all(&self, sync: bool);
+    /// The default instance (if any) followed by every other live instance.
+    fn live_instances(&self) -> Vec<Arc<dyn XlogBackend>>;
     fn global_is_enabled(&self, level: LogLevel) -> bool;
     #[allow(clippy::too_many_arguments)]
     fn write_global_with_meta(
@@ -74,6 +136,14 @@ pub(crate) trait XlogBackendProvider: Send + Sync {
     ))]
     fn set_console_fun(&self, fun: ConsoleFun);
 
+    #[cfg(any(
+        target_os = "ios",
+        target_os = "macos",
+        target_os = "tvos",
+        target_os = "watchos"
+    ))]
+    fn set_oslog_privacy(&self, privacy: OsLogPrivacy);
+
     fn current_log_path(&self) -> Option<String>;
     fn current_log_cache_path(&self) -> Option<String>;
     fn filepaths_from_timespan(&self, timespan: i32, prefix: &str) -> Vec<String>;
@@ -81,6 +151,7 @@ pub(crate) trait XlogBackendProvider: Send + Sync {
     fn oneshot_flush(&self, config: &XlogConfig) -> Result<FileIoAction, XlogError>;
     fn dump(&self, buffer: &[u8]) -> String;
     fn memory_dump(&self, buffer: &[u8]) -> String;
+    fn is_valid_pub_key(&self, pub_key: &str) -> bool;
 }
 
 pub(crate) fn provider() -> &'static dyn XlogBackendProvider {
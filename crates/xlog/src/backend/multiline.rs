@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::MultilineMode;
+
+static NEXT_MULTILINE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Entries to write instead of `msg`, or `None` to write it unchanged.
+///
+/// `\r\n`, `\n` and a lone `\r` all count as line breaks; a single trailing
+/// one does not make a message multi-line.
+pub(super) fn rewrite(mode: MultilineMode, msg: &str) -> Option<Vec<String>> {
+    if mode == MultilineMode::Keep {
+        return None;
+    }
+    let body = msg
+        .strip_suffix("\r\n")
+        .or_else(|| msg.strip_suffix(['\n', '\r']))
+        .unwrap_or(msg);
+    if !body.contains(['\n', '\r']) {
+        return None;
+    }
+    match mode {
+        MultilineMode::Keep => None,
+        MultilineMode::Escape => Some(vec![body
+            .replace("\r\n", "\\n")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")]),
+        MultilineMode::Split => {
+            let id = NEXT_MULTILINE_ID.fetch_add(1, Ordering::Relaxed);
+            let body = body.replace("\r\n", "\n");
+            let lines: Vec<&str> = body.split(['\n', '\r']).collect();
+            let total = lines.len();
+            Some(
+                lines
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, line)| format!("[ml {id} {}/{total}] {line}", idx + 1))
+                    .collect(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rewrite;
+    use crate::test_support::{logged_text, LogDir};
+    use crate::{AppenderMode, LogLevel, MultilineMode, Xlog};
+
+    #[test]
+    fn escape_and_split_agree_on_boundaries() {
+        assert_eq!(rewrite(MultilineMode::Escape, "one line\n"), None);
+        assert_eq!(rewrite(MultilineMode::Keep, "a\nb"), None);
+        assert_eq!(
+            rewrite(MultilineMode::Escape, "a\r\nb\nc\n"),
+            Some(vec!["a\\nb\\nc".to_string()])
+        );
+        assert_eq!(rewrite(MultilineMode::Escape, "one line\r"), None);
+        assert_eq!(
+            rewrite(MultilineMode::Escape, "a\rb"),
+            Some(vec!["a\\rb".to_string()])
+        );
+
+        let entries = rewrite(MultilineMode::Split, "a\r\nb\n\nc").expect("split");
+        let id = entries[0]
+            .strip_prefix("[ml ")
+            .and_then(|rest| rest.split_once(' '))
+            .map(|(id, _)| id.to_string())
+            .expect("id");
+        assert_eq!(
+            entries,
+            vec![
+                format!("[ml {id} 1/4] a"),
+                format!("[ml {id} 2/4] b"),
+                format!("[ml {id} 3/4] "),
+                format!("[ml {id} 4/4] c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_breaks_on_a_lone_carriage_return() {
+        let entries = rewrite(MultilineMode::Split, "a\rb").expect("split");
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].ends_with(" 1/2] a") && entries[1].ends_with(" 2/2] b"));
+    }
+
+    #[test]
+    fn multiline_split_writes_one_entry_per_line() {
+        let dir = LogDir::new();
+        let cfg = dir
+            .config("multiline")
+            .mode(AppenderMode::Sync)
+            .multiline(MultilineMode::Split);
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        logger.write(
+            LogLevel::Info,
+            Some("ml"),
+            "header\n  detail one\n  detail two",
+        );
+
+        let text = logged_text(&logger);
+        let entries: Vec<&str> = text.lines().filter(|line| line.contains("[ml ")).collect();
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].starts_with("[I]") && entries[0].ends_with(" 1/3] header"));
+        assert!(entries[2].starts_with("[I]") && entries[2].ends_with(" 3/3]   detail two"));
+    }
+}
@@ -0,0 +1,267 @@
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "metrics")]
+use metrics::{counter, histogram, Counter, Histogram};
+
+use super::MetricsReporter;
+use crate::{LogLevel, XlogMetricsSnapshot};
+
+/// Floor for the report interval; shorter waits would spin the thread.
+const MIN_REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Per-instance health counters, reset each time a snapshot is taken.
+///
+/// With the `metrics` feature the same counts also go to the installed
+/// `metrics` recorder, labelled with the instance's `name_prefix`.
+pub(super) struct InstanceCounters {
+    lines: [AtomicU64; 6],
+    bytes: AtomicU64,
+    dropped: AtomicU64,
+    flushes: AtomicU64,
+    flush_ns_total: AtomicU64,
+    flush_ns_max: AtomicU64,
+    #[cfg(feature = "metrics")]
+    exported: ExportedCounters,
+}
+
+/// Recorder handles resolved once per instance so the write path does not
+/// look up labels.
+#[cfg(feature = "metrics")]
+struct ExportedCounters {
+    lines: [Counter; 6],
+    bytes: Counter,
+    dropped: Counter,
+    flushes: Counter,
+    flush_ns: Histogram,
+}
+
+#[cfg(feature = "metrics")]
+impl ExportedCounters {
+    fn new(instance: &str) -> Self {
+        const LEVELS: [&str; 6] = ["verbose", "debug", "info", "warn", "error", "fatal"];
+        let instance = instance.to_string();
+        Self {
+            lines: LEVELS.map(|level| {
+                counter!(
+                    "xlog.instance.lines_total",
+                    "instance" => instance.clone(),
+                    "level" => level
+                )
+            }),
+            bytes: counter!("xlog.instance.bytes_total", "instance" => instance.clone()),
+            dropped: counter!("xlog.instance.dropped_total", "instance" => instance.clone()),
+            flushes: counter!("xlog.instance.flush_total", "instance" => instance.clone()),
+            flush_ns: histogram!("xlog.instance.flush_ns", "instance" => instance),
+        }
+    }
+}
+
+impl InstanceCounters {
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(super) fn new(instance: &str) -> Self {
+        Self {
+            lines: Default::default(),
+            bytes: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            flushes: AtomicU64::new(0),
+            flush_ns_total: AtomicU64::new(0),
+            flush_ns_max: AtomicU64::new(0),
+            #[cfg(feature = "metrics")]
+            exported: ExportedCounters::new(instance),
+        }
+    }
+
+    pub(super) fn record_line(&self, level: LogLevel, bytes: usize) {
+        if let Some(slot) = level_slot(level) {
+            self.lines[slot].fetch_add(1, Ordering::Relaxed);
+            #[cfg(feature = "metrics")]
+            self.exported.lines[slot].increment(1);
+        }
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        self.exported.bytes.increment(bytes as u64);
+    }
+
+    pub(super) fn record_drop(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        self.exported.dropped.increment(1);
+    }
+
+    pub(super) fn record_flush(&self, elapsed: Duration) {
+        let ns = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+        self.flush_ns_total.fetch_add(ns, Ordering::Relaxed);
+        self.flush_ns_max.fetch_max(ns, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        {
+            self.exported.flushes.increment(1);
+            self.exported.flush_ns.record(ns as f64);
+        }
+    }
+
+    fn take(&self, interval: Duration) -> XlogMetricsSnapshot {
+        XlogMetricsSnapshot {
+            interval,
+            lines_by_level: std::array::from_fn(|slot| self.lines[slot].swap(0, Ordering::Relaxed)),
+            bytes: self.bytes.swap(0, Ordering::Relaxed),
+            dropped: self.dropped.swap(0, Ordering::Relaxed),
+            flushes: self.flushes.swap(0, Ordering::Relaxed),
+            flush_time_total: Duration::from_nanos(self.flush_ns_total.swap(0, Ordering::Relaxed)),
+            flush_time_max: Duration::from_nanos(self.flush_ns_max.swap(0, Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Index into [`XlogMetricsSnapshot::lines_by_level`].
+pub(crate) fn level_slot(level: LogLevel) -> Option<usize> {
+    match level {
+        LogLevel::Verbose => Some(0),
+        LogLevel::Debug => Some(1),
+        LogLevel::Info => Some(2),
+        LogLevel::Warn => Some(3),
+        LogLevel::Error => Some(4),
+        LogLevel::Fatal => Some(5),
+        LogLevel::None => None,
+    }
+}
+
+/// Background thread handing counter deltas to a [`MetricsReporter`].
+pub(super) struct ReporterWorker {
+    worker: Mutex<Option<(Sender<()>, JoinHandle<()>)>>,
+}
+
+impl ReporterWorker {
+    pub(super) fn new() -> Self {
+        Self {
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// Replace the running reporter; `None` only stops it.
+    ///
+    /// A stopped reporter receives one last snapshot covering the time since
+    /// its previous report. Fails when the thread cannot be spawned, leaving
+    /// no reporter running.
+    pub(super) fn set(
+        &self,
+        counters: &Arc<InstanceCounters>,
+        interval: Duration,
+        reporter: Option<MetricsReporter>,
+    ) -> io::Result<()> {
+        self.stop();
+        let Some(mut reporter) = reporter else {
+            return Ok(());
+        };
+        // Drop counts from before the reporter existed.
+        counters.take(Duration::ZERO);
+        let counters = Arc::clone(counters);
+        let interval = interval.max(MIN_REPORT_INTERVAL);
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("xlog-metrics".into())
+            .spawn(move || {
+                let mut since = Instant::now();
+                loop {
+                    let stop = match stop_rx.recv_timeout(interval) {
+                        Err(RecvTimeoutError::Timeout) => false,
+                        Ok(()) | Err(RecvTimeoutError::Disconnected) => true,
+                    };
+                    let now = Instant::now();
+                    reporter(counters.take(now - since));
+                    since = now;
+                    if stop {
+                        break;
+                    }
+                }
+            })?;
+        *self.worker.lock().expect("metrics reporter lock poisoned") = Some((stop_tx, handle));
+        Ok(())
+    }
+
+    pub(super) fn stop(&self) {
+        let worker = self
+            .worker
+            .lock()
+            .expect("metrics reporter lock poisoned")
+            .take();
+        if let Some((stop_tx, handle)) = worker {
+            let _ = stop_tx.send(());
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::test_support::LogDir;
+    use crate::{LogLevel, XlogMetricsSnapshot};
+
+    #[test]
+    fn metrics_reporter_delivers_interval_counters() {
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("report", LogLevel::Info);
+        let reports = Arc::new(Mutex::new(Vec::<XlogMetricsSnapshot>::new()));
+        let sink = Arc::clone(&reports);
+        logger
+            .set_metrics_reporter(Duration::from_secs(60), move |snapshot| {
+                sink.lock().expect("reports").push(snapshot);
+            })
+            .expect("reporter");
+
+        logger.write(LogLevel::Info, Some("m"), "hello");
+        logger.write(LogLevel::Error, Some("m"), "oops");
+        logger.write(LogLevel::Debug, Some("m"), "filtered");
+        logger.flush(true);
+        logger.clear_metrics_reporter();
+
+        let reports = reports.lock().expect("reports");
+        assert_eq!(reports.len(), 1);
+        let snapshot = &reports[0];
+        assert_eq!(snapshot.lines(LogLevel::Info), 1);
+        assert_eq!(snapshot.lines(LogLevel::Error), 1);
+        assert_eq!(snapshot.total_lines(), 2);
+        assert_eq!(snapshot.bytes, 9);
+        assert_eq!(snapshot.dropped, 0);
+        assert_eq!(snapshot.flushes, 1);
+        assert!(snapshot.flush_time_max <= snapshot.flush_time_total);
+    }
+
+    #[cfg(feature = "metrics-prometheus")]
+    #[test]
+    fn instance_counters_reach_the_metrics_recorder() {
+        use crate::{AppenderMode, Xlog};
+
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let dir = LogDir::new();
+        let cfg = dir.config("prom").mode(AppenderMode::Sync);
+        let prefix = cfg.name_prefix.clone();
+        let logger = metrics::with_local_recorder(&recorder, || {
+            Xlog::init(cfg, LogLevel::Info).expect("init")
+        });
+        logger.write(LogLevel::Info, Some("m"), "one");
+        logger.write(LogLevel::Info, Some("m"), "two");
+        logger.write(LogLevel::Warn, Some("m"), "three");
+        logger.flush(true);
+
+        let text = handle.render();
+        assert!(text.contains(&format!(
+            "xlog_instance_lines_total{{instance=\"{prefix}\",level=\"info\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "xlog_instance_bytes_total{{instance=\"{prefix}\"}} 11"
+        )));
+        assert!(text.contains(&format!(
+            "xlog_instance_flush_total{{instance=\"{prefix}\"}} 1"
+        )));
+    }
+}
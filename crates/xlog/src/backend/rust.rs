@@ -1,25 +1,31 @@
 use std::cell::{Cell, RefCell};
 use std::fmt::Write as _;
-use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
 use std::sync::mpsc::{
-    channel as std_channel, sync_channel, Receiver as StdReceiver, SendError, Sender as StdSender,
-    SyncSender, TryRecvError, TrySendError,
+    channel as std_channel, sync_channel, Receiver as StdReceiver, RecvTimeoutError, SendError,
+    Sender as StdSender, SyncSender, TryRecvError, TrySendError,
 };
-use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use arc_swap::ArcSwap;
 use chrono::TimeZone;
 use crossbeam_queue::ArrayQueue;
 use mars_xlog_core::appender_engine::{
-    AppenderEngine, AsyncFlushReason as EngineAsyncFlushReason, EngineMode,
+    AppenderEngine, AppenderEngineError, AsyncFlushReason as EngineAsyncFlushReason,
+    EngineErrorHandler, EngineMode,
 };
-use mars_xlog_core::buffer::{PersistentBuffer, DEFAULT_BUFFER_BLOCK_LEN};
+use mars_xlog_core::buffer::{BufferError, PersistentBuffer, DEFAULT_BUFFER_BLOCK_LEN};
 use mars_xlog_core::compress::{StreamCompressor, ZlibStreamCompressor, ZstdStreamCompressor};
 use mars_xlog_core::crypto::EcdhTeaCipher;
 use mars_xlog_core::dump::{dump_to_file, memory_dump};
-use mars_xlog_core::file_manager::FileManager;
+use mars_xlog_core::file_events::{FileEventListener, FileEvents};
+use mars_xlog_core::file_manager::{FileManager, FileManagerError};
 use mars_xlog_core::formatter::format_record_parts_into;
+use mars_xlog_core::mmap_store::MmapStoreError;
 use mars_xlog_core::oneshot::{
     oneshot_flush as core_oneshot_flush, FileIoAction as CoreFileIoAction,
 };
@@ -30,7 +36,9 @@ use mars_xlog_core::platform_console::write_console_line;
     target_os = "tvos",
     target_os = "watchos"
 ))]
-use mars_xlog_core::platform_console::{set_apple_console_fun, AppleConsoleFun};
+use mars_xlog_core::platform_console::{
+    set_apple_console_fun, set_apple_oslog_privacy, AppleConsoleFun, AppleOsLogPrivacy,
+};
 use mars_xlog_core::platform_tid::{current_tid, main_tid};
 use mars_xlog_core::protocol::{
     select_magic, AppendMode, CompressionKind, LogHeader, SeqGenerator, HEADER_LEN,
@@ -38,6 +46,8 @@ use mars_xlog_core::protocol::{
 use mars_xlog_core::record::LogLevel as CoreLogLevel;
 use mars_xlog_core::registry::InstanceRegistry;
 
+use super::alerts::ThresholdAlerts;
+use super::entries::EntryListeners;
 use super::metrics::{
     record_async_block_send, record_async_dequeued, record_async_enqueued,
     record_async_flush_requeues, record_async_pending_block, record_async_queue_full,
@@ -45,9 +55,17 @@ use super::metrics::{
     AsyncPendingFinalizeReason, AsyncStageSample, AsyncWriteFrontProfile, SyncBuildStage,
     SyncStageSample, METRICS_ENABLED,
 };
-use super::{XlogBackend, XlogBackendProvider};
+use super::multiline;
+use super::reporter::{InstanceCounters, ReporterWorker};
+use super::{
+    AlertCallback, EntryListener, ErrorHandler, LevelListener, MetricsReporter, XlogBackend,
+    XlogBackendProvider,
+};
+use crate::directives::TagFilter;
+use crate::validate::name_prefix_problem;
 use crate::{
-    AppenderMode, CompressMode, FileIoAction, LogLevel, RawLogMeta, XlogConfig, XlogError,
+    AppenderMode, CompressMode, FileIoAction, FlushOutcome, LogLevel, RawLogMeta, XlogConfig,
+    XlogError,
 };
 
 #[cfg(any(
@@ -56,7 +74,7 @@ use crate::{
     target_os = "tvos",
     target_os = "watchos"
 ))]
-use crate::ConsoleFun;
+use crate::{ConsoleFun, OsLogPrivacy};
 
 pub(super) fn provider() -> &'static dyn XlogBackendProvider {
     static PROVIDER: RustBackendProvider = RustBackendProvider;
@@ -68,9 +86,17 @@ struct RustBackendProvider;
 struct RustBackend {
     id: usize,
     config: XlogConfig,
-    level: AtomicI32,
+    level: AtomicU8,
+    tag_filter: TagFilter,
+    level_listeners: Mutex<Vec<(u64, Arc<Mutex<LevelListener>>)>>,
+    file_events: FileEvents,
+    alerts: ThresholdAlerts,
+    entry_listeners: EntryListeners,
+    counters: Arc<InstanceCounters>,
+    reporter: ReporterWorker,
     console_open: AtomicBool,
-    cipher: EcdhTeaCipher,
+    /// Swapped only while the async state is checked out, see `set_pub_key`.
+    cipher: ArcSwap<EcdhTeaCipher>,
     engine: Arc<AppenderEngine>,
     async_frontend: AsyncFrontend,
     async_state: Mutex<AsyncStateSlot>,
@@ -96,6 +122,10 @@ enum AsyncFrontendCommand {
     Stop {
         ack: StdSender<()>,
     },
+    SetCipher {
+        cipher: EcdhTeaCipher,
+        ack: StdSender<()>,
+    },
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -416,6 +446,7 @@ thread_local! {
 }
 
 static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+static NEXT_LEVEL_LISTENER_ID: AtomicU64 = AtomicU64::new(1);
 
 const ASYNC_WARNING_THRESHOLD_NUM: usize = 4;
 const ASYNC_WARNING_THRESHOLD_DEN: usize = 5;
@@ -427,6 +458,8 @@ const ASYNC_LINE_POOL_SHARDS: usize = 16;
 const ASYNC_LINE_POOL_MAX_BUFFERS_PER_SHARD: usize = 256;
 const ASYNC_LINE_POOL_MAX_CAPACITY: usize = 8 * 1024;
 const ASYNC_LINE_BUFFER_INIT_CAPACITY: usize = 512;
+/// Floor for `XlogConfig::flush_interval`; shorter waits would spin the engine worker.
+const MIN_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 const ASYNC_LINE_POOL_SHARD_SENTINEL: usize = usize::MAX;
 // Keep the historical BUFFER_BLOCK_LENTH typo for compatibility with
 // existing Mars/C++ log text and external grep patterns.
@@ -582,10 +615,58 @@ impl AsyncFrontend {
         }
     }
 
+    /// Sync flush that stops waiting after `timeout`.
+    ///
+    /// `None` means the worker is gone. A full queue or an expired deadline
+    /// leaves the flush queued and reports [`FlushOutcome::Pending`].
+    fn request_flush_bounded(
+        &self,
+        timeout: Duration,
+        reason: AsyncFlushControlReason,
+    ) -> Option<FlushOutcome> {
+        let (ack_tx, ack_rx) = std_channel::<()>();
+        match self.tx.try_send(AsyncFrontendCommand::Flush {
+            sync: true,
+            ack: Some(ack_tx),
+            reason,
+        }) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                return self
+                    .request_flush(false, reason)
+                    .then_some(FlushOutcome::Pending);
+            }
+            Err(TrySendError::Disconnected(_)) => return None,
+        }
+        match ack_rx.recv_timeout(timeout) {
+            Ok(()) => Some(FlushOutcome::Completed),
+            Err(RecvTimeoutError::Timeout) => Some(FlushOutcome::Pending),
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
     fn set_accepting(&self, enabled: bool) {
         self.accepting.store(enabled, Ordering::Release);
     }
 
+    /// Hand a new cipher to the worker and wait until it is in use.
+    ///
+    /// Lines queued before this call are still written with the old cipher.
+    fn set_cipher(&self, cipher: EcdhTeaCipher) -> bool {
+        let (ack_tx, ack_rx) = std_channel::<()>();
+        if self
+            .tx
+            .send(AsyncFrontendCommand::SetCipher {
+                cipher,
+                ack: ack_tx,
+            })
+            .is_err()
+        {
+            return false;
+        }
+        ack_rx.recv().is_ok()
+    }
+
     fn take_line_buffer(&self, shard: usize) -> String {
         debug_assert!(
             shard < self.line_pools.len(),
@@ -631,7 +712,7 @@ fn run_async_frontend_worker(
     line_pools: Arc<[ArrayQueue<String>]>,
     engine: Arc<AppenderEngine>,
     config: XlogConfig,
-    cipher: EcdhTeaCipher,
+    mut cipher: EcdhTeaCipher,
 ) {
     let capacity = engine.buffer_capacity();
     let mut pending: Option<AsyncPendingState> = None;
@@ -700,7 +781,7 @@ fn run_async_frontend_worker(
                         control,
                         &flush_queued,
                         &engine,
-                        &cipher,
+                        &mut cipher,
                         &mut pending,
                         &mut compress_scratch,
                         &mut crypto_scratch,
@@ -714,7 +795,7 @@ fn run_async_frontend_worker(
                     control,
                     &flush_queued,
                     &engine,
-                    &cipher,
+                    &mut cipher,
                     &mut pending,
                     &mut compress_scratch,
                     &mut crypto_scratch,
@@ -730,7 +811,7 @@ fn handle_async_frontend_control(
     cmd: AsyncFrontendCommand,
     flush_queued: &AtomicBool,
     engine: &AppenderEngine,
-    cipher: &EcdhTeaCipher,
+    cipher: &mut EcdhTeaCipher,
     pending: &mut Option<AsyncPendingState>,
     compress_scratch: &mut Vec<u8>,
     crypto_scratch: &mut Vec<u8>,
@@ -772,6 +853,23 @@ fn handle_async_frontend_control(
             let _ = ack.send(());
             true
         }
+        AsyncFrontendCommand::SetCipher { cipher: next, ack } => {
+            // Seal the open block first: its header carries the old client key.
+            worker_finalize_pending(
+                engine,
+                cipher,
+                pending,
+                compress_scratch,
+                crypto_scratch,
+                AsyncPendingFinalizeReason::ExplicitFlush,
+            );
+            // The async buffer holds one block; move the sealed one out before
+            // a block with the new key is started.
+            let _ = engine.flush_with_reason(true, EngineAsyncFlushReason::Explicit);
+            *cipher = next;
+            let _ = ack.send(());
+            false
+        }
         AsyncFrontendCommand::Write(_) => false,
     }
 }
@@ -1058,18 +1156,130 @@ fn registry() -> &'static InstanceRegistry<RustBackend> {
     REGISTRY.get_or_init(InstanceRegistry::new)
 }
 
+/// Serializes instance creation across threads, so the collision check, the
+/// lock-file acquisition, and the registry insert happen as one step and
+/// concurrent inits of a prefix all get the instance the first one created.
+fn init_lock() -> MutexGuard<'static, ()> {
+    static INIT_LOCK: Mutex<()> = Mutex::new(());
+    INIT_LOCK.lock().expect("init lock poisoned")
+}
+
 fn global_async_seq() -> &'static SeqGenerator {
     static SEQ: OnceLock<SeqGenerator> = OnceLock::new();
     SEQ.get_or_init(SeqGenerator::default)
 }
 
+fn check_config(config: &XlogConfig) -> Result<(), XlogError> {
+    for dir in std::iter::once(&config.log_dir).chain(config.cache_dir.as_ref()) {
+        if dir.contains('\0') {
+            return Err(XlogError::NulInPath(dir.clone()));
+        }
+    }
+    if let Some(reason) = name_prefix_problem(&config.name_prefix) {
+        return Err(XlogError::InvalidNamePrefix {
+            name_prefix: config.name_prefix.clone(),
+            reason,
+        });
+    }
+    config.check_compress_level()?;
+    Ok(())
+}
+
+/// Return the live instance for `config.name_prefix`, creating it if needed.
+/// Callers hold [`init_lock`] and apply `level` to a reused instance after
+/// releasing it, since level listeners may call back into init.
+fn named_instance(config: &XlogConfig, level: LogLevel) -> Result<Arc<RustBackend>, XlogError> {
+    check_prefix_collision(config)?;
+    let backend = registry().get_or_try_insert_with(&config.name_prefix, || {
+        Ok::<_, XlogError>(Arc::new(RustBackend::new(config.clone(), level)?))
+    })?;
+    if backend.config != *config {
+        return Err(XlogError::ConfigConflict {
+            name_prefix: config.name_prefix.clone(),
+        });
+    }
+    Ok(backend)
+}
+
+/// Reject a prefix that only differs in case from a live instance sharing one
+/// of its directories; case-insensitive file systems would merge the two.
+fn check_prefix_collision(config: &XlogConfig) -> Result<(), XlogError> {
+    let mut live: Vec<Arc<RustBackend>> = registry().default_instance().into_iter().collect();
+    registry().for_each_live(|backend| live.push(backend));
+    let wanted = config.name_prefix.to_lowercase();
+    for backend in live {
+        let other = &backend.config;
+        if other.name_prefix != config.name_prefix
+            && other.name_prefix.to_lowercase() == wanted
+            && shares_dir(other, config)
+        {
+            return Err(XlogError::NamePrefixCollision {
+                name_prefix: config.name_prefix.clone(),
+                existing: other.name_prefix.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn shares_dir(a: &XlogConfig, b: &XlogConfig) -> bool {
+    let dirs = |config: &XlogConfig| {
+        std::iter::once(PathBuf::from(&config.log_dir))
+            .chain(config.cache_dir.as_ref().map(PathBuf::from))
+            .collect::<Vec<_>>()
+    };
+    let b_dirs = dirs(b);
+    dirs(a).iter().any(|dir| b_dirs.contains(dir))
+}
+
+fn file_manager_error(err: FileManagerError, name_prefix: &str) -> XlogError {
+    match err {
+        FileManagerError::EmptyLogDir | FileManagerError::EmptyNamePrefix => {
+            XlogError::InvalidConfig
+        }
+        FileManagerError::CreateDir(dir, source) => XlogError::DirNotWritable(dir, source),
+        FileManagerError::OpenFile(path, source) => {
+            XlogError::DirNotWritable(parent_dir(path), source)
+        }
+        FileManagerError::LockFile(..) => XlogError::InstanceExists(name_prefix.to_string()),
+        _ => XlogError::InitFailed,
+    }
+}
+
+fn buffer_error(err: BufferError) -> XlogError {
+    match err {
+        BufferError::Mmap(MmapStoreError::CreateParent(dir, source)) => {
+            XlogError::DirNotWritable(dir, source)
+        }
+        BufferError::Mmap(MmapStoreError::OpenFile(path, source)) => {
+            XlogError::DirNotWritable(parent_dir(path), source)
+        }
+        BufferError::Mmap(
+            MmapStoreError::ResizeFile(_, source)
+            | MmapStoreError::PreallocateFile(_, source)
+            | MmapStoreError::MapFile(_, source),
+        ) => source
+            .raw_os_error()
+            .map_or(XlogError::InitFailed, XlogError::Ffi),
+        _ => XlogError::InitFailed,
+    }
+}
+
+fn parent_dir(path: PathBuf) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or(path)
+}
+
 impl RustBackend {
     fn new(config: XlogConfig, level: LogLevel) -> Result<Self, XlogError> {
         if config.log_dir.is_empty() || config.name_prefix.is_empty() {
             return Err(XlogError::InvalidConfig);
         }
+        check_config(&config)?;
 
         let cipher = match config.pub_key.as_deref() {
+            Some(key) if !key.is_empty() && cfg!(feature = "no-crypt") => {
+                return Err(XlogError::CryptoDisabled);
+            }
             Some(key) if !key.is_empty() => EcdhTeaCipher::new(key).unwrap_or_else(|_| {
                 // Keep parity with C++: invalid pubkey falls back to no-crypt.
                 EcdhTeaCipher::disabled()
@@ -1077,25 +1287,30 @@ impl RustBackend {
             _ => EcdhTeaCipher::disabled(),
         };
 
-        let file_manager = FileManager::new(
+        let mut file_manager = FileManager::new(
             config.log_dir.clone().into(),
             config.cache_dir.clone().map(Into::into),
             config.name_prefix.clone(),
             config.cache_days,
         )
-        .map_err(|_| XlogError::InitFailed)?;
+        .map_err(|err| file_manager_error(err, &config.name_prefix))?;
+        if let Some(build_info) = &config.build_info {
+            file_manager = file_manager.with_file_tip(build_info.file_tip());
+        }
+        let file_events = file_manager.events().clone();
         let buffer = PersistentBuffer::open_with_capacity(
             file_manager.mmap_path(),
             DEFAULT_BUFFER_BLOCK_LEN,
         )
-        .map_err(|_| XlogError::InitFailed)?;
+        .map_err(buffer_error)?;
 
-        let engine = Arc::new(AppenderEngine::new(
+        let engine = Arc::new(AppenderEngine::new_with_flush_timeout(
             file_manager,
             buffer,
             appender_to_engine_mode(config.mode),
             0,
             10 * 24 * 60 * 60,
+            config.flush_interval.max(MIN_FLUSH_INTERVAL),
         ));
         let async_frontend =
             AsyncFrontend::new(Arc::clone(&engine), config.clone(), cipher.clone());
@@ -1104,9 +1319,16 @@ impl RustBackend {
         Ok(Self {
             id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
             console_open: AtomicBool::new(false),
-            level: AtomicI32::new(level_to_i32(level)),
+            level: AtomicU8::new(level.rank()),
+            tag_filter: TagFilter::default(),
+            level_listeners: Mutex::new(Vec::new()),
+            file_events,
+            alerts: ThresholdAlerts::new(),
+            entry_listeners: EntryListeners::new(),
+            counters: Arc::new(InstanceCounters::new(&config.name_prefix)),
+            reporter: ReporterWorker::new(),
             config,
-            cipher,
+            cipher: ArcSwap::from_pointee(cipher),
             engine,
             async_frontend,
             async_state: Mutex::new(AsyncStateSlot::empty()),
@@ -1198,14 +1420,15 @@ impl RustBackend {
             CompressMode::Zlib => CompressionKind::Zlib,
             CompressMode::Zstd => CompressionKind::Zstd,
         };
+        let cipher = self.cipher.load();
         let header = LogHeader {
-            magic: select_magic(compression_kind, AppendMode::Sync, self.cipher.enabled()),
+            magic: select_magic(compression_kind, AppendMode::Sync, cipher.enabled()),
             seq: SeqGenerator::sync_seq(),
             begin_hour: hour,
             end_hour: hour,
             len: u32::try_from(scratch.line.len()).ok()?,
-            client_pubkey: if self.cipher.enabled() {
-                self.cipher.client_pubkey()
+            client_pubkey: if cipher.enabled() {
+                cipher.client_pubkey()
             } else {
                 [0; 64]
             },
@@ -1303,7 +1526,32 @@ impl RustBackend {
         if !self.is_enabled(level) {
             return;
         }
+        self.counters.record_line(level, msg.len());
+        if let Some(entries) = multiline::rewrite(self.config.multiline, msg) {
+            for entry in &entries {
+                self.write_entry(level, tag, file, func, line, entry, raw_meta, resolve_mode);
+                self.entry_listeners.notify(level, tag, entry);
+            }
+        } else {
+            self.write_entry(level, tag, file, func, line, msg, raw_meta, resolve_mode);
+            self.entry_listeners.notify(level, tag, msg);
+        }
+        // Counted here so the global appender fires alerts too.
+        self.alerts.record(level);
+    }
 
+    #[allow(clippy::too_many_arguments)]
+    fn write_entry(
+        &self,
+        level: LogLevel,
+        tag: &str,
+        file: &str,
+        func: &str,
+        line: u32,
+        msg: &str,
+        raw_meta: RawLogMeta,
+        resolve_mode: MetaResolveMode,
+    ) {
         #[cfg(target_os = "android")]
         let trace_console_bypass = raw_meta.trace_log;
         #[cfg(not(target_os = "android"))]
@@ -1339,10 +1587,17 @@ impl RustBackend {
                     timestamp,
                     Some(&mut stage),
                 ) else {
+                    self.counters.record_drop();
                     return;
                 };
                 let engine_begin = Instant::now();
-                let _ = self.engine.write_block(block, level == LogLevel::Fatal);
+                if self
+                    .engine
+                    .write_block(block, level == LogLevel::Fatal)
+                    .is_err()
+                {
+                    self.counters.record_drop();
+                }
                 let engine_write_ns = engine_begin.elapsed().as_nanos() as u64;
                 record_sync_stage_sample(SyncStageSample {
                     total_ns: total_begin.elapsed().as_nanos() as u64,
@@ -1357,15 +1612,57 @@ impl RustBackend {
                 let Some(block) = self.build_sync_block_into(
                     scratch, level, tag, file, func, line, msg, pid, tid, maintid, timestamp, None,
                 ) else {
+                    self.counters.record_drop();
                     return;
                 };
-                let _ = self.engine.write_block(block, level == LogLevel::Fatal);
+                if self
+                    .engine
+                    .write_block(block, level == LogLevel::Fatal)
+                    .is_err()
+                {
+                    self.counters.record_drop();
+                }
             });
         }
     }
 
-    fn new_async_pending_state(&self, hour: u8, flush_epoch: u64) -> Option<AsyncPendingState> {
-        new_async_pending_state_for(&self.config, &self.cipher, hour, flush_epoch)
+    fn flush_inner(&self, sync: bool) {
+        let control_reason = take_async_flush_control_reason(sync);
+        if self.engine.mode() == EngineMode::Async {
+            if self.async_frontend.request_flush(sync, control_reason) {
+                return;
+            }
+            self.finalize_async_pending(control_reason.profiler_reason());
+        }
+        let _ = self
+            .engine
+            .flush_with_reason(sync, control_reason.engine_reason());
+    }
+
+    fn flush_bounded_inner(&self, timeout: Duration) -> FlushOutcome {
+        let control_reason = take_async_flush_control_reason(true);
+        if self.engine.mode() == EngineMode::Async {
+            if let Some(outcome) = self
+                .async_frontend
+                .request_flush_bounded(timeout, control_reason)
+            {
+                return outcome;
+            }
+            self.finalize_async_pending(control_reason.profiler_reason());
+        }
+        let _ = self
+            .engine
+            .flush_with_reason(true, control_reason.engine_reason());
+        FlushOutcome::Completed
+    }
+
+    fn new_async_pending_state(
+        &self,
+        cipher: &EcdhTeaCipher,
+        hour: u8,
+        flush_epoch: u64,
+    ) -> Option<AsyncPendingState> {
+        new_async_pending_state_for(&self.config, cipher, hour, flush_epoch)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -1497,13 +1794,18 @@ impl RustBackend {
                 }
                 stage.checkout_lock_ns = checked_out.checkout_lock_ns();
                 stage.checkout_wait_ns = checked_out.checkout_wait_ns();
+                // Load the key only once the state is ours: `set_pub_key`
+                // swaps it under the checkout, so a key change can't land
+                // between opening a pending block and appending to it.
+                let cipher = self.cipher.load();
                 discard_stale_pending_block(
                     &mut checked_out.pending,
                     engine_epoch,
                     engine_flush_reason,
                 );
                 if checked_out.pending().is_none() {
-                    let Some(new_state) = self.new_async_pending_state(now_hour, engine_epoch)
+                    let Some(new_state) =
+                        self.new_async_pending_state(&cipher, now_hour, engine_epoch)
                     else {
                         return;
                     };
@@ -1545,7 +1847,7 @@ impl RustBackend {
                     scratch.line.as_bytes(),
                     &mut scratch.compress,
                     &mut scratch.crypto,
-                    &self.cipher,
+                    &cipher,
                     &self.engine,
                     now_hour,
                     level == LogLevel::Fatal,
@@ -1586,16 +1888,33 @@ impl RustBackend {
     }
 
     fn finalize_async_pending(&self, reason: AsyncPendingFinalizeReason) {
+        let mut checked_out = self.checkout_async_state(false);
+        let needs_force_flush = self.finalize_checked_out(&mut checked_out, reason);
+        drop(checked_out);
+        if needs_force_flush {
+            let _ = self
+                .engine
+                .flush_with_reason(true, EngineAsyncFlushReason::Explicit);
+        }
+    }
+
+    /// Close the pending block of `checked_out`, if any; returns whether the
+    /// engine needs a forced flush once the state is released.
+    fn finalize_checked_out(
+        &self,
+        checked_out: &mut CheckedOutAsyncState<'_>,
+        reason: AsyncPendingFinalizeReason,
+    ) -> bool {
         let now_hour = local_hour_from_timestamp(std::time::SystemTime::now());
+        let cipher = self.cipher.load();
         with_hot_path_scratch(|scratch| {
-            let mut checked_out = self.checkout_async_state(false);
             let Some(state) = checked_out.pending_mut() else {
-                return;
+                return false;
             };
             let finalized = state.finalize(
                 &mut scratch.compress,
                 &mut scratch.crypto,
-                &self.cipher,
+                &cipher,
                 &self.engine,
                 now_hour,
                 false,
@@ -1604,14 +1923,8 @@ impl RustBackend {
                 record_pending_block_profile(state, reason);
             }
             checked_out.set_pending(None);
-            let needs_force_flush = !finalized && self.engine.mode() == EngineMode::Async;
-            drop(checked_out);
-            if needs_force_flush {
-                let _ = self
-                    .engine
-                    .flush_with_reason(true, EngineAsyncFlushReason::Explicit);
-            }
-        });
+            !finalized && self.engine.mode() == EngineMode::Async
+        })
     }
 
     fn make_logfile_name_impl(&self, timespan: i32, prefix: &str) -> Vec<String> {
@@ -1629,15 +1942,11 @@ impl XlogBackendProvider for RustBackendProvider {
         config: &XlogConfig,
         level: LogLevel,
     ) -> Result<Arc<dyn XlogBackend>, XlogError> {
-        let backend = registry().get_or_try_insert_with(&config.name_prefix, || {
-            Ok::<_, XlogError>(Arc::new(RustBackend::new(config.clone(), level)?))
-        })?;
-        if backend.config != *config {
-            return Err(XlogError::ConfigConflict {
-                name_prefix: config.name_prefix.clone(),
-            });
-        }
-        backend.set_level(level);
+        let backend = {
+            let _init = init_lock();
+            named_instance(config, level)?
+        };
+        backend.set_base_level(level);
         Ok(backend)
     }
 
@@ -1648,17 +1957,27 @@ impl XlogBackendProvider for RustBackendProvider {
     }
 
     fn appender_open(&self, config: &XlogConfig, level: LogLevel) -> Result<(), XlogError> {
-        if let Some(default) = registry().default_instance() {
-            if default.config != *config {
-                return Err(XlogError::ConfigConflict {
-                    name_prefix: default.config.name_prefix.clone(),
-                });
+        let backend = {
+            let _init = init_lock();
+            match registry().default_instance() {
+                Some(default) if default.config != *config => {
+                    return Err(XlogError::ConfigConflict {
+                        name_prefix: default.config.name_prefix.clone(),
+                    });
+                }
+                Some(default) => default,
+                None => {
+                    // Share the named instance, so `Xlog::get` and
+                    // `Xlog::init` with the same prefix reach the default
+                    // appender instead of racing it for the lock files.
+                    let backend = named_instance(config, level)?;
+                    registry().set_default(Arc::clone(&backend));
+                    backend
+                }
             }
-            default.set_level(level);
-            return Ok(());
-        }
-        let backend = Arc::new(RustBackend::new(config.clone(), level)?);
-        registry().set_default(backend);
+        };
+        // Unlocked: level listeners may call back into init.
+        backend.set_base_level(level);
         Ok(())
     }
 
@@ -1680,6 +1999,21 @@ impl XlogBackendProvider for RustBackendProvider {
         });
     }
 
+    fn live_instances(&self) -> Vec<Arc<dyn XlogBackend>> {
+        let mut instances: Vec<Arc<dyn XlogBackend>> = Vec::new();
+        let mut default_id = None;
+        if let Some(default) = registry().default_instance() {
+            default_id = Some(default.id);
+            instances.push(default);
+        }
+        registry().for_each_live(|backend| {
+            if default_id != Some(backend.id) {
+                instances.push(backend);
+            }
+        });
+        instances
+    }
+
     fn global_is_enabled(&self, level: LogLevel) -> bool {
         registry()
             .default_instance()
@@ -1700,6 +2034,9 @@ impl XlogBackendProvider for RustBackendProvider {
         let Some(default) = registry().default_instance() else {
             return;
         };
+        if !default.tag_filter.allows(level, tag) {
+            return;
+        }
         default.write_with_meta_internal(
             level,
             tag,
@@ -1727,6 +2064,20 @@ impl XlogBackendProvider for RustBackendProvider {
         set_apple_console_fun(core_fun);
     }
 
+    #[cfg(any(
+        target_os = "ios",
+        target_os = "macos",
+        target_os = "tvos",
+        target_os = "watchos"
+    ))]
+    fn set_oslog_privacy(&self, privacy: OsLogPrivacy) {
+        set_apple_oslog_privacy(match privacy {
+            OsLogPrivacy::Public => AppleOsLogPrivacy::Public,
+            OsLogPrivacy::PrivateMessage => AppleOsLogPrivacy::PrivateMessage,
+            OsLogPrivacy::Private => AppleOsLogPrivacy::Private,
+        });
+    }
+
     fn current_log_path(&self) -> Option<String> {
         registry()
             .default_instance()
@@ -1757,6 +2108,7 @@ impl XlogBackendProvider for RustBackendProvider {
         if config.log_dir.is_empty() || config.name_prefix.is_empty() {
             return Err(XlogError::InvalidConfig);
         }
+        check_config(config)?;
 
         let file_manager = FileManager::new(
             config.log_dir.clone().into(),
@@ -1764,7 +2116,7 @@ impl XlogBackendProvider for RustBackendProvider {
             config.name_prefix.clone(),
             config.cache_days,
         )
-        .map_err(|_| XlogError::InitFailed)?;
+        .map_err(|err| file_manager_error(err, &config.name_prefix))?;
 
         let max_file_size = registry()
             .get(&config.name_prefix)
@@ -1798,6 +2150,10 @@ impl XlogBackendProvider for RustBackendProvider {
     fn memory_dump(&self, buffer: &[u8]) -> String {
         memory_dump(buffer)
     }
+
+    fn is_valid_pub_key(&self, pub_key: &str) -> bool {
+        EcdhTeaCipher::new(pub_key).is_ok()
+    }
 }
 
 impl XlogBackend for RustBackend {
@@ -1806,15 +2162,91 @@ impl XlogBackend for RustBackend {
     }
 
     fn is_enabled(&self, level: LogLevel) -> bool {
-        level_to_i32(level) >= self.level.load(Ordering::Relaxed)
+        level.rank() >= self.level.load(Ordering::Relaxed)
     }
 
     fn level(&self) -> LogLevel {
-        i32_to_level(self.level.load(Ordering::Relaxed))
+        level_from_rank(self.level.load(Ordering::Relaxed))
     }
 
     fn set_level(&self, level: LogLevel) {
-        self.level.store(level_to_i32(level), Ordering::Relaxed);
+        let next = level.rank();
+        if self.level.swap(next, Ordering::Relaxed) == next {
+            return;
+        }
+        // Snapshot so listeners run without holding the registry lock.
+        let listeners: Vec<_> = self
+            .level_listeners
+            .lock()
+            .expect("level listener lock poisoned")
+            .iter()
+            .map(|(_, listener)| Arc::clone(listener))
+            .collect();
+        for listener in listeners {
+            if let Ok(mut listener) = listener.lock() {
+                listener(level);
+            }
+        }
+    }
+
+    fn tag_filter(&self) -> &TagFilter {
+        &self.tag_filter
+    }
+
+    fn add_level_listener(&self, listener: LevelListener) -> u64 {
+        let id = NEXT_LEVEL_LISTENER_ID.fetch_add(1, Ordering::Relaxed);
+        self.level_listeners
+            .lock()
+            .expect("level listener lock poisoned")
+            .push((id, Arc::new(Mutex::new(listener))));
+        id
+    }
+
+    fn remove_level_listener(&self, id: u64) -> bool {
+        let mut listeners = self
+            .level_listeners
+            .lock()
+            .expect("level listener lock poisoned");
+        let before = listeners.len();
+        listeners.retain(|(listener_id, _)| *listener_id != id);
+        listeners.len() != before
+    }
+
+    fn add_entry_listener(&self, listener: EntryListener) -> u64 {
+        self.entry_listeners.add(listener)
+    }
+
+    fn remove_entry_listener(&self, id: u64) -> bool {
+        self.entry_listeners.remove(id)
+    }
+
+    fn add_file_listener(&self, listener: FileEventListener) -> u64 {
+        self.file_events.add_listener(listener)
+    }
+
+    fn remove_file_listener(&self, id: u64) -> bool {
+        self.file_events.remove_listener(id)
+    }
+
+    fn add_threshold_alert(
+        &self,
+        level: LogLevel,
+        count: usize,
+        window: Duration,
+        callback: AlertCallback,
+    ) -> u64 {
+        self.alerts.add(level, count, window, callback)
+    }
+
+    fn remove_threshold_alert(&self, id: u64) -> bool {
+        self.alerts.remove(id)
+    }
+
+    fn appender_mode(&self) -> AppenderMode {
+        match self.engine.mode() {
+            EngineMode::Async => AppenderMode::Async,
+            EngineMode::Sync => AppenderMode::Sync,
+        }
     }
 
     fn set_appender_mode(&self, mode: AppenderMode) {
@@ -1841,16 +2273,16 @@ impl XlogBackend for RustBackend {
     }
 
     fn flush(&self, sync: bool) {
-        let control_reason = take_async_flush_control_reason(sync);
-        if self.engine.mode() == EngineMode::Async {
-            if self.async_frontend.request_flush(sync, control_reason) {
-                return;
-            }
-            self.finalize_async_pending(control_reason.profiler_reason());
-        }
-        let _ = self
-            .engine
-            .flush_with_reason(sync, control_reason.engine_reason());
+        let begin = Instant::now();
+        self.flush_inner(sync);
+        self.counters.record_flush(begin.elapsed());
+    }
+
+    fn flush_bounded(&self, timeout: Duration) -> FlushOutcome {
+        let begin = Instant::now();
+        let outcome = self.flush_bounded_inner(timeout);
+        self.counters.record_flush(begin.elapsed());
+        outcome
     }
 
     fn set_console_log_open(&self, open: bool) {
@@ -1866,6 +2298,67 @@ impl XlogBackend for RustBackend {
         self.engine.set_max_alive_time(alive_seconds);
     }
 
+    fn set_error_handler(&self, handler: Option<ErrorHandler>) {
+        let handler = handler.map(|handler| -> EngineErrorHandler {
+            Arc::new(move |err: &AppenderEngineError| {
+                handler(&XlogError::Internal(err.to_string()));
+            })
+        });
+        self.engine.set_error_handler(handler);
+    }
+
+    fn set_metrics_reporter(
+        &self,
+        interval: Duration,
+        reporter: Option<MetricsReporter>,
+    ) -> io::Result<()> {
+        self.reporter.set(&self.counters, interval, reporter)
+    }
+
+    fn migrate_cached_logs(&self) -> Result<(), XlogError> {
+        // Land buffered lines first so the moved files are complete.
+        self.flush(true);
+        self.engine
+            .move_old_cache_files()
+            .map_err(|err| XlogError::Internal(err.to_string()))
+    }
+
+    fn set_pub_key(&self, pub_key: &str) -> Result<(), XlogError> {
+        let next = if pub_key.is_empty() {
+            EcdhTeaCipher::disabled()
+        } else if cfg!(feature = "no-crypt") {
+            return Err(XlogError::CryptoDisabled);
+        } else {
+            EcdhTeaCipher::new(pub_key).map_err(|_| XlogError::InvalidKey)?
+        };
+        // Writers load the key only while they own the async state, so
+        // swapping it under the checkout guarantees no block mixes the old
+        // and new client keys, and concurrent calls apply in order.
+        let mut checked_out = self.checkout_async_state(false);
+        let needs_force_flush =
+            self.finalize_checked_out(&mut checked_out, AsyncPendingFinalizeReason::ExplicitFlush);
+        if !self.async_frontend.set_cipher(next.clone()) || needs_force_flush {
+            let _ = self
+                .engine
+                .flush_with_reason(true, EngineAsyncFlushReason::Explicit);
+        }
+        self.cipher.store(Arc::new(next));
+        drop(checked_out);
+        Ok(())
+    }
+
+    fn log_dir(&self) -> Option<String> {
+        self.engine.log_dir()
+    }
+
+    fn cache_dir(&self) -> Option<String> {
+        self.engine.cache_dir()
+    }
+
+    fn filepaths_from_timespan(&self, timespan: i32, prefix: &str) -> Vec<String> {
+        self.filepaths_from_timespan_impl(timespan, prefix)
+    }
+
     fn write_with_meta(
         &self,
         level: LogLevel,
@@ -1891,24 +2384,15 @@ impl XlogBackend for RustBackend {
 
 impl Drop for RustBackend {
     fn drop(&mut self) {
+        self.reporter.stop();
         self.async_frontend.shutdown();
+        self.file_events.close();
     }
 }
 
-fn level_to_i32(level: LogLevel) -> i32 {
-    match level {
-        LogLevel::Verbose => 0,
-        LogLevel::Debug => 1,
-        LogLevel::Info => 2,
-        LogLevel::Warn => 3,
-        LogLevel::Error => 4,
-        LogLevel::Fatal => 5,
-        LogLevel::None => 6,
-    }
-}
-
-fn i32_to_level(v: i32) -> LogLevel {
-    match v {
+/// Inverse of [`LogLevel::rank`].
+fn level_from_rank(rank: u8) -> LogLevel {
+    match rank {
         0 => LogLevel::Verbose,
         1 => LogLevel::Debug,
         2 => LogLevel::Info,
@@ -1939,9 +2423,13 @@ fn appender_to_engine_mode(mode: AppenderMode) -> EngineMode {
 }
 
 #[cfg(test)]
+// `no-crypt` compiles out the encrypted-block tests and leaves their helpers unused.
+#[cfg_attr(feature = "no-crypt", allow(dead_code, unused_imports))]
 mod tests {
     use std::fs;
     use std::path::Path;
+    use std::sync::Arc;
+    use std::thread;
 
     use mars_xlog_core::buffer::DEFAULT_BUFFER_BLOCK_LEN;
     use mars_xlog_core::compress::{decompress_raw_zlib, decompress_zstd_frames};
@@ -2107,6 +2595,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "no-crypt"))]
     fn sync_mode_with_pubkey_uses_crypt_magic_and_plain_payload() {
         let root = std::env::temp_dir().join(format!(
             "xlog-rust-backend-sync-crypt-{}",
@@ -2336,6 +2825,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "no-crypt"))]
     fn async_mode_crypt_zlib_is_decodable() {
         let root = std::env::temp_dir().join(format!(
             "xlog-rust-backend-async-crypt-zlib-{}",
@@ -2385,6 +2875,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "no-crypt"))]
     fn async_mode_crypt_zstd_is_decodable() {
         let root = std::env::temp_dir().join(format!(
             "xlog-rust-backend-async-crypt-zstd-{}",
@@ -2434,6 +2925,137 @@ mod tests {
         let _ = fs::remove_dir_all(&root);
     }
 
+    #[test]
+    #[cfg(feature = "no-crypt")]
+    fn no_crypt_builds_reject_pub_keys() {
+        let root =
+            std::env::temp_dir().join(format!("xlog-rust-backend-no-crypt-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let dir = root.to_string_lossy().to_string();
+        let cfg = XlogConfig::new(dir.clone(), "demo-no-crypt").pub_key(TEST_SERVER_PUBKEY_HEX);
+        assert!(matches!(
+            RustBackend::new(cfg, LogLevel::Info),
+            Err(crate::XlogError::CryptoDisabled)
+        ));
+
+        let backend =
+            RustBackend::new(XlogConfig::new(dir, "demo-no-crypt"), LogLevel::Info).unwrap();
+        assert!(matches!(
+            backend.set_pub_key(TEST_SERVER_PUBKEY_HEX),
+            Err(crate::XlogError::CryptoDisabled)
+        ));
+        backend.set_pub_key("").unwrap();
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-crypt"))]
+    fn set_pub_key_encrypts_blocks_written_after_the_switch() {
+        let root = std::env::temp_dir().join(format!(
+            "xlog-rust-backend-set-pub-key-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let cfg = XlogConfig::new(root.to_string_lossy().to_string(), "demo-set-pub-key");
+        let backend = RustBackend::new(cfg, LogLevel::Info).unwrap();
+        backend.write_with_meta(
+            LogLevel::Info,
+            "tag",
+            "f1.rs",
+            "f1",
+            1,
+            "before-rotation",
+            RawLogMeta::default(),
+        );
+        assert!(backend.set_pub_key("not-a-key").is_err());
+        backend.set_pub_key(TEST_SERVER_PUBKEY_HEX).unwrap();
+        backend.write_with_meta(
+            LogLevel::Info,
+            "tag",
+            "f2.rs",
+            "f2",
+            2,
+            "after-rotation",
+            RawLogMeta::default(),
+        );
+        backend.flush(true);
+
+        let xlog = fs::read_dir(&root)
+            .unwrap()
+            .flatten()
+            .map(|e| e.path())
+            .find(|p| p.extension().and_then(|x| x.to_str()) == Some("xlog"))
+            .unwrap();
+        let blocks = parse_blocks(&fs::read(&xlog).unwrap());
+        let magics: Vec<_> = blocks.iter().map(|(header, _)| header.magic).collect();
+        assert_eq!(
+            magics,
+            vec![MAGIC_ASYNC_NO_CRYPT_ZLIB_START, MAGIC_ASYNC_ZLIB_START]
+        );
+        let text = collect_decoded_text(&root);
+        assert!(text.contains("before-rotation"));
+        assert!(text.contains("after-rotation"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no-crypt"))]
+    fn set_pub_key_while_writing_keeps_every_block_decodable() {
+        let root = std::env::temp_dir().join(format!(
+            "xlog-rust-backend-rotate-while-writing-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let cfg = XlogConfig::new(root.to_string_lossy().to_string(), "demo-rotate-writing")
+            .pub_key(TEST_SERVER_PUBKEY_HEX);
+        let backend = Arc::new(RustBackend::new(cfg, LogLevel::Info).unwrap());
+        let writer = {
+            let backend = Arc::clone(&backend);
+            thread::spawn(move || {
+                for i in 0..2000 {
+                    backend.write_with_meta(
+                        LogLevel::Info,
+                        "tag",
+                        "f.rs",
+                        "f",
+                        1,
+                        &format!("rotating-line-{i:04}"),
+                        RawLogMeta::default(),
+                    );
+                }
+            })
+        };
+        // Each call generates a fresh client key, so every switch changes the
+        // key new blocks are encrypted with.
+        for round in 0..40 {
+            let key = if round % 4 == 3 {
+                ""
+            } else {
+                TEST_SERVER_PUBKEY_HEX
+            };
+            backend.set_pub_key(key).unwrap();
+            thread::yield_now();
+        }
+        writer.join().unwrap();
+        backend.flush(true);
+
+        let text = collect_decoded_text(&root);
+        for i in 0..2000 {
+            assert!(
+                text.contains(&format!("rotating-line-{i:04}")),
+                "line {i} lost"
+            );
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+
     #[test]
     fn async_to_sync_switch_keeps_pending_logs() {
         let root = std::env::temp_dir().join(format!(
@@ -2498,7 +3120,10 @@ mod tests {
                 .async_state
                 .lock()
                 .expect("async state lock poisoned");
-            let mut state = backend.new_async_pending_state(1, engine_epoch).unwrap();
+            let cipher = backend.cipher.load();
+            let mut state = backend
+                .new_async_pending_state(&cipher, 1, engine_epoch)
+                .unwrap();
             backend.engine.begin_async_pending(&state.header).unwrap();
             state.payload_len = threshold.saturating_sub(HEADER_LEN);
             guard.pending = Some(state);
@@ -0,0 +1,133 @@
+//! One-call setup of a logger and its `tracing` layer.
+//!
+//! This module is gated behind the `tracing` feature.
+use crate::{
+    env_directives, Directives, LogLevel, Xlog, XlogConfig, XlogError, XlogLayer, XlogLayerConfig,
+    XlogLayerHandle,
+};
+
+/// Builds an [`Xlog`] and an [`XlogLayer`] forwarding into it.
+///
+/// The logger and the layer share the level and [`Directives`], so the same
+/// filter applies whether code logs through `Xlog` or through `tracing`.
+///
+/// ```
+/// use mars_xlog::{LogLevel, XlogBuilder, XlogConfig};
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// # let dir = tempfile::tempdir().expect("tempdir");
+/// # let log_dir = dir.path().display().to_string();
+/// let (logger, layer, handle) = XlogBuilder::new(XlogConfig::new(log_dir, "builder-demo"))
+///     .level(LogLevel::Info)
+///     .directives("info,net=debug".parse().expect("directives"))
+///     .layer(|layer| layer.include_spans(true))
+///     .build()
+///     .expect("init xlog");
+/// let _subscriber = tracing_subscriber::registry().with(layer);
+/// handle.set_level(LogLevel::Warn);
+/// logger.flush(true);
+/// ```
+pub struct XlogBuilder {
+    config: XlogConfig,
+    level: LogLevel,
+    directives: Option<Directives>,
+    console: bool,
+    layer: Box<dyn FnOnce(XlogLayerConfig) -> XlogLayerConfig + Send>,
+}
+
+impl XlogBuilder {
+    /// Start from `config` at [`LogLevel::Info`], leaving console output as is.
+    pub fn new(config: XlogConfig) -> Self {
+        Self {
+            config,
+            level: LogLevel::Info,
+            directives: None,
+            console: false,
+            layer: Box::new(|layer| layer),
+        }
+    }
+
+    /// Set the level for the logger and the layer.
+    pub fn level(mut self, level: LogLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Filter the logger by tag and the layer by target.
+    ///
+    /// When the config has [`XlogConfig::env_directives`] on and the
+    /// variable is set, the variable wins so field overrides keep working.
+    pub fn directives(mut self, directives: Directives) -> Self {
+        self.directives = Some(directives);
+        self
+    }
+
+    /// Mirror entries to the platform console.
+    pub fn console(mut self, open: bool) -> Self {
+        self.console = open;
+        self
+    }
+
+    /// Adjust the layer options after the shared level and directives are
+    /// filled in.
+    pub fn layer(
+        mut self,
+        configure: impl FnOnce(XlogLayerConfig) -> XlogLayerConfig + Send + 'static,
+    ) -> Self {
+        self.layer = Box::new(configure);
+        self
+    }
+
+    /// Initialize the logger (reusing a live instance as [`Xlog::init`]
+    /// does) and create its layer.
+    pub fn build(self) -> Result<(Xlog, XlogLayer, XlogLayerHandle), XlogError> {
+        let directives = env_directives(&self.config)?.or(self.directives);
+        let logger = Xlog::init(self.config, self.level)?;
+        let mut layer_config = XlogLayerConfig::new(self.level);
+        if let Some(directives) = directives {
+            logger.set_directives(directives.clone());
+            layer_config = layer_config.directives(directives);
+        }
+        if self.console {
+            logger.set_console_log_open(true);
+        }
+        let (layer, handle) = XlogLayer::with_config(logger.clone(), (self.layer)(layer_config));
+        Ok((logger, layer, handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::XlogBuilder;
+    use crate::test_support::{logged_text, LogDir};
+    use crate::{AppenderMode, LogLevel};
+
+    #[test]
+    fn builder_shares_level_and_directives_with_layer() {
+        let dir = LogDir::new();
+        let cfg = dir.config("builder").mode(AppenderMode::Sync);
+        let (logger, layer, handle) = XlogBuilder::new(cfg)
+            .level(LogLevel::Warn)
+            .directives("warn,net=debug".parse().expect("parse"))
+            .layer(|layer| layer.tag("traced"))
+            .build()
+            .expect("build");
+        assert_eq!(logger.level(), LogLevel::Debug);
+        assert_eq!(handle.level(), LogLevel::Debug);
+        assert_eq!(logger.directives(), handle.directives());
+
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: "net::http", "net debug kept");
+            tracing::info!(target: "ui", "ui info dropped");
+        });
+        logger.log(LogLevel::Info, Some("ui"), "direct info dropped");
+
+        let text = logged_text(&logger);
+        assert!(text.contains("[traced]"));
+        assert!(text.contains("net debug kept"));
+        assert!(!text.contains("dropped"));
+    }
+}
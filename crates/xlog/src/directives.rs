@@ -0,0 +1,515 @@
+//! `env_logger`-style level directives shared by [`Xlog`](crate::Xlog) and
+//! the `tracing` layer.
+use std::ffi::OsString;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use thiserror::Error;
+
+use crate::LogLevel;
+
+/// Environment variable read by [`Directives::from_env`].
+pub const DIRECTIVES_ENV: &str = "XLOG_DIRECTIVES";
+
+/// Per-tag log levels parsed from a string such as `info,net=debug,ui::render=warn`.
+///
+/// Entries are separated by commas. A bare level sets the default for every
+/// tag, `name=level` sets the level for `name` and the tags nested under it
+/// (`net` covers `net` and `net::http`, not `network`), and a bare `name`
+/// enables everything for it. When several names match, the longest wins.
+/// Levels are `trace`/`verbose`, `debug`, `info`, `warn`, `error`, `fatal`
+/// and `off`/`none`, in any case.
+///
+/// For [`Xlog`](crate::Xlog) the name is matched against the entry's tag
+/// (the `name_prefix` when none is given); for
+/// [`XlogLayer`](crate::XlogLayer) it is matched against the event target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directives {
+    default: Option<LogLevel>,
+    /// Longest name first, so the first match is the most specific one.
+    targets: Vec<(String, LogLevel)>,
+}
+
+/// A [`Directives`] string that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid directive {directive:?}: {reason}")]
+pub struct DirectivesError {
+    /// The offending comma-separated entry.
+    pub directive: String,
+    /// What is wrong with it.
+    pub reason: &'static str,
+}
+
+impl Directives {
+    /// Parse a directive string; an empty string yields no directives.
+    pub fn parse(spec: &str) -> Result<Self, DirectivesError> {
+        let mut default = None;
+        let mut targets: Vec<(String, LogLevel)> = Vec::new();
+        for raw in spec.split(',') {
+            let directive = raw.trim();
+            if directive.is_empty() {
+                continue;
+            }
+            let error = |reason| DirectivesError {
+                directive: directive.to_string(),
+                reason,
+            };
+            let (name, level) = match directive.split_once('=') {
+                Some((name, level)) => {
+                    let name = name.trim();
+                    if name.is_empty() {
+                        return Err(error("missing name before `=`"));
+                    }
+                    let level = parse_level(level.trim()).ok_or_else(|| error("unknown level"))?;
+                    (Some(name), level)
+                }
+                None => match parse_level(directive) {
+                    Some(level) => (None, level),
+                    None => (Some(directive), LogLevel::Verbose),
+                },
+            };
+            match name {
+                Some(name) if name.contains(char::is_whitespace) => {
+                    return Err(error("name contains whitespace"));
+                }
+                Some(name) => match targets.iter_mut().find(|(existing, _)| existing == name) {
+                    Some(entry) => entry.1 = level,
+                    None => targets.push((name.to_string(), level)),
+                },
+                None => default = Some(level),
+            }
+        }
+        targets.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Ok(Self { default, targets })
+    }
+
+    /// Parse [`DIRECTIVES_ENV`]; `None` when it is unset or empty.
+    pub fn from_env() -> Result<Option<Self>, DirectivesError> {
+        Self::from_env_var(DIRECTIVES_ENV)
+    }
+
+    /// Parse the environment variable `name`; `None` when it is unset or empty.
+    pub fn from_env_var(name: &str) -> Result<Option<Self>, DirectivesError> {
+        Self::from_env_value(env_value(name))
+    }
+
+    /// Parse a raw environment value; `None` when it is absent or empty.
+    pub(crate) fn from_env_value(value: Option<OsString>) -> Result<Option<Self>, DirectivesError> {
+        let Some(raw) = value else {
+            return Ok(None);
+        };
+        match raw.into_string() {
+            Ok(spec) if spec.trim().is_empty() => Ok(None),
+            Ok(spec) => Self::parse(&spec).map(Some),
+            Err(raw) => Err(DirectivesError {
+                directive: raw.to_string_lossy().into_owned(),
+                reason: "not valid UTF-8",
+            }),
+        }
+    }
+
+    /// Directives that apply `level` to every tag.
+    pub fn with_default(level: LogLevel) -> Self {
+        Self {
+            default: Some(level),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Level set by a bare level entry, if any.
+    pub fn default_level(&self) -> Option<LogLevel> {
+        self.default
+    }
+
+    /// Level for `target`: the longest matching name, then the default.
+    pub fn level_for(&self, target: &str) -> Option<LogLevel> {
+        self.targets
+            .iter()
+            .find(|(name, _)| name_matches(name, target))
+            .map(|(_, level)| *level)
+            .or(self.default)
+    }
+
+    /// Whether an entry at `level` for `target` passes; targets no directive
+    /// covers use `fallback`.
+    pub fn enabled(&self, level: LogLevel, target: &str, fallback: LogLevel) -> bool {
+        level_allows(self.level_for(target).unwrap_or(fallback), level)
+    }
+
+    /// Most verbose level any target can reach, with `fallback` standing in
+    /// for the default when there is no bare level entry.
+    pub fn max_level(&self, fallback: LogLevel) -> LogLevel {
+        self.targets.iter().map(|(_, level)| *level).fold(
+            self.default.unwrap_or(fallback),
+            |most, level| {
+                if level.rank() <= most.rank() {
+                    level
+                } else {
+                    most
+                }
+            },
+        )
+    }
+
+    /// Raise every level more verbose than `min` to `min`.
+    pub(crate) fn clamp(mut self, min: LogLevel) -> Self {
+        let clamp = |level: &mut LogLevel| {
+            if level.rank() < min.rank() {
+                *level = min;
+            }
+        };
+        if let Some(default) = self.default.as_mut() {
+            clamp(default);
+        }
+        for (_, level) in &mut self.targets {
+            clamp(level);
+        }
+        self
+    }
+
+    /// Returns `true` when there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.targets.is_empty()
+    }
+}
+
+impl FromStr for Directives {
+    type Err = DirectivesError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        Self::parse(spec)
+    }
+}
+
+impl fmt::Display for Directives {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        if let Some(level) = self.default {
+            f.write_str(level_name(level))?;
+            first = false;
+        }
+        // Shortest first reads like the usual hand-written order.
+        for (name, level) in self.targets.iter().rev() {
+            if !first {
+                f.write_str(",")?;
+            }
+            write!(f, "{name}={}", level_name(*level))?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// Directives installed on a backend or layer, readable on every write.
+#[derive(Default)]
+pub(crate) struct TagFilter {
+    active: AtomicBool,
+    state: RwLock<Option<Arc<ActiveDirectives>>>,
+}
+
+#[derive(Clone)]
+struct ActiveDirectives {
+    directives: Directives,
+    /// Level the instance had before the directives, used for tags they do
+    /// not cover and restored by [`TagFilter::clear`].
+    base: LogLevel,
+}
+
+impl TagFilter {
+    /// Install `directives` on an instance currently at `level` and return
+    /// the level the instance should switch to.
+    ///
+    /// `level` is kept as the fallback unless directives are already
+    /// installed, in which case their fallback carries over.
+    pub(crate) fn set(&self, directives: Directives, level: LogLevel) -> LogLevel {
+        if directives.is_empty() {
+            return self.clear().unwrap_or(level);
+        }
+        let mut slot = self.state.write().expect("tag filter lock poisoned");
+        let base = slot.as_ref().map_or(level, |active| active.base);
+        let level = directives.max_level(base);
+        *slot = Some(Arc::new(ActiveDirectives { directives, base }));
+        self.active.store(true, Ordering::Release);
+        level
+    }
+
+    /// Remove the directives and return the level saved when they were
+    /// installed, `None` when there were none.
+    pub(crate) fn clear(&self) -> Option<LogLevel> {
+        let mut slot = self.state.write().expect("tag filter lock poisoned");
+        self.active.store(false, Ordering::Release);
+        slot.take().map(|active| active.base)
+    }
+
+    /// Use `level` for uncovered tags and as the level to restore, after an
+    /// explicit level change while directives are installed.
+    pub(crate) fn set_base(&self, level: LogLevel) {
+        if !self.active.load(Ordering::Acquire) {
+            return;
+        }
+        let mut slot = self.state.write().expect("tag filter lock poisoned");
+        if let Some(active) = slot.as_mut() {
+            Arc::make_mut(active).base = level;
+        }
+    }
+
+    pub(crate) fn get(&self) -> Option<Directives> {
+        self.state
+            .read()
+            .expect("tag filter lock poisoned")
+            .as_ref()
+            .map(|active| active.directives.clone())
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// Whether `level` passes for `tag`; free when no directives are set.
+    pub(crate) fn allows(&self, level: LogLevel, tag: &str) -> bool {
+        if !self.active.load(Ordering::Acquire) {
+            return true;
+        }
+        match &*self.state.read().expect("tag filter lock poisoned") {
+            Some(active) => active.directives.enabled(level, tag, active.base),
+            None => true,
+        }
+    }
+}
+
+fn name_matches(name: &str, target: &str) -> bool {
+    match target.strip_prefix(name) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+fn level_allows(min: LogLevel, level: LogLevel) -> bool {
+    min != LogLevel::None && level != LogLevel::None && level.rank() >= min.rank()
+}
+
+fn parse_level(text: &str) -> Option<LogLevel> {
+    const NAMES: [(&str, LogLevel); 9] = [
+        ("trace", LogLevel::Verbose),
+        ("verbose", LogLevel::Verbose),
+        ("debug", LogLevel::Debug),
+        ("info", LogLevel::Info),
+        ("warn", LogLevel::Warn),
+        ("error", LogLevel::Error),
+        ("fatal", LogLevel::Fatal),
+        ("off", LogLevel::None),
+        ("none", LogLevel::None),
+    ];
+    NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(text))
+        .map(|(_, level)| *level)
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Verbose => "verbose",
+        LogLevel::Debug => "debug",
+        LogLevel::Info => "info",
+        LogLevel::Warn => "warn",
+        LogLevel::Error => "error",
+        LogLevel::Fatal => "fatal",
+        LogLevel::None => "off",
+    }
+}
+
+#[cfg(not(test))]
+fn env_value(name: &str) -> Option<OsString> {
+    std::env::var_os(name)
+}
+
+/// Tests read directives from a per-thread value instead of the process
+/// environment, which parallel tests must not mutate.
+#[cfg(test)]
+fn env_value(name: &str) -> Option<OsString> {
+    test_env::get(name)
+}
+
+#[cfg(test)]
+pub(crate) mod test_env {
+    use std::cell::RefCell;
+    use std::ffi::OsString;
+
+    thread_local! {
+        static VARS: RefCell<Vec<(String, OsString)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Make `name` read as `value` (unset for `None`) on this thread.
+    pub(crate) fn set(name: &str, value: Option<&str>) {
+        VARS.with(|vars| {
+            let mut vars = vars.borrow_mut();
+            vars.retain(|(existing, _)| existing != name);
+            if let Some(value) = value {
+                vars.push((name.to_string(), value.into()));
+            }
+        });
+    }
+
+    pub(super) fn get(name: &str) -> Option<OsString> {
+        VARS.with(|vars| {
+            vars.borrow()
+                .iter()
+                .find(|(existing, _)| existing == name)
+                .map(|(_, value)| value.clone())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_env, Directives, DIRECTIVES_ENV};
+    use crate::test_support::{logged_text, LogDir};
+    use crate::{AppenderMode, ConfigIssue, LogLevel, Xlog, XlogError};
+
+    #[test]
+    fn directives_filter_entries_by_tag() {
+        let directives: Directives = "info, net=debug ,ui::render=warn,NET::quiet=off,audit"
+            .parse()
+            .expect("parse");
+        assert_eq!(directives.default_level(), Some(LogLevel::Info));
+        assert_eq!(directives.level_for("net"), Some(LogLevel::Debug));
+        assert_eq!(directives.level_for("net::http"), Some(LogLevel::Debug));
+        assert_eq!(directives.level_for("network"), Some(LogLevel::Info));
+        assert_eq!(
+            directives.level_for("ui::render::tile"),
+            Some(LogLevel::Warn)
+        );
+        assert_eq!(directives.level_for("NET::quiet"), Some(LogLevel::None));
+        assert_eq!(directives.level_for("audit"), Some(LogLevel::Verbose));
+        assert_eq!(directives.max_level(LogLevel::Error), LogLevel::Verbose);
+        let no_default: Directives = "net=debug".parse().expect("parse");
+        assert_eq!(no_default.max_level(LogLevel::Warn), LogLevel::Debug);
+        assert_eq!(no_default.max_level(LogLevel::Verbose), LogLevel::Verbose);
+        assert!(!no_default.enabled(LogLevel::Info, "ui", LogLevel::Warn));
+        assert!(no_default.enabled(LogLevel::Debug, "net", LogLevel::Warn));
+        assert_eq!(
+            directives.to_string(),
+            "info,net=debug,audit=verbose,ui::render=warn,NET::quiet=off"
+        );
+        assert_eq!(directives.to_string().parse::<Directives>(), Ok(directives));
+        assert!("".parse::<Directives>().expect("empty").is_empty());
+        assert_eq!(
+            Directives::parse("info,net=loud")
+                .expect_err("bad level")
+                .directive,
+            "net=loud"
+        );
+        assert!(Directives::parse("=debug").is_err());
+
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("directives", LogLevel::Error);
+        logger.set_directives("warn,net=debug".parse().expect("parse"));
+        assert_eq!(logger.level(), LogLevel::Debug);
+        assert!(logger.directives().is_some());
+
+        logger.log(LogLevel::Debug, Some("net::http"), "net debug kept");
+        logger.log(LogLevel::Info, Some("ui"), "ui info dropped");
+        logger.log(LogLevel::Info, None, "untagged info dropped");
+        logger.log(LogLevel::Warn, Some("ui"), "ui warn kept");
+        logger.clear_directives();
+        assert!(logger.directives().is_none());
+        assert_eq!(logger.level(), LogLevel::Error);
+        logger.log(LogLevel::Warn, Some("net"), "net warn after clear dropped");
+        logger.log(LogLevel::Error, Some("ui"), "ui error kept");
+
+        let text = logged_text(&logger);
+        assert!(text.contains("net debug kept"));
+        assert!(text.contains("ui warn kept"));
+        assert!(text.contains("ui error kept"));
+        assert!(!text.contains("dropped"));
+    }
+
+    #[test]
+    fn directives_without_default_keep_instance_level() {
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("directives-no-default", LogLevel::Warn);
+        logger.set_directives("net=debug".parse().expect("parse"));
+        assert_eq!(logger.level(), LogLevel::Debug);
+
+        logger.log(LogLevel::Debug, Some("net"), "net debug kept");
+        logger.log(LogLevel::Info, Some("ui"), "ui info dropped");
+        logger.log(LogLevel::Debug, None, "untagged debug dropped");
+        logger.log(LogLevel::Warn, Some("ui"), "ui warn kept");
+
+        // An explicit level while directives are set is what clearing restores.
+        logger.set_level(LogLevel::Info);
+        logger.set_directives("net=debug,db=verbose".parse().expect("parse"));
+        assert_eq!(logger.level(), LogLevel::Verbose);
+        logger.log(LogLevel::Debug, Some("ui"), "ui debug dropped");
+        logger.log(LogLevel::Info, Some("ui"), "ui info kept");
+        logger.clear_directives();
+        assert_eq!(logger.level(), LogLevel::Info);
+        logger.log(
+            LogLevel::Debug,
+            Some("net"),
+            "net debug after clear dropped",
+        );
+        logger.log(LogLevel::Info, Some("net"), "net info after clear kept");
+
+        let text = logged_text(&logger);
+        assert!(text.contains("net debug kept"));
+        assert!(text.contains("ui warn kept"));
+        assert!(text.contains("ui info kept"));
+        assert!(text.contains("net info after clear kept"));
+        assert!(!text.contains("dropped"));
+    }
+
+    #[test]
+    fn reinit_level_is_the_base_directives_restore() {
+        let dir = LogDir::new();
+        let cfg = dir.config("directives-reinit").mode(AppenderMode::Sync);
+        let logger = Xlog::init(cfg.clone(), LogLevel::Error).expect("init");
+        logger.set_directives("net=debug".parse().expect("parse"));
+
+        let again = Xlog::init(cfg, LogLevel::Info).expect("reinit");
+        assert_eq!(again.level(), LogLevel::Info);
+        again.log(LogLevel::Info, Some("ui"), "ui info kept");
+        again.clear_directives();
+        assert_eq!(logger.level(), LogLevel::Info);
+
+        assert!(logged_text(&logger).contains("ui info kept"));
+    }
+
+    #[test]
+    fn env_directives_apply_at_init_and_on_reload() {
+        let dir = LogDir::new();
+        test_env::set(DIRECTIVES_ENV, Some("info,net=loud"));
+        let cfg = dir.config("env-directives").env_directives(true);
+        assert!(matches!(
+            cfg.validate().unwrap_err()[..],
+            [ConfigIssue::InvalidDirectives(_)]
+        ));
+        assert!(matches!(
+            Xlog::init(cfg.clone(), LogLevel::Info),
+            Err(XlogError::InvalidDirectives(_))
+        ));
+
+        test_env::set(DIRECTIVES_ENV, Some("warn,net=debug"));
+        let logger = Xlog::init(cfg, LogLevel::Error).expect("init");
+        assert_eq!(logger.level(), LogLevel::Debug);
+        assert_eq!(logger.directives(), "warn,net=debug".parse().ok());
+
+        test_env::set(DIRECTIVES_ENV, Some("error"));
+        assert_eq!(logger.reload_env_directives(), Ok(true));
+        assert_eq!(logger.level(), LogLevel::Error);
+        test_env::set(DIRECTIVES_ENV, Some("  "));
+        assert_eq!(logger.reload_env_directives(), Ok(false));
+        test_env::set(DIRECTIVES_ENV, None);
+        assert_eq!(logger.reload_env_directives(), Ok(false));
+        assert_eq!(
+            logger.directives(),
+            Some(Directives::with_default(LogLevel::Error))
+        );
+
+        let plain = Xlog::init(dir.config("no-env-directives"), LogLevel::Info).expect("init");
+        assert!(plain.directives().is_none());
+    }
+}
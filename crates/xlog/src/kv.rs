@@ -0,0 +1,207 @@
+use std::fmt::{self, Write as _};
+
+use crate::{LogLevel, Xlog};
+
+/// Typed value for [`Xlog::write_kv_values`].
+///
+/// Values are rendered JSON-style inside the `{k=v}` block: numbers and
+/// bools stay bare, strings and `Display` values are quoted and escaped, so
+/// exporters can recover the original types.
+#[derive(Clone)]
+pub enum KvValue<'a> {
+    /// Quoted string.
+    Str(&'a str),
+    /// Signed integer.
+    I64(i64),
+    /// Unsigned integer.
+    U64(u64),
+    /// Float; non-finite values are written as `null`.
+    F64(f64),
+    /// Boolean.
+    Bool(bool),
+    /// Any `Display` value, quoted like a string.
+    Display(&'a dyn fmt::Display),
+    /// Pre-rendered JSON from [`KvValue::serialize`].
+    #[cfg(feature = "serde")]
+    Json(String),
+}
+
+impl<'a> KvValue<'a> {
+    /// Wrap a `Display` value; it is quoted like a string.
+    pub fn display(value: &'a dyn fmt::Display) -> Self {
+        Self::Display(value)
+    }
+
+    /// Render `value` as compact JSON.
+    ///
+    /// Serialization errors are recorded as a quoted error string instead of
+    /// failing the log call.
+    #[cfg(feature = "serde")]
+    pub fn serialize<T: serde::Serialize + ?Sized>(value: &T) -> KvValue<'static> {
+        match serde_json::to_string(value) {
+            Ok(json) => KvValue::Json(json),
+            Err(err) => {
+                let mut quoted = String::new();
+                write_quoted(&mut quoted, &format!("<serialize error: {err}>"));
+                KvValue::Json(quoted)
+            }
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        match self {
+            Self::Str(value) => write_quoted(out, value),
+            Self::I64(value) => {
+                let _ = write!(out, "{value}");
+            }
+            Self::U64(value) => {
+                let _ = write!(out, "{value}");
+            }
+            Self::F64(value) if value.is_finite() => {
+                let _ = write!(out, "{value}");
+            }
+            Self::F64(_) => out.push_str("null"),
+            Self::Bool(value) => {
+                let _ = write!(out, "{value}");
+            }
+            Self::Display(value) => write_quoted(out, &value.to_string()),
+            #[cfg(feature = "serde")]
+            Self::Json(json) => out.push_str(json),
+        }
+    }
+}
+
+impl fmt::Debug for KvValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rendered = String::new();
+        self.render(&mut rendered);
+        f.write_str(&rendered)
+    }
+}
+
+impl<'a> From<&'a str> for KvValue<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<'a> From<&'a String> for KvValue<'a> {
+    fn from(value: &'a String) -> Self {
+        Self::Str(value)
+    }
+}
+
+macro_rules! impl_from_number {
+    ($variant:ident, $target:ty: $($source:ty),+) => {
+        $(
+            impl From<$source> for KvValue<'_> {
+                fn from(value: $source) -> Self {
+                    Self::$variant(value as $target)
+                }
+            }
+        )+
+    };
+}
+
+impl_from_number!(I64, i64: i8, i16, i32, i64, isize);
+impl_from_number!(U64, u64: u8, u16, u32, u64, usize);
+impl_from_number!(F64, f64: f32, f64);
+
+impl From<bool> for KvValue<'_> {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl Xlog {
+    /// Log a message with typed key-value fields appended as `msg {k=v, ..}`.
+    ///
+    /// Same layout as [`Xlog::write_kv`], but values keep their type: see
+    /// [`KvValue`] for how each kind is rendered.
+    pub fn write_kv_values(
+        &self,
+        level: LogLevel,
+        tag: Option<&str>,
+        msg: &str,
+        fields: &[(&str, KvValue<'_>)],
+    ) {
+        if !self.is_enabled(level) {
+            return;
+        }
+        let msg = format_kv_values(msg, fields);
+        self.write_with_meta(level, tag, "", "", 0, &msg);
+    }
+}
+
+fn format_kv_values(msg: &str, fields: &[(&str, KvValue<'_>)]) -> String {
+    let mut output = String::with_capacity(msg.len() + fields.len() * 16);
+    output.push_str(msg);
+    if !fields.is_empty() {
+        if !output.is_empty() {
+            output.push(' ');
+        }
+        output.push('{');
+        for (idx, (name, value)) in fields.iter().enumerate() {
+            if idx > 0 {
+                output.push_str(", ");
+            }
+            output.push_str(name);
+            output.push('=');
+            value.render(&mut output);
+        }
+        output.push('}');
+    }
+    output
+}
+
+fn write_quoted(out: &mut String, value: &str) {
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_kv_values, KvValue};
+
+    #[test]
+    fn values_keep_their_types() {
+        let addr = std::net::Ipv4Addr::LOCALHOST;
+        let name = String::from("bob \"b\"\n");
+        let rendered = format_kv_values(
+            "login",
+            &[
+                ("user", KvValue::from(&name)),
+                ("id", 42u32.into()),
+                ("delta", (-3i64).into()),
+                ("ratio", 0.5f64.into()),
+                ("nan", f64::NAN.into()),
+                ("ok", true.into()),
+                ("addr", KvValue::display(&addr)),
+            ],
+        );
+        assert_eq!(
+            rendered,
+            r#"login {user="bob \"b\"\n", id=42, delta=-3, ratio=0.5, nan=null, ok=true, addr="127.0.0.1"}"#
+        );
+        assert_eq!(format_kv_values("", &[("k", "v".into())]), r#"{k="v"}"#);
+
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            format_kv_values("", &[("list", KvValue::serialize(&[1, 2]))]),
+            "{list=[1,2]}"
+        );
+    }
+}
@@ -0,0 +1,436 @@
+//! Runtime level control driven by remote config or push messages.
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tracing")]
+use crate::XlogLayerHandle;
+use crate::{Directives, LogLevel, Xlog};
+
+/// Configuration for [`LevelController`].
+#[derive(Debug, Clone)]
+pub struct LevelControllerConfig {
+    /// Level applied at start and restored when an override expires.
+    pub default_level: LogLevel,
+    /// Most verbose level an update may request; more verbose requests are clamped.
+    pub min_level: LogLevel,
+    /// Longest time a level more verbose than `default_level` stays active.
+    pub max_elevated_duration: Duration,
+    /// How often a polling source is consulted and expiry is checked.
+    pub poll_interval: Duration,
+}
+
+impl LevelControllerConfig {
+    /// Create a config that restores `Info`, allows `Verbose` for at most
+    /// four hours, and polls every minute.
+    pub fn new() -> Self {
+        Self {
+            default_level: LogLevel::Info,
+            min_level: LogLevel::Verbose,
+            max_elevated_duration: Duration::from_secs(4 * 60 * 60),
+            poll_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Set the baseline level.
+    pub fn default_level(mut self, level: LogLevel) -> Self {
+        self.default_level = level;
+        self
+    }
+
+    /// Set the most verbose level an update may request.
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    /// Cap how long an elevated (more verbose than default) level stays active.
+    pub fn max_elevated_duration(mut self, duration: Duration) -> Self {
+        self.max_elevated_duration = duration;
+        self
+    }
+
+    /// Set the polling and expiry-check interval.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+impl Default for LevelControllerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A level change requested by a remote source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelUpdate {
+    /// Requested level.
+    pub level: LogLevel,
+    /// Per-tag levels applied on top of `level`, see [`Xlog::set_directives`].
+    pub directives: Option<Directives>,
+    /// How long the level should stay active; `None` uses the config maximum
+    /// for elevated levels and keeps other levels until the next update.
+    pub ttl: Option<Duration>,
+}
+
+impl LevelUpdate {
+    /// Request `level` with no explicit expiry.
+    pub fn new(level: LogLevel) -> Self {
+        Self {
+            level,
+            directives: None,
+            ttl: None,
+        }
+    }
+
+    /// Also apply `directives`, e.g. to raise a single module while the rest
+    /// stays at `level`. Their levels are clamped like `level`, and the
+    /// update counts as elevated when any tag becomes more verbose than the
+    /// default.
+    pub fn directives(mut self, directives: Directives) -> Self {
+        self.directives = Some(directives);
+        self
+    }
+
+    /// Revert to the default level after `ttl`.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+}
+
+/// Applies level updates and directives from a remote source to registered
+/// loggers and layers.
+///
+/// Requested levels are clamped to [`LevelControllerConfig::min_level`], and
+/// any level more verbose than the default reverts automatically once its
+/// ttl or [`LevelControllerConfig::max_elevated_duration`] runs out, so a
+/// forgotten verbose switch cannot fill the disk. Registered loggers are kept
+/// alive by the controller.
+pub struct LevelController {
+    state: Arc<ControllerState>,
+    worker: Mutex<Option<(Sender<()>, JoinHandle<()>)>>,
+}
+
+struct ControllerState {
+    config: LevelControllerConfig,
+    targets: Mutex<Targets>,
+}
+
+#[derive(Default)]
+struct Targets {
+    loggers: Vec<Xlog>,
+    #[cfg(feature = "tracing")]
+    layers: Vec<XlogLayerHandle>,
+    level: Option<LogLevel>,
+    directives: Option<Directives>,
+    expires_at: Option<Instant>,
+}
+
+impl LevelController {
+    /// Create a controller with no targets and no polling source.
+    pub fn new(config: LevelControllerConfig) -> Self {
+        Self {
+            state: Arc::new(ControllerState {
+                config,
+                targets: Mutex::new(Targets::default()),
+            }),
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// Register a logger; it immediately receives the current level and
+    /// directives.
+    pub fn add_logger(&self, logger: Xlog) {
+        let mut targets = self.state.lock_targets();
+        logger.set_level(targets.level.unwrap_or(self.state.config.default_level));
+        if let Some(directives) = &targets.directives {
+            logger.set_directives(directives.clone());
+        }
+        targets.loggers.push(logger);
+    }
+
+    /// Register a tracing layer; it immediately receives the current level
+    /// and directives.
+    #[cfg(feature = "tracing")]
+    pub fn add_layer(&self, layer: XlogLayerHandle) {
+        let mut targets = self.state.lock_targets();
+        layer.set_level(targets.level.unwrap_or(self.state.config.default_level));
+        if let Some(directives) = &targets.directives {
+            layer.set_directives(directives.clone());
+        }
+        targets.layers.push(layer);
+    }
+
+    /// Apply an update now, e.g. from a push message. Returns the level
+    /// actually applied after clamping.
+    pub fn apply(&self, update: LevelUpdate) -> LogLevel {
+        self.state.apply(update, Instant::now())
+    }
+
+    /// Restore the default level if the active override has expired.
+    ///
+    /// The polling worker calls this on every tick; call it directly when
+    /// driving the controller without [`LevelController::spawn_polling`].
+    pub fn check_expiry(&self) {
+        self.state.check_expiry(Instant::now());
+    }
+
+    /// Level currently applied to the targets.
+    pub fn level(&self) -> LogLevel {
+        self.state
+            .lock_targets()
+            .level
+            .unwrap_or(self.state.config.default_level)
+    }
+
+    /// Poll `source` every [`LevelControllerConfig::poll_interval`] on a
+    /// background thread, applying the updates it returns.
+    ///
+    /// Replaces any previous polling source. The thread stops when the
+    /// controller is dropped or [`LevelController::stop_polling`] is called.
+    /// Fails when the thread cannot be spawned, leaving no source polled.
+    pub fn spawn_polling(
+        &self,
+        mut source: impl FnMut() -> Option<LevelUpdate> + Send + 'static,
+    ) -> io::Result<()> {
+        self.stop_polling();
+        let state = Arc::clone(&self.state);
+        let interval = state.config.poll_interval;
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("xlog-level-ctl".into())
+            .spawn(move || loop {
+                if let Some(update) = source() {
+                    state.apply(update, Instant::now());
+                }
+                state.check_expiry(Instant::now());
+                match stop_rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+            })?;
+        *self.worker.lock().expect("level worker lock poisoned") = Some((stop_tx, handle));
+        Ok(())
+    }
+
+    /// Stop the polling thread started by [`LevelController::spawn_polling`].
+    pub fn stop_polling(&self) {
+        let worker = self
+            .worker
+            .lock()
+            .expect("level worker lock poisoned")
+            .take();
+        if let Some((stop_tx, handle)) = worker {
+            let _ = stop_tx.send(());
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for LevelController {
+    fn drop(&mut self) {
+        self.stop_polling();
+    }
+}
+
+impl ControllerState {
+    fn lock_targets(&self) -> std::sync::MutexGuard<'_, Targets> {
+        self.targets.lock().expect("level targets lock poisoned")
+    }
+
+    fn apply(&self, update: LevelUpdate, now: Instant) -> LogLevel {
+        let config = &self.config;
+        let level = if update.level.rank() < config.min_level.rank() {
+            config.min_level
+        } else {
+            update.level
+        };
+        let directives = update
+            .directives
+            .filter(|directives| !directives.is_empty())
+            .map(|directives| directives.clamp(config.min_level));
+        let most_verbose = directives
+            .as_ref()
+            .map_or(level, |directives| directives.max_level(level));
+        let elevated = most_verbose.rank() < config.default_level.rank();
+        let ttl = if elevated {
+            Some(
+                update
+                    .ttl
+                    .unwrap_or(config.max_elevated_duration)
+                    .min(config.max_elevated_duration),
+            )
+        } else {
+            update.ttl
+        };
+
+        let mut targets = self.lock_targets();
+        targets.level = Some(level);
+        targets.expires_at = ttl.map(|ttl| now + ttl);
+        targets.set_all(level, directives);
+        level
+    }
+
+    fn check_expiry(&self, now: Instant) {
+        let mut targets = self.lock_targets();
+        if targets.expires_at.is_some_and(|deadline| now >= deadline) {
+            targets.level = None;
+            targets.expires_at = None;
+            targets.set_all(self.config.default_level, None);
+        }
+    }
+}
+
+impl Targets {
+    /// Apply `level` and `directives` to every target. Directives a target
+    /// got elsewhere are left alone unless the controller replaces them.
+    fn set_all(&mut self, level: LogLevel, directives: Option<Directives>) {
+        let clear = self.directives.is_some() && directives.is_none();
+        for logger in &self.loggers {
+            if clear {
+                logger.clear_directives();
+            }
+            logger.set_level(level);
+            if let Some(directives) = &directives {
+                logger.set_directives(directives.clone());
+            }
+        }
+        #[cfg(feature = "tracing")]
+        for layer in &self.layers {
+            if clear {
+                layer.clear_directives();
+            }
+            layer.set_level(level);
+            if let Some(directives) = &directives {
+                layer.set_directives(directives.clone());
+            }
+        }
+        self.directives = directives;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tempfile::TempDir;
+
+    use super::{LevelController, LevelControllerConfig, LevelUpdate};
+    use crate::{LogLevel, Xlog, XlogConfig};
+
+    #[test]
+    fn updates_are_clamped_and_elevated_levels_expire() {
+        let dir = TempDir::new().expect("tempdir");
+        let prefix = format!("level-ctl-{}", std::process::id());
+        let logger = Xlog::init(
+            XlogConfig::new(dir.path().display().to_string(), prefix),
+            LogLevel::Warn,
+        )
+        .expect("init");
+        let controller = LevelController::new(
+            LevelControllerConfig::new()
+                .min_level(LogLevel::Debug)
+                .max_elevated_duration(Duration::from_secs(60)),
+        );
+        controller.add_logger(logger.clone());
+        assert_eq!(logger.level(), LogLevel::Info);
+
+        assert_eq!(
+            controller.apply(LevelUpdate::new(LogLevel::Verbose).ttl(Duration::from_secs(3600))),
+            LogLevel::Debug
+        );
+        assert_eq!(logger.level(), LogLevel::Debug);
+
+        let state = &controller.state;
+        state.check_expiry(Instant::now() + Duration::from_secs(30));
+        assert_eq!(controller.level(), LogLevel::Debug);
+        state.check_expiry(Instant::now() + Duration::from_secs(61));
+        assert_eq!(logger.level(), LogLevel::Info);
+
+        controller.apply(LevelUpdate::new(LogLevel::Error));
+        state.check_expiry(Instant::now() + Duration::from_secs(24 * 60 * 60));
+        assert_eq!(logger.level(), LogLevel::Error);
+    }
+
+    #[test]
+    fn directives_reach_every_target_and_expire_with_the_level() {
+        let dir = TempDir::new().expect("tempdir");
+        let prefix = format!("level-ctl-directives-{}", std::process::id());
+        let logger = Xlog::init(
+            XlogConfig::new(dir.path().display().to_string(), &prefix),
+            LogLevel::Info,
+        )
+        .expect("init");
+        let controller = LevelController::new(
+            LevelControllerConfig::new()
+                .min_level(LogLevel::Debug)
+                .max_elevated_duration(Duration::from_secs(60)),
+        );
+        controller.add_logger(logger.clone());
+        #[cfg(feature = "tracing")]
+        let (_layer, handle) = crate::XlogLayer::new(logger.clone());
+        #[cfg(feature = "tracing")]
+        controller.add_layer(handle.clone());
+
+        // Not elevated by its level, but by the directives.
+        let update =
+            LevelUpdate::new(LogLevel::Warn).directives("net=verbose".parse().expect("parse"));
+        assert_eq!(controller.apply(update), LogLevel::Warn);
+        let clamped = "net=debug".parse().ok();
+        assert_eq!(logger.directives(), clamped);
+        assert_eq!(logger.level(), LogLevel::Debug);
+        #[cfg(feature = "tracing")]
+        {
+            assert_eq!(handle.directives(), clamped);
+            assert_eq!(handle.level(), LogLevel::Debug);
+        }
+
+        let late = Xlog::get(&prefix).expect("lookup");
+        controller.add_logger(late.clone());
+        assert_eq!(late.directives(), clamped);
+
+        controller
+            .state
+            .check_expiry(Instant::now() + Duration::from_secs(61));
+        assert!(logger.directives().is_none());
+        assert_eq!(logger.level(), LogLevel::Info);
+        #[cfg(feature = "tracing")]
+        {
+            assert!(handle.directives().is_none());
+            assert_eq!(handle.level(), LogLevel::Info);
+        }
+    }
+
+    #[test]
+    fn polling_source_applies_updates() {
+        let dir = TempDir::new().expect("tempdir");
+        let prefix = format!("level-poll-{}", std::process::id());
+        let logger = Xlog::init(
+            XlogConfig::new(dir.path().display().to_string(), prefix),
+            LogLevel::Info,
+        )
+        .expect("init");
+        let controller = LevelController::new(
+            LevelControllerConfig::new().poll_interval(Duration::from_millis(5)),
+        );
+        controller.add_logger(logger.clone());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        controller
+            .spawn_polling(move || rx.try_recv().ok())
+            .expect("spawn polling");
+        tx.send(LevelUpdate::new(LogLevel::Warn)).expect("send");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while logger.level() != LogLevel::Warn && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        controller.stop_polling();
+        assert_eq!(logger.level(), LogLevel::Warn);
+    }
+}
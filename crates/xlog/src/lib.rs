@@ -9,25 +9,89 @@
 //! ```
 //! use mars_xlog::{LogLevel, Xlog, XlogConfig};
 //!
-//! let cfg = XlogConfig::new("/tmp/xlog", "demo");
+//! # let dir = tempfile::tempdir().expect("tempdir");
+//! # let log_dir = dir.path().display().to_string();
+//! let cfg = XlogConfig::new(log_dir, "demo");
 //! let logger = Xlog::init(cfg, LogLevel::Info).expect("init xlog");
 //! logger.log(LogLevel::Info, None, "hello from rust");
 //! logger.flush(true);
 //! ```
 //!
 //! # Feature flags
-//! - `macros`: `xlog!` and level helpers that capture file/module/line.
-//! - `tracing`: `XlogLayer` for `tracing-subscriber`.
-//! - `metrics`: emits structured runtime metrics via the `metrics` crate.
+//! - `macros`: `xlog!` and level helpers that capture file/module/line, plus
+//!   the `#[xlog_instrument]` attribute.
+//! - `tracing`: `XlogLayer` for `tracing-subscriber`, and `XlogBuilder` to
+//!   set up a logger and its layer in one call.
+//! - `metrics`: emits structured runtime metrics via the `metrics` crate,
+//!   including per-instance `xlog.instance.*` throughput, drop and flush
+//!   counters labelled with `instance` (the `name_prefix`), so services can
+//!   expose them through their existing recorder (e.g. a Prometheus
+//!   `/metrics` endpoint).
+//! - `serde`: [`KvValue::serialize`] for logging any `Serialize` value as JSON.
+//! - `testing`: the [`testing`] module and `assert_logged!`, for checking
+//!   what code under test logged.
+//! - `watch`: `Xlog::watch_log_dir`, a filesystem watch on the log
+//!   directory that also sees files other processes touch.
 use libc::c_int;
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+// Lets `#[xlog_instrument]` expansions resolve `::mars_xlog` in this crate's tests.
+#[cfg(test)]
+extern crate self as mars_xlog;
+
+#[cfg(feature = "tokio")]
+mod async_api;
 mod backend;
 #[cfg(feature = "tracing")]
+mod builder;
+mod directives;
+mod kv;
+pub mod level_control;
+mod lifecycle;
+mod non_blocking;
+mod stream;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod timed;
+#[cfg(feature = "tracing")]
 mod tracing_layer;
+mod validate;
+#[cfg(feature = "watch")]
+mod watch;
 
+#[cfg(feature = "tokio")]
+pub use async_api::UploadReport;
+#[cfg(feature = "tracing")]
+pub use builder::XlogBuilder;
+pub use directives::{Directives, DirectivesError, DIRECTIVES_ENV};
+pub use kv::KvValue;
+pub use level_control::{LevelController, LevelControllerConfig, LevelUpdate};
+pub use lifecycle::LifecycleHooks;
+#[cfg(feature = "rust-backend")]
+pub use mars_xlog_core::appender_engine::MIN_LOG_ALIVE_SECONDS;
+#[cfg(feature = "rust-backend")]
+pub use mars_xlog_core::decoder::{DecodeError, Decoder, LogFileHeader};
+pub use mars_xlog_core::file_events::FileEvent as LogFileEvent;
+#[cfg(all(feature = "rust-backend", feature = "tokio"))]
+pub use mars_xlog_core::follow::FollowStream;
+#[cfg(feature = "rust-backend")]
+pub use mars_xlog_core::follow::{FollowedEntry, LogFollower};
+#[cfg(feature = "macros")]
+pub use mars_xlog_macros::xlog_instrument;
+pub use non_blocking::{NonBlocking, NonBlockingConfig, WorkerGuard, DEFAULT_BUFFERED_LINES_LIMIT};
+pub use stream::{LogStream, STREAM_CHUNK_BYTES};
+pub use timed::TimedGuard;
 #[cfg(feature = "tracing")]
 pub use tracing_layer::{XlogLayer, XlogLayerConfig, XlogLayerHandle};
+pub use validate::{sanitize_name_prefix, ConfigIssue, InvalidValue};
+#[cfg(feature = "watch")]
+pub use watch::LogDirWatch;
 
 /// Log severity levels supported by Mars Xlog.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -48,6 +112,21 @@ pub enum LogLevel {
     None,
 }
 
+impl LogLevel {
+    /// Severity order, from `Verbose` (0) to `None` (6).
+    pub(crate) fn rank(self) -> u8 {
+        match self {
+            LogLevel::Verbose => 0,
+            LogLevel::Debug => 1,
+            LogLevel::Info => 2,
+            LogLevel::Warn => 3,
+            LogLevel::Error => 4,
+            LogLevel::Fatal => 5,
+            LogLevel::None => 6,
+        }
+    }
+}
+
 /// Controls whether logs are appended asynchronously or synchronously.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AppenderMode {
@@ -66,6 +145,64 @@ pub enum CompressMode {
     Zstd,
 }
 
+/// How messages containing line breaks are written.
+///
+/// A single trailing line break is not treated as multi-line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum MultilineMode {
+    /// Write the message unchanged; one entry may span several decoded lines.
+    #[default]
+    Keep,
+    /// Replace line breaks with the two characters `\n` (`\r` for a lone CR).
+    Escape,
+    /// Write one entry per line, each prefixed with `[ml <id> <n>/<total>] `
+    /// so the lines can be regrouped.
+    Split,
+}
+
+/// Logging health counters for one [`Xlog::set_metrics_reporter`] interval.
+///
+/// Every counter covers only the interval, so reports can be summed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XlogMetricsSnapshot {
+    /// Time covered by this snapshot.
+    pub interval: Duration,
+    /// Records accepted per level, indexed `Verbose` through `Fatal`; see
+    /// [`XlogMetricsSnapshot::lines`].
+    pub lines_by_level: [u64; 6],
+    /// Message bytes accepted, before formatting and compression.
+    pub bytes: u64,
+    /// Sync-mode records that could not be encoded or written.
+    pub dropped: u64,
+    /// Calls to [`Xlog::flush`] and [`Xlog::flush_bounded`].
+    pub flushes: u64,
+    /// Time spent inside those flush calls.
+    pub flush_time_total: Duration,
+    /// Longest single flush call.
+    pub flush_time_max: Duration,
+}
+
+impl XlogMetricsSnapshot {
+    /// Records accepted at `level`.
+    pub fn lines(&self, level: LogLevel) -> u64 {
+        backend::level_slot(level).map_or(0, |slot| self.lines_by_level[slot])
+    }
+
+    /// Records accepted at any level.
+    pub fn total_lines(&self) -> u64 {
+        self.lines_by_level.iter().sum()
+    }
+}
+
+/// How a [`Xlog::flush_bounded`] call finished.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FlushOutcome {
+    /// The sync flush finished before the deadline.
+    Completed,
+    /// The deadline passed first; the flush continues in the background.
+    Pending,
+}
+
 /// Result code returned by `Xlog::oneshot_flush`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FileIoAction {
@@ -150,6 +287,7 @@ impl RawLogMeta {
 
 /// Errors returned by Xlog initialization helpers.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum XlogError {
     #[error("log_dir and name_prefix must be non-empty")]
     /// Required config fields such as `log_dir` or `name_prefix` were empty.
@@ -160,9 +298,63 @@ pub enum XlogError {
         /// Name prefix of the already-initialized logger instance.
         name_prefix: String,
     },
+    #[error("name_prefix `{name_prefix}` {reason}")]
+    /// `name_prefix` cannot be used as a file name stem; see
+    /// [`sanitize_name_prefix`].
+    InvalidNamePrefix {
+        /// Rejected prefix.
+        name_prefix: String,
+        /// What is wrong with it.
+        reason: &'static str,
+    },
+    #[error("name_prefix `{name_prefix}` collides with live instance `{existing}` in the same directory")]
+    /// Another live instance writes to the same directory under a prefix
+    /// that differs only in letter case, so on case-insensitive file
+    /// systems both would share files and locks.
+    NamePrefixCollision {
+        /// Requested prefix.
+        name_prefix: String,
+        /// Prefix of the live instance it collides with.
+        existing: String,
+    },
     #[error("xlog initialization failed")]
-    /// Backend initialization failed.
+    /// Backend initialization failed for a reason not covered by a more
+    /// specific variant.
     InitFailed,
+    #[error("path contains a NUL byte: {0:?}")]
+    /// `log_dir` or `cache_dir` contains an interior NUL byte, which no
+    /// platform file API accepts.
+    NulInPath(String),
+    #[error("directory {} is not writable: {1}", .0.display())]
+    /// A log, cache, or mmap directory could not be created, or a file in it
+    /// could not be opened for writing.
+    DirNotWritable(PathBuf, #[source] std::io::Error),
+    #[error("logger `{0}` is already running in another process")]
+    /// Another process (or an instance outside this registry) holds the
+    /// `<name_prefix>.lock` file for this prefix and directory.
+    InstanceExists(String),
+    #[error("platform call failed with OS error {0}")]
+    /// A native call such as mapping the mmap buffer failed with this OS
+    /// error code (`errno` / `GetLastError`).
+    Ffi(i32),
+    #[error(transparent)]
+    /// [`DIRECTIVES_ENV`] was set to a string [`Directives::parse`] rejects.
+    InvalidDirectives(#[from] DirectivesError),
+    #[error(transparent)]
+    /// A config value such as `compress_level` is outside the range the
+    /// logger accepts.
+    InvalidValue(#[from] InvalidValue),
+    #[error("invalid encryption public key")]
+    /// The public key was not a valid uncompressed secp256k1 point in hex.
+    InvalidKey,
+    #[error("encryption is disabled in this build (no-crypt)")]
+    /// A public key was supplied to a build with the `no-crypt` feature.
+    CryptoDisabled,
+    #[error("internal logging error: {0}")]
+    /// A write, flush, or file maintenance step failed in the background.
+    ///
+    /// Only reported through [`Xlog::set_error_handler`]; logging keeps going.
+    Internal(String),
 }
 
 /// Configuration used to create an Xlog instance or open the global appender.
@@ -191,6 +383,39 @@ pub struct XlogConfig {
     pub compress_mode: CompressMode,
     /// Compression level forwarded to the compressor.
     pub compress_level: i32,
+    /// How long async-mode data may sit in the buffer before the worker
+    /// flushes it to the log file.
+    pub flush_interval: Duration,
+    /// Producing build, written at the start of every log file.
+    pub build_info: Option<BuildInfo>,
+    /// Handling of messages that contain line breaks.
+    pub multiline: MultilineMode,
+    /// Apply [`DIRECTIVES_ENV`] at init.
+    pub env_directives: bool,
+}
+
+/// Identifies the app build that produced a log file.
+///
+/// Written as a plaintext line at the start of each file so a decoded file
+/// names its build even without the upload metadata that came with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// App version, e.g. `8.0.1`.
+    pub version: String,
+    /// Source revision the build was made from.
+    pub commit: String,
+    /// Distribution channel, e.g. `appstore` or `beta`.
+    pub channel: String,
+}
+
+impl BuildInfo {
+    /// The line written at the start of each log file.
+    pub fn file_tip(&self) -> String {
+        format!(
+            "build: version={} commit={} channel={}",
+            self.version, self.commit, self.channel
+        )
+    }
 }
 
 impl XlogConfig {
@@ -205,15 +430,39 @@ impl XlogConfig {
             mode: AppenderMode::Async,
             compress_mode: CompressMode::Zlib,
             compress_level: 6,
+            flush_interval: Duration::from_secs(15 * 60),
+            build_info: None,
+            multiline: MultilineMode::Keep,
+            env_directives: false,
         }
     }
 
     /// Set the public key used to encrypt logs.
+    ///
+    /// With the `no-crypt` feature, init fails with [`XlogError::CryptoDisabled`]
+    /// when a non-empty key is set.
     pub fn pub_key(mut self, key: impl Into<String>) -> Self {
         self.pub_key = Some(key.into());
         self
     }
 
+    /// Set the public key from its raw 64-byte uncompressed point (x || y).
+    ///
+    /// Equivalent to [`XlogConfig::pub_key`] with the hex encoding of `key`,
+    /// except that the point is checked here: init silently disables
+    /// encryption for a bad hex key, while this returns
+    /// [`XlogError::InvalidKey`] for a point that is not on the curve.
+    pub fn pub_key_bytes(self, key: [u8; 64]) -> Result<Self, XlogError> {
+        let mut hex = String::with_capacity(128);
+        for byte in key {
+            let _ = write!(hex, "{byte:02x}");
+        }
+        if !backend::provider().is_valid_pub_key(&hex) {
+            return Err(XlogError::InvalidKey);
+        }
+        Ok(self.pub_key(hex))
+    }
+
     /// Set the optional cache directory for mmap buffers and temp files.
     pub fn cache_dir(mut self, dir: impl Into<String>) -> Self {
         self.cache_dir = Some(dir.into());
@@ -239,16 +488,66 @@ impl XlogConfig {
     }
 
     /// Set the compression level forwarded to the compressor.
+    ///
+    /// Checked against [`CompressMode::level_range`] of the final
+    /// [`compress_mode`](Self::compress_mode) by [`XlogConfig::validate`] and
+    /// at init, which returns [`XlogError::InvalidValue`] when it is out of
+    /// range, so the two setters may be called in either order.
     pub fn compress_level(mut self, level: i32) -> Self {
         self.compress_level = level;
         self
     }
+
+    /// Set the async-mode flush interval; defaults to 15 minutes like Mars.
+    ///
+    /// Shorter intervals make lines reach the file sooner at the cost of more
+    /// disk wakeups. Values under one second are treated as one second.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Record the producing build in every log file this instance creates.
+    pub fn build_info(
+        mut self,
+        version: impl Into<String>,
+        commit: impl Into<String>,
+        channel: impl Into<String>,
+    ) -> Self {
+        self.build_info = Some(BuildInfo {
+            version: version.into(),
+            commit: commit.into(),
+            channel: channel.into(),
+        });
+        self
+    }
+
+    /// Set how multi-line messages are written; defaults to
+    /// [`MultilineMode::Keep`].
+    pub fn multiline(mut self, mode: MultilineMode) -> Self {
+        self.multiline = mode;
+        self
+    }
+
+    /// Read per-tag directives from [`DIRECTIVES_ENV`] at init, so verbosity
+    /// can be raised on a deployed build without a release.
+    ///
+    /// When the variable is set it replaces the level passed to init (see
+    /// [`Xlog::set_directives`]); a malformed value fails init with
+    /// [`XlogError::InvalidDirectives`]. Use [`Xlog::reload_env_directives`]
+    /// to pick up later changes.
+    pub fn env_directives(mut self, enabled: bool) -> Self {
+        self.env_directives = enabled;
+        self
+    }
 }
 
 /// Handle to a Mars Xlog instance.
 ///
 /// Cloning the handle is cheap; the underlying instance is reference-counted
-/// and released when the last handle is dropped.
+/// and released when the last handle is dropped. Releasing it writes every
+/// line still buffered in async mode to the log file before its workers
+/// stop, so dropping the last handle needs no explicit flush.
 #[derive(Clone)]
 pub struct Xlog {
     inner: Arc<Inner>,
@@ -273,16 +572,25 @@ impl Xlog {
 
     #[doc(hidden)]
     pub fn new(config: XlogConfig, level: LogLevel) -> Result<Self, XlogError> {
+        let directives = env_directives(&config)?;
         let backend = backend::provider().new_instance(&config, level)?;
-        Ok(Self {
+        let logger = Self {
             inner: Arc::new(Inner {
                 backend,
                 name_prefix: config.name_prefix,
             }),
-        })
+        };
+        if let Some(directives) = directives {
+            logger.set_directives(directives);
+        }
+        Ok(logger)
     }
 
     /// Look up an existing instance by name prefix.
+    ///
+    /// The returned handle shares ownership of the instance with every other
+    /// handle for `name_prefix`; the instance is released only after the last
+    /// handle is dropped, so dropping a looked-up handle never affects others.
     pub fn get(name_prefix: &str) -> Option<Self> {
         let backend = backend::provider().get_instance(name_prefix)?;
         Some(Self {
@@ -293,13 +601,29 @@ impl Xlog {
         })
     }
 
+    /// Pin the instance for the rest of the process and return a `'static` handle.
+    ///
+    /// The instance is never released, whatever happens to other handles. Use
+    /// this when handles live in foreign-language objects whose destruction
+    /// order is not under Rust's control; [`Xlog::get`] keeps working as usual.
+    pub fn into_leaked(self) -> &'static Self {
+        Box::leak(Box::new(self))
+    }
+
     #[doc(hidden)]
     /// Open the global/default appender.
     ///
     /// If already open with a different config, returns
     /// [`XlogError::ConfigConflict`].
     pub fn appender_open(config: XlogConfig, level: LogLevel) -> Result<(), XlogError> {
-        backend::provider().appender_open(&config, level)
+        let directives = env_directives(&config)?;
+        backend::provider().appender_open(&config, level)?;
+        if let Some(directives) = directives {
+            if let Some(logger) = Self::get(&config.name_prefix) {
+                logger.set_directives(directives);
+            }
+        }
+        Ok(())
     }
 
     #[doc(hidden)]
@@ -323,6 +647,21 @@ impl Xlog {
         backend::provider().set_console_fun(fun);
     }
 
+    /// Choose which parts of `os_log` console lines stay unredacted.
+    ///
+    /// Applies process-wide when the console sink is [`ConsoleFun::OSLog`].
+    /// Defaults to [`OsLogPrivacy::Public`]; pick a private variant for
+    /// release builds that echo user data to the console.
+    #[cfg(any(
+        target_os = "ios",
+        target_os = "macos",
+        target_os = "tvos",
+        target_os = "watchos"
+    ))]
+    pub fn set_oslog_privacy(privacy: OsLogPrivacy) {
+        backend::provider().set_oslog_privacy(privacy);
+    }
+
     /// Returns the raw instance handle used by the underlying C++ library.
     pub fn instance(&self) -> usize {
         self.inner.backend.instance()
@@ -339,10 +678,186 @@ impl Xlog {
     }
 
     /// Set the minimum log level for this instance.
+    ///
+    /// While directives are set this is also the level for tags they do
+    /// not cover and the level [`Xlog::clear_directives`] restores.
     pub fn set_level(&self, level: LogLevel) {
+        self.inner.backend.set_base_level(level);
+    }
+
+    /// Filter this instance per tag with `directives`.
+    ///
+    /// Tags the directives do not cover keep the level the instance had
+    /// before. The instance level becomes [`Directives::max_level`] of that
+    /// level so every tag the directives enable gets through, and each entry
+    /// is then checked against the level for its tag (the `name_prefix`
+    /// when no tag is given). A later [`Xlog::set_level`] still caps every
+    /// tag. Shared by every handle to the same instance.
+    pub fn set_directives(&self, directives: Directives) {
+        let level = self
+            .inner
+            .backend
+            .tag_filter()
+            .set(directives, self.inner.backend.level());
         self.inner.backend.set_level(level);
     }
 
+    /// Remove the directives set by [`Xlog::set_directives`] and restore the
+    /// level the instance had before them.
+    pub fn clear_directives(&self) {
+        if let Some(level) = self.inner.backend.tag_filter().clear() {
+            self.inner.backend.set_level(level);
+        }
+    }
+
+    /// The directives set by [`Xlog::set_directives`], if any.
+    pub fn directives(&self) -> Option<Directives> {
+        self.inner.backend.tag_filter().get()
+    }
+
+    /// Re-read [`DIRECTIVES_ENV`] and apply it, e.g. after a debug menu or
+    /// remote command changed it.
+    ///
+    /// Returns `Ok(false)` and leaves the current filter alone when the
+    /// variable is unset or empty.
+    pub fn reload_env_directives(&self) -> Result<bool, DirectivesError> {
+        match Directives::from_env()? {
+            Some(directives) => {
+                self.set_directives(directives);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Register a callback run whenever this instance's level changes.
+    ///
+    /// The callback is shared by every handle to the same instance and runs
+    /// on the thread that called [`Xlog::set_level`], so it must not set the
+    /// level of this instance itself. Returns an id for
+    /// [`Xlog::remove_level_listener`].
+    pub fn on_level_changed(&self, listener: impl FnMut(LogLevel) + Send + 'static) -> u64 {
+        self.inner.backend.add_level_listener(Box::new(listener))
+    }
+
+    /// Remove a callback registered with [`Xlog::on_level_changed`].
+    pub fn remove_level_listener(&self, id: u64) -> bool {
+        self.inner.backend.remove_level_listener(id)
+    }
+
+    /// Call `listener` with the level, tag and message of every entry this
+    /// instance writes.
+    ///
+    /// The listener is shared by every handle to the same instance and sees
+    /// entries from all of them, from the global appender and from the
+    /// `tracing` layer, after the level and tag filters. It runs on the
+    /// logging thread, once per entry when multiline messages are split.
+    /// Entries it logs to the same instance itself are not passed back to
+    /// it. Returns an id for [`Xlog::remove_entry_listener`].
+    pub fn on_entry_written(
+        &self,
+        listener: impl FnMut(LogLevel, &str, &str) + Send + 'static,
+    ) -> u64 {
+        self.inner.backend.add_entry_listener(Box::new(listener))
+    }
+
+    /// Remove a listener registered with [`Xlog::on_entry_written`].
+    pub fn remove_entry_listener(&self, id: u64) -> bool {
+        self.inner.backend.remove_entry_listener(id)
+    }
+
+    /// Call `callback` when this instance creates, rotates, or removes a log
+    /// file, so uploaders and viewers can react without polling the
+    /// directory.
+    ///
+    /// Events come from this instance's own writer, not from a filesystem
+    /// watch: they cover files it writes in its log and cache directories,
+    /// including expired files it deletes, but not files other processes
+    /// or instances touch there; the `watch` feature's
+    /// `Xlog::watch_log_dir` covers those. Callbacks run in order on a
+    /// background thread and may log. Returns an id for
+    /// [`Xlog::remove_file_event_listener`].
+    pub fn on_file_event(&self, callback: impl FnMut(&LogFileEvent) + Send + 'static) -> u64 {
+        self.inner.backend.add_file_listener(Box::new(callback))
+    }
+
+    /// Remove a callback registered with [`Xlog::on_file_event`].
+    pub fn remove_file_event_listener(&self, id: u64) -> bool {
+        self.inner.backend.remove_file_listener(id)
+    }
+
+    /// Call `callback` whenever `count` records at `level` or above are
+    /// written within `window`.
+    ///
+    /// Meant for reacting to error spikes, e.g. uploading logs right away.
+    /// The callback runs on the logging thread that crossed the threshold,
+    /// so it should hand off real work. After firing, the rule needs another
+    /// `count` records before it fires again. Returns an id for
+    /// [`Xlog::remove_threshold_alert`].
+    pub fn on_threshold(
+        &self,
+        level: LogLevel,
+        count: usize,
+        window: Duration,
+        callback: impl FnMut() + Send + 'static,
+    ) -> u64 {
+        self.inner
+            .backend
+            .add_threshold_alert(level, count, window, Box::new(callback))
+    }
+
+    /// Remove an alert registered with [`Xlog::on_threshold`].
+    pub fn remove_threshold_alert(&self, id: u64) -> bool {
+        self.inner.backend.remove_threshold_alert(id)
+    }
+
+    /// Call `reporter` every `interval` with this instance's counters for
+    /// that interval.
+    ///
+    /// Runs on a dedicated thread and replaces any previous reporter;
+    /// intervals under 100 ms are treated as 100 ms. The reporter gets a
+    /// final snapshot when it is replaced, cleared, or the instance closes.
+    /// Fails when the thread cannot be spawned, leaving no reporter running.
+    pub fn set_metrics_reporter(
+        &self,
+        interval: Duration,
+        reporter: impl FnMut(XlogMetricsSnapshot) + Send + 'static,
+    ) -> io::Result<()> {
+        self.inner
+            .backend
+            .set_metrics_reporter(interval, Some(Box::new(reporter)))
+    }
+
+    /// Stop the reporter installed by [`Xlog::set_metrics_reporter`].
+    pub fn clear_metrics_reporter(&self) {
+        // Clearing spawns nothing, so it cannot fail.
+        let _ = self
+            .inner
+            .backend
+            .set_metrics_reporter(Duration::ZERO, None);
+    }
+
+    /// Install a handler for internal failures such as a full disk.
+    ///
+    /// Logging never fails loudly, so this is the only way to notice that
+    /// writes are being dropped. The handler may run on a background thread
+    /// and replaces any previous one for this instance.
+    pub fn set_error_handler(&self, handler: impl Fn(&XlogError) + Send + Sync + 'static) {
+        self.inner
+            .backend
+            .set_error_handler(Some(Arc::new(handler)));
+    }
+
+    /// Remove the handler installed with [`Xlog::set_error_handler`].
+    pub fn clear_error_handler(&self) {
+        self.inner.backend.set_error_handler(None);
+    }
+
+    /// Current appender mode of this instance.
+    pub fn appender_mode(&self) -> AppenderMode {
+        self.inner.backend.appender_mode()
+    }
+
     /// Switch between async and sync appender modes.
     pub fn set_appender_mode(&self, mode: AppenderMode) {
         self.inner.backend.set_appender_mode(mode);
@@ -353,19 +868,81 @@ impl Xlog {
         self.inner.backend.flush(sync);
     }
 
+    /// Sync flush that gives up waiting after `timeout`.
+    ///
+    /// Use this from handlers with a watchdog, such as Android `onStop`: the
+    /// flush is never cancelled, but once `timeout` passes it carries on in
+    /// the background and [`FlushOutcome::Pending`] is returned.
+    pub fn flush_bounded(&self, timeout: Duration) -> FlushOutcome {
+        self.inner.backend.flush_bounded(timeout)
+    }
+
     /// Enable or disable console logging for this instance (platform dependent).
     pub fn set_console_log_open(&self, open: bool) {
         self.inner.backend.set_console_log_open(open);
     }
 
     /// Set the max log file size in bytes for this instance (0 disables splitting).
-    pub fn set_max_file_size(&self, max_bytes: i64) {
+    ///
+    /// Negative sizes are rejected.
+    pub fn set_max_file_size(&self, max_bytes: i64) -> Result<(), InvalidValue> {
+        InvalidValue::check("max_file_size", max_bytes, 0..=i64::MAX)?;
         self.inner.backend.set_max_file_size(max_bytes);
+        Ok(())
     }
 
     /// Set the max log file age in seconds for this instance before deletion/rotation.
-    pub fn set_max_alive_time(&self, alive_seconds: i64) {
+    ///
+    /// Ages under [`MIN_LOG_ALIVE_SECONDS`] (one day) are rejected; Mars
+    /// would silently keep the previous value.
+    pub fn set_max_alive_time(&self, alive_seconds: i64) -> Result<(), InvalidValue> {
+        InvalidValue::check(
+            "max_alive_time",
+            alive_seconds,
+            MIN_LOG_ALIVE_SECONDS..=i64::MAX,
+        )?;
         self.inner.backend.set_max_alive_time(alive_seconds);
+        Ok(())
+    }
+
+    /// Move cached log files older than `cache_days` into `log_dir` now.
+    ///
+    /// The backend does this on its own every few minutes; call it before
+    /// collecting `log_dir` for upload so nothing eligible is left behind in
+    /// the cache. Buffered lines are flushed first. A no-op without a
+    /// `cache_dir`.
+    pub fn migrate_cached_logs(&self) -> Result<(), XlogError> {
+        self.inner.backend.migrate_cached_logs()
+    }
+
+    /// Replace the encryption public key at runtime.
+    ///
+    /// Blocks already in the buffer keep the key they were written with; new
+    /// lines use `pub_key`. An empty key turns encryption off. Unlike init,
+    /// an invalid key is rejected instead of silently disabling encryption.
+    /// `no-crypt` builds reject every non-empty key.
+    pub fn set_pub_key(&self, pub_key: &str) -> Result<(), XlogError> {
+        self.inner.backend.set_pub_key(pub_key)
+    }
+
+    /// Directory this instance writes finished log files into.
+    pub fn log_dir(&self) -> Option<String> {
+        self.inner.backend.log_dir()
+    }
+
+    /// Cache directory configured for this instance, if any.
+    pub fn cache_dir(&self) -> Option<String> {
+        self.inner.backend.cache_dir()
+    }
+
+    /// List this instance's log files covering `timespan` days back from today.
+    ///
+    /// Unlike [`Xlog::filepaths_from_timespan`], this does not depend on the
+    /// global appender and always uses this instance's `name_prefix`.
+    pub fn log_files_for_timespan(&self, timespan: i32) -> Vec<String> {
+        self.inner
+            .backend
+            .filepaths_from_timespan(timespan, &self.inner.name_prefix)
     }
 
     /// Log a message with caller file/line captured via `#[track_caller]`.
@@ -381,6 +958,23 @@ impl Xlog {
         self.write_with_meta(level, tag, loc.file(), "", loc.line(), msg.as_ref());
     }
 
+    /// Like [`Xlog::log`], but only builds the message when `level` is enabled.
+    ///
+    /// Use this for expensive messages when the macros are not available.
+    #[track_caller]
+    pub fn log_lazy<S: AsRef<str>>(
+        &self,
+        level: LogLevel,
+        tag: Option<&str>,
+        msg: impl FnOnce() -> S,
+    ) {
+        if !self.is_enabled(level) {
+            return;
+        }
+        let loc = std::panic::Location::caller();
+        self.write_with_meta(level, tag, loc.file(), "", loc.line(), msg().as_ref());
+    }
+
     /// Compatibility wrapper for older APIs. Prefer `log` or the macros.
     #[track_caller]
     pub fn write(&self, level: LogLevel, tag: Option<&str>, msg: &str) {
@@ -390,6 +984,30 @@ impl Xlog {
         self.write_with_meta(level, tag, "", "", 0, msg);
     }
 
+    /// Log a message with key-value fields appended as `msg {k=v, k2=v2}`.
+    ///
+    /// The field layout matches the `tracing` layer output, so structured
+    /// entries look the same regardless of which API produced them.
+    pub fn write_kv(&self, level: LogLevel, tag: Option<&str>, msg: &str, fields: &[(&str, &str)]) {
+        if !self.is_enabled(level) {
+            return;
+        }
+        let msg = format_kv(msg, fields);
+        self.write_with_meta(level, tag, "", "", 0, &msg);
+    }
+
+    /// Log a binary payload rendered as a bounded hex + ASCII dump.
+    ///
+    /// The payload is encoded with the same format as [`Xlog::memory_dump`],
+    /// so every binding produces identical output for the same bytes.
+    pub fn write_bytes(&self, level: LogLevel, tag: Option<&str>, data: &[u8]) {
+        if !self.is_enabled(level) {
+            return;
+        }
+        let msg = Self::memory_dump(data);
+        self.write_with_meta(level, tag, "", "", 0, &msg);
+    }
+
     /// Log with explicit metadata (file, function, line).
     ///
     /// Use this when callers already provide metadata (for example from JNI).
@@ -419,18 +1037,40 @@ impl Xlog {
         line: u32,
         msg: &str,
         raw_meta: RawLogMeta,
+    ) {
+        let tag = tag.unwrap_or(&self.inner.name_prefix);
+        if !self.is_enabled(level) || !self.inner.backend.tag_filter().allows(level, tag) {
+            return;
+        }
+        self.inner
+            .backend
+            .write_with_meta(level, tag, file, func, line, msg, raw_meta);
+    }
+
+    /// Write an entry the caller already filtered by its own directives,
+    /// skipping this instance's tag filter: the `tracing` layer matches
+    /// event targets, which differ from the tag when it is overridden.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn write_prefiltered(
+        &self,
+        level: LogLevel,
+        tag: &str,
+        file: &str,
+        func: &str,
+        line: u32,
+        msg: &str,
     ) {
         if !self.is_enabled(level) {
             return;
         }
         self.inner.backend.write_with_meta(
             level,
-            tag.unwrap_or(&self.inner.name_prefix),
+            tag,
             file,
             func,
             line,
             msg,
-            raw_meta,
+            RawLogMeta::default(),
         );
     }
 
@@ -514,10 +1154,35 @@ pub enum ConsoleFun {
     OSLog = 2,
 }
 
+/// Which parts of an `os_log` console line Console.app shows unredacted.
+///
+/// The tag is used as the `os_log` category and is always public.
+#[cfg(any(
+    target_os = "ios",
+    target_os = "macos",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OsLogPrivacy {
+    /// File, function and message are public.
+    Public,
+    /// File and function are public; the message shows as `<private>`.
+    PrivateMessage,
+    /// File, function and message all show as `<private>`.
+    Private,
+}
+
 /// Log with explicit metadata captured by the macro call site.
+///
+/// Separate the level from the format string with `;` instead of passing a
+/// tag to use [`xlog_module_tag!`] as the tag.
 #[cfg(feature = "macros")]
 #[macro_export]
 macro_rules! xlog {
+    ($logger:expr, $level:expr; $($arg:tt)+) => {{
+        $crate::xlog!($logger, $level, $crate::xlog_module_tag!(), $($arg)+)
+    }};
     ($logger:expr, $level:expr, $tag:expr, $($arg:tt)+) => {{
         let logger_ref = $logger;
         let level = $level;
@@ -528,10 +1193,26 @@ macro_rules! xlog {
     }};
 }
 
+/// Tag derived from the caller's `module_path!()` at compile time.
+///
+/// Keeps the last two path segments, so `app::net::http` becomes `net::http`
+/// and a crate root stays as the crate name.
+#[cfg(feature = "macros")]
+#[macro_export]
+macro_rules! xlog_module_tag {
+    () => {{
+        const TAG: &str = $crate::__module_tag(module_path!());
+        TAG
+    }};
+}
+
 /// Convenience macro for `LogLevel::Debug`.
 #[cfg(feature = "macros")]
 #[macro_export]
 macro_rules! xlog_debug {
+    ($logger:expr; $($arg:tt)+) => {{
+        $crate::xlog!($logger, $crate::LogLevel::Debug; $($arg)+)
+    }};
     ($logger:expr, $tag:expr, $($arg:tt)+) => {{
         $crate::xlog!($logger, $crate::LogLevel::Debug, $tag, $($arg)+)
     }};
@@ -541,6 +1222,9 @@ macro_rules! xlog_debug {
 #[cfg(feature = "macros")]
 #[macro_export]
 macro_rules! xlog_info {
+    ($logger:expr; $($arg:tt)+) => {{
+        $crate::xlog!($logger, $crate::LogLevel::Info; $($arg)+)
+    }};
     ($logger:expr, $tag:expr, $($arg:tt)+) => {{
         $crate::xlog!($logger, $crate::LogLevel::Info, $tag, $($arg)+)
     }};
@@ -550,6 +1234,9 @@ macro_rules! xlog_info {
 #[cfg(feature = "macros")]
 #[macro_export]
 macro_rules! xlog_warn {
+    ($logger:expr; $($arg:tt)+) => {{
+        $crate::xlog!($logger, $crate::LogLevel::Warn; $($arg)+)
+    }};
     ($logger:expr, $tag:expr, $($arg:tt)+) => {{
         $crate::xlog!($logger, $crate::LogLevel::Warn, $tag, $($arg)+)
     }};
@@ -559,39 +1246,82 @@ macro_rules! xlog_warn {
 #[cfg(feature = "macros")]
 #[macro_export]
 macro_rules! xlog_error {
+    ($logger:expr; $($arg:tt)+) => {{
+        $crate::xlog!($logger, $crate::LogLevel::Error; $($arg)+)
+    }};
     ($logger:expr, $tag:expr, $($arg:tt)+) => {{
         $crate::xlog!($logger, $crate::LogLevel::Error, $tag, $($arg)+)
     }};
 }
 
-#[cfg(test)]
-mod tests {
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::{Mutex, OnceLock};
-
-    use tempfile::TempDir;
-
-    use super::{CompressMode, LogLevel, Xlog, XlogConfig, XlogError};
-
-    static NEXT_PREFIX_ID: AtomicUsize = AtomicUsize::new(1);
-    static APPENDER_TEST_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
-
-    fn unique_prefix(label: &str) -> String {
-        let id = NEXT_PREFIX_ID.fetch_add(1, Ordering::Relaxed);
-        format!("{label}-{}-{id}", std::process::id())
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub const fn __module_tag(path: &'static str) -> &'static str {
+    let bytes = path.as_bytes();
+    let mut separators = 0;
+    let mut idx = bytes.len();
+    while idx >= 2 {
+        if bytes[idx - 1] == b':' && bytes[idx - 2] == b':' {
+            separators += 1;
+            if separators == 2 {
+                let (_, tail) = bytes.split_at(idx);
+                return match std::str::from_utf8(tail) {
+                    Ok(tail) => tail,
+                    Err(_) => path,
+                };
+            }
+            idx -= 2;
+        } else {
+            idx -= 1;
+        }
     }
+    path
+}
 
-    fn appender_test_lock() -> &'static Mutex<()> {
-        APPENDER_TEST_LOCK.get_or_init(|| Mutex::new(()))
+fn env_directives(config: &XlogConfig) -> Result<Option<Directives>, DirectivesError> {
+    if config.env_directives {
+        Directives::from_env()
+    } else {
+        Ok(None)
     }
+}
 
-    struct AppenderCloseGuard;
-
-    impl Drop for AppenderCloseGuard {
-        fn drop(&mut self) {
-            Xlog::appender_close();
+fn format_kv(msg: &str, fields: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(msg.len() + fields.len() * 16);
+    output.push_str(msg);
+    if !fields.is_empty() {
+        if !output.is_empty() {
+            output.push(' ');
+        }
+        output.push('{');
+        for (idx, (name, value)) in fields.iter().enumerate() {
+            if idx > 0 {
+                output.push_str(", ");
+            }
+            output.push_str(name);
+            output.push('=');
+            output.push_str(value);
         }
+        output.push('}');
     }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    use super::{
+        format_kv, AppenderMode, CompressMode, Decoder, FlushOutcome, LogFileEvent, LogLevel, Xlog,
+        XlogConfig, XlogError,
+    };
+    use crate::test_support::{
+        appender_test_lock, logged_text, unique_prefix, AppenderCloseGuard, GlobalAppender, LogDir,
+    };
 
     #[test]
     fn init_reuses_same_name_prefix_and_applies_latest_level() {
@@ -606,6 +1336,24 @@ mod tests {
         assert_eq!(first.level(), LogLevel::Debug);
     }
 
+    #[test]
+    fn pub_key_bytes_matches_hex_key() {
+        const PUB_KEY: &str = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+        let mut bytes = [0u8; 64];
+        for (idx, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&PUB_KEY[idx * 2..idx * 2 + 2], 16).expect("hex");
+        }
+        let cfg = XlogConfig::new("logs", "key")
+            .pub_key_bytes(bytes)
+            .expect("valid point");
+        assert_eq!(cfg.pub_key.as_deref(), Some(PUB_KEY));
+
+        assert!(matches!(
+            XlogConfig::new("logs", "key").pub_key_bytes([0; 64]),
+            Err(XlogError::InvalidKey)
+        ));
+    }
+
     #[test]
     fn init_rejects_conflicting_config_for_same_name_prefix() {
         let dir = TempDir::new().expect("tempdir");
@@ -624,6 +1372,33 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn init_reports_nul_paths_and_unwritable_dirs() {
+        use std::error::Error as _;
+
+        let dir = LogDir::new();
+        let nul_dir = format!("{}/bad\0dir", dir.log_dir());
+        let err = Xlog::init(
+            XlogConfig::new(&nul_dir, unique_prefix("nul")),
+            LogLevel::Info,
+        )
+        .err()
+        .expect("must reject NUL");
+        assert!(matches!(err, XlogError::NulInPath(ref path) if path == &nul_dir));
+
+        let file = dir.path().join("not-a-dir");
+        std::fs::write(&file, b"x").expect("write file");
+        let log_dir = file.join("logs");
+        let err = Xlog::init(
+            XlogConfig::new(log_dir.display().to_string(), unique_prefix("unwritable")),
+            LogLevel::Info,
+        )
+        .err()
+        .expect("must reject dir under a file");
+        assert!(matches!(err, XlogError::DirNotWritable(ref path, _) if path == &log_dir));
+        assert!(err.source().is_some());
+    }
+
     #[test]
     fn appender_open_rejects_conflicting_config_when_default_exists() {
         let _lock = appender_test_lock().lock().expect("lock poisoned");
@@ -639,4 +1414,384 @@ mod tests {
         let err = Xlog::appender_open(cfg2, LogLevel::Info).expect_err("must reject conflict");
         assert!(matches!(err, XlogError::ConfigConflict { .. }));
     }
+
+    #[test]
+    fn concurrent_init_of_one_prefix_shares_one_instance() {
+        let dir = LogDir::new();
+        let cfg = dir.config("race");
+        let barrier = Arc::new(std::sync::Barrier::new(8));
+        let handles: Vec<Xlog> = (0..8)
+            .map(|_| {
+                let cfg = cfg.clone();
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    Xlog::init(cfg, LogLevel::Info).expect("init")
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|thread| thread.join().expect("init thread"))
+            .collect();
+        for handle in &handles[1..] {
+            assert!(Arc::ptr_eq(
+                &handles[0].inner.backend,
+                &handle.inner.backend
+            ));
+        }
+    }
+
+    #[test]
+    fn init_after_appender_open_reuses_default_instance() {
+        let _appender = GlobalAppender::lock();
+        let dir = LogDir::new();
+        let cfg = dir.config("shared");
+        Xlog::appender_open(cfg.clone(), LogLevel::Info).expect("open appender");
+        let logger = Xlog::init(cfg.clone(), LogLevel::Info).expect("init same prefix");
+        let looked_up = Xlog::get(&cfg.name_prefix).expect("get default by prefix");
+        assert!(Arc::ptr_eq(&logger.inner.backend, &looked_up.inner.backend));
+    }
+
+    #[test]
+    fn instance_paths_do_not_depend_on_global_appender() {
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("paths", LogLevel::Info);
+
+        logger.write(LogLevel::Info, None, "hello");
+        logger.flush(true);
+
+        let log_dir = dir.log_dir();
+        assert_eq!(logger.log_dir().as_deref(), Some(log_dir.as_str()));
+        assert_eq!(logger.cache_dir(), None);
+        let files = logger.log_files_for_timespan(0);
+        assert_eq!(files.len(), 1);
+        assert!(files[0].starts_with(&log_dir));
+    }
+
+    #[test]
+    fn write_bytes_skips_disabled_levels() {
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("bytes", LogLevel::Warn);
+
+        logger.write_bytes(LogLevel::Info, None, b"payload");
+        logger.flush(true);
+        assert!(logger.log_files_for_timespan(0).is_empty());
+
+        logger.write_bytes(LogLevel::Warn, None, b"payload");
+        logger.flush(true);
+        assert_eq!(logger.log_files_for_timespan(0).len(), 1);
+    }
+
+    #[test]
+    fn level_listeners_see_changes_from_any_handle() {
+        let dir = LogDir::new();
+        let cfg = dir.config("level-listener");
+        let prefix = cfg.name_prefix.clone();
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let id = logger.on_level_changed(move |level| sink.lock().unwrap().push(level));
+
+        let other = Xlog::get(&prefix).expect("lookup");
+        other.set_level(LogLevel::Debug);
+        other.set_level(LogLevel::Debug);
+        assert!(logger.remove_level_listener(id));
+        logger.set_level(LogLevel::Error);
+
+        assert_eq!(*seen.lock().unwrap(), vec![LogLevel::Debug]);
+        assert!(!logger.remove_level_listener(id));
+    }
+
+    #[test]
+    fn level_listeners_may_init_and_look_up_instances() {
+        let _appender = GlobalAppender::lock();
+        let dir = LogDir::new();
+        let cfg = dir.config("reentrant-level");
+        let nested = dir.config("reentrant-nested");
+        let logger = Xlog::init(cfg.clone(), LogLevel::Info).expect("init");
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let prefix = cfg.name_prefix.clone();
+        logger.on_level_changed(move |level| {
+            let found = Xlog::get(&prefix).is_some();
+            let nested = Xlog::init(nested.clone(), level).is_ok();
+            sink.lock().unwrap().push((level, found, nested));
+        });
+
+        // Run the re-inits on another thread so a deadlock fails the test
+        // instead of hanging it.
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            Xlog::init(cfg.clone(), LogLevel::Warn).expect("re-init");
+            Xlog::appender_open(cfg, LogLevel::Error).expect("open appender");
+            let _ = done_tx.send(());
+        });
+        done_rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("level listener deadlocked init");
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(LogLevel::Warn, true, true), (LogLevel::Error, true, true)]
+        );
+        drop(logger);
+    }
+
+    #[test]
+    fn dropping_looked_up_handle_keeps_instance_alive() {
+        let dir = LogDir::new();
+        let cfg = dir.config("shared").mode(AppenderMode::Sync);
+        let prefix = cfg.name_prefix.clone();
+        let owner = Xlog::init(cfg, LogLevel::Info).expect("init");
+
+        let looked_up = Xlog::get(&prefix).expect("lookup");
+        assert_eq!(looked_up.instance(), owner.instance());
+        drop(looked_up);
+
+        let again = Xlog::get(&prefix).expect("instance must outlive dropped handle");
+        assert_eq!(again.instance(), owner.instance());
+        drop(again);
+
+        owner.write(LogLevel::Info, None, "still alive");
+        owner.flush(true);
+        assert_eq!(owner.log_files_for_timespan(0).len(), 1);
+
+        drop(owner);
+        assert!(Xlog::get(&prefix).is_none());
+    }
+
+    #[test]
+    fn leaked_handle_keeps_instance_after_other_handles_drop() {
+        let dir = LogDir::new();
+        let cfg = dir.config("leaked");
+        let prefix = cfg.name_prefix.clone();
+        let owner = Xlog::init(cfg, LogLevel::Info).expect("init");
+        let instance = owner.instance();
+
+        let leaked = owner.clone().into_leaked();
+        drop(owner);
+
+        assert_eq!(leaked.instance(), instance);
+        let looked_up = Xlog::get(&prefix).expect("leaked instance stays registered");
+        assert_eq!(looked_up.instance(), instance);
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn instrument_logs_arguments_and_elapsed() {
+        #[super::xlog_instrument(logger = logger, tag = "inst", skip(secret))]
+        fn work(logger: &Xlog, n: u32, name: &str, secret: &str) -> usize {
+            if n == 0 {
+                return 0;
+            }
+            name.len() + secret.len()
+        }
+
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("instrument", LogLevel::Info);
+
+        assert_eq!(work(&logger, 0, "early", "hidden"), 0);
+        assert_eq!(work(&logger, 1, "abc", "hidden"), 9);
+
+        let text = logged_text(&logger);
+        assert!(text.contains("][inst][") && text.contains(", work]["));
+        assert!(text.contains("work started {n=0, name=\"early\"}"));
+        assert!(text.contains("work started {n=1, name=\"abc\"}"));
+        assert_eq!(text.matches("work finished {elapsed=").count(), 2);
+        assert!(!text.contains("hidden"));
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn module_tag_keeps_last_two_segments() {
+        assert_eq!(super::__module_tag("app::net::http"), "net::http");
+        assert_eq!(super::__module_tag("app::net"), "app::net");
+        assert_eq!(super::__module_tag("app"), "app");
+
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("module-tag", LogLevel::Info);
+        crate::xlog_info!(&logger; "hello {}", 1);
+        crate::xlog!(&logger, LogLevel::Warn, "explicit", "tagged");
+
+        let text = logged_text(&logger);
+        assert_eq!(crate::xlog_module_tag!(), "mars_xlog::tests");
+        assert!(text.contains("][mars_xlog::tests][") && text.contains("hello 1"));
+        assert!(text.contains("][explicit]["));
+    }
+
+    #[test]
+    fn log_lazy_only_builds_enabled_messages() {
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("lazy", LogLevel::Info);
+        let built = AtomicUsize::new(0);
+
+        logger.log_lazy(LogLevel::Debug, None, || {
+            built.fetch_add(1, Ordering::Relaxed);
+            "debug".to_string()
+        });
+        logger.log_lazy(LogLevel::Info, Some("lazy"), || {
+            built.fetch_add(1, Ordering::Relaxed);
+            format!("answer={}", 42)
+        });
+
+        let text = logged_text(&logger);
+        assert_eq!(built.load(Ordering::Relaxed), 1);
+        assert!(text.contains("][lazy][") && text.contains("answer=42"));
+        assert!(!text.contains("debug"));
+    }
+
+    #[test]
+    fn flush_bounded_completes_within_generous_deadline() {
+        let dir = LogDir::new();
+        let logger = Xlog::init(dir.config("bounded"), LogLevel::Info).expect("init");
+
+        logger.write(LogLevel::Info, None, "before onStop");
+        assert_eq!(
+            logger.flush_bounded(Duration::from_secs(10)),
+            FlushOutcome::Completed
+        );
+        assert_eq!(logger.log_files_for_timespan(0).len(), 1);
+
+        logger.set_appender_mode(AppenderMode::Sync);
+        assert_eq!(
+            logger.flush_bounded(Duration::ZERO),
+            FlushOutcome::Completed
+        );
+    }
+
+    #[test]
+    fn flush_interval_drains_idle_async_buffer() {
+        let dir = LogDir::new();
+        let cfg = dir
+            .config("interval")
+            .flush_interval(Duration::from_secs(1));
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        logger.write(LogLevel::Info, Some("tick"), "reached disk without flush");
+
+        // Reads the files directly: `logged_text` would flush itself.
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        let text = loop {
+            let text = logger
+                .log_files_for_timespan(0)
+                .first()
+                .and_then(|file| Decoder::new().decode_file(file).ok())
+                .map(|decoded| String::from_utf8_lossy(&decoded).into_owned())
+                .unwrap_or_default();
+            if text.contains("reached disk") || std::time::Instant::now() > deadline {
+                break text;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        };
+        assert!(text.contains("reached disk without flush"));
+    }
+
+    #[test]
+    fn build_info_leads_each_log_file() {
+        let dir = LogDir::new();
+        let cfg = dir
+            .config("build")
+            .mode(AppenderMode::Sync)
+            .build_info("8.0.1", "3f2a9c1", "beta");
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        logger.write(LogLevel::Info, Some("app"), "first line");
+        logger.write(LogLevel::Info, Some("app"), "second line");
+
+        let text = logged_text(&logger);
+        assert!(text.starts_with("build: version=8.0.1 commit=3f2a9c1 channel=beta\n"));
+        assert_eq!(text.matches("build: ").count(), 1);
+        assert!(text.contains("second line"));
+    }
+
+    #[test]
+    fn file_events_report_created_and_rotated_files() {
+        let dir = LogDir::new();
+        let cfg = dir.config("watch").mode(AppenderMode::Async);
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        logger.set_max_file_size(1).expect("max file size");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let id = logger.on_file_event(move |event| {
+            let _ = tx.send(event.clone());
+        });
+
+        logger.write(LogLevel::Info, Some("w"), "first");
+        logger.flush(true);
+        logger.write(LogLevel::Info, Some("w"), "second");
+        logger.flush(true);
+        let next = || rx.recv_timeout(Duration::from_secs(5)).expect("event");
+        let LogFileEvent::Created(first) = next() else {
+            panic!("expected created event");
+        };
+        let LogFileEvent::Created(second) = next() else {
+            panic!("expected created event");
+        };
+        assert_ne!(first, second);
+        assert_eq!(
+            next(),
+            LogFileEvent::Rotated {
+                from: first.clone(),
+                to: second.clone(),
+            }
+        );
+        assert!(first.starts_with(dir.path()) && second.exists());
+
+        assert!(logger.remove_file_event_listener(id));
+        logger.write(LogLevel::Info, Some("w"), "third");
+        logger.flush(true);
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn dropping_last_handle_writes_buffered_lines() {
+        let dir = LogDir::new();
+        let cfg = dir.config("drop").mode(AppenderMode::Async);
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        let second = logger.clone();
+        logger.write(LogLevel::Info, None, "last words");
+        drop(logger);
+        drop(second);
+
+        let file = std::fs::read_dir(dir.path())
+            .expect("read log dir")
+            .map(|entry| entry.expect("entry").path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "xlog"))
+            .expect("log file written on drop");
+        let text = String::from_utf8(Decoder::new().decode_file(&file).expect("decode")).unwrap();
+        assert!(text.contains("last words"), "{text}");
+    }
+
+    #[test]
+    fn migrate_cached_logs_moves_eligible_cache_files() {
+        let root = LogDir::new();
+        let log_dir = root.path().join("log");
+        let cache_dir = root.path().join("cache");
+        let prefix = unique_prefix("migrate");
+        let logger = Xlog::init(
+            XlogConfig::new(log_dir.display().to_string(), &prefix)
+                .cache_dir(cache_dir.display().to_string())
+                .cache_days(0),
+            LogLevel::Info,
+        )
+        .expect("init");
+
+        let cached = cache_dir.join(format!("{prefix}_legacy.xlog"));
+        std::fs::create_dir_all(&cache_dir).expect("cache dir");
+        std::fs::write(&cached, b"cached").expect("write cache file");
+
+        logger.migrate_cached_logs().expect("migrate");
+        assert!(!cached.exists());
+        assert_eq!(
+            std::fs::read(log_dir.join(format!("{prefix}_legacy.xlog"))).expect("moved file"),
+            b"cached"
+        );
+    }
+
+    #[test]
+    fn format_kv_matches_tracing_layout() {
+        assert_eq!(format_kv("msg", &[]), "msg");
+        assert_eq!(format_kv("", &[("k", "v")]), "{k=v}");
+        assert_eq!(
+            format_kv("login", &[("user", "42"), ("ok", "true")]),
+            "login {user=42, ok=true}"
+        );
+    }
 }
@@ -0,0 +1,116 @@
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+use crate::backend::{self, XlogBackend};
+use crate::AppenderMode;
+
+/// Flush and appender-mode policy for app lifecycle events.
+///
+/// Platform bindings forward their lifecycle callbacks here instead of each
+/// re-implementing the policy. Hooks act on every live instance:
+///
+/// - [`on_background`](Self::on_background): sync flush; with
+///   `sync_in_background` (the default), async instances also switch to sync
+///   so a kill while backgrounded loses nothing.
+/// - [`on_foreground`](Self::on_foreground): restore async mode on the
+///   instances switched by the last `on_background`.
+/// - [`on_terminate`](Self::on_terminate): switch everything to sync and
+///   flush, so lines written during teardown reach disk.
+pub struct LifecycleHooks {
+    sync_in_background: bool,
+    switched: Mutex<Vec<Weak<dyn XlogBackend>>>,
+}
+
+impl LifecycleHooks {
+    /// Create hooks with `sync_in_background` enabled.
+    pub fn new() -> Self {
+        Self {
+            sync_in_background: true,
+            switched: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Shared hooks used by the platform bindings.
+    pub fn global() -> &'static LifecycleHooks {
+        static GLOBAL: OnceLock<LifecycleHooks> = OnceLock::new();
+        GLOBAL.get_or_init(LifecycleHooks::new)
+    }
+
+    /// Whether async instances run in sync mode while the app is backgrounded.
+    pub fn sync_in_background(mut self, enabled: bool) -> Self {
+        self.sync_in_background = enabled;
+        self
+    }
+
+    /// The app moved to the background.
+    pub fn on_background(&self) {
+        let mut switched = self.switched.lock().expect("lifecycle lock poisoned");
+        for instance in backend::provider().live_instances() {
+            if self.sync_in_background && instance.appender_mode() == AppenderMode::Async {
+                // Switching to sync drains the async queue and flushes.
+                instance.set_appender_mode(AppenderMode::Sync);
+                switched.push(Arc::downgrade(&instance));
+            } else {
+                instance.flush(true);
+            }
+        }
+    }
+
+    /// The app returned to the foreground.
+    pub fn on_foreground(&self) {
+        let switched = std::mem::take(&mut *self.switched.lock().expect("lifecycle lock poisoned"));
+        for instance in switched.iter().filter_map(Weak::upgrade) {
+            if instance.appender_mode() == AppenderMode::Sync {
+                instance.set_appender_mode(AppenderMode::Async);
+            }
+        }
+    }
+
+    /// The process is about to exit.
+    pub fn on_terminate(&self) {
+        self.switched
+            .lock()
+            .expect("lifecycle lock poisoned")
+            .clear();
+        for instance in backend::provider().live_instances() {
+            if instance.appender_mode() == AppenderMode::Async {
+                instance.set_appender_mode(AppenderMode::Sync);
+            } else {
+                instance.flush(true);
+            }
+        }
+    }
+}
+
+impl Default for LifecycleHooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LifecycleHooks;
+    use crate::test_support::{GlobalAppender, LogDir};
+    use crate::{AppenderMode, LogLevel, Xlog};
+
+    #[test]
+    fn lifecycle_hooks_switch_async_instances_while_backgrounded() {
+        let _appender = GlobalAppender::lock();
+        let dir = LogDir::new();
+        let async_logger = Xlog::init(dir.config("bg-async"), LogLevel::Info).expect("init async");
+        let sync_logger = dir.sync_logger("bg-sync", LogLevel::Info);
+        let hooks = LifecycleHooks::new();
+
+        async_logger.write(LogLevel::Info, None, "queued");
+        hooks.on_background();
+        assert_eq!(async_logger.appender_mode(), AppenderMode::Sync);
+        assert_eq!(async_logger.log_files_for_timespan(0).len(), 1);
+
+        hooks.on_foreground();
+        assert_eq!(async_logger.appender_mode(), AppenderMode::Async);
+        assert_eq!(sync_logger.appender_mode(), AppenderMode::Sync);
+
+        hooks.on_terminate();
+        assert_eq!(async_logger.appender_mode(), AppenderMode::Sync);
+    }
+}
@@ -0,0 +1,258 @@
+use std::io;
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use mars_xlog_core::platform_tid::{current_tid, main_tid};
+
+use crate::{LogLevel, RawLogMeta, Xlog};
+
+/// Default number of records [`NonBlocking`] queues before it blocks or drops.
+pub const DEFAULT_BUFFERED_LINES_LIMIT: usize = 128_000;
+
+/// Options for [`Xlog::non_blocking_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonBlockingConfig {
+    /// Records queued before writers block (or drop, when `lossy`).
+    pub buffered_lines_limit: usize,
+    /// Drop records instead of blocking when the queue is full.
+    pub lossy: bool,
+}
+
+impl Default for NonBlockingConfig {
+    fn default() -> Self {
+        Self {
+            buffered_lines_limit: DEFAULT_BUFFERED_LINES_LIMIT,
+            lossy: true,
+        }
+    }
+}
+
+impl NonBlockingConfig {
+    /// Same as [`NonBlockingConfig::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many records may be queued; at least one.
+    pub fn buffered_lines_limit(mut self, limit: usize) -> Self {
+        self.buffered_lines_limit = limit.max(1);
+        self
+    }
+
+    /// Choose between dropping (`true`) and blocking (`false`) on a full queue.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+}
+
+struct Record {
+    level: LogLevel,
+    tag: Option<String>,
+    file: String,
+    func: String,
+    line: u32,
+    msg: String,
+    meta: RawLogMeta,
+}
+
+enum Message {
+    Record(Record),
+    Shutdown,
+}
+
+/// Cloneable writer that hands records to a dedicated thread, so callers
+/// never wait for formatting, compression, or disk I/O.
+///
+/// Created by [`Xlog::non_blocking`] together with a [`WorkerGuard`]. The
+/// caller's pid/tid are captured when a record is queued, so entries look
+/// as if they were written directly. Records sent after the guard is dropped
+/// are discarded and counted in [`NonBlocking::dropped_lines`].
+#[derive(Clone)]
+pub struct NonBlocking {
+    logger: Xlog,
+    sender: SyncSender<Message>,
+    lossy: bool,
+    dropped: Arc<AtomicU64>,
+}
+
+impl NonBlocking {
+    /// Whether `level` would be written by the underlying instance.
+    pub fn is_enabled(&self, level: LogLevel) -> bool {
+        self.logger.is_enabled(level)
+    }
+
+    /// Queue a message with caller file/line captured via `#[track_caller]`.
+    #[track_caller]
+    pub fn log(&self, level: LogLevel, tag: Option<&str>, msg: impl AsRef<str>) {
+        let loc = Location::caller();
+        self.write_with_meta(level, tag, loc.file(), "", loc.line(), msg.as_ref());
+    }
+
+    /// Queue a message without source metadata.
+    pub fn write(&self, level: LogLevel, tag: Option<&str>, msg: &str) {
+        self.write_with_meta(level, tag, "", "", 0, msg);
+    }
+
+    /// Queue a message with explicit source metadata.
+    pub fn write_with_meta(
+        &self,
+        level: LogLevel,
+        tag: Option<&str>,
+        file: &str,
+        func: &str,
+        line: u32,
+        msg: &str,
+    ) {
+        if !self.is_enabled(level) {
+            return;
+        }
+        let record = Record {
+            level,
+            tag: tag.map(str::to_string),
+            file: file.to_string(),
+            func: func.to_string(),
+            line,
+            msg: msg.to_string(),
+            meta: RawLogMeta::new(i64::from(std::process::id()), current_tid(), main_tid()),
+        };
+        let sent = if self.lossy {
+            match self.sender.try_send(Message::Record(record)) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+            }
+        } else {
+            self.sender.send(Message::Record(record)).is_ok()
+        };
+        if !sent {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records discarded because the queue was full (lossy mode) or the
+    /// worker had already shut down.
+    pub fn dropped_lines(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Keeps the [`NonBlocking`] worker alive; dropping it writes every record
+/// queued so far, flushes the instance, and stops the worker.
+///
+/// Hold it for as long as logging should work, typically in `main`.
+#[must_use = "dropping the guard immediately stops the non-blocking worker"]
+pub struct WorkerGuard {
+    sender: SyncSender<Message>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        // Queued behind every pending record, so they are all written first.
+        let _ = self.sender.send(Message::Shutdown);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Xlog {
+    /// Split this instance into a non-blocking writer and the guard that
+    /// owns its worker thread, like `tracing_appender::non_blocking`.
+    ///
+    /// Uses [`NonBlockingConfig::default`]: a lossy queue of
+    /// [`DEFAULT_BUFFERED_LINES_LIMIT`] records. Fails when the worker thread
+    /// cannot be spawned.
+    pub fn non_blocking(&self) -> io::Result<(NonBlocking, WorkerGuard)> {
+        self.non_blocking_with(NonBlockingConfig::default())
+    }
+
+    /// [`Xlog::non_blocking`] with explicit queue options.
+    pub fn non_blocking_with(
+        &self,
+        config: NonBlockingConfig,
+    ) -> io::Result<(NonBlocking, WorkerGuard)> {
+        let (sender, receiver) = mpsc::sync_channel(config.buffered_lines_limit.max(1));
+        let logger = self.clone();
+        let worker = thread::Builder::new()
+            .name("xlog-non-blocking".to_string())
+            .spawn(move || run_worker(logger, receiver))?;
+        Ok((
+            NonBlocking {
+                logger: self.clone(),
+                sender: sender.clone(),
+                lossy: config.lossy,
+                dropped: Arc::new(AtomicU64::new(0)),
+            },
+            WorkerGuard {
+                sender,
+                worker: Some(worker),
+            },
+        ))
+    }
+}
+
+fn run_worker(logger: Xlog, receiver: Receiver<Message>) {
+    for message in receiver {
+        match message {
+            Message::Record(record) => logger.write_with_meta_raw(
+                record.level,
+                record.tag.as_deref(),
+                &record.file,
+                &record.func,
+                record.line,
+                &record.msg,
+                record.meta,
+            ),
+            Message::Shutdown => break,
+        }
+    }
+    logger.flush(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NonBlockingConfig;
+    use crate::test_support::{logged_text, LogDir};
+    use crate::{AppenderMode, LogLevel, Xlog};
+
+    #[test]
+    fn non_blocking_guard_drains_queue_on_drop() {
+        let dir = LogDir::new();
+        let cfg = dir.config("nb").mode(AppenderMode::Async);
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        let (writer, guard) = logger
+            .non_blocking_with(
+                NonBlockingConfig::new()
+                    .buffered_lines_limit(4)
+                    .lossy(false),
+            )
+            .expect("non-blocking");
+
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let writer = writer.clone();
+                std::thread::spawn(move || {
+                    for i in 0..25 {
+                        writer.write(LogLevel::Info, Some("nb"), &format!("line {t}-{i}"));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().expect("writer thread");
+        }
+        drop(guard);
+        assert_eq!(writer.dropped_lines(), 0);
+
+        let text = logged_text(&logger);
+        assert_eq!(text.matches("][line ").count(), 100);
+        assert!(text.contains("][line 3-24\n"));
+
+        writer.write(LogLevel::Info, Some("nb"), "after shutdown");
+        assert_eq!(writer.dropped_lines(), 1);
+    }
+}
@@ -0,0 +1,235 @@
+use std::io;
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{LogLevel, Xlog};
+
+/// Payload bytes per entry; keeps each line well under the formatter's
+/// 16 KiB line cap so nothing is truncated.
+pub const STREAM_CHUNK_BYTES: usize = 8 * 1024;
+
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Writer returned by [`Xlog::write_stream`] that logs a large payload as a
+/// run of ordered entries.
+///
+/// Each entry carries at most [`STREAM_CHUNK_BYTES`] of payload and starts
+/// with `[stream <id> #<seq>] `; dropping the writer (or calling
+/// [`LogStream::finish`]) writes the tail and a closing
+/// `[stream <id> end] {chunks=.., bytes=..}` entry. Chunks are cut at a
+/// newline when one falls in the second half of the chunk, otherwise at a
+/// UTF-8 boundary. Nothing is buffered or written when `level` is disabled
+/// at creation time.
+#[must_use = "the stream tail is only logged when the writer is dropped"]
+pub struct LogStream<'a> {
+    logger: &'a Xlog,
+    level: LogLevel,
+    tag: Option<&'a str>,
+    file: &'static str,
+    line: u32,
+    id: u64,
+    seq: u64,
+    bytes: u64,
+    pending: Vec<u8>,
+    enabled: bool,
+}
+
+impl<'a> LogStream<'a> {
+    pub(crate) fn new(
+        logger: &'a Xlog,
+        level: LogLevel,
+        tag: Option<&'a str>,
+        loc: &'static Location<'static>,
+    ) -> Self {
+        let enabled = logger.is_enabled(level);
+        Self {
+            logger,
+            level,
+            tag,
+            file: loc.file(),
+            line: loc.line(),
+            id: if enabled {
+                NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed)
+            } else {
+                0
+            },
+            seq: 0,
+            bytes: 0,
+            pending: Vec::new(),
+            enabled,
+        }
+    }
+
+    /// Stream id shared by every entry of this payload; `0` when disabled.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Write the buffered tail and the closing entry now.
+    pub fn finish(self) {}
+
+    fn emit_chunk(&mut self, len: usize) {
+        let chunk: Vec<u8> = self.pending.drain(..len).collect();
+        self.seq += 1;
+        self.bytes += chunk.len() as u64;
+        let msg = format!(
+            "[stream {} #{}] {}",
+            self.id,
+            self.seq,
+            String::from_utf8_lossy(&chunk)
+        );
+        self.logger
+            .write_with_meta(self.level, self.tag, self.file, "", self.line, &msg);
+    }
+
+    fn emit_full_chunks(&mut self) {
+        while self.pending.len() >= STREAM_CHUNK_BYTES {
+            let len = chunk_len(&self.pending[..STREAM_CHUNK_BYTES]);
+            self.emit_chunk(len);
+        }
+    }
+}
+
+impl io::Write for LogStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.enabled {
+            self.pending.extend_from_slice(buf);
+            self.emit_full_chunks();
+        }
+        Ok(buf.len())
+    }
+
+    /// Logs whatever is buffered as a (short) chunk, keeping an incomplete
+    /// trailing UTF-8 sequence for the next write.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.enabled && !self.pending.is_empty() {
+            let len = utf8_prefix_len(&self.pending);
+            if len > 0 {
+                self.emit_chunk(len);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LogStream<'_> {
+    fn drop(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if !self.pending.is_empty() {
+            let len = self.pending.len();
+            self.emit_chunk(len);
+        }
+        let msg = format!(
+            "[stream {} end] {{chunks={}, bytes={}}}",
+            self.id, self.seq, self.bytes
+        );
+        self.logger
+            .write_with_meta(self.level, self.tag, self.file, "", self.line, &msg);
+    }
+}
+
+impl Xlog {
+    /// Open a writer that logs an arbitrarily large payload (for example a
+    /// dumped state snapshot) as ordered, sequence-tagged entries.
+    ///
+    /// A single [`Xlog::write`] is capped by the line formatter; see
+    /// [`LogStream`] for the entry layout.
+    #[track_caller]
+    pub fn write_stream<'a>(&'a self, level: LogLevel, tag: Option<&'a str>) -> LogStream<'a> {
+        LogStream::new(self, level, tag, Location::caller())
+    }
+}
+
+/// Length of the next chunk taken from the front of a full window.
+fn chunk_len(window: &[u8]) -> usize {
+    let half = window.len() / 2;
+    if let Some(pos) = window[half..].iter().rposition(|&b| b == b'\n') {
+        return half + pos + 1;
+    }
+    match utf8_prefix_len(window) {
+        0 => window.len(),
+        len => len,
+    }
+}
+
+/// Longest prefix of `bytes` that does not end inside a UTF-8 sequence.
+fn utf8_prefix_len(bytes: &[u8]) -> usize {
+    // A sequence is at most four bytes, so only the tail needs checking.
+    let tail_start = bytes.len().saturating_sub(3);
+    for start in (tail_start..bytes.len()).rev() {
+        let byte = bytes[start];
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let width = match byte {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        return if start + width <= bytes.len() {
+            bytes.len()
+        } else {
+            start
+        };
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::{chunk_len, utf8_prefix_len};
+    use crate::test_support::{logged_text, LogDir};
+    use crate::LogLevel;
+
+    #[test]
+    fn chunks_prefer_newlines_then_utf8_boundaries() {
+        let mut window = vec![b'a'; 100];
+        window[70] = b'\n';
+        assert_eq!(chunk_len(&window), 71);
+        window[70] = b'a';
+        assert_eq!(chunk_len(&window), 100);
+
+        let mut split = "x".repeat(98).into_bytes();
+        split.extend_from_slice(&"é".as_bytes()[..1]);
+        assert_eq!(utf8_prefix_len(&split), 98);
+        assert_eq!(chunk_len(&split), 98);
+        split.push("é".as_bytes()[1]);
+        assert_eq!(utf8_prefix_len(&split), 100);
+    }
+
+    #[test]
+    fn write_stream_splits_large_payload_into_ordered_chunks() {
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("stream", LogLevel::Info);
+        let payload: String = (0..40_000)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect();
+
+        let mut stream = logger.write_stream(LogLevel::Info, Some("dump"));
+        let id = stream.id();
+        for part in payload.as_bytes().chunks(3_000) {
+            stream.write_all(part).expect("write");
+        }
+        drop(stream);
+
+        let text = logged_text(&logger);
+        let mut rebuilt = String::new();
+        for (seq, line) in text
+            .lines()
+            .filter_map(|line| line.split_once(&format!("[stream {id} #")))
+            .map(|(_, rest)| rest.split_once("] ").expect("seq"))
+            .enumerate()
+        {
+            assert_eq!(line.0, (seq + 1).to_string());
+            rebuilt.push_str(line.1);
+        }
+        assert_eq!(rebuilt, payload);
+        assert!(text.contains(&format!("[stream {id} end] {{chunks=5, bytes=40000}}")));
+    }
+}
@@ -0,0 +1,91 @@
+//! Fixtures shared by the unit tests of every module.
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use tempfile::TempDir;
+
+use crate::{AppenderMode, Decoder, LogLevel, Xlog, XlogConfig};
+
+static NEXT_PREFIX_ID: AtomicUsize = AtomicUsize::new(1);
+static APPENDER_TEST_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+
+/// A `name_prefix` no other test in this process uses, so parallel tests
+/// never share an instance by accident.
+pub(crate) fn unique_prefix(label: &str) -> String {
+    let id = NEXT_PREFIX_ID.fetch_add(1, Ordering::Relaxed);
+    format!("{label}-{}-{id}", std::process::id())
+}
+
+/// Temporary log directory, removed on drop.
+pub(crate) struct LogDir(TempDir);
+
+impl LogDir {
+    pub(crate) fn new() -> Self {
+        Self(TempDir::new().expect("tempdir"))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.0.path()
+    }
+
+    /// The directory as the `log_dir` string [`XlogConfig::new`] takes.
+    pub(crate) fn log_dir(&self) -> String {
+        self.path().display().to_string()
+    }
+
+    /// Default config for a fresh instance in this directory.
+    pub(crate) fn config(&self, label: &str) -> XlogConfig {
+        XlogConfig::new(self.log_dir(), unique_prefix(label))
+    }
+
+    /// Fresh sync-mode instance, so every write is on disk once flushed.
+    pub(crate) fn sync_logger(&self, label: &str, level: LogLevel) -> Xlog {
+        Xlog::init(self.config(label).mode(AppenderMode::Sync), level).expect("init")
+    }
+}
+
+/// Flush `logger` and decode every file it has written.
+pub(crate) fn logged_text(logger: &Xlog) -> String {
+    logger.flush(true);
+    let decoder = Decoder::new();
+    logger
+        .log_files_for_timespan(0)
+        .iter()
+        .map(|file| {
+            String::from_utf8_lossy(&decoder.decode_file(file).expect("decode")).into_owned()
+        })
+        .collect()
+}
+
+/// Held by every test that touches the global appender.
+pub(crate) fn appender_test_lock() -> &'static Mutex<()> {
+    APPENDER_TEST_LOCK.get_or_init(|| Mutex::new(()))
+}
+
+/// Closes the global appender on drop.
+pub(crate) struct AppenderCloseGuard;
+
+impl Drop for AppenderCloseGuard {
+    fn drop(&mut self) {
+        Xlog::appender_close();
+    }
+}
+
+/// [`appender_test_lock`] plus an [`AppenderCloseGuard`], with the global
+/// appender closed before the test starts too.
+pub(crate) struct GlobalAppender {
+    _close: AppenderCloseGuard,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl GlobalAppender {
+    pub(crate) fn lock() -> Self {
+        let guard = appender_test_lock().lock().expect("lock poisoned");
+        Xlog::appender_close();
+        Self {
+            _close: AppenderCloseGuard,
+            _lock: guard,
+        }
+    }
+}
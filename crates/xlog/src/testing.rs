@@ -0,0 +1,419 @@
+//! Fixtures and assertions for testing code that logs through [`Xlog`].
+//!
+//! [`Capture`] records entries in memory without touching the file system;
+//! [`TestLogger`] runs a real instance in a temporary directory and decodes
+//! what reached disk. Both work with [`assert_logged!`](crate::assert_logged).
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mars_xlog_core::file_events::FileEventListener;
+use tempfile::TempDir;
+
+use crate::backend::{
+    AlertCallback, EntryListener, ErrorHandler, LevelListener, MetricsReporter, XlogBackend,
+};
+use crate::directives::TagFilter;
+use crate::{
+    AppenderMode, Decoder, FlushOutcome, Inner, LogLevel, RawLogMeta, Xlog, XlogConfig, XlogError,
+};
+
+/// One entry written to a [`Capture`] logger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedRecord {
+    /// Level it was written at.
+    pub level: LogLevel,
+    /// Tag, or the capture's name when none was given.
+    pub tag: String,
+    /// Source file passed by the caller.
+    pub file: String,
+    /// Function name passed by the caller.
+    pub func: String,
+    /// Source line passed by the caller.
+    pub line: u32,
+    /// Message text.
+    pub message: String,
+}
+
+/// In-memory logger for unit tests.
+///
+/// [`Capture::logger`] returns an ordinary [`Xlog`] handle whose writes are
+/// kept in memory instead of being formatted, compressed, and written to a
+/// file. Level filtering works as usual; flushing, file maintenance, and
+/// listeners are accepted but do nothing.
+#[derive(Clone)]
+pub struct Capture {
+    backend: Arc<CaptureBackend>,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self::new(LogLevel::Verbose)
+    }
+}
+
+impl Capture {
+    /// Create a capture that accepts `level` and above.
+    pub fn new(level: LogLevel) -> Self {
+        static NEXT_INSTANCE: AtomicUsize = AtomicUsize::new(1);
+        Self {
+            backend: Arc::new(CaptureBackend {
+                instance: NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed),
+                level: Mutex::new(level),
+                tag_filter: TagFilter::default(),
+                records: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// A handle writing into this capture; `name` is the default tag.
+    pub fn logger(&self, name: impl Into<String>) -> Xlog {
+        Xlog {
+            inner: Arc::new(Inner {
+                backend: self.backend.clone(),
+                name_prefix: name.into(),
+            }),
+        }
+    }
+
+    /// Every entry written so far, oldest first.
+    pub fn records(&self) -> Vec<CapturedRecord> {
+        self.backend.lock_records().clone()
+    }
+
+    /// Forget the entries written so far.
+    pub fn clear(&self) {
+        self.backend.lock_records().clear();
+    }
+}
+
+/// Real logger in a temporary directory, removed when the fixture drops.
+///
+/// Uses sync mode so every accepted line is on disk as soon as the write
+/// returns; [`TestLogger::lines`] decodes the files back to text.
+pub struct TestLogger {
+    logger: Xlog,
+    dir: TempDir,
+}
+
+impl TestLogger {
+    /// Create a sync-mode logger at [`LogLevel::Verbose`].
+    pub fn new() -> Result<Self, XlogError> {
+        Self::with_config(|config| config)
+    }
+
+    /// Like [`TestLogger::new`], with `configure` applied to the default
+    /// config before init.
+    pub fn with_config(
+        configure: impl FnOnce(XlogConfig) -> XlogConfig,
+    ) -> Result<Self, XlogError> {
+        static NEXT_PREFIX: AtomicU64 = AtomicU64::new(1);
+        let dir = tempfile::tempdir().map_err(|err| XlogError::Internal(err.to_string()))?;
+        let prefix = format!(
+            "test-{}-{}",
+            std::process::id(),
+            NEXT_PREFIX.fetch_add(1, Ordering::Relaxed)
+        );
+        let config = configure(
+            XlogConfig::new(dir.path().display().to_string(), prefix).mode(AppenderMode::Sync),
+        );
+        let logger = Xlog::init(config, LogLevel::Verbose)?;
+        Ok(Self { logger, dir })
+    }
+
+    /// The logger under test.
+    pub fn logger(&self) -> &Xlog {
+        &self.logger
+    }
+
+    /// Temporary directory holding the log files.
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Flush, then decode every log file in the directory into text lines,
+    /// oldest file first.
+    pub fn lines(&self) -> Vec<String> {
+        self.logger.flush(true);
+        let mut files: Vec<_> = std::fs::read_dir(self.dir.path())
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "xlog"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        let decoder = Decoder::new();
+        files
+            .iter()
+            .filter_map(|file| decoder.decode_file(file).ok())
+            .flat_map(|bytes| {
+                String::from_utf8_lossy(&bytes)
+                    .lines()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Something [`assert_logged!`](crate::assert_logged) can inspect.
+pub trait LogSource {
+    /// Entries written at `level`, as text to search.
+    fn logged_at(&self, level: LogLevel) -> Vec<String>;
+}
+
+impl LogSource for Capture {
+    fn logged_at(&self, level: LogLevel) -> Vec<String> {
+        self.backend
+            .lock_records()
+            .iter()
+            .filter(|record| record.level == level)
+            .map(|record| record.message.clone())
+            .collect()
+    }
+}
+
+impl LogSource for TestLogger {
+    fn logged_at(&self, level: LogLevel) -> Vec<String> {
+        let marker = format!("[{}][", level_mark(level));
+        self.lines()
+            .into_iter()
+            .filter(|line| line.starts_with(&marker))
+            .collect()
+    }
+}
+
+impl<T: LogSource + ?Sized> LogSource for &T {
+    fn logged_at(&self, level: LogLevel) -> Vec<String> {
+        (**self).logged_at(level)
+    }
+}
+
+/// Panic unless `source` has an entry at `level` containing `needle`.
+///
+/// Prefer the [`assert_logged!`](crate::assert_logged) macro.
+#[track_caller]
+pub fn assert_logged(source: &impl LogSource, level: LogLevel, needle: &str) {
+    let entries = source.logged_at(level);
+    if !entries.iter().any(|entry| entry.contains(needle)) {
+        panic!(
+            "no {level:?} entry contains {needle:?}; {level:?} entries were:\n{}",
+            entries.join("\n")
+        );
+    }
+}
+
+/// Assert that a [`Capture`] or [`TestLogger`] has an entry at a level whose
+/// text contains a string.
+///
+/// ```
+/// use mars_xlog::testing::Capture;
+/// use mars_xlog::{assert_logged, LogLevel};
+///
+/// let capture = Capture::default();
+/// capture.logger("net").log(LogLevel::Warn, None, "retrying request");
+/// assert_logged!(capture, Warn, contains "retrying");
+/// ```
+#[macro_export]
+macro_rules! assert_logged {
+    ($source:expr, $level:ident, contains $needle:expr $(,)?) => {
+        $crate::testing::assert_logged(&$source, $crate::LogLevel::$level, $needle)
+    };
+}
+
+fn level_mark(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Verbose => "V",
+        LogLevel::Debug => "D",
+        LogLevel::Info => "I",
+        LogLevel::Warn => "W",
+        LogLevel::Error => "E",
+        LogLevel::Fatal => "F",
+        LogLevel::None => "N",
+    }
+}
+
+struct CaptureBackend {
+    instance: usize,
+    level: Mutex<LogLevel>,
+    tag_filter: TagFilter,
+    records: Mutex<Vec<CapturedRecord>>,
+}
+
+impl CaptureBackend {
+    fn lock_records(&self) -> std::sync::MutexGuard<'_, Vec<CapturedRecord>> {
+        self.records.lock().expect("capture records lock poisoned")
+    }
+}
+
+impl XlogBackend for CaptureBackend {
+    fn instance(&self) -> usize {
+        self.instance
+    }
+
+    fn is_enabled(&self, level: LogLevel) -> bool {
+        let current = self.level();
+        level != LogLevel::None && current != LogLevel::None && level.rank() >= current.rank()
+    }
+
+    fn level(&self) -> LogLevel {
+        *self.level.lock().expect("capture level lock poisoned")
+    }
+
+    fn set_level(&self, level: LogLevel) {
+        *self.level.lock().expect("capture level lock poisoned") = level;
+    }
+
+    fn tag_filter(&self) -> &TagFilter {
+        &self.tag_filter
+    }
+
+    fn add_level_listener(&self, _listener: LevelListener) -> u64 {
+        0
+    }
+
+    fn remove_level_listener(&self, _id: u64) -> bool {
+        false
+    }
+
+    fn add_entry_listener(&self, _listener: EntryListener) -> u64 {
+        0
+    }
+
+    fn remove_entry_listener(&self, _id: u64) -> bool {
+        false
+    }
+
+    fn add_file_listener(&self, _listener: FileEventListener) -> u64 {
+        0
+    }
+
+    fn remove_file_listener(&self, _id: u64) -> bool {
+        false
+    }
+
+    fn add_threshold_alert(
+        &self,
+        _level: LogLevel,
+        _count: usize,
+        _window: Duration,
+        _callback: AlertCallback,
+    ) -> u64 {
+        0
+    }
+
+    fn remove_threshold_alert(&self, _id: u64) -> bool {
+        false
+    }
+
+    fn appender_mode(&self) -> AppenderMode {
+        AppenderMode::Sync
+    }
+
+    fn set_appender_mode(&self, _mode: AppenderMode) {}
+
+    fn flush(&self, _sync: bool) {}
+
+    fn flush_bounded(&self, _timeout: Duration) -> FlushOutcome {
+        FlushOutcome::Completed
+    }
+
+    fn set_console_log_open(&self, _open: bool) {}
+
+    fn set_max_file_size(&self, _max_bytes: i64) {}
+
+    fn set_max_alive_time(&self, _alive_seconds: i64) {}
+
+    fn set_pub_key(&self, _pub_key: &str) -> Result<(), XlogError> {
+        Ok(())
+    }
+
+    fn migrate_cached_logs(&self) -> Result<(), XlogError> {
+        Ok(())
+    }
+
+    fn set_error_handler(&self, _handler: Option<ErrorHandler>) {}
+
+    fn set_metrics_reporter(
+        &self,
+        _interval: Duration,
+        _reporter: Option<MetricsReporter>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn log_dir(&self) -> Option<String> {
+        None
+    }
+
+    fn cache_dir(&self) -> Option<String> {
+        None
+    }
+
+    fn filepaths_from_timespan(&self, _timespan: i32, _prefix: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn write_with_meta(
+        &self,
+        level: LogLevel,
+        tag: &str,
+        file: &str,
+        func: &str,
+        line: u32,
+        msg: &str,
+        _raw_meta: RawLogMeta,
+    ) {
+        self.lock_records().push(CapturedRecord {
+            level,
+            tag: tag.to_string(),
+            file: file.to_string(),
+            func: func.to_string(),
+            line,
+            message: msg.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capture, CapturedRecord, TestLogger};
+    use crate::LogLevel;
+
+    #[test]
+    fn capture_and_temp_dir_fixture() {
+        let capture = Capture::new(LogLevel::Info);
+        let logger = capture.logger("svc");
+        logger.write(LogLevel::Debug, None, "filtered out");
+        logger.write_with_meta(LogLevel::Warn, Some("net"), "net.rs", "send", 7, "retry 3");
+        crate::assert_logged!(capture, Warn, contains "retry");
+        assert_eq!(
+            capture.records(),
+            vec![CapturedRecord {
+                level: LogLevel::Warn,
+                tag: "net".to_string(),
+                file: "net.rs".to_string(),
+                func: "send".to_string(),
+                line: 7,
+                message: "retry 3".to_string(),
+            }]
+        );
+        let missing = std::panic::catch_unwind(|| {
+            crate::assert_logged!(capture, Error, contains "retry");
+        });
+        assert!(missing.is_err());
+        capture.clear();
+        assert!(capture.records().is_empty());
+
+        let fixture = TestLogger::new().expect("fixture");
+        fixture
+            .logger()
+            .log(LogLevel::Error, Some("db"), "connection lost");
+        crate::assert_logged!(fixture, Error, contains "connection lost");
+        assert!(fixture.lines().iter().any(|line| line.contains("[db]")));
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt::Debug;
+use std::panic::Location;
+use std::time::Instant;
+
+use crate::{format_kv, LogLevel, Xlog};
+
+/// Guard returned by [`Xlog::timed`] that logs how long a scope took.
+///
+/// Logs `"<operation> started"` when created and
+/// `"<operation> finished {elapsed=..}"` when dropped, both attributed to the
+/// call site of [`Xlog::timed`]. Nothing is measured or written when `level`
+/// is disabled at creation time.
+#[must_use = "the elapsed time is logged when the guard is dropped"]
+pub struct TimedGuard<'a> {
+    logger: &'a Xlog,
+    level: LogLevel,
+    tag: Option<&'a str>,
+    operation: &'a str,
+    file: &'a str,
+    func: &'a str,
+    line: u32,
+    start: Option<Instant>,
+}
+
+impl<'a> TimedGuard<'a> {
+    /// Finish the measurement now instead of at the end of the scope.
+    pub fn finish(self) {}
+
+    #[doc(hidden)]
+    /// Entry point for `#[xlog_instrument]`; logs the arguments with the start line.
+    #[allow(clippy::too_many_arguments)]
+    pub fn __instrument(
+        logger: &'a Xlog,
+        level: LogLevel,
+        tag: Option<&'a str>,
+        func: &'a str,
+        file: &'a str,
+        line: u32,
+        args: &[(&str, &dyn Debug)],
+    ) -> Self {
+        let mut guard = Self {
+            logger,
+            level,
+            tag,
+            operation: func,
+            file,
+            func,
+            line,
+            start: None,
+        };
+        if logger.is_enabled(level) {
+            let values: Vec<String> = args.iter().map(|(_, value)| format!("{value:?}")).collect();
+            let fields: Vec<(&str, &str)> = args
+                .iter()
+                .zip(&values)
+                .map(|((name, _), value)| (*name, value.as_str()))
+                .collect();
+            guard.write(&format_kv(&format!("{func} started"), &fields));
+            guard.start = Some(Instant::now());
+        }
+        guard
+    }
+
+    fn write(&self, msg: &str) {
+        self.logger
+            .write_with_meta(self.level, self.tag, self.file, self.func, self.line, msg);
+    }
+}
+
+impl Drop for TimedGuard<'_> {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return;
+        };
+        let elapsed = format!("{:?}", start.elapsed());
+        self.write(&format_kv(
+            &format!("{} finished", self.operation),
+            &[("elapsed", &elapsed)],
+        ));
+    }
+}
+
+impl Xlog {
+    /// Log the start of `operation` and, when the returned guard drops, its duration.
+    ///
+    /// A lightweight alternative to `tracing` spans for code that only uses
+    /// the direct API.
+    #[track_caller]
+    pub fn timed<'a>(
+        &'a self,
+        level: LogLevel,
+        tag: &'a str,
+        operation: &'a str,
+    ) -> TimedGuard<'a> {
+        let location = Location::caller();
+        let mut guard = TimedGuard {
+            logger: self,
+            level,
+            tag: Some(tag),
+            operation,
+            file: location.file(),
+            func: "",
+            line: location.line(),
+            start: None,
+        };
+        if self.is_enabled(level) {
+            guard.write(&format!("{operation} started"));
+            guard.start = Some(Instant::now());
+        }
+        guard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{logged_text, LogDir};
+    use crate::LogLevel;
+
+    #[test]
+    fn timed_guard_logs_start_and_elapsed() {
+        let dir = LogDir::new();
+        let logger = dir.sync_logger("timed", LogLevel::Info);
+
+        drop(logger.timed(LogLevel::Debug, "db", "skipped"));
+        {
+            let _guard = logger.timed(LogLevel::Info, "db", "migrate");
+        }
+
+        let text = logged_text(&logger);
+        assert!(!text.contains("skipped"));
+        assert!(text.contains("][db][") && text.contains("migrate started"));
+        assert!(text.contains("migrate finished {elapsed="));
+    }
+}
@@ -1,11 +1,19 @@
 //! `tracing` layer that forwards events into Mars Xlog.
 //!
 //! This module is gated behind the `tracing` feature.
-use crate::{LogLevel, Xlog};
-use std::fmt;
+//!
+//! Level filtering is resolved per callsite through `register_callsite`, so
+//! disabled levels cost nothing at runtime. Updating the filter through
+//! `XlogLayerHandle` rebuilds the global callsite interest cache.
+use crate::directives::TagFilter;
+use crate::{Directives, LogLevel, Xlog};
+use std::cell::RefCell;
+use std::fmt::{self, Write as _};
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::subscriber::Interest;
 use tracing::{Event, Level, Metadata, Subscriber};
 use tracing_subscriber::layer::{Context, Layer};
 use tracing_subscriber::registry::LookupSpan;
@@ -21,6 +29,14 @@ pub struct XlogLayerConfig {
     pub tag: Option<String>,
     /// Include span names in the formatted message.
     pub include_spans: bool,
+    /// Maximum number of fields (besides `message`) written per event.
+    pub max_fields: usize,
+    /// Maximum length in bytes of each formatted field value, including `message`.
+    pub max_field_len: usize,
+    /// Escape newlines and braces in field values so each entry stays on one line.
+    pub escape_fields: bool,
+    /// Optional per-target levels; when set they replace `level`.
+    pub directives: Option<Directives>,
 }
 
 impl XlogLayerConfig {
@@ -32,6 +48,10 @@ impl XlogLayerConfig {
             level,
             tag: None,
             include_spans: false,
+            max_fields: DEFAULT_MAX_FIELDS,
+            max_field_len: DEFAULT_MAX_FIELD_LEN,
+            escape_fields: true,
+            directives: None,
         }
     }
 
@@ -58,6 +78,32 @@ impl XlogLayerConfig {
         self.include_spans = include;
         self
     }
+
+    /// Cap the number of fields per event; the rest are summarized as `...+N`.
+    pub fn max_fields(mut self, max: usize) -> Self {
+        self.max_fields = max;
+        self
+    }
+
+    /// Cap each formatted value at `max` bytes; longer values end in `...`.
+    pub fn max_field_len(mut self, max: usize) -> Self {
+        self.max_field_len = max;
+        self
+    }
+
+    /// Escape `\n`, `\r`, `{` and `}` in field values (on by default).
+    pub fn escape_fields(mut self, escape: bool) -> Self {
+        self.escape_fields = escape;
+        self
+    }
+
+    /// Filter per event target; targets the directives do not cover use
+    /// the configured level, and the layer level becomes
+    /// [`Directives::max_level`] of it.
+    pub fn directives(mut self, directives: Directives) -> Self {
+        self.directives = Some(directives);
+        self
+    }
 }
 
 /// Handle used to toggle a running `XlogLayer`.
@@ -70,6 +116,7 @@ impl XlogLayerHandle {
     /// Enable or disable forwarding.
     pub fn set_enabled(&self, enabled: bool) {
         self.state.enabled.store(enabled, Ordering::Release);
+        tracing::callsite::rebuild_interest_cache();
     }
 
     /// Check whether forwarding is enabled.
@@ -78,16 +125,47 @@ impl XlogLayerHandle {
     }
 
     /// Update the minimum forwarded level for this layer only.
+    ///
+    /// While directives are set this is also the level for targets they do
+    /// not cover and the level [`XlogLayerHandle::clear_directives`] restores.
     pub fn set_level(&self, level: LogLevel) {
-        self.state
-            .level
-            .store(level_to_u8(level), Ordering::Release);
+        self.state.filter.set_base(level);
+        self.store_level(level);
     }
 
     /// Read the current minimum log level.
     pub fn level(&self) -> LogLevel {
         level_from_u8(self.state.level.load(Ordering::Acquire))
     }
+
+    /// Filter per event target with `directives`, mirroring
+    /// [`Xlog::set_directives`]: uncovered targets keep the current level,
+    /// the layer level becomes [`Directives::max_level`] of it and a later
+    /// [`XlogLayerHandle::set_level`] caps every target.
+    pub fn set_directives(&self, directives: Directives) {
+        let level = self.state.filter.set(directives, self.level());
+        self.store_level(level);
+    }
+
+    /// Remove per-target directives and restore the level the layer had
+    /// before them.
+    pub fn clear_directives(&self) {
+        if let Some(level) = self.state.filter.clear() {
+            self.store_level(level);
+        }
+    }
+
+    /// The directives currently applied by this layer, if any.
+    pub fn directives(&self) -> Option<Directives> {
+        self.state.filter.get()
+    }
+
+    fn store_level(&self, level: LogLevel) {
+        self.state
+            .level
+            .store(level_to_u8(level), Ordering::Release);
+        tracing::callsite::rebuild_interest_cache();
+    }
 }
 
 /// `tracing-subscriber` layer that forwards events to a `Xlog` instance.
@@ -95,6 +173,7 @@ pub struct XlogLayer {
     state: Arc<LayerState>,
     tag: Option<String>,
     include_spans: bool,
+    limits: FieldLimits,
 }
 
 impl XlogLayer {
@@ -110,10 +189,19 @@ impl XlogLayer {
     /// underlying logger's level.
     pub fn with_config(logger: Xlog, config: XlogLayerConfig) -> (Self, XlogLayerHandle) {
         let state = Arc::new(LayerState::new(logger, config.enabled, config.level));
+        if let Some(directives) = config.directives {
+            let level = state.filter.set(directives, config.level);
+            state.level.store(level_to_u8(level), Ordering::Release);
+        }
         let layer = Self {
             state: Arc::clone(&state),
             tag: config.tag,
             include_spans: config.include_spans,
+            limits: FieldLimits {
+                max_fields: config.max_fields,
+                max_field_len: config.max_field_len,
+                escape: config.escape_fields,
+            },
         };
         let handle = XlogLayerHandle { state };
         (layer, handle)
@@ -131,12 +219,43 @@ impl XlogLayer {
             return false;
         }
         let min_level = level_from_u8(self.state.level.load(Ordering::Acquire));
-        level_rank(level) >= level_rank(min_level)
+        level.rank() >= min_level.rank()
     }
 
     fn is_metadata_enabled(&self, metadata: &Metadata<'_>) -> bool {
         let level = tracing_level_to_log_level(metadata.level());
-        level != LogLevel::None && self.is_enabled_for(level)
+        level != LogLevel::None
+            && self.is_enabled_for(level)
+            && self.state.filter.allows(level, metadata.target())
+    }
+
+    fn max_level_filter(&self) -> LevelFilter {
+        if !self.state.enabled.load(Ordering::Acquire) {
+            return LevelFilter::OFF;
+        }
+        match level_from_u8(self.state.level.load(Ordering::Acquire)) {
+            LogLevel::Verbose => LevelFilter::TRACE,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Fatal | LogLevel::None => LevelFilter::OFF,
+        }
+    }
+
+    fn write_event(&self, metadata: &Metadata<'_>, level: LogLevel, message: &str) {
+        let tag = self.tag.as_deref().unwrap_or_else(|| metadata.target());
+        let file = metadata.file().unwrap_or("<unknown>");
+        let module = metadata.module_path().unwrap_or("<unknown>");
+        let line = metadata.line().unwrap_or(0);
+
+        let logger = &self.state.logger;
+        if self.state.filter.is_active() {
+            // Already matched against the target; the written tag may differ.
+            logger.write_prefiltered(level, tag, file, module, line, message);
+        } else {
+            logger.write_with_meta(level, Some(tag), file, module, line, message);
+        }
     }
 }
 
@@ -144,63 +263,93 @@ impl<S> Layer<S> for XlogLayer
 where
     S: Subscriber + for<'a> LookupSpan<'a>,
 {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if self.is_metadata_enabled(metadata) {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
     fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
         self.is_metadata_enabled(metadata)
     }
 
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.max_level_filter())
+    }
+
     fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
         let metadata = event.metadata();
         let level = tracing_level_to_log_level(metadata.level());
         if level == LogLevel::None {
             return;
         }
-        if !self.is_enabled_for(level) {
+        if !self.is_enabled_for(level) || !self.state.filter.allows(level, metadata.target()) {
             return;
         }
         if !self.state.logger.is_enabled(level) {
             return;
         }
 
-        let mut visitor = EventVisitor::default();
-        event.record(&mut visitor);
-
-        let mut message = visitor.finish();
-        if self.include_spans {
-            if let Some(scope) = ctx.event_scope(event) {
-                let mut spans = String::new();
-                for span in scope.from_root() {
-                    if !spans.is_empty() {
-                        spans.push_str(" > ");
-                    }
-                    spans.push_str(span.metadata().name());
+        with_scratch(|scratch| {
+            let Scratch {
+                message,
+                fields,
+                output,
+            } = scratch;
+            let mut visitor = EventVisitor {
+                message,
+                fields,
+                limits: self.limits,
+                count: 0,
+                dropped: 0,
+            };
+            event.record(&mut visitor);
+            visitor.finish();
+            if !fields.is_empty() {
+                if !message.is_empty() {
+                    message.push(' ');
                 }
-                if !spans.is_empty() {
-                    if message.is_empty() {
-                        message = spans;
-                    } else {
-                        message = format!("[{}] {}", spans, message);
+                message.push('{');
+                message.push_str(fields);
+                message.push('}');
+            }
+
+            if self.include_spans {
+                if let Some(scope) = ctx.event_scope(event) {
+                    for span in scope.from_root() {
+                        if !output.is_empty() {
+                            output.push_str(" > ");
+                        }
+                        output.push_str(span.metadata().name());
                     }
                 }
             }
-        }
-        if message.is_empty() {
-            message = metadata.name().to_string();
-        }
-
-        let tag = self.tag.as_deref().unwrap_or_else(|| metadata.target());
-        let file = metadata.file().unwrap_or("<unknown>");
-        let module = metadata.module_path().unwrap_or("<unknown>");
-        let line = metadata.line().unwrap_or(0);
-
-        self.state
-            .logger
-            .write_with_meta(level, Some(tag), file, module, line, &message);
+            let text = if output.is_empty() {
+                message.as_str()
+            } else if message.is_empty() {
+                output.as_str()
+            } else {
+                output.insert(0, '[');
+                output.push_str("] ");
+                output.push_str(message);
+                output.as_str()
+            };
+            let text = if text.is_empty() {
+                metadata.name()
+            } else {
+                text
+            };
+            self.write_event(metadata, level, text);
+        });
     }
 }
 
 struct LayerState {
     enabled: AtomicBool,
     level: AtomicU8,
+    filter: TagFilter,
     logger: Xlog,
 }
 
@@ -209,73 +358,184 @@ impl LayerState {
         Self {
             enabled: AtomicBool::new(enabled),
             level: AtomicU8::new(level_to_u8(level)),
+            filter: TagFilter::default(),
             logger,
         }
     }
 }
 
+/// Per-thread formatting buffers reused across events.
 #[derive(Default)]
-struct EventVisitor {
-    message: Option<String>,
-    fields: Vec<(String, String)>,
+struct Scratch {
+    message: String,
+    fields: String,
+    output: String,
 }
 
-impl EventVisitor {
-    fn finish(self) -> String {
-        let mut output = String::new();
-        if let Some(message) = self.message {
-            output.push_str(&message);
-        }
-        if !self.fields.is_empty() {
-            if !output.is_empty() {
-                output.push(' ');
+/// Buffers that grew past this after a huge event are released instead of kept.
+const SCRATCH_RETAIN_CAPACITY: usize = 16 * 1024;
+
+impl Scratch {
+    fn reset(&mut self) {
+        for buf in [&mut self.message, &mut self.fields, &mut self.output] {
+            buf.clear();
+            if buf.capacity() > SCRATCH_RETAIN_CAPACITY {
+                buf.shrink_to(SCRATCH_RETAIN_CAPACITY);
             }
-            output.push('{');
-            for (idx, (name, value)) in self.fields.iter().enumerate() {
-                if idx > 0 {
-                    output.push_str(", ");
-                }
-                output.push_str(name);
-                output.push('=');
-                output.push_str(value);
+        }
+    }
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::new(Scratch::default());
+}
+
+fn with_scratch(f: impl FnOnce(&mut Scratch)) {
+    let mut f = Some(f);
+    let _ = SCRATCH.try_with(|cell| {
+        // A field's `Debug` impl may emit an event through another dispatcher.
+        if let Ok(mut scratch) = cell.try_borrow_mut() {
+            if let Some(f) = f.take() {
+                f(&mut scratch);
             }
-            output.push('}');
+            scratch.reset();
         }
-        output
+    });
+    // Re-entrant events and events during thread teardown use fresh buffers.
+    if let Some(f) = f {
+        f(&mut Scratch::default());
     }
+}
 
-    fn record_field(&mut self, field: &Field, value: String) {
-        if field.name() == "message" {
-            self.message = Some(value);
+const DEFAULT_MAX_FIELDS: usize = 32;
+const DEFAULT_MAX_FIELD_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy)]
+struct FieldLimits {
+    max_fields: usize,
+    max_field_len: usize,
+    escape: bool,
+}
+
+/// Writes the `message` field and the `k=v` field list straight into scratch buffers.
+struct EventVisitor<'a> {
+    message: &'a mut String,
+    fields: &'a mut String,
+    limits: FieldLimits,
+    count: usize,
+    dropped: usize,
+}
+
+impl EventVisitor<'_> {
+    fn record(&mut self, field: &Field, value: fmt::Arguments<'_>) {
+        let (out, escape) = if field.name() == "message" {
+            self.message.clear();
+            (&mut *self.message, false)
         } else {
-            self.fields.push((field.name().to_string(), value));
+            if self.count >= self.limits.max_fields {
+                self.dropped += 1;
+                return;
+            }
+            self.count += 1;
+            if !self.fields.is_empty() {
+                self.fields.push_str(", ");
+            }
+            self.fields.push_str(field.name());
+            self.fields.push('=');
+            (&mut *self.fields, self.limits.escape)
+        };
+        let mut writer = LimitedWriter {
+            out,
+            remaining: self.limits.max_field_len,
+            escape,
+        };
+        // An error means the cap was hit; it also stops formatting huge values early.
+        if writer.write_fmt(value).is_err() {
+            writer.out.push_str("...");
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.dropped > 0 {
+            if !self.fields.is_empty() {
+                self.fields.push_str(", ");
+            }
+            let _ = write!(self.fields, "...+{}", self.dropped);
         }
     }
 }
 
-impl Visit for EventVisitor {
+impl Visit for EventVisitor<'_> {
     fn record_f64(&mut self, field: &Field, value: f64) {
-        self.record_field(field, value.to_string());
+        self.record(field, format_args!("{value}"));
     }
 
     fn record_i64(&mut self, field: &Field, value: i64) {
-        self.record_field(field, value.to_string());
+        self.record(field, format_args!("{value}"));
     }
 
     fn record_u64(&mut self, field: &Field, value: u64) {
-        self.record_field(field, value.to_string());
+        self.record(field, format_args!("{value}"));
     }
 
     fn record_bool(&mut self, field: &Field, value: bool) {
-        self.record_field(field, value.to_string());
+        self.record(field, format_args!("{value}"));
     }
 
     fn record_str(&mut self, field: &Field, value: &str) {
-        self.record_field(field, value.to_string());
+        self.record(field, format_args!("{value}"));
     }
 
     fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
-        self.record_field(field, format!("{value:?}"));
+        self.record(field, format_args!("{value:?}"));
+    }
+}
+
+/// `fmt::Write` adapter that enforces the per-field byte cap and escaping.
+struct LimitedWriter<'a> {
+    out: &'a mut String,
+    remaining: usize,
+    escape: bool,
+}
+
+impl LimitedWriter<'_> {
+    fn push(&mut self, text: &str) -> fmt::Result {
+        if text.len() <= self.remaining {
+            self.out.push_str(text);
+            self.remaining -= text.len();
+            return Ok(());
+        }
+        let mut end = self.remaining;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.out.push_str(&text[..end]);
+        self.remaining = 0;
+        Err(fmt::Error)
+    }
+}
+
+impl fmt::Write for LimitedWriter<'_> {
+    fn write_str(&mut self, mut text: &str) -> fmt::Result {
+        if !self.escape {
+            return self.push(text);
+        }
+        while let Some(idx) = text.find(['\n', '\r', '{', '}']) {
+            self.push(&text[..idx])?;
+            let escaped = match text.as_bytes()[idx] {
+                b'\n' => "\\n",
+                b'\r' => "\\r",
+                b'{' => "\\{",
+                _ => "\\}",
+            };
+            if escaped.len() > self.remaining {
+                self.remaining = 0;
+                return Err(fmt::Error);
+            }
+            self.push(escaped)?;
+            text = &text[idx + 1..];
+        }
+        self.push(text)
     }
 }
 
@@ -289,20 +549,8 @@ fn tracing_level_to_log_level(level: &Level) -> LogLevel {
     }
 }
 
-fn level_rank(level: LogLevel) -> u8 {
-    match level {
-        LogLevel::Verbose => 0,
-        LogLevel::Debug => 1,
-        LogLevel::Info => 2,
-        LogLevel::Warn => 3,
-        LogLevel::Error => 4,
-        LogLevel::Fatal => 5,
-        LogLevel::None => 6,
-    }
-}
-
 fn level_to_u8(level: LogLevel) -> u8 {
-    level_rank(level)
+    level.rank()
 }
 
 fn level_from_u8(value: u8) -> LogLevel {
@@ -322,9 +570,10 @@ mod tests {
     use std::sync::atomic::{AtomicUsize, Ordering};
 
     use tempfile::TempDir;
+    use tracing_subscriber::layer::SubscriberExt;
 
     use super::{XlogLayer, XlogLayerConfig};
-    use crate::{LogLevel, Xlog, XlogConfig};
+    use crate::{AppenderMode, Decoder, Directives, LogLevel, Xlog, XlogConfig};
 
     static NEXT_PREFIX_ID: AtomicUsize = AtomicUsize::new(1);
 
@@ -366,4 +615,120 @@ mod tests {
         assert_eq!(handle.level(), LogLevel::Debug);
         assert_eq!(logger.level(), LogLevel::Warn);
     }
+
+    #[test]
+    fn callsite_interest_follows_handle_updates() {
+        let dir = TempDir::new().expect("tempdir");
+        let logger = Xlog::init(
+            XlogConfig::new(dir.path().display().to_string(), unique_prefix()),
+            LogLevel::Verbose,
+        )
+        .expect("init logger");
+        let (layer, handle) = XlogLayer::with_config(logger, XlogLayerConfig::new(LogLevel::Info));
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(!tracing::enabled!(tracing::Level::DEBUG));
+            assert!(tracing::enabled!(tracing::Level::INFO));
+
+            handle.set_level(LogLevel::Debug);
+            assert!(tracing::enabled!(tracing::Level::DEBUG));
+
+            handle.set_enabled(false);
+            assert!(!tracing::enabled!(tracing::Level::ERROR));
+        });
+    }
+
+    #[test]
+    fn directives_filter_by_event_target() {
+        let dir = TempDir::new().expect("tempdir");
+        let logger = Xlog::init(
+            XlogConfig::new(dir.path().display().to_string(), unique_prefix()),
+            LogLevel::Verbose,
+        )
+        .expect("init logger");
+        let directives: Directives = "warn,net=debug".parse().expect("parse");
+        let (layer, handle) = XlogLayer::with_config(
+            logger,
+            XlogLayerConfig::new(LogLevel::Error).directives(directives.clone()),
+        );
+        assert_eq!(handle.level(), LogLevel::Debug);
+        assert_eq!(handle.directives(), Some(directives));
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(tracing::enabled!(target: "net::http", tracing::Level::DEBUG));
+            assert!(!tracing::enabled!(target: "ui", tracing::Level::INFO));
+            assert!(tracing::enabled!(target: "ui", tracing::Level::WARN));
+
+            handle.clear_directives();
+            assert_eq!(handle.level(), LogLevel::Error);
+            assert!(!tracing::enabled!(target: "ui", tracing::Level::WARN));
+            assert!(tracing::enabled!(target: "ui", tracing::Level::ERROR));
+
+            handle.set_directives("net=debug".parse().expect("parse"));
+            assert_eq!(handle.level(), LogLevel::Debug);
+            assert!(tracing::enabled!(target: "net", tracing::Level::DEBUG));
+            assert!(!tracing::enabled!(target: "ui", tracing::Level::WARN));
+            handle.clear_directives();
+            assert!(!tracing::enabled!(target: "net", tracing::Level::DEBUG));
+        });
+    }
+
+    #[test]
+    fn events_format_message_fields_and_spans() {
+        let dir = TempDir::new().expect("tempdir");
+        let logger = Xlog::init(
+            XlogConfig::new(dir.path().display().to_string(), unique_prefix())
+                .mode(AppenderMode::Sync),
+            LogLevel::Info,
+        )
+        .expect("init logger");
+        let (layer, _handle) = XlogLayer::with_config(
+            logger.clone(),
+            XlogLayerConfig::new(LogLevel::Info).include_spans(true),
+        );
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(user = 42, ok = true, name = "bob", "login");
+            let _span = tracing::info_span!("request").entered();
+            tracing::info!(value = ?vec![1, 2], "outer");
+        });
+        logger.flush(true);
+
+        let files = logger.log_files_for_timespan(0);
+        let decoded = Decoder::new().decode_file(&files[0]).expect("decode");
+        let text = String::from_utf8_lossy(&decoded);
+        assert!(text.contains("][login {user=42, ok=true, name=bob}\n"));
+        assert!(text.contains("][[request] outer {value=[1, 2]}\n"));
+    }
+
+    #[test]
+    fn field_limits_and_escaping_keep_entries_on_one_line() {
+        let dir = TempDir::new().expect("tempdir");
+        let logger = Xlog::init(
+            XlogConfig::new(dir.path().display().to_string(), unique_prefix())
+                .mode(AppenderMode::Sync),
+            LogLevel::Info,
+        )
+        .expect("init logger");
+        let (layer, _handle) = XlogLayer::with_config(
+            logger.clone(),
+            XlogLayerConfig::new(LogLevel::Info)
+                .max_fields(2)
+                .max_field_len(8),
+        );
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(a = "x\n{y}", b = "0123456789", c = 1, d = 2, "limited");
+        });
+        logger.flush(true);
+
+        let files = logger.log_files_for_timespan(0);
+        let decoded = Decoder::new().decode_file(&files[0]).expect("decode");
+        let text = String::from_utf8_lossy(&decoded);
+        assert!(text.contains("][limited {a=x\\n\\{y\\}, b=01234567..., ...+2}\n"));
+    }
 }
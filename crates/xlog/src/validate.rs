@@ -0,0 +1,434 @@
+use std::fs::{self, OpenOptions};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::{backend, CompressMode, Directives, DirectivesError, XlogConfig};
+
+/// One problem found by [`XlogConfig::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConfigIssue {
+    #[error("log_dir is empty")]
+    /// `log_dir` was empty.
+    EmptyLogDir,
+    #[error("name_prefix is empty")]
+    /// `name_prefix` was empty.
+    EmptyNamePrefix,
+    #[error("name_prefix {reason}")]
+    /// `name_prefix` cannot be used as a file name stem; see
+    /// [`sanitize_name_prefix`].
+    InvalidNamePrefix {
+        /// What is wrong with it.
+        reason: &'static str,
+    },
+    #[error("{} exists but is not a directory", .0.display())]
+    /// A configured directory path points at something other than a directory.
+    NotADirectory(PathBuf),
+    #[error("{} is not writable: {reason}", .dir.display())]
+    /// A configured directory, or the closest existing parent init would
+    /// create it under, cannot be written.
+    DirNotWritable {
+        /// Directory that was probed.
+        dir: PathBuf,
+        /// Why the probe failed.
+        reason: String,
+    },
+    #[error("cache_dir is the same directory as log_dir")]
+    /// `cache_dir` and `log_dir` name the same directory, so cached files
+    /// would never be moved.
+    CacheDirIsLogDir,
+    #[error("cache_days is {0}; it must not be negative")]
+    /// `cache_days` was negative.
+    NegativeCacheDays(i32),
+    #[error("cache_days is {0} but no cache_dir is set")]
+    /// `cache_days` was set without a `cache_dir` to keep files in.
+    CacheDaysWithoutCacheDir(i32),
+    #[error("pub_key is not a valid uncompressed secp256k1 public key in hex")]
+    /// The key is not 128 hex chars of a point on the curve; init would
+    /// silently fall back to unencrypted logs.
+    InvalidPubKey,
+    #[error("pub_key is set but encryption is disabled in this build (no-crypt)")]
+    /// A key was set in a `no-crypt` build; init would fail.
+    CryptoDisabled,
+    #[error("compress_level {level} is outside {min}..={max} for {mode:?}")]
+    /// `compress_level` is outside the range of the selected algorithm and
+    /// would be clamped or rejected.
+    CompressLevelOutOfRange {
+        /// Selected algorithm.
+        mode: CompressMode,
+        /// Configured level.
+        level: i32,
+        /// Lowest accepted level.
+        min: i32,
+        /// Highest accepted level.
+        max: i32,
+    },
+    #[error("{0}")]
+    /// `env_directives` is on and the environment variable does not parse.
+    InvalidDirectives(DirectivesError),
+}
+
+/// A setter argument outside the range the logger accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("{name} {value} is outside {min}..={max}")]
+pub struct InvalidValue {
+    /// Setting that rejected the value.
+    pub name: &'static str,
+    /// Value that was passed.
+    pub value: i64,
+    /// Lowest accepted value.
+    pub min: i64,
+    /// Highest accepted value.
+    pub max: i64,
+}
+
+impl InvalidValue {
+    pub(crate) fn check(
+        name: &'static str,
+        value: i64,
+        range: RangeInclusive<i64>,
+    ) -> Result<(), Self> {
+        if range.contains(&value) {
+            Ok(())
+        } else {
+            Err(Self {
+                name,
+                value,
+                min: *range.start(),
+                max: *range.end(),
+            })
+        }
+    }
+}
+
+impl CompressMode {
+    /// Compression levels accepted by this algorithm.
+    pub fn level_range(self) -> RangeInclusive<i32> {
+        match self {
+            CompressMode::Zlib => 0..=9,
+            CompressMode::Zstd => 1..=22,
+        }
+    }
+}
+
+impl XlogConfig {
+    /// Reject a `compress_level` outside the range of `compress_mode`.
+    pub(crate) fn check_compress_level(&self) -> Result<(), InvalidValue> {
+        let range = self.compress_mode.level_range();
+        InvalidValue::check(
+            "compress_level",
+            i64::from(self.compress_level),
+            i64::from(*range.start())..=i64::from(*range.end()),
+        )
+    }
+
+    /// Check this config without initializing anything, collecting every
+    /// problem instead of stopping at the first.
+    ///
+    /// Directories that exist are probed by creating and removing a small
+    /// file; missing ones are checked against the closest existing parent,
+    /// which init would create them under. Passing validation does not
+    /// guarantee init succeeds (another process may hold the instance lock),
+    /// but every issue reported here would break or silently degrade it.
+    pub fn validate(&self) -> Result<(), Vec<ConfigIssue>> {
+        let mut issues = Vec::new();
+        if self.log_dir.is_empty() {
+            issues.push(ConfigIssue::EmptyLogDir);
+        } else {
+            check_dir(Path::new(&self.log_dir), &mut issues);
+        }
+        if self.name_prefix.is_empty() {
+            issues.push(ConfigIssue::EmptyNamePrefix);
+        } else if let Some(reason) = name_prefix_problem(&self.name_prefix) {
+            issues.push(ConfigIssue::InvalidNamePrefix { reason });
+        }
+
+        match self.cache_dir.as_deref().filter(|dir| !dir.is_empty()) {
+            Some(cache_dir) => {
+                if !self.log_dir.is_empty() && same_dir(&self.log_dir, cache_dir) {
+                    issues.push(ConfigIssue::CacheDirIsLogDir);
+                } else {
+                    check_dir(Path::new(cache_dir), &mut issues);
+                }
+            }
+            None if self.cache_days > 0 => {
+                issues.push(ConfigIssue::CacheDaysWithoutCacheDir(self.cache_days));
+            }
+            None => {}
+        }
+        if self.cache_days < 0 {
+            issues.push(ConfigIssue::NegativeCacheDays(self.cache_days));
+        }
+
+        if let Some(key) = self.pub_key.as_deref().filter(|key| !key.is_empty()) {
+            if cfg!(feature = "no-crypt") {
+                issues.push(ConfigIssue::CryptoDisabled);
+            } else if !backend::provider().is_valid_pub_key(key) {
+                issues.push(ConfigIssue::InvalidPubKey);
+            }
+        }
+
+        let range = self.compress_mode.level_range();
+        if !range.contains(&self.compress_level) {
+            issues.push(ConfigIssue::CompressLevelOutOfRange {
+                mode: self.compress_mode,
+                level: self.compress_level,
+                min: *range.start(),
+                max: *range.end(),
+            });
+        }
+
+        if self.env_directives {
+            if let Err(err) = Directives::from_env() {
+                issues.push(ConfigIssue::InvalidDirectives(err));
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+/// Characters Windows does not allow in file names.
+const RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+/// Why `prefix` cannot be used for `<prefix>_<date>.xlog` and the instance's
+/// lock and mmap files, or `None` when it can.
+pub(crate) fn name_prefix_problem(prefix: &str) -> Option<&'static str> {
+    if prefix.contains(['/', '\\']) {
+        Some("contains a path separator")
+    } else if prefix.contains(char::is_whitespace) {
+        Some("contains whitespace")
+    } else if prefix.contains(char::is_control) {
+        Some("contains a control character")
+    } else if prefix.contains(RESERVED_CHARS) {
+        Some("contains a character reserved in file names (<>:\"|?*)")
+    } else if prefix.starts_with('.') || prefix.ends_with('.') {
+        Some("starts or ends with a dot")
+    } else {
+        None
+    }
+}
+
+/// Turn an arbitrary string, such as a user or module name, into a
+/// `name_prefix` that init accepts.
+///
+/// Leading and trailing whitespace and dots are trimmed, and every other
+/// rejected character becomes `_`. Returns `"xlog"` when nothing is left.
+pub fn sanitize_name_prefix(raw: &str) -> String {
+    let trimmed = raw.trim_matches(|c: char| c.is_whitespace() || c == '.');
+    let sanitized: String = trimmed
+        .chars()
+        .map(|c| {
+            if c == '/'
+                || c == '\\'
+                || c.is_whitespace()
+                || c.is_control()
+                || RESERVED_CHARS.contains(&c)
+            {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if sanitized.is_empty() {
+        "xlog".to_string()
+    } else {
+        sanitized
+    }
+}
+
+fn check_dir(dir: &Path, issues: &mut Vec<ConfigIssue>) {
+    match fs::metadata(dir) {
+        Ok(meta) if !meta.is_dir() => issues.push(ConfigIssue::NotADirectory(dir.to_path_buf())),
+        Ok(_) => {
+            if let Err(reason) = probe_writable(dir) {
+                issues.push(ConfigIssue::DirNotWritable {
+                    dir: dir.to_path_buf(),
+                    reason,
+                });
+            }
+        }
+        Err(_) => {
+            // Init creates the directory; check what it would be created in,
+            // without leaving files behind in a directory we do not own.
+            let Some(parent) = dir.ancestors().skip(1).find(|parent| parent.exists()) else {
+                return;
+            };
+            match fs::metadata(parent) {
+                Ok(meta) if !meta.is_dir() => {
+                    issues.push(ConfigIssue::NotADirectory(parent.to_path_buf()))
+                }
+                Ok(meta) if meta.permissions().readonly() => {
+                    issues.push(ConfigIssue::DirNotWritable {
+                        dir: parent.to_path_buf(),
+                        reason: "read-only".to_string(),
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn probe_writable(dir: &Path) -> Result<(), String> {
+    let probe = dir.join(format!(".xlog-validate-{}", std::process::id()));
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&probe)
+        .map_err(|err| err.to_string())?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+fn same_dir(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => Path::new(a) == Path::new(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize_name_prefix, ConfigIssue, InvalidValue};
+    use crate::test_support::{unique_prefix, LogDir};
+    use crate::{CompressMode, LogLevel, Xlog, XlogConfig, XlogError, MIN_LOG_ALIVE_SECONDS};
+
+    #[test]
+    fn validate_reports_every_config_issue() {
+        let dir = LogDir::new();
+        let log_dir = dir.path().join("logs").display().to_string();
+        assert_eq!(XlogConfig::new(&log_dir, "ok").validate(), Ok(()));
+
+        let file = dir.path().join("plain-file");
+        std::fs::write(&file, b"x").expect("write file");
+        let issues = XlogConfig::new(file.display().to_string(), "")
+            .cache_days(3)
+            .pub_key("00")
+            .compress_level(0)
+            .compress_mode(CompressMode::Zstd)
+            .validate()
+            .unwrap_err();
+        let mut expected = vec![
+            ConfigIssue::NotADirectory(file.clone()),
+            ConfigIssue::EmptyNamePrefix,
+            ConfigIssue::CacheDaysWithoutCacheDir(3),
+        ];
+        expected.push(if cfg!(feature = "no-crypt") {
+            ConfigIssue::CryptoDisabled
+        } else {
+            ConfigIssue::InvalidPubKey
+        });
+        expected.push(ConfigIssue::CompressLevelOutOfRange {
+            mode: CompressMode::Zstd,
+            level: 0,
+            min: 1,
+            max: 22,
+        });
+        assert_eq!(issues, expected);
+
+        let issues = XlogConfig::new(&log_dir, "p")
+            .cache_dir(&log_dir)
+            .cache_days(-1)
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            issues,
+            vec![
+                ConfigIssue::CacheDirIsLogDir,
+                ConfigIssue::NegativeCacheDays(-1)
+            ]
+        );
+        assert_eq!(
+            issues[0].to_string(),
+            "cache_dir is the same directory as log_dir"
+        );
+    }
+
+    #[test]
+    fn init_rejects_unusable_and_colliding_name_prefixes() {
+        let dir = LogDir::new();
+        let log_dir = dir.log_dir();
+        for bad in ["a/b", "has space", "what?", ".hidden"] {
+            let err = Xlog::init(XlogConfig::new(&log_dir, bad), LogLevel::Info)
+                .err()
+                .expect("must reject prefix");
+            assert!(
+                matches!(err, XlogError::InvalidNamePrefix { ref name_prefix, .. } if name_prefix == bad),
+                "{bad}: {err}"
+            );
+            assert_eq!(
+                XlogConfig::new(&log_dir, bad).validate().unwrap_err().len(),
+                1
+            );
+            let sanitized = sanitize_name_prefix(bad);
+            assert!(XlogConfig::new(&log_dir, &sanitized).validate().is_ok());
+        }
+        assert_eq!(sanitize_name_prefix(" my app/v2. "), "my_app_v2");
+        assert_eq!(sanitize_name_prefix(".."), "xlog");
+
+        let prefix = unique_prefix("Collide");
+        let _first = Xlog::init(XlogConfig::new(&log_dir, &prefix), LogLevel::Info).expect("init");
+        let lower = prefix.to_lowercase();
+        let err = Xlog::init(XlogConfig::new(&log_dir, &lower), LogLevel::Info)
+            .err()
+            .expect("must reject case collision");
+        assert!(matches!(
+            err,
+            XlogError::NamePrefixCollision { ref name_prefix, ref existing }
+                if name_prefix == &lower && existing == &prefix
+        ));
+        let other_dir = LogDir::new();
+        let _elsewhere = Xlog::init(XlogConfig::new(other_dir.log_dir(), &lower), LogLevel::Info)
+            .expect("same prefix in another directory is fine");
+    }
+
+    #[test]
+    fn setters_reject_out_of_range_values() {
+        let dir = LogDir::new();
+        let cfg = dir.config("ranges");
+        let logger = Xlog::init(cfg.clone(), LogLevel::Info).expect("init");
+
+        assert_eq!(
+            logger.set_max_file_size(-1),
+            Err(InvalidValue {
+                name: "max_file_size",
+                value: -1,
+                min: 0,
+                max: i64::MAX,
+            })
+        );
+        assert!(logger.set_max_file_size(0).is_ok());
+        let err = logger.set_max_alive_time(60).unwrap_err();
+        assert_eq!(
+            (err.name, err.min),
+            ("max_alive_time", MIN_LOG_ALIVE_SECONDS)
+        );
+        assert!(logger.set_max_alive_time(MIN_LOG_ALIVE_SECONDS).is_ok());
+
+        // The level is checked against the final mode, in either call order.
+        let zstd = cfg
+            .clone()
+            .compress_level(22)
+            .compress_mode(CompressMode::Zstd);
+        assert!(zstd.validate().is_ok());
+        let err = Xlog::init(dir.config("zlib-level").compress_level(10), LogLevel::Info)
+            .err()
+            .expect("must reject compress level");
+        assert!(matches!(
+            err,
+            XlogError::InvalidValue(InvalidValue {
+                name: "compress_level",
+                value: 10,
+                ..
+            })
+        ));
+    }
+}
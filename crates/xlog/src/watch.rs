@@ -0,0 +1,181 @@
+//! Filesystem watch on an instance's log directory.
+//!
+//! This module is gated behind the `watch` feature.
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{LogFileEvent, Xlog};
+
+/// Guard returned by [`Xlog::watch_log_dir`]; dropping it stops the watch.
+pub struct LogDirWatch {
+    _watcher: RecommendedWatcher,
+}
+
+struct WatchState {
+    /// `<name_prefix>_`, the stem every file of the instance starts with.
+    stem: String,
+    last_created: Option<PathBuf>,
+    callback: Box<dyn FnMut(&LogFileEvent) + Send>,
+}
+
+impl WatchState {
+    fn handle(&mut self, event: notify::Event) {
+        match event.kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                event.paths.iter().for_each(|path| self.created(path));
+            }
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                event.paths.iter().for_each(|path| self.removed(path));
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                if let [from, to] = &event.paths[..] {
+                    self.removed(from);
+                    self.created(to);
+                }
+            }
+            // Backends that cannot tell the two sides of a rename apart.
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                for path in &event.paths {
+                    if path.exists() {
+                        self.created(path);
+                    } else {
+                        self.removed(path);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn created(&mut self, path: &Path) {
+        if !self.is_log_file(path) {
+            return;
+        }
+        self.emit(LogFileEvent::Created(path.to_path_buf()));
+        let previous = self.last_created.replace(path.to_path_buf());
+        if let Some(from) = previous.filter(|from| from != path) {
+            self.emit(LogFileEvent::Rotated {
+                from,
+                to: path.to_path_buf(),
+            });
+        }
+    }
+
+    fn removed(&mut self, path: &Path) {
+        if self.is_log_file(path) {
+            self.emit(LogFileEvent::Removed(path.to_path_buf()));
+        }
+    }
+
+    /// `<name_prefix>_<date>[_<n>].xlog`, so a longer prefix sharing the
+    /// stem (`app_v2` next to `app`) is not mistaken for this instance.
+    fn is_log_file(&self, path: &Path) -> bool {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(&self.stem))
+            .is_some_and(|rest| {
+                rest.starts_with(|c: char| c.is_ascii_digit()) && rest.ends_with(".xlog")
+            })
+    }
+
+    fn emit(&mut self, event: LogFileEvent) {
+        // A panicking callback must not take the watcher thread down.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| (self.callback)(&event)));
+    }
+}
+
+impl Xlog {
+    /// Watch this instance's log directory and call `callback` when one of
+    /// its `.xlog` files appears, is followed by a newer one, or disappears,
+    /// whoever caused it.
+    ///
+    /// Unlike [`Xlog::on_file_event`], which reports only this instance's
+    /// own writes, this also sees other processes, such as an uploader
+    /// deleting what it sent. Events come from the platform watcher
+    /// (inotify, FSEvents, `ReadDirectoryChangesW`), so they may be
+    /// coalesced or arrive late, and callbacks run on its thread. The watch
+    /// stops when the returned guard is dropped.
+    pub fn watch_log_dir(
+        &self,
+        callback: impl FnMut(&LogFileEvent) + Send + 'static,
+    ) -> io::Result<LogDirWatch> {
+        let dir = self
+            .log_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "instance has no log_dir"))?;
+        let mut state = WatchState {
+            stem: format!("{}_", self.inner.name_prefix),
+            last_created: None,
+            callback: Box::new(callback),
+        };
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+                if let Ok(event) = result {
+                    state.handle(event);
+                }
+            })
+            .map_err(io::Error::other)?;
+        watcher
+            .watch(Path::new(&dir), RecursiveMode::NonRecursive)
+            .map_err(io::Error::other)?;
+        Ok(LogDirWatch { _watcher: watcher })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::Duration;
+
+    use crate::test_support::LogDir;
+    use crate::{LogFileEvent, LogLevel, Xlog};
+
+    #[test]
+    fn watch_reports_files_other_processes_touch() {
+        let dir = LogDir::new();
+        let cfg = dir.config("watch-dir");
+        let prefix = cfg.name_prefix.clone();
+        let logger = Xlog::init(cfg, LogLevel::Info).expect("init");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let watch = logger
+            .watch_log_dir(move |event| {
+                let _ = tx.send(event.clone());
+            })
+            .expect("watch");
+
+        let first = dir.path().join(format!("{prefix}_20260101.xlog"));
+        let second = dir.path().join(format!("{prefix}_20260102.xlog"));
+        std::fs::write(dir.path().join(format!("{prefix}_v2_20260101.xlog")), b"")
+            .expect("other prefix");
+        std::fs::write(dir.path().join("notes.txt"), b"").expect("other file");
+        std::fs::write(&first, b"").expect("first");
+        std::fs::write(&second, b"").expect("second");
+        std::fs::remove_file(&first).expect("remove");
+
+        let next = || rx.recv_timeout(Duration::from_secs(5)).expect("event");
+        assert_eq!(next(), LogFileEvent::Created(first.clone()));
+        assert_eq!(next(), LogFileEvent::Created(second.clone()));
+        assert_eq!(
+            next(),
+            LogFileEvent::Rotated {
+                from: first.clone(),
+                to: second,
+            }
+        );
+        assert_eq!(next(), LogFileEvent::Removed(first));
+
+        // Dropping the guard stops the watcher thread, which then releases
+        // the callback and with it the sender.
+        drop(watch);
+        let stopped = loop {
+            match rx.recv_timeout(Duration::from_secs(5)) {
+                Ok(_) => continue,
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(stopped, RecvTimeoutError::Disconnected);
+    }
+}
@@ -43,6 +43,31 @@ object XlogBridge {
         ERROR(-1),
     }
 
+    /**
+     * Logger configuration read field-by-field by the native side.
+     *
+     * Keep field names stable (or exclude this class from obfuscation); fields
+     * the native side does not find fall back to their defaults.
+     */
+    class XlogConfig(
+        @JvmField val logDir: String,
+        @JvmField val namePrefix: String,
+        @JvmField val pubKey: String? = null,
+        @JvmField val cacheDir: String? = null,
+        @JvmField val cacheDays: Int = 0,
+        @JvmField val mode: Int = AppenderMode.ASYNC.value,
+        @JvmField val compressMode: Int = CompressMode.ZLIB.value,
+        @JvmField val compressLevel: Int = 6,
+    )
+
+    /** Receives the new level ordinal when a logger's level changes. */
+    fun interface LevelListener {
+        fun onLevelChanged(level: Int)
+    }
+
+    external fun nativeCreateLoggerWithConfig(config: XlogConfig, level: Int): Long
+    external fun nativeOpenAppenderWithConfig(config: XlogConfig, level: Int): Boolean
+
     external fun nativeCreateLogger(
         logDir: String,
         namePrefix: String,
@@ -72,16 +97,46 @@ object XlogBridge {
 
     external fun nativeCloseAppender()
     external fun nativeFlushAll(sync: Boolean)
+    external fun nativeOnBackground()
+    external fun nativeOnForeground()
+    external fun nativeOnTerminate()
     external fun nativeIsEnabled(handle: Long, level: Int): Boolean
     external fun nativeGetLevel(handle: Long): Int
     external fun nativeSetLevel(handle: Long, level: Int)
+    external fun nativeSetLevelListener(handle: Long, listener: LevelListener?): Boolean
     external fun nativeSetAppenderMode(handle: Long, mode: Int)
     external fun nativeFlush(handle: Long, sync: Boolean)
+    external fun nativeFlushBounded(handle: Long, maxMillis: Long): Boolean
+    external fun nativeFlushAsync(handle: Long, callback: Runnable?): Boolean
     external fun nativeSetConsoleLogOpen(handle: Long, open: Boolean)
-    external fun nativeSetMaxFileSize(handle: Long, maxBytes: Long)
-    external fun nativeSetMaxAliveTime(handle: Long, aliveSeconds: Long)
+    external fun nativeSetMaxFileSize(handle: Long, maxBytes: Long): Boolean
+    external fun nativeSetMaxAliveTime(handle: Long, aliveSeconds: Long): Boolean
+    external fun nativeSetPubKey(handle: Long, pubKey: String?): Boolean
+    external fun nativeSetDirectives(handle: Long, directives: String?): Boolean
 
     external fun nativeWrite(handle: Long, level: Int, tag: String?, message: String)
+    external fun nativeWriteDirect(
+        handle: Long,
+        level: Int,
+        tag: String?,
+        buffer: java.nio.ByteBuffer,
+        length: Int,
+    )
+    external fun nativeWriteKv(
+        handle: Long,
+        level: Int,
+        tag: String?,
+        message: String,
+        keys: Array<String>,
+        values: Array<String>,
+    )
+    external fun nativeLogThrowable(
+        handle: Long,
+        level: Int,
+        tag: String?,
+        message: String,
+        stackTrace: String,
+    )
     external fun nativeWriteWithMeta(
         handle: Long,
         level: Int,
@@ -96,6 +151,8 @@ object XlogBridge {
     external fun nativeCurrentLogCachePath(): String?
     external fun nativeFilepathsFromTimespan(timespan: Int, prefix: String): Array<String>
     external fun nativeMakeLogfileName(timespan: Int, prefix: String): Array<String>
+    external fun nativeCurrentLogPathForHandle(handle: Long): String?
+    external fun nativeListLogFiles(handle: Long, days: Int): Array<String>
 
     external fun nativeOneshotFlush(
         logDir: String,
@@ -110,4 +167,29 @@ object XlogBridge {
 
     external fun nativeDump(buffer: ByteArray): String
     external fun nativeMemoryDump(buffer: ByteArray): String
+    external fun nativeMemoryDumpBytes(buffer: ByteArray): ByteArray?
+    external fun nativeDecodeFile(path: String, privateKey: String?): String?
+
+    /** System property holding directives such as `info,net=debug`. */
+    const val DIRECTIVES_PROPERTY = "debug.xlog.directives"
+
+    /**
+     * Apply [DIRECTIVES_PROPERTY] to [handle], e.g. at startup and from a
+     * debug menu after `adb shell setprop debug.xlog.directives ...`.
+     *
+     * Returns false when the property is unset or the value is rejected.
+     */
+    fun applyDirectivesProperty(handle: Long): Boolean {
+        val value = try {
+            Class.forName("android.os.SystemProperties")
+                .getMethod("get", String::class.java)
+                .invoke(null, DIRECTIVES_PROPERTY) as String?
+        } catch (e: ReflectiveOperationException) {
+            null
+        }
+        if (value.isNullOrEmpty()) {
+            return false
+        }
+        return nativeSetDirectives(handle, value)
+    }
 }
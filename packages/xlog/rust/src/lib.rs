@@ -488,10 +488,14 @@ pub extern "C" fn mxl_logger_new(config_json: *const c_char, level: i32) -> *mut
         let logger = Xlog::init(config, level).map_err(|err| err.to_string())?;
         logger.set_console_log_open(dto.enable_console);
         if let Some(max_bytes) = dto.max_file_size_bytes {
-            logger.set_max_file_size(max_bytes);
+            logger
+                .set_max_file_size(max_bytes)
+                .map_err(|err| err.to_string())?;
         }
         if let Some(alive_seconds) = dto.max_alive_time_seconds {
-            logger.set_max_alive_time(alive_seconds);
+            logger
+                .set_max_alive_time(alive_seconds)
+                .map_err(|err| err.to_string())?;
         }
 
         Ok(Box::into_raw(Box::new(LoggerState {
@@ -551,8 +555,10 @@ pub extern "C" fn mxl_logger_set_console_open(handle: *mut LoggerState, open: bo
 pub extern "C" fn mxl_logger_set_max_file_size(handle: *mut LoggerState, max_bytes: i64) -> bool {
     wrap_bool(|| {
         let state = logger_state(handle)?;
-        state.logger.set_max_file_size(max_bytes);
-        Ok(())
+        state
+            .logger
+            .set_max_file_size(max_bytes)
+            .map_err(|err| err.to_string())
     })
 }
 
@@ -563,8 +569,10 @@ pub extern "C" fn mxl_logger_set_max_alive_time(
 ) -> bool {
     wrap_bool(|| {
         let state = logger_state(handle)?;
-        state.logger.set_max_alive_time(alive_seconds);
-        Ok(())
+        state
+            .logger
+            .set_max_alive_time(alive_seconds)
+            .map_err(|err| err.to_string())
     })
 }
 
@@ -123,6 +123,10 @@ run_bench \
   "mars_xlog" \
   cargo bench -p mars-xlog --bench criterion_write_path -- --noplot --save-baseline "$baseline_name"
 
+run_bench \
+  "mars_xlog_api_matrix" \
+  cargo bench -p mars-xlog --features macros,tracing --bench criterion_api_matrix -- --noplot --save-baseline "$baseline_name"
+
 python3 "$repo_root/scripts/xlog/analyze_criterion.py" \
   --root "$criterion_root" \
   --profile "$baseline_name" \